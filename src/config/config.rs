@@ -2,3 +2,5 @@ pub const RUSTY_DB_PAGE_SIZE_BYTES: usize = 4096;
 pub const MAX_STRING_LENGTH: usize = 2048;
 // relative path from the project root, i.e., the root of the repository that contains `cargo.toml`
 pub const RUST_DB_DATA_DIR: &str = "data";
+// default maximum number of iterations of a WITH RECURSIVE query, see Session::set_recursion_limit()
+pub const RECURSION_LIMIT: usize = 1000;
@@ -39,6 +39,14 @@ fn execute<'a, E: Engine<'a>>(command: &str, session: &mut Session<'a, E>) -> Re
             true => println!("[console] Dropped table '{}'.", name),
             false => println!("[console] Table '{}' does not exist.", name),
         },
+        StatementResult::CreateView { name } => println!("[console] Created view '{}'.", name),
+        StatementResult::DropView { name, existed } => match existed {
+            true => println!("[console] Dropped view '{}'.", name),
+            false => println!("[console] View '{}' does not exist.", name),
+        },
+        StatementResult::RefreshMaterializedView { name, count } => {
+            println!("[console] Refreshed view '{}' with {} tuples.", name, count)
+        }
         StatementResult::Delete { count } => println!("[console] Deleted {} tuples.", count),
         StatementResult::Insert {
             count,
@@ -49,6 +57,11 @@ fn execute<'a, E: Engine<'a>>(command: &str, session: &mut Session<'a, E>) -> Re
             print_columns(&columns);
             print_rows(&rows);
         }
+        StatementResult::Prepare { name } => println!("[console] Prepared statement '{}'.", name),
+        StatementResult::Deallocate { name } => match name {
+            Some(name) => println!("[console] Deallocated statement '{}'.", name),
+            None => println!("[console] Deallocated all statements."),
+        },
     }
     Ok(())
 }
@@ -3,7 +3,7 @@ use crate::errinput;
 use crate::sql::planner::Expression;
 use crate::storage::page::RecordId;
 use crate::storage::tuple::{Row, Rows};
-use crate::types::Table;
+use crate::types::{Table, View};
 use std::collections::BTreeMap;
 
 /// A SQL query engine.
@@ -37,7 +37,7 @@ pub trait Transaction {
     fn update(&self, table_name: &str, rows: BTreeMap<RecordId, Row>) -> Result<()>;
 }
 
-/// Stores table schema information.
+/// Stores table schema and view information.
 pub trait Catalog {
     /// Creates a new table. Errors if the specified table already exists.
     fn create_table(&self, table: Table) -> Result<()>;
@@ -55,4 +55,19 @@ pub trait Catalog {
         self.get_table(table_name)?
             .ok_or_else(|| errinput!("No table with name {table_name} exists."))
     }
+
+    /// Creates a view. Errors if a table or view with the same name already
+    /// exists, unless `replace` is set and it's a view of the same kind.
+    fn create_view(&self, view: View, replace: bool) -> Result<()>;
+    /// Drops the view corresponding to `view_name`, but not the table of a
+    /// materialized view. Returns whether the view existed, or errors if it
+    /// doesn't and `if_exists` isn't set. Errors if other views depend on it.
+    fn drop_view(&self, view_name: &str, if_exists: bool) -> Result<bool>;
+    /// Fetches the view corresponding to `view_name`.
+    /// Returns `None` if no such view exists.
+    fn get_view(&self, view_name: &str) -> Result<Option<View>>;
+
+    /// Returns the schema version, which is incremented whenever a table or
+    /// view is created or dropped. Used to invalidate cached plans.
+    fn schema_version(&self) -> u64;
 }
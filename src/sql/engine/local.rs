@@ -6,15 +6,21 @@ use crate::storage::simple::Simple;
 use crate::storage::tuple::{Row, Rows};
 use crate::storage::{simple, Key};
 use crate::types::field::Field;
-use crate::types::Table;
+use crate::types::{Table, View};
 use crate::{errinput, storage};
 use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
 /// A SQL engine using local storage. This is a single-transaction,
 /// basic execution engine without concurrency support.
 pub struct Local<E: storage::Engine + 'static> {
     /// The local non-concurrent storage engine.
     pub simple: Simple<E>,
+    /// The views, by name. Like table schemas, these are only kept in memory.
+    views: Arc<Mutex<BTreeMap<String, View>>>,
+    /// The schema version, see Catalog::schema_version().
+    schema_version: Arc<AtomicU64>,
 }
 
 impl<'a, E: storage::Engine> Local<E> {
@@ -22,6 +28,8 @@ impl<'a, E: storage::Engine> Local<E> {
     pub fn new(engine: E) -> Self {
         Self {
             simple: Simple::new(engine),
+            views: Arc::default(),
+            schema_version: Arc::default(),
         }
     }
 
@@ -36,21 +44,42 @@ impl<'a, E: storage::Engine> super::Engine<'a> for Local<E> {
     type Transaction = Transaction<E>;
 
     fn begin(&'a self) -> Result<Self::Transaction> {
-        Ok(Transaction::new(self.simple.begin()?))
+        Ok(Transaction::new(
+            self.simple.begin()?,
+            Arc::clone(&self.views),
+            Arc::clone(&self.schema_version),
+        ))
     }
 }
 
 /// A SQL transaction, wrapping a simple transaction.
 pub struct Transaction<E: storage::Engine + 'static> {
     txn: simple::Transaction<E>,
+    /// The engine's views, shared by all transactions.
+    views: Arc<Mutex<BTreeMap<String, View>>>,
+    /// The engine's schema version, shared by all transactions.
+    schema_version: Arc<AtomicU64>,
 }
 
 #[allow(dead_code)]
 impl<E: storage::Engine> Transaction<E> {
     /// Creates a new SQL transaction using the given simple transaction.
     /// This "transaction" is just a reference to the engine wrapped in a mutex.
-    fn new(txn: simple::Transaction<E>) -> Self {
-        Self { txn }
+    fn new(
+        txn: simple::Transaction<E>,
+        views: Arc<Mutex<BTreeMap<String, View>>>,
+        schema_version: Arc<AtomicU64>,
+    ) -> Self {
+        Self {
+            txn,
+            views,
+            schema_version,
+        }
+    }
+
+    /// Increments the schema version after a schema change.
+    fn schema_changed(&self) {
+        self.schema_version.fetch_add(1, Ordering::SeqCst);
     }
 }
 
@@ -113,12 +142,19 @@ impl<E: storage::Engine> super::Transaction for Transaction<E> {
 /// e.g. Transaction::create_table(). You also might need `Error::InvalidInput`.
 impl<E: storage::Engine> Catalog for Transaction<E> {
     fn create_table(&self, table: Table) -> Result<()> {
+        // A materialized view is backed by a table of the same name.
+        if let Some(view) = self.get_view(table.name())? {
+            if !view.materialized {
+                return errinput!("View '{}' already exists", table.name());
+            }
+        }
         // Check if the table already exists.
         match self.txn.fetch_table(table.name()) {
             Ok(Some(_)) => Err(Error::InvalidInput(format!("Table '{}' already exists", table.name()))),
             Ok(None) => {
                 // Table does not exist, proceed with creation.
                 self.txn.create_table(table)?;
+                self.schema_changed();
                 Ok(())
             }
             Err(e) => Err(e), // Propagate any errors from `fetch_table`.
@@ -126,10 +162,18 @@ impl<E: storage::Engine> Catalog for Transaction<E> {
     }
 
     fn drop_table(&self, table_name: &str, if_exists: bool) -> Result<bool> {
+        if let Some(view) = self.get_view(table_name)? {
+            let hint = match view.materialized {
+                true => "is a materialized view, use DROP MATERIALIZED VIEW",
+                false => "is a view, use DROP VIEW",
+            };
+            return errinput!("{table_name} {hint}");
+        }
         match self.txn.fetch_table(table_name) {
             Ok(Some(_)) => {
                 // Table exists, attempt to delete it
                 self.txn.delete_table(table_name)?;
+                self.schema_changed();
                 Ok(true)
             }
             Ok(None) => {
@@ -155,4 +199,48 @@ impl<E: storage::Engine> Catalog for Transaction<E> {
             .fetch_table(table_name)
             .map_or(Ok(None), |result| Ok(result.or(None)))
     }
+
+    fn create_view(&self, view: View, replace: bool) -> Result<()> {
+        let mut views = self.views.lock()?;
+        match views.get(&view.name) {
+            Some(existing) if replace && existing.materialized == view.materialized => {}
+            Some(_) => return errinput!("View '{}' already exists", view.name),
+            None if self.txn.fetch_table(&view.name)?.is_some() => {
+                return errinput!("Table '{}' already exists", view.name)
+            }
+            None => {}
+        }
+        views.insert(view.name.clone(), view);
+        self.schema_changed();
+        Ok(())
+    }
+
+    fn drop_view(&self, view_name: &str, if_exists: bool) -> Result<bool> {
+        let mut views = self.views.lock()?;
+        if let Some(dependent) = views
+            .values()
+            .find(|view| view.dependencies.contains(view_name))
+        {
+            return errinput!(
+                "can't drop {view_name}, view {} depends on it",
+                dependent.name
+            );
+        }
+        match views.remove(view_name) {
+            Some(_) => {
+                self.schema_changed();
+                Ok(true)
+            }
+            None if if_exists => Ok(false),
+            None => errinput!("View {view_name} does not exist"),
+        }
+    }
+
+    fn get_view(&self, view_name: &str) -> Result<Option<View>> {
+        Ok(self.views.lock()?.get(view_name).cloned())
+    }
+
+    fn schema_version(&self) -> u64 {
+        self.schema_version.load(Ordering::SeqCst)
+    }
 }
@@ -4,4 +4,4 @@ mod session;
 
 pub use engine::{Catalog, Engine, Transaction};
 pub use local::Local;
-pub use session::{Session, StatementResult};
+pub use session::{PreparedStatement, Session, StatementResult};
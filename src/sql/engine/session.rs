@@ -1,31 +1,253 @@
-use super::Engine;
+use super::{Catalog, Engine};
 use crate::common::{Error, Result};
+use crate::config::config::RECURSION_LIMIT;
+use crate::errinput;
 use crate::sql::execution::ExecutionResult;
-use crate::sql::parser::Parser;
-use crate::sql::planner::Plan;
+use crate::sql::parser::{ast, Parser};
+use crate::sql::planner::{Plan, Planner};
 use crate::storage::page::RecordId;
 use crate::storage::tuple::Row;
-use crate::types::field::Label;
+use crate::types::field::{Field, Label};
+use crate::types::DataType;
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 
 /// A SQL session, which executes raw SQL statements against a query engine.
 pub struct Session<'a, E: Engine<'a>> {
-    txn: E::Transaction,
+    /// The session transaction, shared with prepared statements.
+    txn: Rc<E::Transaction>,
+    /// Statements prepared via SQL PREPARE, by name.
+    prepared: HashMap<String, PreparedStatement<'a, E>>,
+    /// The maximum number of iterations of recursive CTEs.
+    recursion_limit: usize,
 }
 
 impl<'a, E: Engine<'a>> Session<'a, E> {
     /// Creates a new session with the given query engine.
     pub fn new(engine: &'a E) -> Self {
         Self {
-            txn: engine.begin().expect("Could not begin new transaction."),
+            txn: Rc::new(engine.begin().expect("Could not begin new transaction.")),
+            prepared: HashMap::new(),
+            recursion_limit: RECURSION_LIMIT,
         }
     }
 
+    /// Sets the maximum number of iterations of WITH RECURSIVE queries, after
+    /// which they error. Applies to statements planned after the call.
+    pub fn set_recursion_limit(&mut self, limit: usize) {
+        self.recursion_limit = limit;
+    }
+
+    /// Builds a plan for a parsed SQL statement.
+    fn plan(&self, statement: ast::Statement) -> Result<Plan> {
+        Planner::new(self.txn.as_ref())
+            .with_recursion_limit(self.recursion_limit)
+            .build(statement)
+    }
+
+    /// Prepares a raw SQL statement, which may contain bind parameters (? or
+    /// $1). The statement is parsed once, and can then be executed repeatedly
+    /// with different parameter values.
+    pub fn prepare(&self, statement: &str) -> Result<PreparedStatement<'a, E>> {
+        self.prepare_statement(Parser::new(statement).parse()?, Vec::new())
+    }
+
+    /// Prepares a parsed SQL statement. The given parameter types override
+    /// the inferred ones, by position. The statement is planned once here,
+    /// which validates it and infers its parameter types.
+    fn prepare_statement(
+        &self,
+        statement: ast::Statement,
+        types: Vec<DataType>,
+    ) -> Result<PreparedStatement<'a, E>> {
+        let mut prepared = PreparedStatement {
+            txn: self.txn.clone(),
+            statement,
+            types,
+            recursion_limit: self.recursion_limit,
+            parameters: Vec::new(),
+            cache: RefCell::new(None),
+        };
+        let cached = prepared.plan()?;
+        prepared.parameters = cached.parameters.clone();
+        prepared.cache = RefCell::new(Some(cached));
+        Ok(prepared)
+    }
+
     /// Executes a raw SQL statement.
     pub fn execute(&mut self, statement: &str) -> Result<StatementResult> {
-        Plan::build(Parser::new(statement).parse()?, &self.txn)?
+        self.execute_statement(Parser::new(statement).parse()?)
+    }
+
+    /// Executes a script of raw SQL statements separated by semicolons, which
+    /// may contain comments. Returns one result per statement, in order. If
+    /// continue_on_error is false, execution stops after the first error (the
+    /// last result), otherwise the remaining statements are executed too.
+    pub fn execute_script(
+        &mut self,
+        script: &str,
+        continue_on_error: bool,
+    ) -> Vec<Result<StatementResult>> {
+        let mut parser = Parser::new(script);
+        let mut results = Vec::new();
+        while let Some(statement) = parser.parse_next() {
+            let result = statement.and_then(|statement| self.execute_statement(statement));
+            let failed = result.is_err();
+            results.push(result);
+            if failed && !continue_on_error {
+                break;
+            }
+        }
+        results
+    }
+
+    /// Executes a parsed SQL statement.
+    fn execute_statement(&mut self, statement: ast::Statement) -> Result<StatementResult> {
+        match statement {
+            ast::Statement::Prepare {
+                name,
+                types,
+                statement,
+            } => {
+                if self.prepared.contains_key(&name) {
+                    return errinput!("prepared statement {name} already exists");
+                }
+                let prepared = self.prepare_statement(*statement, types)?;
+                self.prepared.insert(name.clone(), prepared);
+                Ok(StatementResult::Prepare { name })
+            }
+            ast::Statement::Execute { name, parameters } => {
+                let Some(prepared) = self.prepared.get(&name) else {
+                    return errinput!("unknown prepared statement {name}");
+                };
+                let planner = Planner::new(self.txn.as_ref());
+                let values = parameters
+                    .into_iter()
+                    .map(|expr| planner.evaluate_constant(expr))
+                    .collect::<Result<Vec<_>>>()?;
+                prepared.execute(&values)
+            }
+            ast::Statement::Deallocate { name: Some(name) } => {
+                if self.prepared.remove(&name).is_none() {
+                    return errinput!("unknown prepared statement {name}");
+                }
+                Ok(StatementResult::Deallocate { name: Some(name) })
+            }
+            ast::Statement::Deallocate { name: None } => {
+                self.prepared.clear();
+                Ok(StatementResult::Deallocate { name: None })
+            }
+            statement => self
+                .plan(statement)?
+                .bind(&[])?
+                .optimize()?
+                .execute(self.txn.as_ref())?
+                .try_into(),
+        }
+    }
+}
+
+/// A prepared statement, which has been parsed once and can be executed
+/// repeatedly with different bind parameter values. It executes in the
+/// transaction of the session that prepared it.
+///
+/// The plan is built when the statement is prepared, and reused by each
+/// execution. It is only planned again if the catalog's schema version has
+/// changed since, e.g. if a table was dropped or recreated.
+pub struct PreparedStatement<'a, E: Engine<'a>> {
+    txn: Rc<E::Transaction>,
+    statement: ast::Statement,
+    /// Explicit parameter types, overriding the inferred ones by position.
+    types: Vec<DataType>,
+    recursion_limit: usize,
+    /// The parameter types as of when the statement was prepared.
+    parameters: Vec<Option<DataType>>,
+    /// The current plan, replaced when the schema version changes.
+    cache: RefCell<Option<CachedPlan>>,
+}
+
+/// A prepared statement's plan, for a given schema version.
+struct CachedPlan {
+    schema_version: u64,
+    plan: Plan,
+    parameters: Vec<Option<DataType>>,
+}
+
+impl<'a, E: Engine<'a>> PreparedStatement<'a, E> {
+    /// Returns the statement's parameter types, by index, as of when it was
+    /// prepared. None accepts any value, if the type couldn't be inferred.
+    pub fn parameters(&self) -> &[Option<DataType>] {
+        &self.parameters
+    }
+
+    /// Plans the statement against the current schema, along with its
+    /// parameter types.
+    fn plan(&self) -> Result<CachedPlan> {
+        let schema_version = self.txn.schema_version();
+        let plan = Planner::new(self.txn.as_ref())
+            .with_recursion_limit(self.recursion_limit)
+            .build(self.statement.clone())?;
+        let mut parameters = plan.parameters();
+        if parameters.len() < self.types.len() {
+            parameters.resize(self.types.len(), None);
+        }
+        for (parameter, data_type) in parameters.iter_mut().zip(&self.types) {
+            *parameter = Some(*data_type);
+        }
+        Ok(CachedPlan {
+            schema_version,
+            plan,
+            parameters,
+        })
+    }
+
+    /// Executes the statement with the given parameter values, one for each
+    /// parameter. Values are coerced to the parameter types like values
+    /// stored in a column, e.g. numbers are converted to the parameter's
+    /// numeric type and strings are parsed as dates, except that only strings
+    /// are accepted as text. NULL is always accepted.
+    pub fn execute(&self, values: &[Field]) -> Result<StatementResult> {
+        // Re-plan if the schema has changed since the plan was built.
+        let mut cache = self.cache.borrow_mut();
+        let version = self.txn.schema_version();
+        if cache.as_ref().is_none_or(|c| c.schema_version != version) {
+            *cache = Some(self.plan()?);
+        }
+        let CachedPlan {
+            plan, parameters, ..
+        } = cache.as_ref().expect("plan is cached");
+        if values.len() != parameters.len() {
+            return errinput!(
+                "expected {} parameters, got {}",
+                parameters.len(),
+                values.len()
+            );
+        }
+        let values = values
+            .iter()
+            .zip(parameters)
+            .enumerate()
+            .map(|(i, (value, data_type))| {
+                let Some(to) = *data_type else {
+                    return Ok(value.clone());
+                };
+                let mismatch = || errinput!("parameter ${} expects {to}, got {value}", i + 1);
+                if to == DataType::Text && !matches!(value, Field::String(_) | Field::Null) {
+                    return mismatch();
+                }
+                match value.clone().coerce(to) {
+                    Err(Error::InvalidInput(_)) => mismatch(),
+                    result => result,
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let plan = plan.clone();
+        drop(cache);
+        plan.bind(&values)?
             .optimize()?
-            .execute(&self.txn)?
+            .execute(self.txn.as_ref())?
             .try_into()
     }
 }
@@ -41,6 +263,17 @@ pub enum StatementResult {
         name: String,
         existed: bool,
     },
+    CreateView {
+        name: String,
+    },
+    DropView {
+        name: String,
+        existed: bool,
+    },
+    RefreshMaterializedView {
+        name: String,
+        count: u64,
+    },
     Delete {
         count: u64,
     },
@@ -55,6 +288,12 @@ pub enum StatementResult {
         columns: Vec<Label>,
         rows: Vec<Row>,
     },
+    Prepare {
+        name: String,
+    },
+    Deallocate {
+        name: Option<String>, // None for DEALLOCATE ALL
+    },
 }
 
 /// Converts an execution result into a statement result.
@@ -64,6 +303,11 @@ impl TryFrom<ExecutionResult> for StatementResult {
         Ok(match result {
             ExecutionResult::CreateTable { name } => Self::CreateTable { name },
             ExecutionResult::DropTable { name, existed } => Self::DropTable { name, existed },
+            ExecutionResult::CreateView { name } => Self::CreateView { name },
+            ExecutionResult::DropView { name, existed } => Self::DropView { name, existed },
+            ExecutionResult::RefreshMaterializedView { name, count } => {
+                Self::RefreshMaterializedView { name, count }
+            }
             ExecutionResult::Delete { count } => Self::Delete { count },
             ExecutionResult::Insert { count, record_ids } => Self::Insert { count, record_ids },
             ExecutionResult::Update { count } => Self::Update { count },
@@ -1,85 +1,154 @@
 use crate::common::Result;
-use crate::sql::planner::{Aggregate, Expression};
+use crate::errinput;
+use crate::sql::execution::sketch::{HyperLogLog, TDigest};
+use crate::sql::planner::{Aggregate, Direction, Expression};
 
 use crate::storage::page::INVALID_RID;
 use crate::storage::tuple::{Row, Rows};
+use crate::types::collation::Collation;
 use crate::types::field::Field;
+use crate::types::DataType;
 use itertools::Itertools as _;
-use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashSet};
 
 /// Aggregates row values from the source according to the aggregates, using the
 /// group_by expressions as buckets. Emits rows with group_by buckets then
-/// aggregates in the given order.
+/// aggregates in the given order. The rows are bucketed once per grouping set,
+/// with NULL for the group_by values that aren't in the set.
 pub fn aggregate(
     mut source: Rows,
     group_by: Vec<Expression>,
+    grouping_sets: Vec<Vec<usize>>,
     aggregates: Vec<Aggregate>,
 ) -> Result<Rows> {
-    let mut aggregator = Aggregator::new(group_by, aggregates);
+    let mut aggregator = Aggregator::new(group_by, grouping_sets, aggregates);
     while let Some((_, row)) = source.next().transpose()? {
         aggregator.add(row)?;
     }
     aggregator.into_rows()
 }
 
+/// A bucket's group_by values to emit and its accumulators.
+type Bucket = (Vec<Field>, Vec<Accumulator>);
+
 /// Computes bucketed aggregates for rows.
 struct Aggregator {
-    /// Bucketed accumulators (by group_by values).
+    /// Bucketed accumulators (by grouping set index and group_by values).
     ///
     /// For example, if we are computing COUNT and MAX aggregations over "GROUP BY id"
     /// and "GROUP BY name, age, height", then `buckets` would have two entries:
     /// - vec![ id ]                 -> vec![ Accumulator::Count, Accumulator::Max ]
     /// - vec![ name, age, height ]  -> vec![ Accumulator::Count, Accumulator::Max ]
-    buckets: BTreeMap<Vec<Field>, Vec<Accumulator>>,
-    /// The set of empty accumulators. Used to create new buckets.
-    empty: Vec<Accumulator>,
+    ///
+    /// Each bucket also holds the group_by values to emit for it. These are
+    /// the bucket values, except for collated group_by expressions which are
+    /// bucketed by collation sort key and emit the first value seen, e.g.
+    /// 'Alice' for both 'Alice' and 'ALICE' with a nocase collation.
+    buckets: BTreeMap<(usize, Vec<Field>), Bucket>,
+    /// The set of empty accumulators for each grouping set. Used to create new
+    /// buckets.
+    empty: Vec<Vec<Accumulator>>,
     /// Group by expressions. Indexes map to bucket values.
     group_by: Vec<Expression>,
-    /// Expressions to accumulate. Indexes map to accumulators.
-    expressions: Vec<Expression>,
+    /// Grouping sets, as group_by indexes.
+    grouping_sets: Vec<Vec<usize>>,
+    /// Expressions to accumulate, if any. Indexes map to accumulators.
+    expressions: Vec<Option<Expression>>,
 }
 
 impl Aggregator {
-    /// Creates a new aggregator for the given GROUP BY buckets and aggregates.
-    fn new(group_by: Vec<Expression>, aggregates: Vec<Aggregate>) -> Self {
-        use Aggregate::*;
-        let accumulators = aggregates.iter().map(Accumulator::new).collect();
-        let expressions = aggregates
-            .into_iter()
-            .map(|aggregate| match aggregate {
-                Average(expr) | Count(expr) | Max(expr) | Min(expr) | Sum(expr) => expr,
+    /// Creates a new aggregator for the given GROUP BY buckets, grouping sets
+    /// and aggregates.
+    fn new(
+        group_by: Vec<Expression>,
+        grouping_sets: Vec<Vec<usize>>,
+        aggregates: Vec<Aggregate>,
+    ) -> Self {
+        let empty = grouping_sets
+            .iter()
+            .map(|set| {
+                aggregates
+                    .iter()
+                    .map(|aggregate| match aggregate {
+                        // GROUPING() is a constant for each grouping set.
+                        Aggregate::Grouping(indexes) => Accumulator::Grouping(
+                            indexes
+                                .iter()
+                                .fold(0, |mask, i| mask << 1 | !set.contains(i) as i32),
+                        ),
+                        aggregate => Accumulator::new(aggregate),
+                    })
+                    .collect()
             })
             .collect();
+        let expressions = aggregates
+            .iter()
+            .map(|aggregate| aggregate.expression().cloned())
+            .collect();
         Self {
             buckets: BTreeMap::new(),
-            empty: accumulators,
+            empty,
             group_by,
+            grouping_sets,
             expressions,
         }
     }
 
     /// Adds a row to the aggregator.
     fn add(&mut self, row: Row) -> Result<()> {
-        // Step 1: Compute the bucket value based on the group_by expressions.
-        let bucket: Vec<Field> = self
+        // Step 1: Compute the group_by and aggregate input values. These are
+        // shared by all grouping sets.
+        let group_values: Vec<Field> = self
             .group_by
             .iter()
             .map(|expr| expr.evaluate(Some(&row)))
             .try_collect()?;
+        let values: Vec<Field> = self
+            .expressions
+            .iter()
+            .map(|expr| match expr {
+                Some(expr) => expr.evaluate(Some(&row)),
+                None => Ok(Field::Null),
+            })
+            .try_collect()?;
 
-        // Step 2: Get or initialize the accumulators for the current bucket.
-        let accumulators = self
-            .buckets
-            .entry(bucket)
-            .or_insert_with(|| self.empty.clone());
+        for (index, set) in self.grouping_sets.iter().enumerate() {
+            // Step 2: Compute the bucket value for the grouping set, using NULL
+            // for group_by values that aren't in the set.
+            let bucket = group_values
+                .iter()
+                .enumerate()
+                .map(|(i, value)| match set.contains(&i) {
+                    true => value.clone(),
+                    false => Field::Null,
+                })
+                .collect();
 
-        // Step 3: Iterate over the accumulators and expressions, updating each accumulator.
-        for (accumulator, expression) in accumulators.iter_mut().zip(&self.expressions) {
-            // Evaluate the expression to get the value.
-            let value = expression.evaluate(Some(&row))?;
+            // Step 3: Get or initialize the accumulators for the current bucket.
+            let (_, accumulators) = match self.buckets.entry((index, bucket)) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let values = entry
+                        .key()
+                        .1
+                        .iter()
+                        .zip(&self.group_by)
+                        .map(|(value, expr)| match expr {
+                            Expression::Collate(expr, _) if !value.is_null() => {
+                                expr.evaluate(Some(&row))
+                            }
+                            _ => Ok(value.clone()),
+                        })
+                        .try_collect()?;
+                    entry.insert((values, self.empty[index].clone()))
+                }
+            };
 
-            // Update the accumulator with the evaluated value.
-            accumulator.add(value)?;
+            // Step 4: Update each accumulator with its value.
+            for (accumulator, value) in accumulators.iter_mut().zip(&values) {
+                accumulator.add(value.clone())?;
+            }
         }
 
         Ok(())
@@ -88,16 +157,23 @@ impl Aggregator {
 
     /// Returns a row iterator over the aggregate result.
     fn into_rows(self) -> Result<Rows> {
-        // If there were no rows and no group_by expressions, return a row of
-        // empty accumulators, e.g. SELECT COUNT(*) FROM t WHERE FALSE
-        if self.buckets.is_empty() && self.group_by.is_empty() {
-            let result = Row::from(
-                self.empty
-                    .into_iter()
-                    .map(|acc| acc.value())
-                    .collect::<Result<Vec<_>>>()?,
-            );
-            return Ok(Box::new(std::iter::once(Ok((INVALID_RID, result)))));
+        // If there were no rows, return a row of empty accumulators for each
+        // empty grouping set, e.g. SELECT COUNT(*) FROM t WHERE FALSE
+        if self.buckets.is_empty() {
+            let rows = self
+                .grouping_sets
+                .iter()
+                .zip(self.empty)
+                .filter(|(set, _)| set.is_empty())
+                .map(|(_, accumulators)| {
+                    let values = std::iter::repeat_n(Field::Null, self.group_by.len())
+                        .map(Ok)
+                        .chain(accumulators.into_iter().map(|acc| acc.value()))
+                        .collect::<Result<Vec<_>>>()?;
+                    Ok((INVALID_RID, Row::from(values)))
+                })
+                .collect_vec();
+            return Ok(Box::new(rows.into_iter()));
         }
 
         // Emit the group_by and aggregate values for each bucket. We use an
@@ -105,11 +181,11 @@ impl Aggregator {
         // (required by Rows).
         let buckets = self.buckets.into_iter().collect_vec();
         Ok(Box::new(buckets.into_iter().map(
-            |(bucket, accumulators)| {
+            |(_, (values, accumulators))| {
                 Ok((
                     INVALID_RID,
                     Row::from(
-                        bucket
+                        values
                             .into_iter()
                             .map(Ok)
                             .chain(accumulators.into_iter().map(|acc| acc.value()))
@@ -124,26 +200,111 @@ impl Aggregator {
 /// Accumulates aggregate values. Uses an enum rather than a trait since we need
 /// to keep these in a vector (could use boxed trait objects too).
 #[derive(Clone)]
-enum Accumulator {
-    Average { count: i32, sum: Field },
-    Count(i32),
+pub(super) enum Accumulator {
+    ApproxCountDistinct(HyperLogLog),
+    ApproxPercentile {
+        digest: TDigest,
+        fraction: f64,
+    },
+    /// Collects the values, and encodes them as a text array literal.
+    ArrayAgg(Vec<Field>),
+    Average {
+        count: i64,
+        sum: Field,
+    },
+    BoolAnd(Option<bool>),
+    BoolOr(Option<bool>),
+    Count(i64),
+    /// Only passes values to the inner accumulator the first time they're
+    /// seen. Since each bucket has its own accumulators, it also has its own
+    /// set of seen values. Values are seen by their collation sort key.
+    Distinct {
+        seen: HashSet<Field>,
+        collation: Collation,
+        accumulator: Box<Accumulator>,
+    },
+    /// GROUPING() bitmask, which is constant for a bucket.
+    Grouping(i32),
     Max(Option<Field>),
     Min(Option<Field>),
+    /// Collects the values, and picks the percentile once they're sorted.
+    Percentile {
+        values: Vec<Field>,
+        fraction: f64,
+        descending: bool,
+        continuous: bool,
+    },
+    StringAgg {
+        separator: String,
+        value: Option<String>,
+    },
     Sum(Option<Field>),
+    /// Computes the variance or standard deviation using Welford's online
+    /// algorithm, which avoids the precision loss of summing squares.
+    Variance {
+        count: i64,
+        mean: f64,
+        m2: f64,
+        sample: bool,
+        sqrt: bool,
+    },
 }
 
 impl Accumulator {
     /// Creates a new accumulator from an aggregate kind.
-    fn new(aggregate: &Aggregate) -> Self {
+    pub(super) fn new(aggregate: &Aggregate) -> Self {
         use Aggregate::*;
 
         // Use a destructured match with type inference for concise initialization
         match *aggregate {
-            Average(_) => Self::Average { count: 0, sum: Field::Integer(0) },
+            ApproxCountDistinct(_) => Self::ApproxCountDistinct(HyperLogLog::new()),
+            ApproxPercentile(_, fraction) => Self::ApproxPercentile {
+                digest: TDigest::new(),
+                fraction,
+            },
+            ArrayAgg(_) => Self::ArrayAgg(Vec::new()),
+            Average(_) => Self::Average {
+                count: 0,
+                sum: Field::Integer(0),
+            },
+            BoolAnd(_) => Self::BoolAnd(None),
+            BoolOr(_) => Self::BoolOr(None),
             Count(_) => Self::Count(0),
+            Distinct(ref aggregate, collation) => Self::Distinct {
+                seen: HashSet::new(),
+                collation,
+                accumulator: Box::new(Self::new(aggregate)),
+            },
+            Grouping(_) => Self::Grouping(0),
             Max(_) => Self::Max(None),
             Min(_) => Self::Min(None),
+            PercentileCont(_, fraction, ref direction)
+            | PercentileDisc(_, fraction, ref direction) => Self::Percentile {
+                values: Vec::new(),
+                fraction,
+                descending: *direction == Direction::Descending,
+                continuous: matches!(aggregate, PercentileCont(..)),
+            },
+            StdDevPop(_) => Self::variance(false, true),
+            StdDevSamp(_) => Self::variance(true, true),
+            StringAgg(_, ref separator) => Self::StringAgg {
+                separator: separator.clone(),
+                value: None,
+            },
             Sum(_) => Self::Sum(None),
+            VarPop(_) => Self::variance(false, false),
+            VarSamp(_) => Self::variance(true, false),
+        }
+    }
+
+    /// Creates a new variance or standard deviation accumulator.
+    fn variance(sample: bool, sqrt: bool) -> Self {
+        Self::Variance {
+            count: 0,
+            mean: 0.0,
+            m2: 0.0,
+            sample,
+            sqrt,
         }
     }
 
@@ -185,12 +346,13 @@ impl Accumulator {
     ///     todo!()
     ///  }
     /// ```
-    fn add(&mut self, value: Field) -> Result<()> {
+    pub(super) fn add(&mut self, value: Field) -> Result<()> {
         use Accumulator::*;
 
         match self {
             // For the `Sum` accumulator, add the current value or initialize it if not set.
             Sum(sum) => {
+                let value = Self::widen(value);
                 if let Some(current) = sum {
                     *current = current.checked_add(&value)?;
                 } else {
@@ -201,7 +363,7 @@ impl Accumulator {
             // For the `Average` accumulator, increment count and add to sum.
             Average { count, sum } => {
                 *count += 1;
-                *sum = sum.checked_add(&value)?;
+                *sum = sum.checked_add(&Self::widen(value))?;
             }
 
             // For the `Count` accumulator, only increment count if the value is not null.
@@ -211,45 +373,179 @@ impl Accumulator {
                 }
             }
 
-            // For the `Max` accumulator, update the maximum value if needed.
-            Max(max) => {
-                match max {
-                    Some(current_max) if value > *current_max => *current_max = value,
-                    None => *max = Some(value),
-                    _ => {}
+            // For the `Distinct` accumulator, skip values that were already seen.
+            Distinct {
+                seen,
+                collation,
+                accumulator,
+            } => {
+                if seen.insert(collation.key(value.clone())) {
+                    accumulator.add(value)?;
                 }
             }
 
+            // The `Grouping` accumulator doesn't take input values.
+            Grouping(_) => {}
+
+            // For the `Max` accumulator, update the maximum value if needed.
+            Max(max) => match max {
+                Some(current_max) if value > *current_max => *current_max = value,
+                None => *max = Some(value),
+                _ => {}
+            },
+
             // For the `Min` accumulator, update the minimum value if needed.
-            Min(min) => {
-                match min {
-                    Some(current_min) if value < *current_min => *current_min = value,
-                    None => *min = Some(value),
-                    _ => {}
+            Min(min) => match min {
+                Some(current_min) if value < *current_min => *current_min = value,
+                None => *min = Some(value),
+                _ => {}
+            },
+
+            // The remaining accumulators skip NULL values.
+            _ if value.is_null() => {}
+
+            // For the approximate accumulators, add the value to the sketch.
+            ApproxCountDistinct(hll) => hll.add(&value),
+            ApproxPercentile { digest, .. } => match value.as_double() {
+                Some(value) => digest.add(value),
+                None => return errinput!("can't compute approx_percentile of {value}"),
+            },
+
+            // For the `ArrayAgg` accumulator, collect the values.
+            ArrayAgg(values) => values.push(value),
+
+            // For the `BoolAnd` and `BoolOr` accumulators, combine boolean values.
+            BoolAnd(result) => *result = Some(result.unwrap_or(true) && Self::boolean(value)?),
+            BoolOr(result) => *result = Some(result.unwrap_or(false) || Self::boolean(value)?),
+
+            // For the `Percentile` accumulator, collect the values.
+            Percentile { values, .. } => values.push(value),
+
+            // For the `StringAgg` accumulator, append the value as a string.
+            StringAgg {
+                separator,
+                value: result,
+            } => {
+                let Field::String(value) = value.cast(DataType::Text)? else {
+                    unreachable!("text cast must return a string");
+                };
+                match result {
+                    Some(result) => {
+                        result.push_str(separator);
+                        result.push_str(&value);
+                    }
+                    None => *result = Some(value),
                 }
             }
+
+            // For the `Variance` accumulator, update the running mean and sum
+            // of squared differences from it.
+            Variance {
+                count, mean, m2, ..
+            } => {
+                let Some(value) = value.as_double() else {
+                    return errinput!("can't compute variance of {value}");
+                };
+                *count += 1;
+                let delta = value - *mean;
+                *mean += delta / *count as f64;
+                *m2 += delta * (value - *mean);
+            }
         }
 
         Ok(())
     }
 
+    /// Merges another accumulator's state into this one, such that it
+    /// aggregates the values of both, e.g. to combine partial aggregates of
+    /// different rows. Only the approximate accumulators support this, by
+    /// merging their sketches, which keeps memory bounded.
+    #[allow(dead_code)]
+    pub(super) fn merge(&mut self, other: &Accumulator) -> Result<()> {
+        match (self, other) {
+            (Self::ApproxCountDistinct(hll), Self::ApproxCountDistinct(other)) => hll.merge(other),
+            (
+                Self::ApproxPercentile { digest, fraction },
+                Self::ApproxPercentile {
+                    digest: other,
+                    fraction: other_fraction,
+                },
+            ) if fraction == other_fraction => digest.merge(other),
+            _ => return errinput!("can't merge aggregate states"),
+        }
+        Ok(())
+    }
+
+    /// Returns a boolean input value, or errors for other types.
+    fn boolean(value: Field) -> Result<bool> {
+        match value {
+            Field::Boolean(b) => Ok(b),
+            value => errinput!("can't aggregate {value} as a boolean"),
+        }
+    }
+
+    /// Returns a value as an array literal element. Elements are quoted if
+    /// they're empty, NULL, or contain delimiters, quotes or whitespace, and
+    /// quotes and backslashes are escaped, like in PostgreSQL.
+    fn array_element(value: Field) -> Result<String> {
+        let Field::String(value) = value.cast(DataType::Text)? else {
+            unreachable!("text cast must return a string");
+        };
+        let special = |c: char| "{},\"\\".contains(c) || c.is_whitespace();
+        if !value.is_empty() && !value.eq_ignore_ascii_case("null") && !value.contains(special) {
+            return Ok(value);
+        }
+        let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+        Ok(format!("\"{escaped}\""))
+    }
+
+    /// Widens SMALLINT and INT values to BIGINT before they're summed, such
+    /// that large sums don't overflow the input type.
+    fn widen(value: Field) -> Field {
+        match value {
+            Field::SmallInt(i) => Field::BigInt(i.into()),
+            Field::Integer(i) => Field::BigInt(i.into()),
+            value => value,
+        }
+    }
 
     /// Returns the aggregate value.
-    fn value(self) -> Result<Field> {
+    pub(super) fn value(self) -> Result<Field> {
         match self {
             Accumulator::Average { count, sum } => {
                 // Return Null if the count is zero; otherwise, compute the average.
                 return if count == 0 {
                     Ok(Field::Null)
                 } else {
-                    let divisor = Field::Integer(count);
+                    let divisor = Field::BigInt(count);
                     sum.checked_div(&divisor)
                 };
             }
             Accumulator::Count(count) => {
                 // Directly return the count wrapped in a Field.
-                return Ok(Field::Integer(count));
+                return Ok(Field::BigInt(count));
+            }
+            Accumulator::ApproxCountDistinct(hll) => Ok(Field::BigInt(hll.estimate() as i64)),
+            Accumulator::ApproxPercentile {
+                mut digest,
+                fraction,
+            } => Ok(digest
+                .quantile(fraction)
+                .map(Field::Double)
+                .unwrap_or(Field::Null)),
+            Accumulator::ArrayAgg(values) => {
+                if values.is_empty() {
+                    return Ok(Field::Null);
+                }
+                let elements: Vec<String> =
+                    values.into_iter().map(Self::array_element).try_collect()?;
+                Ok(Field::String(format!("{{{}}}", elements.join(","))))
+            }
+            Accumulator::BoolAnd(result) | Accumulator::BoolOr(result) => {
+                Ok(result.map(Field::Boolean).unwrap_or(Field::Null))
             }
+            Accumulator::Distinct { accumulator, .. } => accumulator.value(),
+            Accumulator::Grouping(mask) => Ok(Field::Integer(mask)),
             // For Max, Min, and Sum, use a common handling pattern with `unwrap_or`.
             Accumulator::Max(value) => {
                 return Ok(value.unwrap_or_else(|| Field::Null));
@@ -257,10 +553,120 @@ impl Accumulator {
             Accumulator::Min(value) => {
                 return Ok(value.unwrap_or_else(|| Field::Null));
             }
+            Accumulator::Percentile {
+                mut values,
+                fraction,
+                descending,
+                continuous,
+            } => {
+                if values.is_empty() {
+                    return Ok(Field::Null);
+                }
+                values.sort();
+                if descending {
+                    values.reverse();
+                }
+                if !continuous {
+                    // The first value whose cumulative distribution is at or
+                    // above the fraction.
+                    let index = (fraction * values.len() as f64).ceil() as usize;
+                    return Ok(values.swap_remove(index.saturating_sub(1)));
+                }
+                // Interpolate between the values around the fractional position.
+                let position = fraction * (values.len() - 1) as f64;
+                let (lower, upper) = (position.floor() as usize, position.ceil() as usize);
+                let (Some(low), Some(high)) =
+                    (values[lower].as_double(), values[upper].as_double())
+                else {
+                    return errinput!("can't interpolate {}", values[lower]);
+                };
+                Ok(Field::Double(
+                    low + (high - low) * (position - lower as f64),
+                ))
+            }
+            Accumulator::StringAgg { value, .. } => {
+                Ok(value.map(Field::String).unwrap_or(Field::Null))
+            }
             Accumulator::Sum(value) => {
                 return Ok(value.unwrap_or_else(|| Field::Null));
             }
+            Accumulator::Variance {
+                count,
+                m2,
+                sample,
+                sqrt,
+                ..
+            } => {
+                let divisor = match sample {
+                    true => count - 1,
+                    false => count,
+                };
+                if divisor <= 0 {
+                    return Ok(Field::Null);
+                }
+                let variance = m2 / divisor as f64;
+                Ok(Field::Double(if sqrt { variance.sqrt() } else { variance }))
+            }
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    /// Aggregates the values in a single pass, and in two partial
+    /// accumulators which are then merged, returning both results.
+    fn single_and_merged(aggregate: &Aggregate, values: &[Field]) -> (Field, Field) {
+        let mut single = Accumulator::new(aggregate);
+        let (mut a, mut b) = (Accumulator::new(aggregate), Accumulator::new(aggregate));
+        for (i, value) in values.iter().enumerate() {
+            single.add(value.clone()).unwrap();
+            match i % 3 {
+                0 => a.add(value.clone()).unwrap(),
+                _ => b.add(value.clone()).unwrap(),
+            }
+        }
+        a.merge(&b).unwrap();
+        (single.value().unwrap(), a.value().unwrap())
+    }
+
+    #[test]
+    fn test_merge() {
+        let input = Expression::Column(0);
+        let values = (0..10_000)
+            .map(|i| match i % 100 {
+                0 => Field::Null,
+                _ => Field::Integer(i * 7_919 % 5_000),
+            })
+            .collect::<Vec<_>>();
+
+        // HyperLogLog registers merge exactly.
+        let aggregate = Aggregate::ApproxCountDistinct(input.clone());
+        let (single, merged) = single_and_merged(&aggregate, &values);
+        assert_eq!(merged, single);
+
+        // A t-digest's centroids depend on the order values are compressed
+        // in, so the estimates can differ slightly. Small digests are exact.
+        for fraction in [0.01, 0.5, 0.99] {
+            let aggregate = Aggregate::ApproxPercentile(input.clone(), fraction);
+            let (Field::Double(single), Field::Double(merged)) =
+                single_and_merged(&aggregate, &values)
+            else {
+                panic!("expected doubles");
+            };
+            assert!((merged - single).abs() < 50.0, "{merged} vs {single}");
+
+            let (single, merged) = single_and_merged(&aggregate, &values[..100]);
+            assert_eq!(merged, single);
+        }
+
+        // Other accumulators and mismatched states can't be merged.
+        let sum = Accumulator::new(&Aggregate::Sum(input.clone()));
+        assert!(sum.clone().merge(&sum).is_err());
+        let mut median = Accumulator::new(&Aggregate::ApproxPercentile(input.clone(), 0.5));
+        let p90 = Accumulator::new(&Aggregate::ApproxPercentile(input, 0.9));
+        assert!(median.merge(&p90).is_err());
+        assert!(median.merge(&sum).is_err());
+    }
 }
@@ -0,0 +1,65 @@
+use crate::common::Result;
+use crate::errinput;
+use crate::sql::engine::Transaction;
+use crate::sql::execution::execute::execute;
+use crate::sql::planner::{BoxedNode, Expression, Node};
+use crate::storage::page::INVALID_RID;
+use crate::storage::tuple::{Row, Rows};
+use crate::types::field::Field;
+use std::collections::HashSet;
+
+/// Executes a recursive CTE. The anchor rows are emitted first, then the
+/// recursive node is executed repeatedly with the working table named name
+/// replaced by the rows of the previous iteration, until an iteration emits no
+/// (new) rows. Since each iteration needs the transaction, the result is
+/// buffered in memory.
+pub fn recursive(
+    name: String,
+    anchor: BoxedNode,
+    recursive: BoxedNode,
+    distinct: bool,
+    limit: usize,
+    txn: &impl Transaction,
+) -> Result<Rows> {
+    let mut seen = HashSet::new();
+    let mut rows = Vec::new();
+    let mut working = collect(execute(anchor, txn)?, distinct, &mut seen)?;
+    let mut iterations = 0;
+    while !working.is_empty() {
+        if iterations == limit {
+            return errinput!("recursive query {name} exceeded {limit} iterations");
+        }
+        iterations += 1;
+
+        let values: Vec<Vec<Expression>> = working
+            .iter()
+            .map(|row| row.iter().cloned().map(Expression::Constant).collect())
+            .collect();
+        let node = recursive.inner.clone().transform(
+            &|node| match node {
+                Node::WorkingTable { name: n, .. } if n == name => Ok(Node::Values {
+                    rows: values.clone(),
+                }),
+                node => Ok(node),
+            },
+            &Ok,
+        )?;
+        rows.append(&mut working);
+        working = collect(execute(node.into(), txn)?, distinct, &mut seen)?;
+    }
+    Ok(Box::new(rows.into_iter().map(|row| Ok((INVALID_RID, row)))))
+}
+
+/// Collects rows into a vector. If distinct is true, rows that have already
+/// been seen are discarded, and the remaining rows are marked as seen.
+fn collect(rows: Rows, distinct: bool, seen: &mut HashSet<Vec<Field>>) -> Result<Vec<Row>> {
+    let mut collected = Vec::new();
+    for result in rows {
+        let (_, row) = result?;
+        if distinct && !seen.insert(row.iter().cloned().collect()) {
+            continue;
+        }
+        collected.push(row);
+    }
+    Ok(collected)
+}
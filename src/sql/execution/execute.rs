@@ -1,9 +1,12 @@
 use crate::common::Result;
+use crate::errinput;
 use crate::sql::engine::{Catalog, Transaction};
-use crate::sql::execution::{aggregate, join, source, transform};
-use crate::sql::planner::{BoxedNode, Node, Plan};
+use crate::sql::execution::{
+    aggregate, cte, join, set, source, subquery, transform, window, write,
+};
+use crate::sql::planner::{BoxedNode, Expression, Node, Plan, Returning};
 use crate::storage::page::RecordId;
-use crate::storage::tuple::Rows;
+use crate::storage::tuple::{Row, Rows};
 use crate::types::field::{Field, Label};
 
 /// Executes a query plan.
@@ -38,23 +41,53 @@ pub fn execute_plan(
                 name: schema.name().to_string(),
             }
         }
+        // Stores a view in the catalog.
+        Plan::CreateView { view, replace } => {
+            let name = view.name.clone();
+            catalog.create_view(view, replace)?;
+            ExecutionResult::CreateView { name }
+        }
+        // Stores a materialized view in the catalog, and materializes the
+        // rows emitted from the source node into its table.
+        Plan::CreateMaterializedView {
+            view,
+            source,
+            columns,
+        } => {
+            let name = view.name.clone();
+            let types = (0..columns.len()).map(|i| source.column_type(i)).collect();
+            let rows = execute(source, txn)?;
+            let (schema, rows) = write::materialize(&name, &columns, types, rows)?;
+            catalog.create_view(view, false)?;
+            catalog.create_table(schema.clone())?;
+            write::insert(txn, schema, rows)?;
+            ExecutionResult::CreateView { name }
+        }
         // Deletes the rows emitted from the source node from the given table.
         //
         // Hint: you'll need to use the `write::delete` method that you also have implement,
         // which returns the number of rows that were deleted if successful (another hint:
         // use the ? operator. Last reminder!).
-        Plan::Delete { table, source } => {
+        Plan::Delete {
+            table,
+            source,
+            returning,
+        } => {
+            let returning = resolve_returning(returning, txn)?;
+
             // Execute the source node to get the rows to be deleted
             let rows = execute(source, txn)?;
 
             // Perform the delete operation using the write::delete function
-            let deleted_count = match crate::sql::execution::write::delete(txn, table, rows) {
-                Ok(count) => count,
-                Err(e) => return Err(e),
-            };
+            let deleted = crate::sql::execution::write::delete(txn, table, rows)?;
 
             // Return the result as ExecutionResult::Delete with the count of deleted rows
-            ExecutionResult::Delete { count: deleted_count }
+            match returning {
+                Some(returning) => project_returning(deleted, returning, txn)?,
+                None => ExecutionResult::Delete {
+                    count: deleted.len() as u64,
+                },
+            }
         }
 
         // Drops the given table.
@@ -76,12 +109,66 @@ pub fn execute_plan(
                 existed,
             }
         }
+        // Drops the given view, and the table of a materialized view.
+        //
+        // Returns an error if the view is of the other kind, or does not exist
+        // unless `if_exists` is true.
+        Plan::DropView {
+            view,
+            if_exists,
+            materialized,
+        } => {
+            match catalog.get_view(&view)? {
+                Some(v) if v.materialized && !materialized => {
+                    return errinput!("{view} is a materialized view, use DROP MATERIALIZED VIEW")
+                }
+                Some(v) if !v.materialized && materialized => {
+                    return errinput!("{view} is not a materialized view, use DROP VIEW")
+                }
+                _ => {}
+            }
+            let existed = catalog.drop_view(&view, if_exists)?;
+            if existed && materialized {
+                catalog.drop_table(&view, false)?;
+            }
+            ExecutionResult::DropView {
+                name: view,
+                existed,
+            }
+        }
+        // Replaces the rows of a materialized view's table with the rows
+        // emitted from the source node. The source is executed before the
+        // existing rows are deleted, since it may read them.
+        Plan::RefreshMaterializedView { table, source } => {
+            let name = table.name().to_string();
+            let columns: Vec<_> = table.columns().iter().map(|c| c.get_name()).collect();
+            let types = table
+                .columns()
+                .iter()
+                .map(|c| Some(c.get_data_type()))
+                .collect();
+            // Materialize the new rows before deleting the old ones, such
+            // that a failed refresh leaves the view intact.
+            let rows = execute(source, txn)?;
+            let (_, rows) = write::materialize(&name, &columns, types, rows)?;
+            write::delete(txn, name.clone(), txn.scan(&name, None)?)?;
+            let count = write::insert(txn, table, rows)?.len();
+            ExecutionResult::RefreshMaterializedView {
+                name,
+                count: count as u64,
+            }
+        }
         // Inserts the rows emitted from the source node into the given table.
         //
         // Hint: you'll need to use the `write::insert` method that you have to implement,
         // which returns the record id's corresponding to the rows that were inserted into
         // the table.
-        Plan::Insert { table, source } => {
+        Plan::Insert {
+            table,
+            source,
+            returning,
+        } => {
+            let returning = resolve_returning(returning, txn)?;
             let rows = execute(source, txn)?;
 
             // Fetch the table schema using the catalog.
@@ -91,11 +178,37 @@ pub fn execute_plan(
             })?;
 
             // Use the `write::insert` function to insert the rows into the table.
-            let record_ids = crate::sql::execution::write::insert(txn, schema, rows)?;
+            let inserted = crate::sql::execution::write::insert(txn, schema, rows)?;
 
             // Return the number of rows inserted and their corresponding record IDs.
-            let count = record_ids.len() as u64;
-            ExecutionResult::Insert { count, record_ids }
+            match returning {
+                Some(returning) => project_returning(inserted, returning, txn)?,
+                None => ExecutionResult::Insert {
+                    count: inserted.len() as u64,
+                    record_ids: inserted.into_iter().map(|(id, _)| id).collect(),
+                },
+            }
+        }
+        // Inserts the rows emitted from the source node into the given table,
+        // taking the conflict action for rows that conflict with existing ones.
+        Plan::Upsert {
+            table,
+            source,
+            constraints,
+            target,
+            action,
+            returning,
+        } => {
+            let returning = resolve_returning(returning, txn)?;
+            let rows = execute(source, txn)?;
+            let affected = write::upsert(txn, table, rows, constraints, target, *action)?;
+            match returning {
+                Some(returning) => project_returning(affected, returning, txn)?,
+                None => ExecutionResult::Insert {
+                    count: affected.len() as u64,
+                    record_ids: affected.into_iter().map(|(id, _)| id).collect(),
+                },
+            }
         }
         // Obtains a `Rows` iterator of the emitted rows and the emitted rows' corresponding
         // column labels from the root node, packaging the two as an `ExecutionResult::Select`.
@@ -121,40 +234,146 @@ pub fn execute_plan(
             table,
             source,
             expressions,
+            returning,
         } => {
             let table_name = table.name();
+            let returning = resolve_returning(returning, txn)?;
+
+            // Execute uncorrelated subqueries in the SET expressions once.
+            // Correlated ones are executed per row by write::update.
+            let expressions = expressions
+                .into_iter()
+                .map(|(index, expr)| {
+                    Ok((
+                        index,
+                        expr.transform(&|expr| subquery::resolve(expr, txn), &Ok)?,
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()?;
 
             // Step 2: Execute the source node to obtain the rows to be updated.
             let rows = execute(source, txn)?;
 
             // Step 3: Fetch the schema of the table using `catalog.get_table`.
-            let schema = catalog
-                .get_table(table_name)?
-                .ok_or_else(|| crate::common::Error::InvalidInput(format!("Table {} does not exist", table_name)))?;
+            let schema = catalog.get_table(table_name)?.ok_or_else(|| {
+                crate::common::Error::InvalidInput(format!("Table {} does not exist", table_name))
+            })?;
 
             // Step 4: Use the `write::update` method to perform the update operation.
-            let updated_count = crate::sql::execution::write::update(txn, table_name.to_string(), rows, expressions)?;
+            let updated = crate::sql::execution::write::update(txn, schema, rows, expressions)?;
 
             // Step 5: Return an `ExecutionResult::Update` with the count of updated rows.
-            ExecutionResult::Update { count: updated_count }
+            match returning {
+                Some(returning) => project_returning(updated, returning, txn)?,
+                None => ExecutionResult::Update {
+                    count: updated.len() as u64,
+                },
+            }
         }
     })
 }
 
+/// Executes uncorrelated subqueries in a RETURNING clause once, before the
+/// statement modifies the table. Correlated ones are executed per row by
+/// project_returning().
+fn resolve_returning(
+    returning: Option<Returning>,
+    txn: &impl Transaction,
+) -> Result<Option<Returning>> {
+    let Some(Returning {
+        expressions,
+        labels,
+    }) = returning
+    else {
+        return Ok(None);
+    };
+    let expressions = expressions
+        .into_iter()
+        .map(|expr| expr.transform(&|expr| subquery::resolve(expr, txn), &Ok))
+        .collect::<Result<_>>()?;
+    Ok(Some(Returning {
+        expressions,
+        labels,
+    }))
+}
+
+/// Projects the rows affected by a DML statement through its RETURNING
+/// clause, returning them like a SELECT.
+fn project_returning(
+    rows: Vec<(RecordId, Row)>,
+    returning: Returning,
+    txn: &impl Transaction,
+) -> Result<ExecutionResult> {
+    let Returning {
+        expressions,
+        labels,
+    } = returning;
+    let source: Rows = Box::new(rows.into_iter().map(Ok));
+    let rows = match expressions.iter().any(|expr| expr.contains_subquery()) {
+        true => subquery::project(source, expressions, txn)?,
+        false => transform::project(source, expressions),
+    };
+    Ok(ExecutionResult::Select {
+        rows,
+        columns: labels,
+    })
+}
+
 /// Recursively executes a query plan node, returning a tuple iterator.
 ///
 /// Tuples stream through the plan node tree from the branches to the root. Nodes
 /// recursively pull input rows upwards from their child node(s), process them,
 /// and hand the resulting rows off to their parent node.
 pub fn execute(node: BoxedNode, txn: &impl Transaction) -> Result<Rows> {
-    Ok(match *node.inner {
+    // Execute uncorrelated subqueries in the node's expressions once, and
+    // substitute their results. Any remaining subqueries are correlated, and
+    // are executed per row for filters and projections.
+    let correlated = std::cell::Cell::new(false);
+    let resolve = |expr| {
+        let expr = subquery::resolve(expr, txn)?;
+        if matches!(
+            expr,
+            Expression::Subquery(_) | Expression::Exists(_) | Expression::InSubquery(_, _)
+        ) {
+            correlated.set(true);
+        }
+        Ok(expr)
+    };
+    let node = node.inner.transform_expressions(&resolve, &Ok)?;
+    if correlated.get() {
+        return match node {
+            Node::Filter { source, predicate } => {
+                subquery::filter(execute(source, txn)?, predicate, txn)
+            }
+            Node::Projection {
+                source,
+                expressions,
+                ..
+            } => subquery::project(execute(source, txn)?, expressions, txn),
+            _ => errinput!("correlated subqueries are only supported in WHERE, HAVING and SELECT"),
+        };
+    }
+
+    Ok(match node {
         Node::Aggregate {
             source,
             group_by,
+            grouping_sets,
             aggregates,
         } => {
             let source = execute(source, txn)?;
-            aggregate::aggregate(source, group_by, aggregates)?
+            aggregate::aggregate(source, group_by, grouping_sets, aggregates)?
+        }
+
+        Node::Distinct { source, on } => {
+            let source = execute(source, txn)?;
+            transform::distinct(source, on)
+        }
+
+        Node::Except { left, right, all } => {
+            let left = execute(left, txn)?;
+            let right = execute(right, txn)?;
+            set::except(left, right, all)?
         }
 
         Node::Filter { source, predicate } => {
@@ -206,6 +425,12 @@ pub fn execute(node: BoxedNode, txn: &impl Transaction) -> Result<Rows> {
             todo!();
         }
 
+        Node::Intersect { left, right, all } => {
+            let left = execute(left, txn)?;
+            let right = execute(right, txn)?;
+            set::intersect(left, right, all)?
+        }
+
         Node::KeyLookup {
             table: _table,
             keys: _keys,
@@ -267,11 +492,30 @@ pub fn execute(node: BoxedNode, txn: &impl Transaction) -> Result<Rows> {
             projected_rows
         }
 
+        Node::RecursiveCte {
+            name,
+            anchor,
+            recursive,
+            distinct,
+            limit,
+        } => cte::recursive(name, anchor, recursive, distinct, limit, txn)?,
+
         Node::Remap { source, targets } => {
             let source = execute(source, txn)?;
             transform::remap(source, targets)
         }
 
+        Node::SemiJoin {
+            left,
+            right,
+            predicate,
+            anti,
+        } => {
+            let left = execute(left, txn)?;
+            let right = execute(right, txn)?;
+            join::semi(left, right, predicate, anti)?
+        }
+
         Node::Scan {
             table,
             filter,
@@ -282,7 +526,24 @@ pub fn execute(node: BoxedNode, txn: &impl Transaction) -> Result<Rows> {
             rows // Directly return the `Rows` type
         }
 
+        Node::Union { left, right, all } => {
+            let left = execute(left, txn)?;
+            let right = execute(right, txn)?;
+            set::union(left, right, all)
+        }
+
         Node::Values { rows } => source::values(rows),
+
+        Node::Window { source, windows } => {
+            let source = execute(source, txn)?;
+            window::window(source, windows)?
+        }
+
+        // Working tables are replaced by RecursiveCte during execution, except
+        // in subqueries.
+        Node::WorkingTable { name, .. } => {
+            return errinput!("recursive reference to {name} can't be in a subquery")
+        }
     })
 }
 
@@ -295,6 +556,17 @@ pub enum ExecutionResult {
         name: String,
         existed: bool,
     },
+    CreateView {
+        name: String,
+    },
+    DropView {
+        name: String,
+        existed: bool,
+    },
+    RefreshMaterializedView {
+        name: String,
+        count: u64,
+    },
     Delete {
         count: u64,
     },
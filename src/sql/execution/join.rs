@@ -143,6 +143,34 @@ impl Iterator for NestedLoopIterator {
     }
 }
 
+/// Executes a semi join. Buffers the right source in memory, then emits the
+/// left rows that have a right match, i.e. a right row for which the predicate
+/// is true when evaluated against the joined left and right row. If anti is
+/// true, the left rows without a right match are emitted instead.
+pub fn semi(left: Rows, right: Rows, predicate: Option<Expression>, anti: bool) -> Result<Rows> {
+    let right: Vec<Row> = right.map_ok(|(_, row)| row).try_collect()?;
+    let matches = move |left: &Row| -> Result<bool> {
+        let Some(predicate) = &predicate else {
+            return Ok(!right.is_empty());
+        };
+        for right in &right {
+            let row = Row::from(left.iter().chain(right.iter()).collect::<Vec<&Field>>());
+            if predicate.evaluate(Some(&row))? == Field::Boolean(true) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    };
+    Ok(Box::new(left.filter_map(move |result| match result {
+        Ok((rid, row)) => match matches(&row) {
+            Ok(matched) if matched != anti => Some(Ok((rid, row))),
+            Ok(_) => None,
+            Err(err) => Some(Err(err)),
+        },
+        Err(err) => Some(Err(err)),
+    })))
+}
+
 /// Executes a hash join. This builds a hash table of rows from the right source
 /// keyed on the join value, then iterates over the left source and looks up
 /// matching rows in the hash table. If outer is true, and there is no match
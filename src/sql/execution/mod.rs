@@ -1,9 +1,14 @@
 //! SQL Query Execution Engine and related machinery.
 mod aggregate;
+mod cte;
 mod execute;
 mod join;
+mod set;
+pub mod sketch;
 mod source;
+mod subquery;
 mod transform;
+mod window;
 mod write;
 
 pub use execute::{execute_plan, ExecutionResult};
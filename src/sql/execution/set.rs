@@ -0,0 +1,76 @@
+use crate::common::Result;
+use crate::storage::tuple::Rows;
+use crate::types::field::Field;
+use itertools::Itertools as _;
+use std::collections::{HashMap, HashSet};
+
+/// Executes a UNION by chaining the left and right rows. If all is false,
+/// duplicate rows are discarded, keeping the emitted rows in memory.
+pub fn union(left: Rows, right: Rows, all: bool) -> Rows {
+    let rows = left.chain(right);
+    if all {
+        return Box::new(rows);
+    }
+    let mut seen = HashSet::new();
+    Box::new(rows.filter(move |result| match result {
+        Ok((_, row)) => seen.insert(row.iter().cloned().collect::<Vec<Field>>()),
+        Err(_) => true,
+    }))
+}
+
+/// Executes an INTERSECT by counting the right rows in a hashmap, and emitting
+/// the left rows that are found in it. If all is true, each right row matches
+/// a single left row, otherwise a row is only emitted once.
+pub fn intersect(left: Rows, right: Rows, all: bool) -> Result<Rows> {
+    let mut counts = count(right)?;
+    Ok(Box::new(left.filter(move |result| {
+        let Ok((_, row)) = result else {
+            return true;
+        };
+        let key: Vec<Field> = row.iter().cloned().collect();
+        match counts.get_mut(&key) {
+            Some(count) if *count > 0 => {
+                *count = if all { *count - 1 } else { 0 };
+                true
+            }
+            Some(_) | None => false,
+        }
+    })))
+}
+
+/// Executes an EXCEPT by counting the right rows in a hashmap, and emitting
+/// the left rows that aren't found in it. If all is true, each right row
+/// discards a single left row, otherwise a row is only emitted once.
+pub fn except(left: Rows, right: Rows, all: bool) -> Result<Rows> {
+    let mut counts = count(right)?;
+    Ok(Box::new(left.filter(move |result| {
+        let Ok((_, row)) = result else {
+            return true;
+        };
+        let key: Vec<Field> = row.iter().cloned().collect();
+        match counts.get_mut(&key) {
+            Some(count) if all && *count > 0 => {
+                *count -= 1;
+                false
+            }
+            Some(_) if all => true,
+            Some(_) => false,
+            None => {
+                // Discard later duplicates of the row.
+                if !all {
+                    counts.insert(key, 0);
+                }
+                true
+            }
+        }
+    })))
+}
+
+/// Counts the number of occurrences of each row.
+fn count(rows: Rows) -> Result<HashMap<Vec<Field>, usize>> {
+    let mut counts = HashMap::new();
+    for row in rows.map_ok(|(_, row)| row.iter().cloned().collect::<Vec<Field>>()) {
+        *counts.entry(row?).or_default() += 1;
+    }
+    Ok(counts)
+}
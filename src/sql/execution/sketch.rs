@@ -0,0 +1,273 @@
+//! Probabilistic sketches for approximate aggregates. These summarize any
+//! number of values in bounded memory, and sketches of disjoint inputs can be
+//! merged, e.g. to combine partial aggregates.
+
+use std::hash::{DefaultHasher, Hash, Hasher as _};
+
+/// A HyperLogLog sketch, which estimates the number of distinct values.
+///
+/// Each value is hashed, and the first PRECISION bits of the hash select a
+/// register. The register keeps the maximum number of leading zeros (plus one)
+/// seen in the remaining bits: observing n leading zeros takes about 2^n
+/// distinct values. The estimate is the normalized harmonic mean of the
+/// registers, with a linear counting correction for small cardinalities. The
+/// standard error is about 1.04 / sqrt(2^PRECISION), i.e. 1.6%.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// The number of hash bits used to select a register.
+    const PRECISION: u32 = 12;
+
+    /// Creates a new, empty sketch.
+    pub fn new() -> Self {
+        Self {
+            registers: vec![0; 1 << Self::PRECISION],
+        }
+    }
+
+    /// Adds a value to the sketch.
+    pub fn add(&mut self, value: &impl Hash) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+        let index = (hash >> (64 - Self::PRECISION)) as usize;
+        // Set a sentinel bit, such that the rank is bounded when the
+        // remaining bits are all zero.
+        let rest = hash << Self::PRECISION | 1 << (Self::PRECISION - 1);
+        let rank = rest.leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    /// Merges another sketch into this one. The result is the sketch of the
+    /// union of their values.
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        for (register, other) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(*other);
+        }
+    }
+
+    /// Returns the estimated number of distinct values.
+    pub fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|r| 2f64.powi(-(*r as i32))).sum();
+        let estimate = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|r| **r == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            return (m * (m / zeros as f64).ln()).round() as u64;
+        }
+        estimate.round() as u64
+    }
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A merging t-digest, which estimates quantiles.
+///
+/// Values are summarized as centroids (a mean and a weight), sorted by mean.
+/// Centroids near the median can absorb many values, while centroids near the
+/// extremes are kept small, such that tail quantiles are accurate. The size is
+/// limited by the scale function k(q) = COMPRESSION / 2π * asin(2q - 1): a
+/// centroid spanning quantiles q1 to q2 must have k(q2) - k(q1) <= 1, which
+/// bounds the number of centroids to about COMPRESSION. New values are
+/// buffered, and merged into the centroids when the buffer fills up.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TDigest {
+    centroids: Vec<Centroid>,
+    buffer: Vec<Centroid>,
+    min: f64,
+    max: f64,
+}
+
+/// A t-digest centroid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+impl TDigest {
+    /// The compression factor, trading accuracy for size.
+    const COMPRESSION: f64 = 100.0;
+    /// The number of buffered values before they're merged.
+    const BUFFER_SIZE: usize = 500;
+
+    /// Creates a new, empty digest.
+    pub fn new() -> Self {
+        Self {
+            centroids: Vec::new(),
+            buffer: Vec::new(),
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Adds a value to the digest. NaN values are ignored.
+    pub fn add(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.buffer.push(Centroid {
+            mean: value,
+            weight: 1.0,
+        });
+        if self.buffer.len() >= Self::BUFFER_SIZE {
+            self.compress();
+        }
+    }
+
+    /// Merges another digest into this one. The result is the digest of the
+    /// union of their values.
+    pub fn merge(&mut self, other: &TDigest) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.buffer.extend(&other.centroids);
+        self.buffer.extend(&other.buffer);
+        if self.buffer.len() >= Self::BUFFER_SIZE {
+            self.compress();
+        }
+    }
+
+    /// Merges the buffered values into the centroids.
+    fn compress(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut centroids = std::mem::take(&mut self.centroids);
+        centroids.append(&mut self.buffer);
+        centroids.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+        let total: f64 = centroids.iter().map(|c| c.weight).sum();
+        let scale = |weight: f64| {
+            let q = weight / total;
+            Self::COMPRESSION / (2.0 * std::f64::consts::PI) * (2.0 * q - 1.0).asin()
+        };
+
+        let mut centroids = centroids.into_iter();
+        let mut current = centroids.next().expect("no centroids");
+        let mut cumulative = 0.0; // the weight before the current centroid
+        for next in centroids {
+            let weight = current.weight + next.weight;
+            if scale(cumulative + weight) - scale(cumulative) <= 1.0 {
+                current.mean += (next.mean - current.mean) * next.weight / weight;
+                current.weight = weight;
+            } else {
+                cumulative += current.weight;
+                self.centroids.push(current);
+                current = next;
+            }
+        }
+        self.centroids.push(current);
+    }
+
+    /// Returns the estimated value at the given quantile (0 to 1), or None if
+    /// the digest is empty. Interpolates between centroids, which are taken
+    /// to be located at the middle of their weight.
+    pub fn quantile(&mut self, q: f64) -> Option<f64> {
+        self.compress();
+        let (first, last) = (self.centroids.first()?, self.centroids.last()?);
+        let total: f64 = self.centroids.iter().map(|c| c.weight).sum();
+        let target = q.clamp(0.0, 1.0) * total;
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t.clamp(0.0, 1.0);
+
+        if target <= first.weight / 2.0 {
+            return Some(lerp(self.min, first.mean, target / (first.weight / 2.0)));
+        }
+        if target >= total - last.weight / 2.0 {
+            let t = (target - (total - last.weight / 2.0)) / (last.weight / 2.0);
+            return Some(lerp(last.mean, self.max, t));
+        }
+        let mut center = first.weight / 2.0;
+        for (a, b) in self.centroids.iter().zip(&self.centroids[1..]) {
+            let next = center + (a.weight + b.weight) / 2.0;
+            if target <= next {
+                return Some(lerp(a.mean, b.mean, (target - center) / (next - center)));
+            }
+            center = next;
+        }
+        Some(last.mean)
+    }
+}
+
+impl Default for TDigest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hyperloglog() {
+        let mut hll = HyperLogLog::new();
+        assert_eq!(hll.estimate(), 0);
+        for i in 0..3 {
+            hll.add(&i);
+            hll.add(&i);
+        }
+        assert_eq!(hll.estimate(), 3);
+
+        let (mut a, mut b) = (HyperLogLog::new(), HyperLogLog::new());
+        for i in 0..100_000 {
+            a.add(&i);
+            b.add(&(i + 50_000));
+        }
+        let estimate = a.estimate() as f64;
+        assert!(
+            (estimate - 100_000.0).abs() < 5_000.0,
+            "estimate {estimate}"
+        );
+
+        // Merging yields the sketch of the union.
+        a.merge(&b);
+        let estimate = a.estimate() as f64;
+        assert!(
+            (estimate - 150_000.0).abs() < 7_500.0,
+            "estimate {estimate}"
+        );
+    }
+
+    #[test]
+    fn test_tdigest() {
+        let mut digest = TDigest::new();
+        assert_eq!(digest.quantile(0.5), None);
+        for i in 1..=5 {
+            digest.add(i as f64);
+        }
+        assert_eq!(digest.quantile(0.0), Some(1.0));
+        assert_eq!(digest.quantile(0.5), Some(3.0));
+        assert_eq!(digest.quantile(1.0), Some(5.0));
+
+        // Add values in a scrambled order.
+        let (mut a, mut b) = (TDigest::new(), TDigest::new());
+        for i in 0..100_000u64 {
+            let value = (i * 7_919 % 100_000) as f64;
+            match i % 2 {
+                0 => a.add(value),
+                _ => b.add(value),
+            }
+        }
+        a.merge(&b);
+        a.compress();
+        assert!(a.centroids.len() <= 100, "{} centroids", a.centroids.len());
+        for (q, expect) in [
+            (0.001, 100.0),
+            (0.1, 10_000.0),
+            (0.5, 50_000.0),
+            (0.99, 99_000.0),
+        ] {
+            let value = a.quantile(q).unwrap();
+            assert!((value - expect).abs() < 500.0, "quantile {q} is {value}");
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::common::Result;
+use crate::errinput;
+use crate::sql::engine::Transaction;
+use crate::sql::execution::execute::execute;
+use crate::sql::planner::{BoxedNode, Expression};
+use crate::storage::tuple::{Row, Rows};
+use crate::types::field::Field;
+use itertools::Itertools as _;
+
+/// Resolves an uncorrelated subquery expression by executing the subquery
+/// and replacing it with its result. Other expressions, including correlated
+/// subqueries, are returned as is. Does not recurse into child expressions,
+/// use with Expression::transform().
+pub fn resolve(expr: Expression, txn: &impl Transaction) -> Result<Expression> {
+    match &expr {
+        Expression::Subquery(node) | Expression::Exists(node) | Expression::InSubquery(_, node)
+            if !node.is_correlated() =>
+        {
+            execute_subquery(expr, txn)
+        }
+        _ => Ok(expr),
+    }
+}
+
+/// Evaluates an expression containing correlated subqueries against a row.
+/// The row values are bound to the subqueries' outer column references, and
+/// the subqueries are executed for every call.
+pub fn evaluate(expr: &Expression, row: &Row, txn: &impl Transaction) -> Result<Field> {
+    let resolve = |expr| match expr {
+        Expression::Subquery(node) => {
+            execute_subquery(Expression::Subquery(bind_outer(node, row, 0)?), txn)
+        }
+        Expression::Exists(node) => {
+            execute_subquery(Expression::Exists(bind_outer(node, row, 0)?), txn)
+        }
+        Expression::InSubquery(lhs, node) => {
+            execute_subquery(Expression::InSubquery(lhs, bind_outer(node, row, 0)?), txn)
+        }
+        expr => Ok(expr),
+    };
+    expr.clone().transform(&resolve, &Ok)?.evaluate(Some(row))
+}
+
+/// Filters rows with a predicate containing correlated subqueries (i.e.
+/// WHERE or HAVING). Since subqueries are executed per row, which requires
+/// the transaction, the result is buffered in memory.
+pub fn filter(source: Rows, predicate: Expression, txn: &impl Transaction) -> Result<Rows> {
+    let mut rows = Vec::new();
+    for result in source {
+        let (record_id, row) = result?;
+        match evaluate(&predicate, &row, txn)? {
+            Field::Boolean(true) => rows.push((record_id, row)),
+            Field::Boolean(false) | Field::Null => {}
+            value => return errinput!("filter returned {value}, expected boolean"),
+        }
+    }
+    Ok(Box::new(rows.into_iter().map(Ok)))
+}
+
+/// Projects rows with expressions containing correlated subqueries (i.e.
+/// SELECT). Like filter(), the result is buffered in memory.
+pub fn project(source: Rows, expressions: Vec<Expression>, txn: &impl Transaction) -> Result<Rows> {
+    let mut rows = Vec::new();
+    for result in source {
+        let (record_id, row) = result?;
+        let values: Vec<Field> = expressions
+            .iter()
+            .map(|expr| evaluate(expr, &row, txn))
+            .try_collect()?;
+        rows.push((record_id, Row::from(values)));
+    }
+    Ok(Box::new(rows.into_iter().map(Ok)))
+}
+
+/// Executes an uncorrelated subquery expression, returning its result as a
+/// constant expression. IN subqueries return an IN list of the subquery
+/// values, applying the operand's collation to them.
+fn execute_subquery(expr: Expression, txn: &impl Transaction) -> Result<Expression> {
+    Ok(match expr {
+        Expression::Subquery(node) => {
+            let mut rows = execute(node, txn)?;
+            let value = match rows.next().transpose()? {
+                Some((_, row)) => row.get_field(0)?,
+                None => Field::Null,
+            };
+            if rows.next().transpose()?.is_some() {
+                return errinput!("subquery returned more than one row");
+            }
+            Expression::Constant(value)
+        }
+        Expression::Exists(node) => {
+            let exists = execute(node, txn)?.next().transpose()?.is_some();
+            Expression::Constant(Field::Boolean(exists))
+        }
+        Expression::InSubquery(expr, node) => {
+            let list = execute(node, txn)?
+                .map(|result| {
+                    let value = Expression::Constant(result?.1.get_field(0)?);
+                    Ok(match expr.as_ref() {
+                        Expression::Collate(_, collation) => {
+                            Expression::Collate(value.into(), *collation)
+                        }
+                        _ => value,
+                    })
+                })
+                .collect::<Result<_>>()?;
+            Expression::In(expr, list)
+        }
+        expr => expr,
+    })
+}
+
+/// Binds a row's values to a subquery's outer column references at the given
+/// depth, including in nested subqueries (at increasing depths).
+fn bind_outer(node: BoxedNode, row: &Row, depth: usize) -> Result<BoxedNode> {
+    let bind = |expr| {
+        Ok(match expr {
+            Expression::OuterColumn(d, index) if d == depth => {
+                Expression::Constant(row.get_field(index)?)
+            }
+            Expression::Subquery(node) => Expression::Subquery(bind_outer(node, row, depth + 1)?),
+            Expression::Exists(node) => Expression::Exists(bind_outer(node, row, depth + 1)?),
+            Expression::InSubquery(lhs, node) => {
+                Expression::InSubquery(lhs, bind_outer(node, row, depth + 1)?)
+            }
+            expr => expr,
+        })
+    };
+    let node = node
+        .inner
+        .transform(&|node| node.transform_expressions(&bind, &Ok), &Ok)?;
+    Ok(node.into())
+}
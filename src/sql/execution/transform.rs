@@ -4,6 +4,27 @@ use crate::sql::planner::Expression;
 use crate::storage::tuple::{Row, Rows};
 use crate::types::field::Field;
 use itertools::{izip, Itertools as _};
+use std::collections::HashSet;
+
+/// Discards rows whose values for the given expressions were already seen in
+/// a previous row (i.e. DISTINCT and DISTINCT ON).
+pub fn distinct(source: Rows, on: Vec<Expression>) -> Rows {
+    let mut seen: HashSet<Vec<Field>> = HashSet::new();
+    Box::new(source.filter_map(move |result| {
+        let (rid, row) = match result {
+            Ok(row) => row,
+            Err(err) => return Some(Err(err)),
+        };
+        match on
+            .iter()
+            .map(|expr| expr.evaluate(Some(&row)))
+            .try_collect()
+        {
+            Ok(values) => seen.insert(values).then_some(Ok((rid, row))),
+            Err(err) => Some(Err(err)),
+        }
+    }))
+}
 
 /// Filters the input rows (i.e. WHERE).
 ///
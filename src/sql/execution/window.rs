@@ -0,0 +1,293 @@
+use crate::common::{Error, Result};
+use crate::sql::execution::aggregate::Accumulator;
+use crate::sql::planner::{
+    Aggregate, Direction, Expression, FrameBound, FrameUnits, Window, WindowFunction,
+};
+use crate::storage::tuple::{Row, Rows};
+use crate::types::field::Field;
+use itertools::Itertools as _;
+use std::cmp::Ordering;
+
+/// Computes window functions for the source rows, emitting each source row
+/// followed by its window function values, in the source order. For each
+/// window, the rows are sorted by their partition and order keys and split
+/// into partitions, which the function is computed over. Buffers the entire
+/// row set in memory.
+pub fn window(source: Rows, windows: Vec<Window>) -> Result<Rows> {
+    let rows: Vec<_> = source.try_collect()?;
+    let mut values = vec![Vec::with_capacity(windows.len()); rows.len()];
+    for window in &windows {
+        // Evaluate the partition and order keys of each row, and sort the row
+        // indexes by them. The sort is stable, so peers retain their order.
+        let mut keys = Vec::with_capacity(rows.len());
+        for (_, row) in &rows {
+            let partition: Vec<Field> = window
+                .partition_by
+                .iter()
+                .map(|expr| expr.evaluate(Some(row)))
+                .try_collect()?;
+            let order: Vec<Field> = window
+                .order_by
+                .iter()
+                .map(|(expr, _)| expr.evaluate(Some(row)))
+                .try_collect()?;
+            keys.push((partition, order));
+        }
+        let mut indexes = (0..rows.len()).collect_vec();
+        indexes.sort_by(|&a, &b| {
+            let (a, b) = (&keys[a], &keys[b]);
+            a.0.cmp(&b.0)
+                .then_with(|| compare_order(&a.1, &b.1, &window.order_by))
+        });
+
+        for indexes in indexes.chunk_by(|&a, &b| keys[a].0 == keys[b].0) {
+            let partition = Partition {
+                window,
+                rows: indexes.iter().map(|&i| &rows[i].1).collect(),
+                order: indexes.iter().map(|&i| keys[i].1.as_slice()).collect(),
+            };
+            for (&i, value) in indexes.iter().zip(partition.evaluate()?) {
+                values[i].push(value);
+            }
+        }
+    }
+
+    Ok(Box::new(rows.into_iter().zip(values).map(
+        |((rid, row), values)| Ok((rid, Row::from(row.into_iter().chain(values).collect_vec()))),
+    )))
+}
+
+/// Compares the order keys of two rows, in the given directions.
+fn compare_order(a: &[Field], b: &[Field], order_by: &[(Expression, Direction)]) -> Ordering {
+    for ((a, b), (_, direction)) in a.iter().zip(b).zip(order_by) {
+        match a.cmp(b) {
+            Ordering::Equal => {}
+            order if *direction == Direction::Descending => return order.reverse(),
+            order => return order,
+        }
+    }
+    Ordering::Equal
+}
+
+/// Compares an order key with a RANGE frame bound value. These may have
+/// different types, e.g. an INT column and a BIGINT bound, so numbers and
+/// dates/times are compared by value. NULLs sort first, as in compare_order().
+fn compare_range(lhs: &Field, rhs: &Field) -> Ordering {
+    if lhs.is_null() || rhs.is_null() {
+        return lhs.cmp(rhs);
+    }
+    if lhs.is_temporal() && rhs.is_temporal() {
+        if let Ok(ordering) = lhs.cmp_temporal(rhs) {
+            return ordering;
+        }
+    }
+    lhs.partial_cmp_numeric(rhs).unwrap_or_else(|| lhs.cmp(rhs))
+}
+
+/// A window partition, i.e. the rows with the same partition key, in order.
+struct Partition<'a> {
+    window: &'a Window,
+    rows: Vec<&'a Row>,
+    /// The order key of each row.
+    order: Vec<&'a [Field]>,
+}
+
+impl Partition<'_> {
+    /// Computes the window function for each row in the partition.
+    fn evaluate(&self) -> Result<Vec<Field>> {
+        let peers = |i: usize| {
+            compare_order(self.order[i - 1], self.order[i], &self.window.order_by).is_eq()
+        };
+        let mut values = Vec::with_capacity(self.rows.len());
+        match &self.window.function {
+            WindowFunction::RowNumber => {
+                values.extend((1..=self.rows.len()).map(|n| Field::BigInt(n as i64)));
+            }
+            WindowFunction::Rank => {
+                let mut rank = 0;
+                for i in 0..self.rows.len() {
+                    if i == 0 || !peers(i) {
+                        rank = i + 1;
+                    }
+                    values.push(Field::BigInt(rank as i64));
+                }
+            }
+            WindowFunction::DenseRank => {
+                let mut rank = 0;
+                for i in 0..self.rows.len() {
+                    if i == 0 || !peers(i) {
+                        rank += 1;
+                    }
+                    values.push(Field::BigInt(rank));
+                }
+            }
+            WindowFunction::Lag {
+                expression,
+                offset,
+                default,
+            } => {
+                for (i, row) in self.rows.iter().enumerate() {
+                    values.push(match i.checked_sub(*offset) {
+                        Some(j) => expression.evaluate(Some(self.rows[j]))?,
+                        None => default.evaluate(Some(row))?,
+                    });
+                }
+            }
+            WindowFunction::Lead {
+                expression,
+                offset,
+                default,
+            } => {
+                for (i, row) in self.rows.iter().enumerate() {
+                    values.push(match self.rows.get(i.saturating_add(*offset)) {
+                        Some(next) => expression.evaluate(Some(next))?,
+                        None => default.evaluate(Some(row))?,
+                    });
+                }
+            }
+            WindowFunction::FirstValue(expr) => {
+                for i in 0..self.rows.len() {
+                    values.push(match self.frame(i)? {
+                        (start, end) if start < end => expr.evaluate(Some(self.rows[start]))?,
+                        _ => Field::Null,
+                    });
+                }
+            }
+            WindowFunction::LastValue(expr) => {
+                for i in 0..self.rows.len() {
+                    values.push(match self.frame(i)? {
+                        (start, end) if start < end => expr.evaluate(Some(self.rows[end - 1]))?,
+                        _ => Field::Null,
+                    });
+                }
+            }
+            WindowFunction::Aggregate(aggregate) => return self.aggregate(aggregate),
+        }
+        Ok(values)
+    }
+
+    /// Computes an aggregate over each row's frame. If frames start at the
+    /// start of the partition, each frame extends the previous one, so the
+    /// accumulator is reused and only given the new rows. Otherwise, each
+    /// frame is aggregated from scratch.
+    fn aggregate(&self, aggregate: &Aggregate) -> Result<Vec<Field>> {
+        let inputs: Vec<Field> = self
+            .rows
+            .iter()
+            .map(|row| match aggregate.expression() {
+                Some(expr) => expr.evaluate(Some(row)),
+                None => Ok(Field::Null),
+            })
+            .try_collect()?;
+        let incremental = self.window.frame.start == FrameBound::UnboundedPreceding;
+        let mut accumulator = Accumulator::new(aggregate);
+        let mut added = 0;
+        let mut values = Vec::with_capacity(self.rows.len());
+        for i in 0..self.rows.len() {
+            let (start, end) = self.frame(i)?;
+            if !incremental {
+                accumulator = Accumulator::new(aggregate);
+                added = start;
+            }
+            while added < end {
+                accumulator.add(inputs[added].clone())?;
+                added += 1;
+            }
+            values.push(accumulator.clone().value()?);
+        }
+        Ok(values)
+    }
+
+    /// Returns the frame of the row at index i, as a range of row indexes.
+    /// The frame is empty if start >= end.
+    fn frame(&self, i: usize) -> Result<(usize, usize)> {
+        let frame = &self.window.frame;
+        Ok((
+            self.bound(i, &frame.start, false)?,
+            self.bound(i, &frame.end, true)?,
+        ))
+    }
+
+    /// Returns the row index of a frame bound for the row at index i. For the
+    /// end bound, this is the index after the last row in the frame.
+    fn bound(&self, i: usize, bound: &FrameBound, end: bool) -> Result<usize> {
+        let len = self.rows.len();
+        let current = i + end as usize;
+        Ok(match (self.window.frame.units, bound) {
+            (_, FrameBound::UnboundedPreceding) => 0,
+            (_, FrameBound::UnboundedFollowing) => len,
+            (FrameUnits::Rows, FrameBound::CurrentRow) => current,
+            (FrameUnits::Rows, FrameBound::Preceding(offset)) => {
+                current.saturating_sub(Self::rows_offset(offset))
+            }
+            (FrameUnits::Rows, FrameBound::Following(offset)) => {
+                current.saturating_add(Self::rows_offset(offset)).min(len)
+            }
+            // RANGE frames include the current row's peers.
+            (FrameUnits::Range, FrameBound::CurrentRow) => {
+                let order_by = &self.window.order_by;
+                let current = self.order[i];
+                self.order
+                    .partition_point(|key| match compare_order(key, current, order_by) {
+                        Ordering::Less => true,
+                        Ordering::Equal => end,
+                        Ordering::Greater => false,
+                    })
+            }
+            (FrameUnits::Range, FrameBound::Preceding(offset)) => {
+                self.range_bound(i, offset, true, end)?
+            }
+            (FrameUnits::Range, FrameBound::Following(offset)) => {
+                self.range_bound(i, offset, false, end)?
+            }
+        })
+    }
+
+    /// Returns a ROWS frame offset as a row count.
+    fn rows_offset(offset: &Field) -> usize {
+        offset
+            .as_i64()
+            .and_then(|n| usize::try_from(n).ok())
+            .unwrap_or(0)
+    }
+
+    /// Returns the row index of a RANGE offset bound for the row at index i,
+    /// by searching for the rows whose order value is within the offset of the
+    /// current row's. The planner ensures there's a single order key. A NULL
+    /// order value is only within range of other NULLs, i.e. its peers.
+    fn range_bound(&self, i: usize, offset: &Field, preceding: bool, end: bool) -> Result<usize> {
+        let value = &self.order[i][0];
+        if value.is_null() {
+            return self.bound(i, &FrameBound::CurrentRow, end);
+        }
+        // Preceding rows have lower values when ascending, higher when
+        // descending.
+        let descending = self.window.order_by[0].1 == Direction::Descending;
+        let target = match preceding != descending {
+            true => value.checked_sub(offset),
+            false => value.checked_add(offset),
+        };
+        // If the target is out of range for the order type, it's beyond every
+        // value in the partition, so the bound is the partition's edge.
+        let out_of_range = |err: &Error| match err {
+            Error::OverflowError => true,
+            _ => value.is_temporal() && matches!(offset, Field::Interval(_)),
+        };
+        let target = match target {
+            Ok(target) => target,
+            Err(err) if out_of_range(&err) => {
+                return Ok(if preceding { 0 } else { self.order.len() })
+            }
+            Err(err) => return Err(err),
+        };
+        // Returns true if a sorts before b.
+        let before = |a: &Field, b: &Field| match descending {
+            false => compare_range(a, b).is_lt(),
+            true => compare_range(a, b).is_gt(),
+        };
+        Ok(match end {
+            false => self.order.partition_point(|key| before(&key[0], &target)),
+            true => self.order.partition_point(|key| !before(&target, &key[0])),
+        })
+    }
+}
@@ -1,28 +1,32 @@
 use crate::common::Result;
+use crate::errinput;
 use crate::sql::engine::Transaction;
-use crate::sql::planner::Expression;
+use crate::sql::execution::subquery;
+use crate::sql::planner::{ConflictAction, Expression};
 use crate::storage::page::RecordId;
-use crate::storage::tuple::Rows;
-use crate::types::Table;
+use crate::storage::tuple::{Row, Rows};
+use crate::types::field::Field;
+use crate::types::{Column, DataType, Table};
+use std::collections::{HashMap, HashSet};
 
 /// Deletes rows, taking primary keys from the source (i.e. DELETE) using the
-/// primary_key column index. Returns the number of rows deleted.
-pub fn delete(txn: &impl Transaction, table: String, source: Rows) -> Result<u64> {
-    let mut count = 0;
+/// primary_key column index. Returns the deleted rows.
+pub fn delete(txn: &impl Transaction, table: String, source: Rows) -> Result<Vec<(RecordId, Row)>> {
+    let mut deleted = Vec::new();
 
     for result in source {
-        let (record_id, _) = result?; // Unwrap the Result to get (RecordId, Row)
-        txn.delete(&table, &[record_id])?;
-        count += 1;
+        let (record_id, row) = result?; // Unwrap the Result to get (RecordId, Row)
+        txn.delete(&table, std::slice::from_ref(&record_id))?;
+        deleted.push((record_id, row));
     }
 
-    Ok(count)
+    Ok(deleted)
 }
 
 /// Inserts rows into a table (i.e. INSERT) from the given source.
-/// Returns the record IDs corresponding to the rows inserted into the table.
-pub fn insert(txn: &impl Transaction, table: Table, source: Rows) -> Result<Vec<RecordId>> {
-    let mut record_ids = Vec::new();
+/// Returns the inserted rows along with their record IDs.
+pub fn insert(txn: &impl Transaction, table: Table, source: Rows) -> Result<Vec<(RecordId, Row)>> {
+    let mut inserted = Vec::new();
 
     // Store the table name to avoid multiple calls and moving issues
     let table_name = table.name().clone();
@@ -30,20 +34,205 @@ pub fn insert(txn: &impl Transaction, table: Table, source: Rows) -> Result<Vec<
     // Insert each row into the table
     for result in source {
         let (_, row) = result?; // Unwrap each row from the Result
+
+        let row = coerce_row(&table, row)?;
         let tuple = row.to_tuple(&table)?; // Convert row to tuple based on schema
 
         // Insert the tuple into the transaction and retrieve the record IDs
-        let inserted_ids = txn.insert(&table_name, vec![row])?; // Directly pass `row`
+        let inserted_ids = txn.insert(&table_name, vec![row.clone()])?;
 
-        // Add the first record ID to the list of record_ids
-        record_ids.push(inserted_ids[0].clone());
+        // Add the first record ID to the list of inserted rows
+        inserted.push((inserted_ids[0].clone(), row));
     }
 
-    Ok(record_ids)
+    Ok(inserted)
+}
+
+/// Materializes the rows of a materialized view, i.e. collects them up front.
+/// Returns them along with the schema of a table with the given name and
+/// column names to store them in. Column types are given by the source where
+/// known, otherwise they're the type of the column's first non-NULL value, or
+/// TEXT.
+///
+/// The rows are coerced to the column types up front, such that a refresh
+/// fails before replacing the view's existing rows.
+pub fn materialize(
+    name: &str,
+    columns: &[String],
+    types: Vec<Option<DataType>>,
+    source: Rows,
+) -> Result<(Table, Rows)> {
+    let rows: Vec<(RecordId, Row)> = source.collect::<Result<_>>()?;
+    let mut table = Table::new(name);
+    for (i, (column, data_type)) in columns.iter().zip(types).enumerate() {
+        let mut values = rows.iter().filter_map(|(_, row)| row.iter().nth(i));
+        let data_type = data_type
+            .filter(|data_type| *data_type != DataType::Invalid)
+            .or_else(|| values.find(|value| !value.is_null()).map(Field::get_type))
+            .unwrap_or(DataType::Text);
+        table.add_column(&Column::new(column, data_type, false, None, None));
+    }
+    let rows = rows
+        .into_iter()
+        .map(|(record_id, row)| Ok((record_id, coerce_row(&table, row)?)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok((table, Box::new(rows.into_iter().map(Ok))))
+}
+
+/// Coerces row values to the table's column types, e.g. INT to BIGINT or TEXT
+/// to DATE.
+fn coerce_row(table: &Table, row: Row) -> Result<Row> {
+    Ok(row
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| coerce_value(table, index, value))
+        .collect::<Result<Vec<_>>>()?
+        .into())
+}
+
+/// Coerces a value to the type of the table column at the given index.
+fn coerce_value(table: &Table, index: usize, value: Field) -> Result<Field> {
+    value.coerce(table.get_column(index).get_data_type())
+}
+
+/// Inserts rows into a table from the given source, handling conflicts with
+/// existing rows on the target constraint, or any constraint if None (i.e.
+/// INSERT ... ON CONFLICT). Returns the inserted and updated rows along with
+/// their record IDs.
+///
+/// The existing rows are indexed by constraint key up front, and the indexes
+/// are kept up to date as rows are inserted and updated. Keys containing NULL
+/// never conflict. Other conflicts are errors, and so is affecting a row twice
+/// with DO UPDATE, since the result would depend on the source row order.
+pub fn upsert(
+    txn: &impl Transaction,
+    table: Table,
+    source: Rows,
+    constraints: Vec<Vec<usize>>,
+    target: Option<usize>,
+    action: ConflictAction,
+) -> Result<Vec<(RecordId, Row)>> {
+    let mut indexes: Vec<HashMap<Vec<Field>, RecordId>> = vec![HashMap::new(); constraints.len()];
+    let mut rows = HashMap::new();
+    for result in txn.scan(table.name(), None)? {
+        let (record_id, row) = result?;
+        for (constraint, index) in constraints.iter().zip(&mut indexes) {
+            if let Some(key) = constraint_key(constraint, &row)? {
+                index.insert(key, record_id.clone());
+            }
+        }
+        rows.insert(record_id, row);
+    }
+
+    let mut affected_rows = Vec::new();
+    let mut affected = HashSet::new();
+    for result in source {
+        let (_, row) = result?;
+        let row = coerce_row(&table, row)?;
+
+        // Find a conflict on the target. Conflicts on other constraints are
+        // only errors if the row would be inserted.
+        let (mut conflict, mut other) = (None, false);
+        for (i, (constraint, index)) in constraints.iter().zip(&indexes).enumerate() {
+            let Some(record_id) = constraint_key(constraint, &row)?.and_then(|k| index.get(&k))
+            else {
+                continue;
+            };
+            if target.is_none_or(|target| target == i) {
+                conflict = Some(record_id.clone());
+                break;
+            }
+            other = true;
+        }
+        if conflict.is_none() && other {
+            return errinput!("INSERT conflicts with an existing row");
+        }
+
+        // Insert the row if there's no conflict.
+        let Some(record_id) = conflict else {
+            let record_id = txn.insert(table.name(), vec![row.clone()])?.remove(0);
+            for (constraint, index) in constraints.iter().zip(&mut indexes) {
+                if let Some(key) = constraint_key(constraint, &row)? {
+                    index.insert(key, record_id.clone());
+                }
+            }
+            rows.insert(record_id.clone(), row.clone());
+            affected.insert(record_id.clone());
+            affected_rows.push((record_id, row));
+            continue;
+        };
+
+        let ConflictAction::Update {
+            expressions,
+            predicate,
+        } = &action
+        else {
+            continue;
+        };
+        if !affected.insert(record_id.clone()) {
+            return errinput!("ON CONFLICT DO UPDATE can't affect a row twice");
+        }
+
+        // Evaluate the expressions on the existing row followed by the
+        // inserted (excluded) row.
+        let existing = rows[&record_id].clone();
+        let input: Row = existing.iter().chain(row.iter()).collect::<Vec<_>>().into();
+        let evaluate = |expr: &Expression| match expr.contains_subquery() {
+            true => subquery::evaluate(expr, &input, txn),
+            false => expr.evaluate(Some(&input)),
+        };
+        if let Some(predicate) = predicate {
+            match evaluate(predicate)? {
+                Field::Boolean(true) => {}
+                Field::Boolean(false) | Field::Null => continue,
+                value => {
+                    return errinput!("ON CONFLICT predicate returned {value}, expected boolean")
+                }
+            }
+        }
+        let mut updated = existing.clone();
+        for (index, expr) in expressions {
+            updated.update_field(*index, coerce_value(&table, *index, evaluate(expr)?)?)?;
+        }
+
+        // Update the indexes. The new keys must not conflict with other rows.
+        for (constraint, index) in constraints.iter().zip(&mut indexes) {
+            let old = constraint_key(constraint, &existing)?;
+            let new = constraint_key(constraint, &updated)?;
+            if old == new {
+                continue;
+            }
+            if let Some(new) = new {
+                if index.contains_key(&new) {
+                    return errinput!("ON CONFLICT DO UPDATE conflicts with another row");
+                }
+                index.insert(new, record_id.clone());
+            }
+            if let Some(old) = old {
+                index.remove(&old);
+            }
+        }
+
+        txn.update(table.name(), [(record_id.clone(), updated.clone())].into())?;
+        rows.insert(record_id.clone(), updated.clone());
+        affected_rows.push((record_id, updated));
+    }
+
+    Ok(affected_rows)
+}
+
+/// Returns a row's key for the given constraint columns, or None if it
+/// contains a NULL.
+fn constraint_key(constraint: &[usize], row: &Row) -> Result<Option<Vec<Field>>> {
+    let key = constraint
+        .iter()
+        .map(|index| row.get_field(*index))
+        .collect::<Result<Vec<_>>>()?;
+    Ok((!key.contains(&Field::Null)).then_some(key))
 }
 
-/// Updates rows passed in from the source (i.e. UPDATE). Returns the number of
-/// rows updated.
+/// Updates rows passed in from the source (i.e. UPDATE). Returns the updated
+/// rows.
 ///
 /// Hint: `<T,E> Option<Result<T,E>>::transpose(self) -> Result<Option<T>, E>` and
 /// the `?` operator might be useful here. An example of `transpose` from the docs:
@@ -57,25 +246,29 @@ pub fn insert(txn: &impl Transaction, table: Table, source: Rows) -> Result<Vec<
 /// ```
 pub fn update(
     txn: &impl Transaction,
-    table: String,
+    table: Table,
     mut source: Rows,
     expressions: Vec<(usize, Expression)>,
-) -> Result<u64> {
-    let mut count = 0;
+) -> Result<Vec<(RecordId, Row)>> {
+    let mut updated = Vec::new();
 
     for result in source {
         let (record_id, mut row) = result?;
 
         // Apply each expression to the specified column index
         for (index, expr) in &expressions {
-            let value = expr.evaluate(Some(&row))?;
+            let value = match expr.contains_subquery() {
+                true => subquery::evaluate(expr, &row, txn)?,
+                false => expr.evaluate(Some(&row))?,
+            };
+            let value = coerce_value(&table, *index, value)?;
             row.update_field(*index, value)?; // Use `update_field` to modify the field
         }
 
         // Update the row in the transaction
-        txn.update(&table, [(record_id, row)].iter().cloned().collect())?;
-        count += 1; // Increment the count of updated rows
+        txn.update(table.name(), [(record_id.clone(), row.clone())].into())?;
+        updated.push((record_id, row));
     }
 
-    Ok(count) // Return the total count of updated rows
+    Ok(updated) // Return the updated rows
 }
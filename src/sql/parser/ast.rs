@@ -1,10 +1,15 @@
+use super::lexer::is_ident;
+use super::Position;
+use crate::types::bytes;
+use crate::types::collation::Collation;
+use crate::types::temporal::{self, Interval};
 use crate::types::DataType;
 use std::collections::BTreeMap;
 
 /// Root node of the abstract syntax tree built from a
 /// SQL query by the parser. It is transformed by the
 /// planner into a tree of query execution plan nodes.
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Statement {
     /// Begin a new transaction.
     Begin { read_only: bool, as_of: Option<u64> },
@@ -18,40 +23,101 @@ pub enum Statement {
     CreateTable { name: String, columns: Vec<Column> },
     /// Drop a table.
     DropTable { name: String, if_exists: bool },
+    /// Create a view, i.e. a named query. A materialized view stores the
+    /// query's rows in a table, until they're refreshed.
+    CreateView {
+        name: String,
+        query: Box<Statement>,
+        replace: bool, // OR REPLACE
+        materialized: bool,
+    },
+    /// Drop a view.
+    DropView {
+        name: String,
+        if_exists: bool,
+        materialized: bool,
+    },
+    /// Recompute the rows of a materialized view.
+    RefreshMaterializedView { name: String },
     /// Delete matching rows.
     Delete {
         table: String,
         r#where: Option<Expression>,
+        returning: Vec<(Expression, Option<String>)>, // RETURNING clause, if any
     },
-    /// Insert new rows into a table.
+    /// Insert new rows into a table. Omitted columns use their default value.
     Insert {
         table: String,
-        values: Vec<Vec<Expression>>, // rows to insert
+        columns: Option<Vec<String>>, // explicit column list
+        source: InsertSource,
+        on_conflict: Option<OnConflict>,
+        returning: Vec<(Expression, Option<String>)>, // RETURNING clause, if any
     },
     /// Update rows in a table.
     Update {
         table: String,
         set: BTreeMap<String, Option<Expression>>, // column → value, None for default value
         r#where: Option<Expression>,
+        returning: Vec<(Expression, Option<String>)>, // RETURNING clause, if any
     },
     /// Select matching rows.
     Select {
+        /// SELECT DISTINCT, or DISTINCT ON if expressions are given.
+        distinct: Option<Vec<Expression>>,
         select: Vec<(Expression, Option<String>)>, // optional column aliases
         from: Vec<From>,
         r#where: Option<Expression>,
-        group_by: Vec<Expression>,
+        group_by: Vec<GroupBy>,
         having: Option<Expression>,
         order_by: Vec<(Expression, Direction)>,
         offset: Option<Expression>,
         limit: Option<Expression>,
     },
+    /// A set operation combining the rows of two queries, with optional
+    /// ORDER BY, LIMIT and OFFSET clauses for the combined rows.
+    SetOperation {
+        operator: SetOperator,
+        all: bool,
+        left: Box<Statement>,
+        right: Box<Statement>,
+        order_by: Vec<(Expression, Direction)>,
+        offset: Option<Expression>,
+        limit: Option<Expression>,
+    },
+    /// A query with common table expressions (WITH). Each expression can be
+    /// referenced by name in FROM clauses of later expressions and the query,
+    /// including their subqueries.
+    With {
+        recursive: bool,
+        ctes: Vec<CommonTableExpression>,
+        query: Box<Statement>,
+    },
+    /// Prepare a statement under the given name, with optional parameter
+    /// types (by position).
+    Prepare {
+        name: String,
+        types: Vec<DataType>,
+        statement: Box<Statement>,
+    },
+    /// Execute a prepared statement with the given parameter values.
+    Execute {
+        name: String,
+        parameters: Vec<Expression>,
+    },
+    /// Deallocate a prepared statement, or all of them if None.
+    Deallocate { name: Option<String> },
 }
 
 /// A FROM item.
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum From {
     /// A table.
     Table { name: String, alias: Option<String> },
+    /// A derived table, i.e. a SELECT subquery with an alias.
+    Subquery {
+        query: Box<Statement>,
+        alias: String,
+    },
     /// A join of two or more tables (may be nested).
     Join {
         left: Box<From>,
@@ -61,21 +127,92 @@ pub enum From {
     },
 }
 
+/// The rows of an INSERT.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum InsertSource {
+    /// VALUES rows.
+    Values(Vec<Vec<Expression>>),
+    /// A query, i.e. INSERT ... SELECT.
+    Query(Box<Statement>),
+}
+
+/// An ON CONFLICT clause of an INSERT, i.e. an upsert.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct OnConflict {
+    /// The conflict target columns. Empty if not given.
+    pub columns: Vec<String>,
+    pub action: ConflictAction,
+}
+
+/// The action to take when an inserted row conflicts with an existing row.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ConflictAction {
+    /// DO NOTHING: skip the inserted row.
+    Nothing,
+    /// DO UPDATE: update the existing row instead. The expressions can
+    /// reference the inserted row via the table name "excluded".
+    Update {
+        set: BTreeMap<String, Option<Expression>>, // column → value, None for default value
+        r#where: Option<Expression>,
+    },
+}
+
+/// A common table expression, i.e. a named query in a WITH clause.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct CommonTableExpression {
+    pub name: String,
+    /// Column names, overriding the query's. Empty if not given.
+    pub columns: Vec<String>,
+    /// The query. In WITH RECURSIVE, a UNION query whose right-hand side
+    /// references the expression itself is recursive.
+    pub query: Box<Statement>,
+}
+
+/// Set operators.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SetOperator {
+    Except,
+    Intersect,
+    Union,
+}
+
+impl SetOperator {
+    /// Returns the operator's precedence. INTERSECT binds tighter than UNION
+    /// and EXCEPT, and all are left-associative.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Self::Except | Self::Union => 1,
+            Self::Intersect => 2,
+        }
+    }
+}
+
+impl std::fmt::Display for SetOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Except => "EXCEPT",
+            Self::Intersect => "INTERSECT",
+            Self::Union => "UNION",
+        })
+    }
+}
+
 /// A CREATE TABLE column definition.
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Column {
     pub name: String,
     pub datatype: DataType,
     pub primary_key: bool,
     pub nullable: Option<bool>,
     pub default: Option<Expression>,
+    pub collation: Option<Collation>,
     pub unique: bool,
     pub index: bool,
     pub references: Option<String>,
 }
 
 /// JOIN types.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum JoinType {
     Cross,
     Inner,
@@ -94,24 +231,99 @@ impl JoinType {
     }
 }
 
+/// A GROUP BY element. The grouping sets of a GROUP BY clause are the cross
+/// product of its elements' grouping sets, e.g. GROUP BY a, ROLLUP (b) groups
+/// by (a, b) and (a).
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum GroupBy {
+    /// A grouping expression, i.e. the single grouping set (expr).
+    Expression(Expression),
+    /// ROLLUP (a, b, ...): the grouping sets (a, b, ...), ..., (a), ().
+    Rollup(Vec<Expression>),
+    /// CUBE (a, b, ...): grouping sets for all subsets of the expressions.
+    Cube(Vec<Expression>),
+    /// GROUPING SETS ((a, b), (a), ...): the given grouping sets.
+    GroupingSets(Vec<Vec<Expression>>),
+}
+
 /// ORDER BY direction.
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Direction {
     Ascending,
     Descending,
 }
 
+/// A window specification: OVER (PARTITION BY ... ORDER BY ... frame).
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Window {
+    pub partition_by: Vec<Expression>,
+    pub order_by: Vec<(Expression, Direction)>,
+    /// The window frame. If None, the frame is the whole partition when there
+    /// is no ORDER BY, otherwise it runs from the start of the partition to
+    /// the current row and its peers.
+    pub frame: Option<WindowFrame>,
+}
+
+/// A window frame: ROWS|RANGE BETWEEN start AND end. A frame with only a
+/// start bound ends at the current row.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct WindowFrame {
+    pub units: FrameUnits,
+    pub start: FrameBound,
+    pub end: FrameBound,
+}
+
+/// Window frame units. ROWS offsets count rows, while RANGE offsets are
+/// distances between ORDER BY values, and CURRENT ROW includes peers.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FrameUnits {
+    Rows,
+    Range,
+}
+
+/// A window frame bound.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum FrameBound {
+    UnboundedPreceding,
+    Preceding(Box<Expression>),
+    CurrentRow,
+    Following(Box<Expression>),
+    UnboundedFollowing,
+}
+
 /// Expressions. Can be nested.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Expression {
     /// All columns, i.e. *.
     All,
-    /// A column reference, optionally qualified with a table name.
-    Column(Option<String>, String),
+    /// A column reference, optionally qualified with a table name, and its
+    /// position in the input for error messages.
+    Column(Option<String>, String, Position),
     /// A literal value.
     Literal(Literal),
-    /// A function call (name and parameters).
-    Function(String, Vec<Expression>),
+    /// A bind parameter, by 0-based index, i.e. ? or $1.
+    Parameter(usize),
+    /// A function call (name and parameters). For aggregate functions, the
+    /// flag is true if only distinct values are aggregated, i.e. DISTINCT.
+    Function(String, Vec<Expression>, bool),
+    /// A window function call (name, parameters and window specification),
+    /// i.e. f(args) OVER (...).
+    Window(String, Vec<Expression>, Box<Window>),
+    /// An ordered-set aggregate call (name, direct arguments and the
+    /// aggregated expression with its order), i.e. f(args) WITHIN GROUP
+    /// (ORDER BY expr).
+    WithinGroup(String, Vec<Expression>, Box<(Expression, Direction)>),
+    /// A CASE expression, with an optional operand for a simple CASE (i.e.
+    /// CASE a WHEN b THEN c END), WHEN/THEN pairs, and an optional ELSE value.
+    Case(
+        Option<Box<Expression>>,
+        Vec<(Expression, Expression)>,
+        Option<Box<Expression>>,
+    ),
+    /// A scalar subquery, which returns a single value: (SELECT ...).
+    Subquery(Box<Statement>),
+    /// True if the subquery returns any rows: EXISTS (SELECT ...).
+    Exists(Box<Statement>),
     /// An operator.
     Operator(Operator),
 }
@@ -121,9 +333,19 @@ pub enum Expression {
 pub enum Literal {
     Null,
     Boolean(bool),
-    Integer(i32),
-    Float(f32),
+    Integer(i64),
+    Float(f64),
     String(String),
+    /// A byte string, e.g. x'deadbeef'.
+    Bytes(Vec<u8>),
+    /// DATE 'YYYY-MM-DD', as days since 1970-01-01.
+    Date(i32),
+    /// TIME 'HH:MM:SS', as microseconds since midnight.
+    Time(i64),
+    /// TIMESTAMP 'YYYY-MM-DD HH:MM:SS', as microseconds since 1970-01-01.
+    Timestamp(i64),
+    /// INTERVAL '1 day'.
+    Interval(Interval),
 }
 
 /// To allow using Expressions and Literals in e.g. hashmaps, implement simple
@@ -138,6 +360,11 @@ impl std::cmp::PartialEq for Literal {
             // Implies NaN == NaN but -NaN != NaN. Similarly with +/-0.0.
             (Self::Float(l), Self::Float(r)) => l.to_bits() == r.to_bits(),
             (Self::String(l), Self::String(r)) => l == r,
+            (Self::Bytes(l), Self::Bytes(r)) => l == r,
+            (Self::Date(l), Self::Date(r)) => l == r,
+            (Self::Time(l), Self::Time(r)) => l == r,
+            (Self::Timestamp(l), Self::Timestamp(r)) => l == r,
+            (Self::Interval(l), Self::Interval(r)) => l == r,
             (l, r) => core::mem::discriminant(l) == core::mem::discriminant(r),
         }
     }
@@ -154,6 +381,11 @@ impl std::hash::Hash for Literal {
             Self::Integer(v) => v.hash(state),
             Self::Float(v) => v.to_bits().hash(state),
             Self::String(v) => v.hash(state),
+            Self::Bytes(v) => v.hash(state),
+            Self::Date(v) => v.hash(state),
+            Self::Time(v) => v.hash(state),
+            Self::Timestamp(v) => v.hash(state),
+            Self::Interval(v) => v.hash(state),
         }
     }
 }
@@ -187,7 +419,22 @@ pub enum Operator {
     Remainder(Box<Expression>, Box<Expression>),    // a % b
     Subtract(Box<Expression>, Box<Expression>),     // a - b
 
-    Like(Box<Expression>, Box<Expression>), // a LIKE b
+    Concat(Box<Expression>, Box<Expression>), // a || b
+
+    Like(
+        Box<Expression>,
+        Box<Expression>,
+        Option<Box<Expression>>,
+        bool,
+    ), // a [I]LIKE b [ESCAPE c]
+    Regex(Box<Expression>, Box<Expression>, bool), // a ~ b or a ~* b
+
+    Between(Box<Expression>, Box<Expression>, Box<Expression>), // a BETWEEN b AND c
+    In(Box<Expression>, Vec<Expression>),                       // a IN (b, c)
+    InSubquery(Box<Expression>, Box<Statement>),                // a IN (SELECT ...)
+
+    Cast(Box<Expression>, DataType),     // CAST(a AS type) or a::type
+    Collate(Box<Expression>, Collation), // a COLLATE collation
 }
 
 impl Expression {
@@ -201,6 +448,7 @@ impl Expression {
         match self {
             Self::Operator(Add(lhs, rhs))
             | Self::Operator(And(lhs, rhs))
+            | Self::Operator(Concat(lhs, rhs))
             | Self::Operator(Divide(lhs, rhs))
             | Self::Operator(Equal(lhs, rhs))
             | Self::Operator(Exponentiate(lhs, rhs))
@@ -208,22 +456,69 @@ impl Expression {
             | Self::Operator(GreaterThanOrEqual(lhs, rhs))
             | Self::Operator(LessThan(lhs, rhs))
             | Self::Operator(LessThanOrEqual(lhs, rhs))
-            | Self::Operator(Like(lhs, rhs))
             | Self::Operator(Multiply(lhs, rhs))
             | Self::Operator(NotEqual(lhs, rhs))
             | Self::Operator(Or(lhs, rhs))
             | Self::Operator(Remainder(lhs, rhs))
             | Self::Operator(Subtract(lhs, rhs)) => lhs.walk(visitor) && rhs.walk(visitor),
 
-            Self::Operator(Factorial(expr))
+            Self::Operator(Cast(expr, _))
+            | Self::Operator(Collate(expr, _))
+            | Self::Operator(Factorial(expr))
             | Self::Operator(Identity(expr))
+            | Self::Operator(InSubquery(expr, _))
             | Self::Operator(Is(expr, _))
             | Self::Operator(Negate(expr))
             | Self::Operator(Not(expr)) => expr.walk(visitor),
 
-            Self::Function(_, exprs) => exprs.iter().any(|expr| expr.walk(visitor)),
+            Self::Operator(Regex(lhs, rhs, _)) => lhs.walk(visitor) && rhs.walk(visitor),
+            Self::Operator(Like(expr, pattern, escape, _)) => {
+                expr.walk(visitor)
+                    && pattern.walk(visitor)
+                    && escape.as_ref().is_none_or(|expr| expr.walk(visitor))
+            }
+            Self::Operator(Between(expr, low, high)) => {
+                expr.walk(visitor) && low.walk(visitor) && high.walk(visitor)
+            }
+            Self::Operator(In(expr, list)) => {
+                expr.walk(visitor) && list.iter().all(|expr| expr.walk(visitor))
+            }
+
+            Self::Function(_, exprs, _) => exprs.iter().all(|expr| expr.walk(visitor)),
+            Self::WithinGroup(_, exprs, order) => {
+                exprs.iter().all(|expr| expr.walk(visitor)) && order.0.walk(visitor)
+            }
+            Self::Window(_, exprs, window) => {
+                exprs.iter().all(|expr| expr.walk(visitor))
+                    && window.partition_by.iter().all(|expr| expr.walk(visitor))
+                    && window.order_by.iter().all(|(expr, _)| expr.walk(visitor))
+                    && window.frame.iter().all(|frame| {
+                        [&frame.start, &frame.end]
+                            .into_iter()
+                            .all(|bound| match bound {
+                                FrameBound::Preceding(expr) | FrameBound::Following(expr) => {
+                                    expr.walk(visitor)
+                                }
+                                _ => true,
+                            })
+                    })
+            }
 
-            Self::All | Self::Column(_, _) | Self::Literal(_) => true,
+            Self::Case(operand, when, r#else) => {
+                operand.as_ref().is_none_or(|expr| expr.walk(visitor))
+                    && when
+                        .iter()
+                        .all(|(cond, then)| cond.walk(visitor) && then.walk(visitor))
+                    && r#else.as_ref().is_none_or(|expr| expr.walk(visitor))
+            }
+
+            // Subqueries are separate queries, and aren't walked.
+            Self::All
+            | Self::Column(_, _, _)
+            | Self::Literal(_)
+            | Self::Parameter(_)
+            | Self::Subquery(_)
+            | Self::Exists(_) => true,
         }
     }
 
@@ -244,6 +539,7 @@ impl Expression {
         match self {
             Self::Operator(Add(lhs, rhs))
             | Self::Operator(And(lhs, rhs))
+            | Self::Operator(Concat(lhs, rhs))
             | Self::Operator(Divide(lhs, rhs))
             | Self::Operator(Equal(lhs, rhs))
             | Self::Operator(Exponentiate(lhs, rhs))
@@ -251,7 +547,6 @@ impl Expression {
             | Self::Operator(GreaterThanOrEqual(lhs, rhs))
             | Self::Operator(LessThan(lhs, rhs))
             | Self::Operator(LessThanOrEqual(lhs, rhs))
-            | Self::Operator(Like(lhs, rhs))
             | Self::Operator(Multiply(lhs, rhs))
             | Self::Operator(NotEqual(lhs, rhs))
             | Self::Operator(Or(lhs, rhs))
@@ -261,15 +556,73 @@ impl Expression {
                 rhs.collect(visitor, c);
             }
 
-            Self::Operator(Factorial(expr))
+            Self::Operator(Cast(expr, _))
+            | Self::Operator(Collate(expr, _))
+            | Self::Operator(Factorial(expr))
             | Self::Operator(Identity(expr))
+            | Self::Operator(InSubquery(expr, _))
             | Self::Operator(Is(expr, _))
             | Self::Operator(Negate(expr))
             | Self::Operator(Not(expr)) => expr.collect(visitor, c),
 
-            Self::Function(_, exprs) => exprs.iter().for_each(|expr| expr.collect(visitor, c)),
+            Self::Operator(Regex(lhs, rhs, _)) => {
+                lhs.collect(visitor, c);
+                rhs.collect(visitor, c);
+            }
+            Self::Operator(Like(expr, pattern, escape, _)) => {
+                expr.collect(visitor, c);
+                pattern.collect(visitor, c);
+                escape.iter().for_each(|expr| expr.collect(visitor, c));
+            }
+            Self::Operator(Between(expr, low, high)) => {
+                expr.collect(visitor, c);
+                low.collect(visitor, c);
+                high.collect(visitor, c);
+            }
+            Self::Operator(In(expr, list)) => {
+                expr.collect(visitor, c);
+                list.iter().for_each(|expr| expr.collect(visitor, c));
+            }
 
-            Self::All | Self::Column(_, _) | Self::Literal(_) => {}
+            Self::Function(_, exprs, _) => exprs.iter().for_each(|expr| expr.collect(visitor, c)),
+            Self::WithinGroup(_, exprs, order) => {
+                exprs.iter().for_each(|expr| expr.collect(visitor, c));
+                order.0.collect(visitor, c);
+            }
+            Self::Window(_, exprs, window) => {
+                exprs.iter().for_each(|expr| expr.collect(visitor, c));
+                window
+                    .partition_by
+                    .iter()
+                    .for_each(|expr| expr.collect(visitor, c));
+                window
+                    .order_by
+                    .iter()
+                    .for_each(|(expr, _)| expr.collect(visitor, c));
+                if let Some(frame) = &window.frame {
+                    for bound in [&frame.start, &frame.end] {
+                        if let FrameBound::Preceding(expr) | FrameBound::Following(expr) = bound {
+                            expr.collect(visitor, c);
+                        }
+                    }
+                }
+            }
+
+            Self::Case(operand, when, r#else) => {
+                operand.iter().for_each(|expr| expr.collect(visitor, c));
+                for (cond, then) in when {
+                    cond.collect(visitor, c);
+                    then.collect(visitor, c);
+                }
+                r#else.iter().for_each(|expr| expr.collect(visitor, c));
+            }
+
+            Self::All
+            | Self::Column(_, _, _)
+            | Self::Literal(_)
+            | Self::Parameter(_)
+            | Self::Subquery(_)
+            | Self::Exists(_) => {}
         }
     }
 }
@@ -291,3 +644,1262 @@ impl core::convert::From<Operator> for Box<Expression> {
         Box::new(value.into())
     }
 }
+
+/// Formats an identifier, quoting it with "" unless it lexes as itself.
+fn format_ident(ident: &str) -> String {
+    match is_ident(ident) {
+        true => ident.to_string(),
+        false => format!("\"{}\"", ident.replace('"', "\"\"")),
+    }
+}
+
+/// Formats a list of items separated by commas.
+fn format_list<T: std::fmt::Display>(items: &[T]) -> String {
+    let items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+    items.join(", ")
+}
+
+/// Formats a SELECT or RETURNING list, with optional column aliases.
+fn format_select(select: &[(Expression, Option<String>)]) -> String {
+    let select: Vec<String> = select
+        .iter()
+        .map(|(expr, alias)| match alias {
+            Some(alias) => format!("{expr} AS {}", format_ident(alias)),
+            None => expr.to_string(),
+        })
+        .collect();
+    select.join(", ")
+}
+
+/// Formats a RETURNING clause, if any, with a leading space.
+fn format_returning(
+    f: &mut std::fmt::Formatter<'_>,
+    returning: &[(Expression, Option<String>)],
+) -> std::fmt::Result {
+    if !returning.is_empty() {
+        write!(f, " RETURNING {}", format_select(returning))?;
+    }
+    Ok(())
+}
+
+/// Formats the SET assignments of an UPDATE or ON CONFLICT DO UPDATE.
+fn format_set(set: &BTreeMap<String, Option<Expression>>) -> String {
+    let set: Vec<String> = set
+        .iter()
+        .map(|(column, expr)| match expr {
+            Some(expr) => format!("{} = {expr}", format_ident(column)),
+            None => format!("{} = DEFAULT", format_ident(column)),
+        })
+        .collect();
+    set.join(", ")
+}
+
+/// Formats the ORDER BY, LIMIT and OFFSET clauses of a query, if any, with a
+/// leading space.
+fn format_query_clauses(
+    order_by: &[(Expression, Direction)],
+    offset: &Option<Expression>,
+    limit: &Option<Expression>,
+) -> String {
+    let mut clauses = String::new();
+    if !order_by.is_empty() {
+        let order_by: Vec<String> = order_by
+            .iter()
+            .map(|(expr, direction)| match direction {
+                Direction::Ascending => expr.to_string(),
+                Direction::Descending => format!("{expr} DESC"),
+            })
+            .collect();
+        clauses += &format!(" ORDER BY {}", order_by.join(", "));
+    }
+    if let Some(expr) = limit {
+        clauses += &format!(" LIMIT {expr}");
+    }
+    if let Some(expr) = offset {
+        clauses += &format!(" OFFSET {expr}");
+    }
+    clauses
+}
+
+impl std::fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Expression(expr) => write!(f, "{expr}"),
+            Self::Rollup(exprs) => write!(f, "ROLLUP ({})", format_list(exprs)),
+            Self::Cube(exprs) => write!(f, "CUBE ({})", format_list(exprs)),
+            Self::GroupingSets(sets) => {
+                let sets = sets.iter().map(|set| format!("({})", format_list(set)));
+                write!(f, "GROUPING SETS ({})", sets.collect::<Vec<_>>().join(", "))
+            }
+        }
+    }
+}
+
+/// Formats a window specification, without the enclosing parentheses.
+impl std::fmt::Display for Window {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut clauses = Vec::new();
+        if !self.partition_by.is_empty() {
+            clauses.push(format!("PARTITION BY {}", format_list(&self.partition_by)));
+        }
+        let order_by = format_query_clauses(&self.order_by, &None, &None);
+        if !order_by.is_empty() {
+            clauses.push(order_by.trim_start().to_string());
+        }
+        if let Some(frame) = &self.frame {
+            let units = match frame.units {
+                FrameUnits::Rows => "ROWS",
+                FrameUnits::Range => "RANGE",
+            };
+            clauses.push(format!("{units} BETWEEN {} AND {}", frame.start, frame.end));
+        }
+        write!(f, "{}", clauses.join(" "))
+    }
+}
+
+/// Formats a frame bound offset. Like BETWEEN bounds, it's parsed as an
+/// arithmetic expression so that the AND after it isn't consumed.
+fn format_offset(expr: &Expression) -> String {
+    match expr.precedence() < 6 {
+        true => format!("({expr})"),
+        false => expr.to_string(),
+    }
+}
+
+impl std::fmt::Display for FrameBound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnboundedPreceding => write!(f, "UNBOUNDED PRECEDING"),
+            Self::Preceding(expr) => write!(f, "{} PRECEDING", format_offset(expr)),
+            Self::CurrentRow => write!(f, "CURRENT ROW"),
+            Self::Following(expr) => write!(f, "{} FOLLOWING", format_offset(expr)),
+            Self::UnboundedFollowing => write!(f, "UNBOUNDED FOLLOWING"),
+        }
+    }
+}
+
+/// Formats an operand of a set operation with the given precedence, in
+/// parentheses if it has ORDER BY, LIMIT or OFFSET clauses, or if it's a set
+/// operation that would otherwise bind differently. Since set operators are
+/// left-associative, right operands of equal precedence need parentheses.
+fn format_set_operand(statement: &Statement, precedence: u8, right: bool) -> String {
+    let parens = match statement {
+        Statement::Select {
+            order_by,
+            offset,
+            limit,
+            ..
+        } => !order_by.is_empty() || offset.is_some() || limit.is_some(),
+        Statement::SetOperation {
+            operator,
+            order_by,
+            offset,
+            limit,
+            ..
+        } => {
+            !order_by.is_empty()
+                || offset.is_some()
+                || limit.is_some()
+                || operator.precedence() < precedence
+                || right && operator.precedence() == precedence
+        }
+        _ => true,
+    };
+    match parens {
+        true => format!("({statement})"),
+        false => statement.to_string(),
+    }
+}
+
+/// Formats statements as canonical SQL, which parses back into the same AST.
+impl std::fmt::Display for Statement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Begin { read_only, as_of } => {
+                write!(f, "BEGIN")?;
+                if *read_only {
+                    write!(f, " READ ONLY")?;
+                }
+                if let Some(version) = as_of {
+                    write!(f, " AS OF SYSTEM TIME {version}")?;
+                }
+                Ok(())
+            }
+            Self::Commit => write!(f, "COMMIT"),
+            Self::Rollback => write!(f, "ROLLBACK"),
+            Self::Explain(statement) => write!(f, "EXPLAIN {statement}"),
+
+            Self::CreateTable { name, columns } => {
+                let columns = format_list(columns);
+                write!(f, "CREATE TABLE {} ({columns})", format_ident(name))
+            }
+            Self::DropTable { name, if_exists } => {
+                write!(f, "DROP TABLE ")?;
+                if *if_exists {
+                    write!(f, "IF EXISTS ")?;
+                }
+                write!(f, "{}", format_ident(name))
+            }
+            Self::CreateView {
+                name,
+                query,
+                replace,
+                materialized,
+            } => {
+                write!(f, "CREATE ")?;
+                if *replace {
+                    write!(f, "OR REPLACE ")?;
+                }
+                if *materialized {
+                    write!(f, "MATERIALIZED ")?;
+                }
+                write!(f, "VIEW {} AS {query}", format_ident(name))
+            }
+            Self::DropView {
+                name,
+                if_exists,
+                materialized,
+            } => {
+                write!(f, "DROP ")?;
+                if *materialized {
+                    write!(f, "MATERIALIZED ")?;
+                }
+                write!(f, "VIEW ")?;
+                if *if_exists {
+                    write!(f, "IF EXISTS ")?;
+                }
+                write!(f, "{}", format_ident(name))
+            }
+            Self::RefreshMaterializedView { name } => {
+                write!(f, "REFRESH MATERIALIZED VIEW {}", format_ident(name))
+            }
+
+            Self::Delete {
+                table,
+                r#where,
+                returning,
+            } => {
+                write!(f, "DELETE FROM {}", format_ident(table))?;
+                if let Some(expr) = r#where {
+                    write!(f, " WHERE {expr}")?;
+                }
+                format_returning(f, returning)
+            }
+            Self::Insert {
+                table,
+                columns,
+                source,
+                on_conflict,
+                returning,
+            } => {
+                write!(f, "INSERT INTO {}", format_ident(table))?;
+                if let Some(columns) = columns {
+                    let columns: Vec<String> = columns.iter().map(|c| format_ident(c)).collect();
+                    write!(f, " ({})", columns.join(", "))?;
+                }
+                match source {
+                    InsertSource::Values(values) => {
+                        let rows: Vec<String> = values
+                            .iter()
+                            .map(|row| format!("({})", format_list(row)))
+                            .collect();
+                        write!(f, " VALUES {}", rows.join(", "))?;
+                    }
+                    InsertSource::Query(query) => write!(f, " {query}")?,
+                }
+                if let Some(OnConflict { columns, action }) = on_conflict {
+                    write!(f, " ON CONFLICT")?;
+                    if !columns.is_empty() {
+                        let columns: Vec<String> =
+                            columns.iter().map(|c| format_ident(c)).collect();
+                        write!(f, " ({})", columns.join(", "))?;
+                    }
+                    match action {
+                        ConflictAction::Nothing => write!(f, " DO NOTHING")?,
+                        ConflictAction::Update { set, r#where } => {
+                            write!(f, " DO UPDATE SET {}", format_set(set))?;
+                            if let Some(expr) = r#where {
+                                write!(f, " WHERE {expr}")?;
+                            }
+                        }
+                    }
+                }
+                format_returning(f, returning)
+            }
+            Self::Update {
+                table,
+                set,
+                r#where,
+                returning,
+            } => {
+                write!(f, "UPDATE {} SET {}", format_ident(table), format_set(set))?;
+                if let Some(expr) = r#where {
+                    write!(f, " WHERE {expr}")?;
+                }
+                format_returning(f, returning)
+            }
+            Self::Select {
+                distinct,
+                select,
+                from,
+                r#where,
+                group_by,
+                having,
+                order_by,
+                offset,
+                limit,
+            } => {
+                write!(f, "SELECT ")?;
+                match distinct {
+                    Some(on) if on.is_empty() => write!(f, "DISTINCT ")?,
+                    Some(on) => write!(f, "DISTINCT ON ({}) ", format_list(on))?,
+                    None => {}
+                }
+                write!(f, "{}", format_select(select))?;
+                if !from.is_empty() {
+                    write!(f, " FROM {}", format_list(from))?;
+                }
+                if let Some(expr) = r#where {
+                    write!(f, " WHERE {expr}")?;
+                }
+                if !group_by.is_empty() {
+                    write!(f, " GROUP BY {}", format_list(group_by))?;
+                }
+                if let Some(expr) = having {
+                    write!(f, " HAVING {expr}")?;
+                }
+                write!(f, "{}", format_query_clauses(order_by, offset, limit))
+            }
+            Self::SetOperation {
+                operator,
+                all,
+                left,
+                right,
+                order_by,
+                offset,
+                limit,
+            } => {
+                let precedence = operator.precedence();
+                let left = format_set_operand(left, precedence, false);
+                let right = format_set_operand(right, precedence, true);
+                let all = if *all { " ALL" } else { "" };
+                write!(f, "{left} {operator}{all} {right}")?;
+                write!(f, "{}", format_query_clauses(order_by, offset, limit))
+            }
+            Self::With {
+                recursive,
+                ctes,
+                query,
+            } => {
+                write!(f, "WITH ")?;
+                if *recursive {
+                    write!(f, "RECURSIVE ")?;
+                }
+                write!(f, "{} {query}", format_list(ctes))
+            }
+
+            Self::Prepare {
+                name,
+                types,
+                statement,
+            } => {
+                write!(f, "PREPARE {}", format_ident(name))?;
+                if !types.is_empty() {
+                    write!(f, " ({})", format_list(types))?;
+                }
+                write!(f, " AS {statement}")
+            }
+            Self::Execute { name, parameters } => {
+                write!(f, "EXECUTE {}", format_ident(name))?;
+                if !parameters.is_empty() {
+                    write!(f, " ({})", format_list(parameters))?;
+                }
+                Ok(())
+            }
+            Self::Deallocate { name: Some(name) } => write!(f, "DEALLOCATE {}", format_ident(name)),
+            Self::Deallocate { name: None } => write!(f, "DEALLOCATE ALL"),
+        }
+    }
+}
+
+impl std::fmt::Display for From {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Table { name, alias } => {
+                write!(f, "{}", format_ident(name))?;
+                if let Some(alias) = alias {
+                    write!(f, " AS {}", format_ident(alias))?;
+                }
+                Ok(())
+            }
+            Self::Subquery { query, alias } => write!(f, "({query}) AS {}", format_ident(alias)),
+            Self::Join {
+                left,
+                right,
+                r#type,
+                predicate,
+            } => {
+                let join = match r#type {
+                    JoinType::Cross => "CROSS JOIN",
+                    JoinType::Inner => "INNER JOIN",
+                    JoinType::Left => "LEFT JOIN",
+                    JoinType::Right => "RIGHT JOIN",
+                };
+                // Joins are left-deep, so only the right side needs grouping.
+                match right.as_ref() {
+                    Self::Join { .. } => write!(f, "{left} {join} ({right})")?,
+                    Self::Table { .. } | Self::Subquery { .. } => {
+                        write!(f, "{left} {join} {right}")?
+                    }
+                }
+                if let Some(predicate) = predicate {
+                    write!(f, " ON {predicate}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for CommonTableExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format_ident(&self.name))?;
+        if !self.columns.is_empty() {
+            let columns: Vec<String> = self.columns.iter().map(|c| format_ident(c)).collect();
+            write!(f, " ({})", columns.join(", "))?;
+        }
+        write!(f, " AS ({})", self.query)
+    }
+}
+
+impl std::fmt::Display for Column {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", format_ident(&self.name), self.datatype)?;
+        if self.primary_key {
+            write!(f, " PRIMARY KEY")?;
+        }
+        match self.nullable {
+            Some(true) => write!(f, " NULL")?,
+            Some(false) => write!(f, " NOT NULL")?,
+            None => {}
+        }
+        if let Some(collation) = &self.collation {
+            write!(f, " COLLATE {collation}")?;
+        }
+        if self.unique {
+            write!(f, " UNIQUE")?;
+        }
+        if self.index {
+            write!(f, " INDEX")?;
+        }
+        if let Some(table) = &self.references {
+            write!(f, " REFERENCES {}", format_ident(table))?;
+        }
+        // The default goes last, since a following COLLATE or NOT would be
+        // parsed as part of the expression.
+        if let Some(expr) = &self.default {
+            write!(f, " DEFAULT {expr}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Expression {
+    /// The precedence of the expression's outermost operator, for grouping
+    /// with (). Matches the parser precedence, where atoms have the highest.
+    fn precedence(&self) -> u8 {
+        use Operator::*;
+        match self {
+            Self::Literal(Literal::Integer(i)) if *i < 0 => 11,
+            Self::Literal(Literal::Float(f)) if f.is_sign_negative() && !f.is_nan() => 11,
+            Self::All
+            | Self::Column(_, _, _)
+            | Self::Literal(_)
+            | Self::Parameter(_)
+            | Self::Function(_, _, _)
+            | Self::Window(_, _, _)
+            | Self::WithinGroup(_, _, _)
+            | Self::Case(_, _, _)
+            | Self::Subquery(_)
+            | Self::Exists(_) => 12,
+            Self::Operator(operator) => match operator {
+                Cast(_, _) | Collate(_, _) => 12,
+                Identity(_) | Negate(_) => 11,
+                Factorial(_) => 10,
+                Exponentiate(_, _) => 9,
+                Multiply(_, _) | Divide(_, _) | Remainder(_, _) => 8,
+                Add(_, _) | Subtract(_, _) => 7,
+                Concat(_, _) => 6,
+                GreaterThan(_, _)
+                | GreaterThanOrEqual(_, _)
+                | LessThan(_, _)
+                | LessThanOrEqual(_, _) => 5,
+                Equal(_, _) | NotEqual(_, _) | Is(_, _) | In(_, _) | InSubquery(_, _) => 4,
+                Between(_, _, _) => 4,
+                Like(_, _, _, _) | Regex(_, _, _) => 4,
+                Not(_) => 3,
+                And(_, _) => 2,
+                Or(_, _) => 1,
+            },
+        }
+    }
+}
+
+/// Formats expressions as SQL, grouping operands with () where needed for the
+/// parser to build the same expression tree.
+impl std::fmt::Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Operator::*;
+
+        // Formats an operand, grouping it with () if it binds less tightly
+        // than the given precedence.
+        let group = |expr: &Expression, precedence: u8| match expr.precedence() < precedence {
+            true => format!("({expr})"),
+            false => expr.to_string(),
+        };
+        // Formats a left-associative infix operator with the given precedence.
+        let infix = |lhs: &Expression, op: &str, rhs: &Expression, precedence: u8| {
+            format!(
+                "{} {op} {}",
+                group(lhs, precedence),
+                group(rhs, precedence + 1)
+            )
+        };
+        // Formats a prefix operator, avoiding -- which starts a comment.
+        let prefix = |op: &str, expr: &Expression| match group(expr, 11) {
+            string if string.starts_with('-') => format!("{op}({string})"),
+            string => format!("{op}{string}"),
+        };
+
+        let string = match self {
+            Self::All => "*".to_string(),
+            Self::Column(Some(table), column, _) => {
+                format!("{}.{}", format_ident(table), format_ident(column))
+            }
+            Self::Column(None, column, _) => format_ident(column),
+            Self::Literal(literal) => literal.to_string(),
+            Self::Parameter(index) => format!("${}", index + 1),
+            Self::Subquery(query) => format!("({query})"),
+            Self::Exists(query) => format!("EXISTS ({query})"),
+
+            // extract() takes the field as FROM field expr.
+            Self::Function(name, args, false) if name == "extract" && args.len() == 2 => {
+                match &args[0] {
+                    Self::Literal(Literal::String(field)) => {
+                        let field = Literal::String(field.clone());
+                        format!("{}({field} FROM {})", format_ident(name), args[1])
+                    }
+                    _ => format!("{}({})", format_ident(name), format_list(args)),
+                }
+            }
+            Self::Function(name, args, false) => {
+                format!("{}({})", format_ident(name), format_list(args))
+            }
+            Self::Function(name, args, true) => {
+                format!("{}(DISTINCT {})", format_ident(name), format_list(args))
+            }
+            Self::Window(name, args, window) => {
+                let args = format_list(args);
+                format!("{}({args}) OVER ({window})", format_ident(name))
+            }
+            Self::WithinGroup(name, args, order) => {
+                let args = format_list(args);
+                let order_by = format_query_clauses(std::slice::from_ref(order), &None, &None);
+                format!(
+                    "{}({args}) WITHIN GROUP ({})",
+                    format_ident(name),
+                    order_by.trim_start()
+                )
+            }
+
+            Self::Case(operand, when, r#else) => {
+                let mut string = "CASE".to_string();
+                if let Some(operand) = operand {
+                    string += &format!(" {operand}");
+                }
+                for (condition, then) in when {
+                    string += &format!(" WHEN {condition} THEN {then}");
+                }
+                if let Some(r#else) = r#else {
+                    string += &format!(" ELSE {else}");
+                }
+                string + " END"
+            }
+
+            Self::Operator(And(lhs, rhs)) => infix(lhs, "AND", rhs, 2),
+            Self::Operator(Or(lhs, rhs)) => infix(lhs, "OR", rhs, 1),
+            Self::Operator(Not(expr)) => format!("NOT {}", group(expr, 3)),
+
+            Self::Operator(Equal(lhs, rhs)) => infix(lhs, "=", rhs, 4),
+            Self::Operator(NotEqual(lhs, rhs)) => infix(lhs, "!=", rhs, 4),
+            Self::Operator(GreaterThan(lhs, rhs)) => infix(lhs, ">", rhs, 5),
+            Self::Operator(GreaterThanOrEqual(lhs, rhs)) => infix(lhs, ">=", rhs, 5),
+            Self::Operator(LessThan(lhs, rhs)) => infix(lhs, "<", rhs, 5),
+            Self::Operator(LessThanOrEqual(lhs, rhs)) => infix(lhs, "<=", rhs, 5),
+            Self::Operator(Is(expr, Literal::Float(f))) if f.is_nan() => {
+                format!("{} IS NAN", group(expr, 4))
+            }
+            Self::Operator(Is(expr, literal)) => format!("{} IS {literal}", group(expr, 4)),
+            Self::Operator(In(expr, list)) => {
+                format!("{} IN ({})", group(expr, 4), format_list(list))
+            }
+            Self::Operator(InSubquery(expr, query)) => format!("{} IN ({query})", group(expr, 4)),
+            // The bounds are parsed as arithmetic expressions.
+            Self::Operator(Between(expr, low, high)) => format!(
+                "{} BETWEEN {} AND {}",
+                group(expr, 4),
+                group(low, 6),
+                group(high, 6)
+            ),
+
+            Self::Operator(Add(lhs, rhs)) => infix(lhs, "+", rhs, 7),
+            Self::Operator(Subtract(lhs, rhs)) => infix(lhs, "-", rhs, 7),
+            Self::Operator(Multiply(lhs, rhs)) => infix(lhs, "*", rhs, 8),
+            Self::Operator(Divide(lhs, rhs)) => infix(lhs, "/", rhs, 8),
+            Self::Operator(Remainder(lhs, rhs)) => infix(lhs, "%", rhs, 8),
+            // Exponentiation is right-associative.
+            Self::Operator(Exponentiate(lhs, rhs)) => {
+                format!("{} ^ {}", group(lhs, 10), group(rhs, 9))
+            }
+            Self::Operator(Factorial(expr)) => format!("{}!", group(expr, 10)),
+            Self::Operator(Identity(expr)) => prefix("+", expr),
+            Self::Operator(Negate(expr)) => prefix("-", expr),
+
+            Self::Operator(Concat(lhs, rhs)) => infix(lhs, "||", rhs, 6),
+
+            Self::Operator(Like(expr, pattern, escape, case_insensitive)) => {
+                let operator = if *case_insensitive { "ILIKE" } else { "LIKE" };
+                let mut string = format!("{} {operator} {}", group(expr, 4), group(pattern, 5));
+                if let Some(escape) = escape {
+                    string += &format!(" ESCAPE {}", group(escape, 5));
+                }
+                string
+            }
+            Self::Operator(Regex(lhs, rhs, false)) => infix(lhs, "~", rhs, 4),
+            Self::Operator(Regex(lhs, rhs, true)) => infix(lhs, "~*", rhs, 4),
+
+            Self::Operator(Cast(expr, data_type)) => format!("CAST({expr} AS {data_type})"),
+            Self::Operator(Collate(expr, collation)) => {
+                format!("{} COLLATE {collation}", group(expr, 12))
+            }
+        };
+        f.write_str(&string)
+    }
+}
+
+impl std::fmt::Display for Literal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Null => write!(f, "NULL"),
+            Self::Boolean(true) => write!(f, "TRUE"),
+            Self::Boolean(false) => write!(f, "FALSE"),
+            Self::Integer(i) => write!(f, "{i}"),
+            Self::Float(v) if v.is_nan() => write!(f, "NAN"),
+            Self::Float(v) if v.is_infinite() && *v > 0.0 => write!(f, "INFINITY"),
+            Self::Float(v) if v.is_infinite() => write!(f, "-INFINITY"),
+            // The debug format always includes a . or exponent, so it's
+            // parsed as a float rather than an integer.
+            Self::Float(v) => write!(f, "{v:?}"),
+            Self::String(s) => write!(f, "'{}'", s.replace('\'', "''")),
+            Self::Bytes(b) => write!(f, "x'{}'", bytes::encode_hex(b)),
+            Self::Date(days) => write!(f, "DATE '{}'", temporal::format_date(*days)),
+            Self::Time(micros) => write!(f, "TIME '{}'", temporal::format_time(*micros)),
+            Self::Timestamp(micros) => {
+                write!(f, "TIMESTAMP '{}'", temporal::format_timestamp(*micros))
+            }
+            Self::Interval(interval) => write!(f, "INTERVAL '{interval}'"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::Result;
+    use crate::sql::parser::Parser;
+    use rand::seq::SliceRandom;
+    use rand::Rng;
+    use rand_chacha::ChaCha8Rng;
+    use rand_core::SeedableRng;
+
+    /// Generates random ASTs of the shapes built by the parser. Numeric
+    /// literals are non-negative, since the parser builds -1 as a negation.
+    struct Generator {
+        rng: ChaCha8Rng,
+    }
+
+    impl Generator {
+        const IDENTS: [&'static str; 7] = ["a", "b2", "t_1", "Mixed", "select", "a b", "q\"t"];
+
+        fn ident(&mut self) -> String {
+            Self::IDENTS.choose(&mut self.rng).unwrap().to_string()
+        }
+
+        fn boxed(&mut self, depth: usize) -> Box<Expression> {
+            Box::new(self.expression(depth))
+        }
+
+        fn expressions(&mut self, depth: usize, min: usize) -> Vec<Expression> {
+            let len = self.rng.gen_range(min..=3);
+            (0..len).map(|_| self.expression(depth)).collect()
+        }
+
+        fn literal(&mut self) -> Literal {
+            let rng = &mut self.rng;
+            match rng.gen_range(0..11) {
+                0 => Literal::Null,
+                1 => Literal::Boolean(rng.gen()),
+                2 => Literal::Integer(rng.gen_range(0..=i64::MAX)),
+                3 => Literal::Float(rng.gen_range(0.0..1e6)),
+                4 => Literal::Float(*[f64::INFINITY, f64::NAN, 1e300, 1e-9].choose(rng).unwrap()),
+                5 => Literal::String(
+                    ["", "abc", "it's", "'' \"x\" --"]
+                        .choose(rng)
+                        .unwrap()
+                        .to_string(),
+                ),
+                6 => Literal::Bytes((0..rng.gen_range(0..4)).map(|_| rng.gen()).collect()),
+                7 => Literal::Date(rng.gen_range(-719162..2932896)),
+                8 => Literal::Time(rng.gen_range(0..86_400_000_000)),
+                9 => Literal::Timestamp(
+                    rng.gen_range(-62_135_596_800_000_000..253_402_300_799_000_000),
+                ),
+                _ => Literal::Interval(Interval::new(
+                    rng.gen_range(-100..100),
+                    rng.gen_range(-100..100),
+                    rng.gen_range(-1_000_000_000..1_000_000_000),
+                )),
+            }
+        }
+
+        fn data_type(&mut self) -> DataType {
+            let precision = self.rng.gen_range(1..=crate::types::decimal::MAX_PRECISION);
+            let scale = self.rng.gen_range(0..=precision);
+            let types = [
+                DataType::Bool,
+                DataType::SmallInt,
+                DataType::Int,
+                DataType::BigInt,
+                DataType::Float,
+                DataType::Double,
+                DataType::Decimal(precision, scale),
+                DataType::Date,
+                DataType::Time,
+                DataType::Timestamp,
+                DataType::Interval,
+                DataType::Text,
+                DataType::Bytea,
+            ];
+            *types.choose(&mut self.rng).unwrap()
+        }
+
+        fn expression(&mut self, depth: usize) -> Expression {
+            use Operator::*;
+            if depth == 0 || self.rng.gen_ratio(1, 4) {
+                return match self.rng.gen_range(0..4) {
+                    0 => Expression::Column(Some(self.ident()), self.ident(), Position::default()),
+                    1 => Expression::Column(None, self.ident(), Position::default()),
+                    2 => Expression::Parameter(self.rng.gen_range(0..10)),
+                    _ => self.literal().into(),
+                };
+            }
+            let d = depth - 1;
+            match self.rng.gen_range(0..37) {
+                0 => {
+                    let args = self.expressions(d, 0);
+                    let distinct = !args.is_empty() && self.rng.gen_ratio(1, 4);
+                    Expression::Function(self.ident(), args, distinct)
+                }
+                1 => Expression::Function("count".into(), vec![Expression::All], false),
+                2 => {
+                    let field =
+                        Literal::String(["year", "DAY"].choose(&mut self.rng).unwrap().to_string());
+                    let args = vec![field.into(), self.expression(d)];
+                    Expression::Function("extract".into(), args, false)
+                }
+                3 => {
+                    let operand = self.rng.gen::<bool>().then(|| self.boxed(d));
+                    let when = (0..self.rng.gen_range(1..3))
+                        .map(|_| (self.expression(d), self.expression(d)))
+                        .collect();
+                    let r#else = self.rng.gen::<bool>().then(|| self.boxed(d));
+                    Expression::Case(operand, when, r#else)
+                }
+                4 => And(self.boxed(d), self.boxed(d)).into(),
+                5 => Or(self.boxed(d), self.boxed(d)).into(),
+                6 => Not(self.boxed(d)).into(),
+                7 => Equal(self.boxed(d), self.boxed(d)).into(),
+                8 => NotEqual(self.boxed(d), self.boxed(d)).into(),
+                9 => GreaterThan(self.boxed(d), self.boxed(d)).into(),
+                10 => GreaterThanOrEqual(self.boxed(d), self.boxed(d)).into(),
+                11 => LessThan(self.boxed(d), self.boxed(d)).into(),
+                12 => LessThanOrEqual(self.boxed(d), self.boxed(d)).into(),
+                13 => Is(self.boxed(d), Literal::Null).into(),
+                14 => Is(self.boxed(d), Literal::Float(f64::NAN)).into(),
+                15 => In(self.boxed(d), self.expressions(d, 1)).into(),
+                16 => Between(self.boxed(d), self.boxed(d), self.boxed(d)).into(),
+                17 => Add(self.boxed(d), self.boxed(d)).into(),
+                18 => Subtract(self.boxed(d), self.boxed(d)).into(),
+                19 => Multiply(self.boxed(d), self.boxed(d)).into(),
+                20 => Divide(self.boxed(d), self.boxed(d)).into(),
+                21 => Remainder(self.boxed(d), self.boxed(d)).into(),
+                22 => Exponentiate(self.boxed(d), self.boxed(d)).into(),
+                23 => Factorial(self.boxed(d)).into(),
+                24 => Identity(self.boxed(d)).into(),
+                25 => Negate(self.boxed(d)).into(),
+                26 => Concat(self.boxed(d), self.boxed(d)).into(),
+                27 => {
+                    let escape = self.rng.gen::<bool>().then(|| self.boxed(d));
+                    Like(self.boxed(d), self.boxed(d), escape, self.rng.gen()).into()
+                }
+                28 => Regex(self.boxed(d), self.boxed(d), self.rng.gen()).into(),
+                29 => Cast(self.boxed(d), self.data_type()).into(),
+                30 => {
+                    let collation = [Collation::Binary, Collation::NoCase, Collation::Unicode];
+                    Collate(self.boxed(d), *collation.choose(&mut self.rng).unwrap()).into()
+                }
+                31 => Expression::Subquery(Box::new(self.select(d))),
+                32 => Expression::Exists(Box::new(self.select(d))),
+                33 => InSubquery(self.boxed(d), Box::new(self.select(d))).into(),
+                34 => Expression::Window(
+                    self.ident(),
+                    self.expressions(d, 0),
+                    Box::new(self.window(d)),
+                ),
+                35 => {
+                    let direction = match self.rng.gen() {
+                        true => Direction::Ascending,
+                        false => Direction::Descending,
+                    };
+                    Expression::WithinGroup(
+                        self.ident(),
+                        self.expressions(d, 0),
+                        Box::new((self.expression(d), direction)),
+                    )
+                }
+                _ => self.literal().into(),
+            }
+        }
+
+        fn from(&mut self, depth: usize) -> From {
+            let table = |g: &mut Self| match depth > 0 && g.rng.gen_ratio(1, 6) {
+                true => From::Subquery {
+                    query: Box::new(g.select(depth - 1)),
+                    alias: g.ident(),
+                },
+                false => From::Table {
+                    name: g.ident(),
+                    alias: g.rng.gen::<bool>().then(|| g.ident()),
+                },
+            };
+            let mut from = table(self);
+            for _ in 0..self.rng.gen_range(0..3) {
+                let r#type = [
+                    JoinType::Cross,
+                    JoinType::Inner,
+                    JoinType::Left,
+                    JoinType::Right,
+                ]
+                .into_iter()
+                .nth(self.rng.gen_range(0..4))
+                .unwrap();
+                let predicate = (r#type != JoinType::Cross).then(|| self.expression(depth.min(2)));
+                from = From::Join {
+                    left: Box::new(from),
+                    right: Box::new(table(self)),
+                    r#type,
+                    predicate,
+                };
+            }
+            from
+        }
+
+        fn optional(&mut self) -> Option<Expression> {
+            self.rng.gen::<bool>().then(|| self.expression(3))
+        }
+
+        /// Generates a SELECT statement. The depth bounds the expression
+        /// depth, and thus the nesting of subqueries.
+        fn select(&mut self, depth: usize) -> Statement {
+            let optional = |g: &mut Self| g.rng.gen::<bool>().then(|| g.expression(depth.min(3)));
+            Statement::Select {
+                distinct: match self.rng.gen_range(0..4) {
+                    0 => Some(Vec::new()),
+                    1 => Some(self.expressions(depth.min(2), 1)),
+                    _ => None,
+                },
+                select: self.select_list(depth, 1),
+                from: (0..self.rng.gen_range(0..3))
+                    .map(|_| self.from(depth))
+                    .collect(),
+                r#where: optional(self),
+                group_by: (0..self.rng.gen_range(0..3))
+                    .map(|_| self.group_by(depth.min(2)))
+                    .collect(),
+                having: optional(self),
+                order_by: self.order_by(depth),
+                offset: optional(self),
+                limit: optional(self),
+            }
+        }
+
+        /// Generates a SELECT or RETURNING list with at least min items.
+        fn select_list(&mut self, depth: usize, min: usize) -> Vec<(Expression, Option<String>)> {
+            (0..self.rng.gen_range(min..4))
+                .map(|_| match self.rng.gen_ratio(1, 5) {
+                    true => (Expression::All, None),
+                    false => (
+                        self.expression(depth),
+                        self.rng.gen::<bool>().then(|| self.ident()),
+                    ),
+                })
+                .collect()
+        }
+
+        fn group_by(&mut self, depth: usize) -> GroupBy {
+            match self.rng.gen_range(0..6) {
+                0 => GroupBy::Rollup(self.expressions(depth, 1)),
+                1 => GroupBy::Cube(self.expressions(depth, 1)),
+                2 => GroupBy::GroupingSets(
+                    (0..self.rng.gen_range(1..3))
+                        .map(|_| self.expressions(depth, 0))
+                        .collect(),
+                ),
+                _ => GroupBy::Expression(self.expression(depth)),
+            }
+        }
+
+        fn window(&mut self, depth: usize) -> Window {
+            let bound = |g: &mut Self| match g.rng.gen_range(0..5) {
+                0 => FrameBound::UnboundedPreceding,
+                1 => FrameBound::Preceding(g.boxed(depth.min(1))),
+                2 => FrameBound::CurrentRow,
+                3 => FrameBound::Following(g.boxed(depth.min(1))),
+                _ => FrameBound::UnboundedFollowing,
+            };
+            let frame = self.rng.gen::<bool>().then(|| WindowFrame {
+                units: match self.rng.gen() {
+                    true => FrameUnits::Rows,
+                    false => FrameUnits::Range,
+                },
+                start: bound(self),
+                end: bound(self),
+            });
+            Window {
+                partition_by: self.expressions(depth.min(2), 0),
+                order_by: self.order_by(depth),
+                frame,
+            }
+        }
+
+        fn order_by(&mut self, depth: usize) -> Vec<(Expression, Direction)> {
+            (0..self.rng.gen_range(0..3))
+                .map(|_| {
+                    let direction = match self.rng.gen() {
+                        true => Direction::Ascending,
+                        false => Direction::Descending,
+                    };
+                    (self.expression(depth.min(2)), direction)
+                })
+                .collect()
+        }
+
+        /// Generates a SELECT statement or a set operation of them.
+        fn query(&mut self, depth: usize) -> Statement {
+            if !self.rng.gen_ratio(1, 4) {
+                return self.select(depth);
+            }
+            let operator = [
+                SetOperator::Except,
+                SetOperator::Intersect,
+                SetOperator::Union,
+            ]
+            .into_iter()
+            .nth(self.rng.gen_range(0..3))
+            .unwrap();
+            Statement::SetOperation {
+                operator,
+                all: self.rng.gen(),
+                left: Box::new(self.query(depth.min(2))),
+                right: Box::new(self.query(depth.min(2))),
+                order_by: self.order_by(depth),
+                offset: self.optional(),
+                limit: self.optional(),
+            }
+        }
+
+        fn statement(&mut self) -> Statement {
+            match self.rng.gen_range(0..16) {
+                0 => Statement::Begin {
+                    read_only: self.rng.gen(),
+                    as_of: self.rng.gen::<bool>().then(|| self.rng.gen()),
+                },
+                1 => Statement::Commit,
+                2 => Statement::Rollback,
+                3 => Statement::Explain(Box::new(self.dml())),
+                4 => Statement::CreateTable {
+                    name: self.ident(),
+                    columns: (0..self.rng.gen_range(1..4))
+                        .map(|_| Column {
+                            name: self.ident(),
+                            datatype: self.data_type(),
+                            primary_key: self.rng.gen(),
+                            nullable: self.rng.gen::<bool>().then(|| self.rng.gen()),
+                            default: self.optional(),
+                            collation: self.rng.gen::<bool>().then_some(Collation::NoCase),
+                            unique: self.rng.gen(),
+                            index: self.rng.gen(),
+                            references: self.rng.gen::<bool>().then(|| self.ident()),
+                        })
+                        .collect(),
+                },
+                5 => Statement::DropTable {
+                    name: self.ident(),
+                    if_exists: self.rng.gen(),
+                },
+                6 => Statement::Prepare {
+                    name: self.ident(),
+                    types: (0..self.rng.gen_range(0..3))
+                        .map(|_| self.data_type())
+                        .collect(),
+                    statement: Box::new(self.dml()),
+                },
+                7 => Statement::Execute {
+                    name: self.ident(),
+                    parameters: self.expressions(2, 0),
+                },
+                8 => Statement::Deallocate {
+                    name: self.rng.gen::<bool>().then(|| self.ident()),
+                },
+                9 => {
+                    let materialized: bool = self.rng.gen();
+                    Statement::CreateView {
+                        name: self.ident(),
+                        query: Box::new(self.query(2)),
+                        replace: !materialized && self.rng.gen(),
+                        materialized,
+                    }
+                }
+                10 => Statement::DropView {
+                    name: self.ident(),
+                    if_exists: self.rng.gen(),
+                    materialized: self.rng.gen(),
+                },
+                11 => Statement::RefreshMaterializedView { name: self.ident() },
+                _ => self.dml(),
+            }
+        }
+
+        fn dml(&mut self) -> Statement {
+            match self.rng.gen_range(0..5) {
+                0 => Statement::Delete {
+                    table: self.ident(),
+                    r#where: self.optional(),
+                    returning: self.select_list(3, 0),
+                },
+                1 => Statement::Insert {
+                    table: self.ident(),
+                    columns: self.rng.gen::<bool>().then(|| {
+                        (0..self.rng.gen_range(1..3))
+                            .map(|_| self.ident())
+                            .collect()
+                    }),
+                    source: match self.rng.gen() {
+                        true => InsertSource::Values(
+                            (0..self.rng.gen_range(1..3))
+                                .map(|_| self.expressions(3, 1))
+                                .collect(),
+                        ),
+                        false => InsertSource::Query(Box::new(self.query(2))),
+                    },
+                    on_conflict: self.rng.gen::<bool>().then(|| OnConflict {
+                        columns: (0..self.rng.gen_range(0..3))
+                            .map(|_| self.ident())
+                            .collect(),
+                        action: match self.rng.gen() {
+                            true => ConflictAction::Nothing,
+                            false => ConflictAction::Update {
+                                set: (0..self.rng.gen_range(1..3))
+                                    .map(|_| (self.ident(), self.optional()))
+                                    .collect(),
+                                r#where: self.optional(),
+                            },
+                        },
+                    }),
+                    returning: self.select_list(3, 0),
+                },
+                2 => Statement::Update {
+                    table: self.ident(),
+                    set: (0..self.rng.gen_range(1..3))
+                        .map(|_| (self.ident(), self.optional()))
+                        .collect(),
+                    r#where: self.optional(),
+                    returning: self.select_list(3, 0),
+                },
+                3 => self.with(),
+                _ => self.query(4),
+            }
+        }
+
+        /// Generates a WITH query.
+        fn with(&mut self) -> Statement {
+            let ctes = (0..self.rng.gen_range(1..3))
+                .map(|_| CommonTableExpression {
+                    name: self.ident(),
+                    columns: (0..self.rng.gen_range(0..3))
+                        .map(|_| self.ident())
+                        .collect(),
+                    query: Box::new(self.query(2)),
+                })
+                .collect();
+            Statement::With {
+                recursive: self.rng.gen(),
+                ctes,
+                query: Box::new(self.query(3)),
+            }
+        }
+    }
+
+    /// Parses a SQL statement.
+    fn parse(sql: &str) -> Result<Statement> {
+        Parser::new(sql).parse()
+    }
+
+    /// Tests that formatting parsed statements yields canonical SQL.
+    #[test]
+    fn test_display() -> Result<()> {
+        let cases = [
+            (
+                "select 1 + 2 * 3, (1 + 2) * 3",
+                "SELECT 1 + 2 * 3, (1 + 2) * 3",
+            ),
+            (
+                "select 1 - (2 - 3), 1 - 2 - 3",
+                "SELECT 1 - (2 - 3), 1 - 2 - 3",
+            ),
+            (
+                "select 2 ^ 3 ^ 2, (2 ^ 3) ^ 2",
+                "SELECT 2 ^ 3 ^ 2, (2 ^ 3) ^ 2",
+            ),
+            ("select - -1, -(2!), (-2)!", "SELECT -(-1), -(2!), -2!"),
+            (
+                "select not a is not null, (not a) is null",
+                "SELECT NOT NOT a IS NULL, (NOT a) IS NULL",
+            ),
+            (
+                "select a between 1 + 1 and (b or c)",
+                "SELECT a BETWEEN 1 + 1 AND (b OR c)",
+            ),
+            (
+                "select a not like 'x''%' escape '!'",
+                "SELECT NOT a LIKE 'x''%' ESCAPE '!'",
+            ),
+            (
+                "select extract(year from d), x::int",
+                "SELECT extract('year' FROM d), CAST(x AS int)",
+            ),
+            (
+                "select \"Foo\".\"a b\" as \"select\" from t x",
+                "SELECT \"Foo\".\"a b\" AS \"select\" FROM t AS x",
+            ),
+            (
+                "select 1.0, 1e3, x'0aff', date '2024-1-2'",
+                "SELECT 1.0, 1000.0, x'0aff', DATE '2024-01-02'",
+            ),
+            (
+                "select * from a join b on a.id = b.id order by 1 desc limit ?",
+                "SELECT * FROM a INNER JOIN b ON a.id = b.id ORDER BY 1 DESC LIMIT $1",
+            ),
+            (
+                "with recursive t(n) as (select 1 union all select n+1 from t) select * from t",
+                "WITH RECURSIVE t (n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t) SELECT * FROM t",
+            ),
+            (
+                "select 1 union select 2 intersect all select 3 except select 4",
+                "SELECT 1 UNION SELECT 2 INTERSECT ALL SELECT 3 EXCEPT SELECT 4",
+            ),
+            (
+                "(select 1 union select 2) intersect (select 3 order by 1) order by 1 limit 1",
+                "(SELECT 1 UNION SELECT 2) INTERSECT (SELECT 3 ORDER BY 1) ORDER BY 1 LIMIT 1",
+            ),
+            (
+                "select 1 except (select 2 except select 3)",
+                "SELECT 1 EXCEPT (SELECT 2 EXCEPT SELECT 3)",
+            ),
+            (
+                "select distinct on (a, b) a, count(distinct b) from t",
+                "SELECT DISTINCT ON (a, b) a, count(DISTINCT b) FROM t",
+            ),
+            ("select all a from t", "SELECT a FROM t"),
+            (
+                "insert into t (a, \"B\") values (1, 2), (3, 4)",
+                "INSERT INTO t (a, \"B\") VALUES (1, 2), (3, 4)",
+            ),
+            (
+                "insert into t (select 1 limit 1) union select 2",
+                "INSERT INTO t (SELECT 1 LIMIT 1) UNION SELECT 2",
+            ),
+            (
+                "insert into t (a) with s as (select 1) select * from s",
+                "INSERT INTO t (a) WITH s AS (SELECT 1) SELECT * FROM s",
+            ),
+            (
+                "update t set a = default, b = a collate nocase",
+                "UPDATE t SET a = DEFAULT, b = a COLLATE nocase",
+            ),
+            (
+                "select sum(x) over (partition by a order by b desc rows 2 preceding), rank() over ()",
+                "SELECT sum(x) OVER (PARTITION BY a ORDER BY b DESC ROWS BETWEEN 2 PRECEDING AND \
+                    CURRENT ROW), rank() OVER ()",
+            ),
+            (
+                "select percentile_cont(0.5) within group (order by x desc), median(x)",
+                "SELECT percentile_cont(0.5) WITHIN GROUP (ORDER BY x DESC), median(x)",
+            ),
+            (
+                "select a, grouping(a, b) from t group by c, rollup(a, b), cube(a), grouping sets ((a, b), a, ())",
+                "SELECT a, grouping(a, b) FROM t GROUP BY c, ROLLUP (a, b), CUBE (a), \
+                    GROUPING SETS ((a, b), (a), ())",
+            ),
+            (
+                "create table t (id int primary key default 1, s string collate nocase not null)",
+                "CREATE TABLE t (id int PRIMARY KEY DEFAULT 1, s varchar NOT NULL COLLATE nocase)",
+            ),
+            (
+                "create or replace view v as (select a from t order by a) union select 1",
+                "CREATE OR REPLACE VIEW v AS (SELECT a FROM t ORDER BY a) UNION SELECT 1",
+            ),
+            (
+                "create materialized view v as with s as (select 1) select * from s",
+                "CREATE MATERIALIZED VIEW v AS WITH s AS (SELECT 1) SELECT * FROM s",
+            ),
+            (
+                "drop materialized view if exists v",
+                "DROP MATERIALIZED VIEW IF EXISTS v",
+            ),
+            ("refresh materialized view v", "REFRESH MATERIALIZED VIEW v"),
+        ];
+        for (sql, expect) in cases {
+            let statement = parse(sql)?;
+            assert_eq!(statement.to_string(), expect, "for {sql}");
+            assert_eq!(parse(expect)?, statement, "for {expect}");
+        }
+        Ok(())
+    }
+
+    /// Tests that random statements round-trip through formatting and
+    /// parsing, i.e. that parse(format(ast)) == ast.
+    #[test]
+    fn test_roundtrip() {
+        let mut generator = Generator {
+            rng: ChaCha8Rng::seed_from_u64(38),
+        };
+        for _ in 0..2000 {
+            let statement = generator.statement();
+            let sql = statement.to_string();
+            let parsed = parse(&sql).unwrap_or_else(|err| panic!("{sql}: {err}"));
+            assert_eq!(parsed, statement, "for {sql}");
+            assert_eq!(parsed.to_string(), sql);
+        }
+    }
+}
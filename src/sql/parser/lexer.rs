@@ -1,12 +1,96 @@
-use crate::common::Result;
+use crate::common::{Error, Result};
 use crate::errinput;
+use std::sync::Arc;
 
 /// The lexer (lexical analyzer) preprocesses raw SQL strings into a sequence of
 /// lexical tokens (e.g. keyword, number, string, etc), which are passed on to
 /// the SQL parser. In doing so, it strips away basic syntactic noise such as
-/// whitespace, case, and quotes, and performs initial symbol validation.
+/// whitespace, comments, case, and quotes, and performs initial symbol
+/// validation.
+#[derive(Clone)]
 pub struct Lexer<'a> {
-    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    input: &'a str,
+    chars: std::str::Chars<'a>,
+    /// The current line number, byte offset and text, used to compute token
+    /// positions incrementally.
+    line: (usize, usize, Arc<str>),
+}
+
+/// The position of a token (or other byte range) in the SQL input, for error
+/// messages.
+///
+/// Positions are ignored when comparing or hashing, such that e.g. AST nodes
+/// are equal regardless of where they appear in the input.
+#[derive(Clone, Debug, Default)]
+pub struct Position {
+    /// The byte offset in the input.
+    pub offset: usize,
+    /// The length in bytes.
+    pub length: usize,
+    /// The line number, starting at 1.
+    pub line: usize,
+    /// The column number in characters, starting at 1.
+    pub column: usize,
+    /// The text of the line, and its byte offset in the input, for snippets.
+    text: Arc<str>,
+    start: usize,
+}
+
+impl Position {
+    /// Returns the position of the given byte range in the input.
+    pub fn new(input: &str, offset: usize, length: usize) -> Self {
+        let start = input[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line = input[..start].matches('\n').count() + 1;
+        let text = input[start..].lines().next().unwrap_or_default();
+        Self::on_line(line, start, text.into(), offset, length)
+    }
+
+    /// Returns the position of the given byte range on the given line, which
+    /// starts at the given byte offset.
+    fn on_line(line: usize, start: usize, text: Arc<str>, offset: usize, length: usize) -> Self {
+        let column = text.get(..offset - start).unwrap_or(&text).chars().count() + 1;
+        Self {
+            offset,
+            length,
+            line,
+            column,
+            text,
+            start,
+        }
+    }
+
+    /// Adds the position to an input error, along with a snippet of the input
+    /// line where the position is underlined with carets. Other errors are
+    /// returned as is.
+    pub fn locate(&self, err: Error) -> Error {
+        let Error::InvalidInput(message) = err else {
+            return err;
+        };
+        let split = (self.offset - self.start).min(self.text.len());
+        let (before, after) = self.text.split_at(split);
+        let indent: String = before
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = after.get(..self.length).unwrap_or(after).chars().count();
+        let carets = "^".repeat(width.max(1));
+        Error::InvalidInput(format!(
+            "{message} at line {}, column {}\n  {}\n  {indent}{carets}",
+            self.line, self.column, self.text
+        ))
+    }
+}
+
+impl PartialEq for Position {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Position {}
+
+impl std::hash::Hash for Position {
+    fn hash<H: std::hash::Hasher>(&self, _: &mut H) {}
 }
 
 /// A lexical token.
@@ -21,11 +105,18 @@ pub enum Token {
     Number(String),
     /// A Unicode string, with quotes stripped and escape sequences resolved.
     String(String),
+    /// A hex byte string such as x'DEADBEEF', as validated lowercase hex
+    /// digits with the quotes stripped.
+    HexString(String),
     /// An identifier, with any quotes stripped.
     Ident(String),
+    /// A numbered bind parameter such as $1, as its digits.
+    Parameter(String),
     /// A SQL keyword.
     Keyword(Keyword),
     Period,             // .
+    Colon,              // :
+    DoubleColon,        // ::
     Equal,              // =
     NotEqual,           // !=
     GreaterThan,        // >
@@ -40,7 +131,13 @@ pub enum Token {
     Caret,              // ^
     Percent,            // %
     Exclamation,        // !
+    NotTilde,           // !~
+    NotTildeAsterisk,   // !~*
     Question,           // ?
+    Tilde,              // ~
+    TildeAsterisk,      // ~*
+    Pipe,               // |
+    Concat,             // ||
     Comma,              // ,
     Semicolon,          // ;
     OpenParen,          // (
@@ -52,9 +149,13 @@ impl std::fmt::Display for Token {
         f.write_str(match self {
             Self::Number(n) => n,
             Self::String(s) => s,
+            Self::HexString(s) => return write!(f, "x'{s}'"),
             Self::Ident(s) => s,
+            Self::Parameter(n) => return write!(f, "${n}"),
             Self::Keyword(k) => return k.fmt(f),
             Self::Period => ".",
+            Self::Colon => ":",
+            Self::DoubleColon => "::",
             Self::Equal => "=",
             Self::NotEqual => "!=",
             Self::GreaterThan => ">",
@@ -69,7 +170,13 @@ impl std::fmt::Display for Token {
             Self::Caret => "^",
             Self::Percent => "%",
             Self::Exclamation => "!",
+            Self::NotTilde => "!~",
+            Self::NotTildeAsterisk => "!~*",
             Self::Question => "?",
+            Self::Tilde => "~",
+            Self::TildeAsterisk => "~*",
+            Self::Pipe => "|",
+            Self::Concat => "||",
             Self::Comma => ",",
             Self::Semicolon => ";",
             Self::OpenParen => "(",
@@ -87,45 +194,74 @@ impl From<Keyword> for Token {
 /// Reserved SQL keywords.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Keyword {
+    All,
     And,
     As,
     Asc,
     Begin,
+    Between,
+    Bigint,
+    Blob,
     Bool,
     Boolean,
     By,
+    Bytea,
+    Case,
+    Cast,
+    Collate,
     Commit,
+    Conflict,
     Create,
     Cross,
+    Cube,
+    Current,
+    Date,
+    Deallocate,
+    Decimal,
     Default,
     Delete,
     Desc,
+    Distinct,
+    Do,
     Double,
     Drop,
+    Else,
+    End,
+    Escape,
+    Except,
+    Execute,
     Exists,
     Explain,
     False,
     Float,
+    Following,
     From,
     Group,
     Having,
     If,
+    ILike,
+    In,
     Index,
     Infinity,
     Inner,
     Insert,
     Int,
     Integer,
+    Intersect,
     Into,
+    Interval,
     Is,
     Join,
     Key,
     Left,
     Like,
     Limit,
+    Materialized,
     NaN,
     Not,
+    Nothing,
     Null,
+    Numeric,
     Of,
     Offset,
     On,
@@ -133,25 +269,48 @@ pub enum Keyword {
     Or,
     Order,
     Outer,
+    Over,
+    Partition,
+    Preceding,
+    Precision,
+    Prepare,
     Primary,
+    Range,
     Read,
+    Real,
+    Recursive,
     References,
+    Refresh,
+    Returning,
     Right,
     Rollback,
+    Rollup,
+    Row,
+    Rows,
     Select,
     Set,
+    Sets,
+    Smallint,
     String,
     System,
     Table,
     Text,
+    Then,
     Time,
+    Timestamp,
     Transaction,
     True,
+    Unbounded,
+    Union,
     Unique,
     Update,
     Values,
     Varchar,
+    View,
+    When,
     Where,
+    With,
+    Within,
     Write,
 }
 
@@ -167,45 +326,74 @@ impl TryFrom<&str> for Keyword {
             "keyword must be lowercase"
         );
         Ok(match value {
+            "all" => Self::All,
             "as" => Self::As,
             "asc" => Self::Asc,
             "and" => Self::And,
             "begin" => Self::Begin,
+            "between" => Self::Between,
+            "bigint" => Self::Bigint,
+            "blob" => Self::Blob,
             "bool" => Self::Bool,
             "boolean" => Self::Boolean,
             "by" => Self::By,
+            "bytea" => Self::Bytea,
+            "case" => Self::Case,
+            "cast" => Self::Cast,
+            "collate" => Self::Collate,
             "commit" => Self::Commit,
+            "conflict" => Self::Conflict,
             "create" => Self::Create,
             "cross" => Self::Cross,
+            "cube" => Self::Cube,
+            "current" => Self::Current,
+            "date" => Self::Date,
+            "deallocate" => Self::Deallocate,
+            "decimal" => Self::Decimal,
             "default" => Self::Default,
             "delete" => Self::Delete,
             "desc" => Self::Desc,
+            "distinct" => Self::Distinct,
+            "do" => Self::Do,
             "double" => Self::Double,
             "drop" => Self::Drop,
+            "else" => Self::Else,
+            "end" => Self::End,
+            "escape" => Self::Escape,
+            "except" => Self::Except,
+            "execute" => Self::Execute,
             "exists" => Self::Exists,
             "explain" => Self::Explain,
             "false" => Self::False,
             "float" => Self::Float,
+            "following" => Self::Following,
             "from" => Self::From,
             "group" => Self::Group,
             "having" => Self::Having,
             "if" => Self::If,
+            "ilike" => Self::ILike,
+            "in" => Self::In,
             "index" => Self::Index,
             "infinity" => Self::Infinity,
             "inner" => Self::Inner,
             "insert" => Self::Insert,
             "int" => Self::Int,
             "integer" => Self::Integer,
+            "intersect" => Self::Intersect,
             "into" => Self::Into,
+            "interval" => Self::Interval,
             "is" => Self::Is,
             "join" => Self::Join,
             "key" => Self::Key,
             "left" => Self::Left,
             "like" => Self::Like,
             "limit" => Self::Limit,
+            "materialized" => Self::Materialized,
             "nan" => Self::NaN,
             "not" => Self::Not,
+            "nothing" => Self::Nothing,
             "null" => Self::Null,
+            "numeric" => Self::Numeric,
             "of" => Self::Of,
             "offset" => Self::Offset,
             "on" => Self::On,
@@ -213,74 +401,142 @@ impl TryFrom<&str> for Keyword {
             "or" => Self::Or,
             "order" => Self::Order,
             "outer" => Self::Outer,
+            "over" => Self::Over,
+            "partition" => Self::Partition,
+            "preceding" => Self::Preceding,
+            "precision" => Self::Precision,
+            "prepare" => Self::Prepare,
             "primary" => Self::Primary,
+            "range" => Self::Range,
             "read" => Self::Read,
+            "real" => Self::Real,
+            "recursive" => Self::Recursive,
             "references" => Self::References,
+            "refresh" => Self::Refresh,
+            "returning" => Self::Returning,
             "right" => Self::Right,
             "rollback" => Self::Rollback,
+            "rollup" => Self::Rollup,
+            "row" => Self::Row,
+            "rows" => Self::Rows,
             "select" => Self::Select,
             "set" => Self::Set,
+            "sets" => Self::Sets,
+            "smallint" => Self::Smallint,
             "string" => Self::String,
             "system" => Self::System,
             "table" => Self::Table,
             "text" => Self::Text,
+            "then" => Self::Then,
             "time" => Self::Time,
+            "timestamp" => Self::Timestamp,
             "transaction" => Self::Transaction,
             "true" => Self::True,
+            "unbounded" => Self::Unbounded,
+            "union" => Self::Union,
             "unique" => Self::Unique,
             "update" => Self::Update,
             "values" => Self::Values,
             "varchar" => Self::Varchar,
+            "view" => Self::View,
+            "when" => Self::When,
             "where" => Self::Where,
+            "with" => Self::With,
+            "within" => Self::Within,
             "write" => Self::Write,
             _ => return Err("not a keyword"),
         })
     }
 }
 
+impl Keyword {
+    /// Returns true if the keyword can also be used as an identifier where
+    /// one is expected, e.g. a column named date, as in Postgres.
+    pub fn is_unreserved(&self) -> bool {
+        matches!(
+            self,
+            Self::Date
+                | Self::Timestamp
+                | Self::Interval
+                | Self::Range
+                | Self::Rows
+                | Self::Current
+        )
+    }
+}
+
 impl std::fmt::Display for Keyword {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         // Display keywords as uppercase.
         f.write_str(match self {
+            Self::All => "ALL",
             Self::As => "AS",
             Self::Asc => "ASC",
             Self::And => "AND",
             Self::Begin => "BEGIN",
+            Self::Between => "BETWEEN",
+            Self::Bigint => "BIGINT",
+            Self::Blob => "BLOB",
             Self::Bool => "BOOL",
             Self::Boolean => "BOOLEAN",
             Self::By => "BY",
+            Self::Bytea => "BYTEA",
+            Self::Case => "CASE",
+            Self::Cast => "CAST",
+            Self::Collate => "COLLATE",
             Self::Commit => "COMMIT",
+            Self::Conflict => "CONFLICT",
             Self::Create => "CREATE",
             Self::Cross => "CROSS",
+            Self::Cube => "CUBE",
+            Self::Current => "CURRENT",
+            Self::Date => "DATE",
+            Self::Deallocate => "DEALLOCATE",
+            Self::Decimal => "DECIMAL",
             Self::Default => "DEFAULT",
             Self::Delete => "DELETE",
             Self::Desc => "DESC",
+            Self::Distinct => "DISTINCT",
+            Self::Do => "DO",
             Self::Double => "DOUBLE",
             Self::Drop => "DROP",
+            Self::Else => "ELSE",
+            Self::End => "END",
+            Self::Escape => "ESCAPE",
+            Self::Except => "EXCEPT",
+            Self::Execute => "EXECUTE",
             Self::Exists => "EXISTS",
             Self::Explain => "EXPLAIN",
             Self::False => "FALSE",
             Self::Float => "FLOAT",
+            Self::Following => "FOLLOWING",
             Self::From => "FROM",
             Self::Group => "GROUP",
             Self::Having => "HAVING",
             Self::If => "IF",
+            Self::ILike => "ILIKE",
+            Self::In => "IN",
             Self::Index => "INDEX",
             Self::Infinity => "INFINITY",
             Self::Inner => "INNER",
             Self::Insert => "INSERT",
             Self::Int => "INT",
             Self::Integer => "INTEGER",
+            Self::Intersect => "INTERSECT",
             Self::Into => "INTO",
+            Self::Interval => "INTERVAL",
             Self::Is => "IS",
             Self::Join => "JOIN",
             Self::Key => "KEY",
             Self::Left => "LEFT",
             Self::Like => "LIKE",
             Self::Limit => "LIMIT",
+            Self::Materialized => "MATERIALIZED",
             Self::NaN => "NAN",
             Self::Not => "NOT",
+            Self::Nothing => "NOTHING",
             Self::Null => "NULL",
+            Self::Numeric => "NUMERIC",
             Self::Of => "OF",
             Self::Offset => "OFFSET",
             Self::On => "ON",
@@ -288,44 +544,75 @@ impl std::fmt::Display for Keyword {
             Self::Outer => "OUTER",
             Self::Or => "OR",
             Self::Order => "ORDER",
+            Self::Over => "OVER",
+            Self::Partition => "PARTITION",
+            Self::Preceding => "PRECEDING",
+            Self::Precision => "PRECISION",
+            Self::Prepare => "PREPARE",
             Self::Primary => "PRIMARY",
+            Self::Range => "RANGE",
             Self::Read => "READ",
+            Self::Real => "REAL",
+            Self::Recursive => "RECURSIVE",
             Self::References => "REFERENCES",
+            Self::Refresh => "REFRESH",
+            Self::Returning => "RETURNING",
             Self::Right => "RIGHT",
             Self::Rollback => "ROLLBACK",
+            Self::Rollup => "ROLLUP",
+            Self::Row => "ROW",
+            Self::Rows => "ROWS",
             Self::Select => "SELECT",
             Self::Set => "SET",
+            Self::Sets => "SETS",
+            Self::Smallint => "SMALLINT",
             Self::String => "STRING",
             Self::System => "SYSTEM",
             Self::Table => "TABLE",
             Self::Text => "TEXT",
+            Self::Then => "THEN",
             Self::Time => "TIME",
+            Self::Timestamp => "TIMESTAMP",
             Self::Transaction => "TRANSACTION",
             Self::True => "TRUE",
+            Self::Unbounded => "UNBOUNDED",
+            Self::Union => "UNION",
             Self::Unique => "UNIQUE",
             Self::Update => "UPDATE",
             Self::Values => "VALUES",
             Self::Varchar => "VARCHAR",
+            Self::View => "VIEW",
+            Self::When => "WHEN",
             Self::Where => "WHERE",
+            Self::With => "WITH",
+            Self::Within => "WITHIN",
             Self::Write => "WRITE",
         })
     }
 }
 
-/// The lexer is used as a token iterator.
+/// The lexer is used as a token iterator, emitting tokens along with their
+/// position. Errors are located at the token (or comment) that failed to lex.
 impl<'a> Iterator for Lexer<'a> {
-    type Item = Result<Token>;
+    type Item = Result<(Token, Position)>;
 
-    fn next(&mut self) -> Option<Result<Token>> {
+    fn next(&mut self) -> Option<Result<(Token, Position)>> {
+        // Ignore whitespace and comments.
+        if let Err(err) = self.skip_whitespace_and_comments() {
+            return Some(Err(err));
+        }
+        let start = self.offset();
         match self.scan() {
-            Ok(Some(token)) => Some(Ok(token)),
+            Ok(Some(token)) => Some(Ok((token, self.position(start)))),
             // If there's any remaining chars, the lexer didn't recognize them.
-            // Otherwise, we're done lexing.
-            Ok(None) => self
-                .chars
-                .peek()
-                .map(|c| errinput!("unexpected character {c}")),
-            Err(err) => Some(Err(err)),
+            // Otherwise, we're done lexing. The character is consumed, such
+            // that the caller can skip past the error.
+            Ok(None) => {
+                let c = self.chars.next()?;
+                let err = Error::InvalidInput(format!("unexpected character {c}"));
+                Some(Err(self.position(start).locate(err)))
+            }
+            Err(err) => Some(Err(self.position(start).locate(err))),
         }
     }
 }
@@ -333,21 +620,50 @@ impl<'a> Iterator for Lexer<'a> {
 impl<'a> Lexer<'a> {
     /// Creates a new lexer for the given string.
     pub fn new(input: &'a str) -> Lexer<'a> {
+        let text = input.lines().next().unwrap_or_default();
         Lexer {
-            chars: input.chars().peekable(),
+            input,
+            chars: input.chars(),
+            line: (1, 0, text.into()),
         }
     }
 
+    /// Returns the current byte offset in the input.
+    fn offset(&self) -> usize {
+        self.input.len() - self.chars.as_str().len()
+    }
+
+    /// Returns the position of the input from the given byte offset up to the
+    /// current offset. The offset can't be before the previous position's.
+    fn position(&mut self, offset: usize) -> Position {
+        let (line, start, text) = &mut self.line;
+        while let Some(i) = self.input[*start..offset].find('\n') {
+            *line += 1;
+            *start += i + 1;
+            *text = self.input[*start..]
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .into();
+        }
+        Position::on_line(*line, *start, text.clone(), offset, self.offset() - offset)
+    }
+
+    /// Returns the next character without consuming it.
+    fn peek(&self) -> Option<char> {
+        self.chars.clone().next()
+    }
+
     /// Returns the next character if it satisfies the predicate.
     fn next_if(&mut self, predicate: impl Fn(char) -> bool) -> Option<char> {
-        self.chars.peek().filter(|&&c| predicate(c))?;
+        self.peek().filter(|&c| predicate(c))?;
         self.chars.next()
     }
 
     /// Applies a function to the next character, returning its result and
     /// consuming the next character if it's Some.
     fn next_if_map<T>(&mut self, map: impl Fn(char) -> Option<T>) -> Option<T> {
-        let value = self.chars.peek().and_then(|&c| map(c))?;
+        let value = self.peek().and_then(map)?;
         self.chars.next();
         Some(value)
     }
@@ -359,12 +675,12 @@ impl<'a> Lexer<'a> {
 
     /// Scans the next token, if any.
     fn scan(&mut self) -> Result<Option<Token>> {
-        // Ignore whitespace.
-        self.skip_whitespace();
         // The first character tells us the token type.
-        match self.chars.peek() {
+        match self.peek() {
+            Some('x' | 'X') if self.chars.clone().nth(1) == Some('\'') => self.scan_hex_string(),
             Some('\'') => self.scan_string(),
             Some('"') => self.scan_ident_quoted(),
+            Some('$') => self.scan_parameter(),
             Some(c) if c.is_ascii_digit() => Ok(self.scan_number()),
             Some(c) if c.is_alphabetic() => Ok(self.scan_ident_or_keyword()),
             Some(_) => Ok(self.scan_symbol()),
@@ -408,6 +724,21 @@ impl<'a> Lexer<'a> {
         Ok(Some(Token::Ident(ident)))
     }
 
+    /// Scans the next numbered bind parameter, e.g. $1, if any.
+    fn scan_parameter(&mut self) -> Result<Option<Token>> {
+        if !self.next_is('$') {
+            return Ok(None);
+        }
+        let mut number = String::new();
+        while let Some(c) = self.next_if(|c| c.is_ascii_digit()) {
+            number.push(c)
+        }
+        if number.is_empty() {
+            return errinput!("expected parameter number after $");
+        }
+        Ok(Some(Token::Parameter(number)))
+    }
+
     /// Scans the next number, if any.
     fn scan_number(&mut self) -> Option<Token> {
         // Scan the integer part. There must be one digit.
@@ -453,11 +784,27 @@ impl<'a> Lexer<'a> {
         Ok(Some(Token::String(string)))
     }
 
+    /// Scans the next hex byte string literal, e.g. x'deadbeef', if any.
+    fn scan_hex_string(&mut self) -> Result<Option<Token>> {
+        if self.next_if(|c| c == 'x' || c == 'X').is_none() {
+            return Ok(None);
+        }
+        let Some(Token::String(string)) = self.scan_string()? else {
+            return errinput!("expected quoted hex string");
+        };
+        let hex = string.to_lowercase();
+        if !hex.len().is_multiple_of(2) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return errinput!("invalid hex string x'{string}'");
+        }
+        Ok(Some(Token::HexString(hex)))
+    }
+
     /// Scans the next symbol token, if any.
     fn scan_symbol(&mut self) -> Option<Token> {
         let mut token = self.next_if_map(|c| {
             Some(match c {
                 '.' => Token::Period,
+                ':' => Token::Colon,
                 '=' => Token::Equal,
                 '>' => Token::GreaterThan,
                 '<' => Token::LessThan,
@@ -469,6 +816,8 @@ impl<'a> Lexer<'a> {
                 '%' => Token::Percent,
                 '!' => Token::Exclamation,
                 '?' => Token::Question,
+                '~' => Token::Tilde,
+                '|' => Token::Pipe,
                 ',' => Token::Comma,
                 ';' => Token::Semicolon,
                 '(' => Token::OpenParen,
@@ -478,27 +827,61 @@ impl<'a> Lexer<'a> {
         })?;
         // Handle two-character tokens, e.g. !=.
         token = match token {
+            Token::Colon if self.next_is(':') => Token::DoubleColon,
             Token::Exclamation if self.next_is('=') => Token::NotEqual,
+            Token::Exclamation if self.next_is('~') => match self.next_is('*') {
+                true => Token::NotTildeAsterisk,
+                false => Token::NotTilde,
+            },
             Token::GreaterThan if self.next_is('=') => Token::GreaterThanOrEqual,
             Token::LessThan if self.next_is('>') => Token::LessOrGreaterThan,
             Token::LessThan if self.next_is('=') => Token::LessThanOrEqual,
+            Token::Tilde if self.next_is('*') => Token::TildeAsterisk,
+            Token::Pipe if self.next_is('|') => Token::Concat,
             token => token,
         };
         Some(token)
     }
 
-    /// Skips any whitespace.
-    fn skip_whitespace(&mut self) {
-        while self.next_if(|c| c.is_whitespace()).is_some() {}
+    /// Skips any whitespace and comments. A -- comment runs until the end of
+    /// the line, and a /* */ comment until the matching */ (they can nest).
+    fn skip_whitespace_and_comments(&mut self) -> Result<()> {
+        loop {
+            while self.next_if(|c| c.is_whitespace()).is_some() {}
+            let mut lookahead = self.chars.clone();
+            match (lookahead.next(), lookahead.next()) {
+                (Some('-'), Some('-')) => while self.next_if(|c| c != '\n').is_some() {},
+                (Some('/'), Some('*')) => {
+                    let start = self.offset();
+                    self.chars.nth(1);
+                    let mut depth = 1;
+                    while depth > 0 {
+                        match self.chars.next() {
+                            Some('/') if self.next_is('*') => depth += 1,
+                            Some('*') if self.next_is('/') => depth -= 1,
+                            Some(_) => {}
+                            None => {
+                                let err = Error::InvalidInput("unterminated comment".into());
+                                return Err(self.position(start).locate(err));
+                            }
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
     }
 }
 
-/// Returns true if the entire given string is a single valid identifier.
-#[allow(dead_code)]
+/// Returns true if the entire given string is a single valid unquoted
+/// identifier, which lexes as itself (i.e. it is lowercase).
 pub fn is_ident(ident: &str) -> bool {
     let mut lexer = Lexer::new(ident);
-    let Some(Ok(Token::Ident(_))) = lexer.next() else {
+    let Some(Ok((Token::Ident(name), _))) = lexer.next() else {
         return false;
     };
+    if name != ident {
+        return false;
+    }
     lexer.next().is_none() // if further tokens, it's not a lone identifier
 }
@@ -2,6 +2,8 @@
 pub mod ast;
 mod lexer;
 mod parser;
+mod suggest;
 
-pub use lexer::{Keyword, Lexer, Token};
+pub use lexer::{Keyword, Lexer, Position, Token};
 pub use parser::Parser;
+pub use suggest::did_you_mean;
@@ -1,8 +1,14 @@
 #![allow(clippy::module_inception)]
 
+use super::lexer::Position;
+use super::suggest::did_you_mean;
 use super::{ast, Keyword, Lexer, Token};
-use crate::common::Result;
+use crate::common::{Error, Result};
 use crate::errinput;
+use crate::types::bytes;
+use crate::types::collation::Collation;
+use crate::types::decimal::MAX_PRECISION;
+use crate::types::temporal::{self, Interval};
 use crate::types::DataType;
 
 /// The SQL parser takes tokens from the lexer and parses the SQL syntax into an
@@ -12,44 +18,114 @@ use crate::types::DataType;
 /// well-formed, and does not know whether e.g. a given table or column exists
 /// or which kind of join to use -- that is the job of the planner.
 pub struct Parser<'a> {
+    input: &'a str,
     pub lexer: std::iter::Peekable<Lexer<'a>>,
+    /// The position of the last token that was consumed or peeked, used to
+    /// locate parse errors. None after a lexer error, which is located by the
+    /// lexer itself.
+    position: Option<Position>,
+    /// True if the last consumed token was a semicolon, i.e. the end of a
+    /// statement. Used to skip the rest of a statement after an error.
+    semicolon: bool,
+    /// The number of ? parameters in the current statement, used to number
+    /// them by position.
+    parameters: usize,
 }
 
 impl<'a> Parser<'a> {
     /// Creates a new parser for the given raw SQL string.
     pub fn new(statement: &str) -> Parser {
         Parser {
+            input: statement,
             lexer: Lexer::new(statement).peekable(),
+            position: None,
+            semicolon: false,
+            parameters: 0,
         }
     }
 
     /// Parses the input string into an AST statement. The whole string must be
     /// parsed as a single statement, ending with an optional semicolon.
     pub fn parse(&mut self) -> Result<ast::Statement> {
-        let statement = self.parse_statement()?;
-        self.next_is(Token::Semicolon);
-        if let Some(token) = self.lexer.next().transpose()? {
-            return errinput!("unexpected token {token}");
+        let result = self.parse_statement().and_then(|statement| {
+            self.next_is(Token::Semicolon);
+            if self.peek()?.is_some() {
+                return errinput!("unexpected token {}", self.next()?);
+            }
+            Ok(statement)
+        });
+        result.map_err(|err| self.locate(err))
+    }
+
+    /// Parses the next statement of a script, where statements are separated
+    /// by semicolons. Returns None at the end of the input, skipping empty
+    /// statements. If a statement fails to parse, the rest of it is skipped up
+    /// to the next semicolon, such that parsing can resume with the next one.
+    pub fn parse_next(&mut self) -> Option<Result<ast::Statement>> {
+        while self.next_is(Token::Semicolon) {}
+        self.lexer.peek()?;
+        self.semicolon = false;
+        self.parameters = 0;
+        let result = self
+            .parse_statement()
+            .and_then(|statement| match self.peek()? {
+                None | Some(Token::Semicolon) => Ok(statement),
+                Some(_) => errinput!("unexpected token {}", self.next()?),
+            })
+            .map_err(|err| self.locate(err));
+        if result.is_err() && !self.semicolon {
+            for token in self.lexer.by_ref() {
+                if let Ok((Token::Semicolon, _)) = token {
+                    break;
+                }
+            }
+        }
+        Some(result)
+    }
+
+    /// Locates a parse error at the last consumed or peeked token.
+    fn locate(&self, err: Error) -> Error {
+        match &self.position {
+            Some(position) => position.locate(err),
+            None => err,
         }
-        Ok(statement)
     }
 
     /// Fetches the next lexer token, or errors if none is found.
     fn next(&mut self) -> Result<Token> {
-        self.lexer
-            .next()
-            .transpose()?
-            .ok_or_else(|| errinput!("unexpected end of input"))
+        let next = self.lexer.next().transpose();
+        self.position = match &next {
+            Ok(Some((_, position))) => Some(position.clone()),
+            Ok(None) => Some(Position::new(self.input, self.input.len(), 0)),
+            Err(_) => None,
+        };
+        let Some((token, _)) = next? else {
+            return errinput!("unexpected end of input");
+        };
+        self.semicolon = token == Token::Semicolon;
+        Ok(token)
     }
 
-    /// Returns the next identifier, or errors if not found.
+    /// Returns the next identifier, or errors if not found. Unreserved
+    /// keywords are accepted as identifiers.
     fn next_ident(&mut self) -> Result<String> {
         match self.next()? {
             Token::Ident(ident) => Ok(ident),
+            Token::Keyword(keyword) if keyword.is_unreserved() => {
+                Ok(keyword.to_string().to_lowercase())
+            }
             token => errinput!("expected identifier, got {token}"),
         }
     }
 
+    /// Returns the next number as a u8, or errors if not found.
+    fn next_u8(&mut self) -> Result<u8> {
+        match self.next()? {
+            Token::Number(n) => n.parse().or_else(|_| errinput!("invalid number {n}")),
+            token => errinput!("expected number, got {token}"),
+        }
+    }
+
     /// Returns the next lexer token if it satisfies the predicate.
     fn next_if(&mut self, predicate: impl Fn(&Token) -> bool) -> Option<Token> {
         self.peek().unwrap_or(None).filter(|t| predicate(t))?;
@@ -83,7 +159,13 @@ impl<'a> Parser<'a> {
     fn expect(&mut self, expect: Token) -> Result<()> {
         let token = self.next()?;
         if token != expect {
-            return errinput!("expected token {expect}, found {token}");
+            let hint = match (&token, &expect) {
+                (Token::Ident(ident), Token::Keyword(keyword)) => {
+                    did_you_mean(ident, [keyword.to_string().as_str()])
+                }
+                _ => String::new(),
+            };
+            return errinput!("expected token {expect}, found {token}{hint}");
         }
         Ok(())
     }
@@ -96,9 +178,18 @@ impl<'a> Parser<'a> {
 
     /// Peeks the next lexer token if any, but transposes it for convenience.
     fn peek(&mut self) -> Result<Option<&Token>> {
+        self.position = match self.lexer.peek() {
+            Some(Ok((_, position))) => Some(position.clone()),
+            Some(Err(_)) => None,
+            None => Some(Position::new(self.input, self.input.len(), 0)),
+        };
         self.lexer
             .peek()
-            .map(|r| r.as_ref().map_err(|err| err.clone()))
+            .map(|r| {
+                r.as_ref()
+                    .map(|(token, _)| token)
+                    .map_err(|err| err.clone())
+            })
             .transpose()
     }
 
@@ -113,14 +204,50 @@ impl<'a> Parser<'a> {
             Token::Keyword(Keyword::Rollback) => self.parse_rollback(),
             Token::Keyword(Keyword::Explain) => self.parse_explain(),
 
-            Token::Keyword(Keyword::Create) => self.parse_create_table(),
-            Token::Keyword(Keyword::Drop) => self.parse_drop_table(),
+            Token::Keyword(Keyword::Prepare) => self.parse_prepare(),
+            Token::Keyword(Keyword::Execute) => self.parse_execute(),
+            Token::Keyword(Keyword::Deallocate) => self.parse_deallocate(),
+
+            // Look ahead past CREATE/DROP for TABLE or VIEW.
+            Token::Keyword(Keyword::Create) => match self.lexer.clone().nth(1) {
+                Some(Ok((Token::Keyword(Keyword::Table), _))) => self.parse_create_table(),
+                _ => self.parse_create_view(),
+            },
+            Token::Keyword(Keyword::Drop) => match self.lexer.clone().nth(1) {
+                Some(Ok((Token::Keyword(Keyword::Table), _))) => self.parse_drop_table(),
+                _ => self.parse_drop_view(),
+            },
+            Token::Keyword(Keyword::Refresh) => self.parse_refresh(),
 
             Token::Keyword(Keyword::Delete) => self.parse_delete(),
             Token::Keyword(Keyword::Insert) => self.parse_insert(),
-            Token::Keyword(Keyword::Select) => self.parse_select(),
+            Token::Keyword(Keyword::Select | Keyword::With) | Token::OpenParen => {
+                self.parse_query()
+            }
             Token::Keyword(Keyword::Update) => self.parse_update(),
 
+            Token::Ident(ident) => {
+                let keywords = [
+                    Keyword::Begin,
+                    Keyword::Commit,
+                    Keyword::Rollback,
+                    Keyword::Explain,
+                    Keyword::Prepare,
+                    Keyword::Execute,
+                    Keyword::Deallocate,
+                    Keyword::Create,
+                    Keyword::Drop,
+                    Keyword::Refresh,
+                    Keyword::Delete,
+                    Keyword::Insert,
+                    Keyword::Select,
+                    Keyword::Update,
+                    Keyword::With,
+                ]
+                .map(|keyword| keyword.to_string());
+                let hint = did_you_mean(ident, keywords.iter().map(String::as_str));
+                errinput!("unexpected token {ident}{hint}")
+            }
             token => errinput!("unexpected token {token}"),
         }
     }
@@ -173,6 +300,71 @@ impl<'a> Parser<'a> {
         Ok(ast::Statement::Explain(Box::new(self.parse_statement()?)))
     }
 
+    /// Parses a PREPARE statement, e.g. PREPARE name (INT) AS SELECT $1. Only
+    /// DELETE, INSERT, SELECT (including WITH) and UPDATE statements can be
+    /// prepared.
+    fn parse_prepare(&mut self) -> Result<ast::Statement> {
+        self.expect(Keyword::Prepare.into())?;
+        let name = self.next_ident()?;
+        let mut types = Vec::new();
+        if self.next_is(Token::OpenParen) {
+            loop {
+                types.push(self.parse_data_type()?);
+                if !self.next_is(Token::Comma) {
+                    break;
+                }
+            }
+            self.expect(Token::CloseParen)?;
+        }
+        self.expect(Keyword::As.into())?;
+        match self.peek()? {
+            Some(Token::Keyword(
+                Keyword::Delete
+                | Keyword::Insert
+                | Keyword::Select
+                | Keyword::Update
+                | Keyword::With,
+            ))
+            | Some(Token::OpenParen) => {}
+            Some(token) => return errinput!("can't prepare {token} statement"),
+            None => return errinput!("unexpected end of input"),
+        }
+        let statement = Box::new(self.parse_statement()?);
+        Ok(ast::Statement::Prepare {
+            name,
+            types,
+            statement,
+        })
+    }
+
+    /// Parses an EXECUTE statement, e.g. EXECUTE name (1, 'a').
+    fn parse_execute(&mut self) -> Result<ast::Statement> {
+        self.expect(Keyword::Execute.into())?;
+        let name = self.next_ident()?;
+        let mut parameters = Vec::new();
+        if self.next_is(Token::OpenParen) {
+            loop {
+                parameters.push(self.parse_expression()?);
+                if !self.next_is(Token::Comma) {
+                    break;
+                }
+            }
+            self.expect(Token::CloseParen)?;
+        }
+        Ok(ast::Statement::Execute { name, parameters })
+    }
+
+    /// Parses a DEALLOCATE [PREPARE] name | ALL statement.
+    fn parse_deallocate(&mut self) -> Result<ast::Statement> {
+        self.expect(Keyword::Deallocate.into())?;
+        self.skip(Keyword::Prepare.into());
+        if self.next_is(Keyword::All.into()) {
+            return Ok(ast::Statement::Deallocate { name: None });
+        }
+        let name = Some(self.next_ident()?);
+        Ok(ast::Statement::Deallocate { name })
+    }
+
     /// Parses a CREATE TABLE statement.
     fn parse_create_table(&mut self) -> Result<ast::Statement> {
         self.expect(Keyword::Create.into())?;
@@ -193,19 +385,14 @@ impl<'a> Parser<'a> {
     /// Parses a CREATE TABLE column definition.
     fn parse_create_table_column(&mut self) -> Result<ast::Column> {
         let name = self.next_ident()?;
-        let datatype = match self.next()? {
-            Token::Keyword(Keyword::Bool | Keyword::Boolean) => DataType::Bool,
-            Token::Keyword(Keyword::Float | Keyword::Double) => DataType::Float,
-            Token::Keyword(Keyword::Int | Keyword::Integer) => DataType::Int,
-            Token::Keyword(Keyword::String | Keyword::Text | Keyword::Varchar) => DataType::Text,
-            token => return errinput!("unexpected token {token}"),
-        };
+        let datatype = self.parse_data_type()?;
         let mut column = ast::Column {
             name,
             datatype,
             primary_key: false,
             nullable: None,
             default: None,
+            collation: None,
             unique: false,
             index: false,
             references: None,
@@ -230,6 +417,7 @@ impl<'a> Parser<'a> {
                     column.nullable = Some(false)
                 }
                 Keyword::Default => column.default = Some(self.parse_expression()?),
+                Keyword::Collate => column.collation = Some(self.parse_collation()?),
                 Keyword::Unique => column.unique = true,
                 Keyword::Index => column.index = true,
                 Keyword::References => column.references = Some(self.next_ident()?),
@@ -239,6 +427,58 @@ impl<'a> Parser<'a> {
         Ok(column)
     }
 
+    /// Parses a column data type, e.g. for CREATE TABLE or CAST.
+    fn parse_data_type(&mut self) -> Result<DataType> {
+        Ok(match self.next()? {
+            Token::Keyword(Keyword::Bool | Keyword::Boolean) => DataType::Bool,
+            Token::Keyword(Keyword::Smallint) => DataType::SmallInt,
+            Token::Keyword(Keyword::Int | Keyword::Integer) => DataType::Int,
+            Token::Keyword(Keyword::Bigint) => DataType::BigInt,
+            Token::Keyword(Keyword::Float | Keyword::Real) => DataType::Float,
+            Token::Keyword(Keyword::Double) => {
+                self.next_is(Keyword::Precision.into());
+                DataType::Double
+            }
+            Token::Keyword(Keyword::Decimal | Keyword::Numeric) => self.parse_decimal_type()?,
+            Token::Keyword(Keyword::Date) => DataType::Date,
+            Token::Keyword(Keyword::Time) => DataType::Time,
+            Token::Keyword(Keyword::Timestamp) => DataType::Timestamp,
+            Token::Keyword(Keyword::Interval) => DataType::Interval,
+            Token::Keyword(Keyword::String | Keyword::Text | Keyword::Varchar) => DataType::Text,
+            Token::Keyword(Keyword::Bytea | Keyword::Blob) => DataType::Bytea,
+            token => return errinput!("unexpected token {token}"),
+        })
+    }
+
+    /// Parses the optional (precision, scale) of a DECIMAL column type. The
+    /// scale defaults to 0, and the precision to the maximum precision.
+    fn parse_decimal_type(&mut self) -> Result<DataType> {
+        let (mut precision, mut scale) = (MAX_PRECISION, 0);
+        if self.next_is(Token::OpenParen) {
+            precision = self.next_u8()?;
+            if self.next_is(Token::Comma) {
+                scale = self.next_u8()?;
+            }
+            self.expect(Token::CloseParen)?;
+        }
+        if precision == 0 || precision > MAX_PRECISION {
+            return errinput!("decimal precision must be between 1 and {MAX_PRECISION}");
+        }
+        if scale > precision {
+            return errinput!("decimal scale {scale} can't exceed precision {precision}");
+        }
+        Ok(DataType::Decimal(precision, scale))
+    }
+
+    /// Parses a collation name, e.g. for COLLATE nocase. The name may be given
+    /// as an identifier or a string.
+    fn parse_collation(&mut self) -> Result<Collation> {
+        match self.next()? {
+            Token::Ident(name) | Token::String(name) => Collation::lookup(&name),
+            token => errinput!("expected collation, got {token}"),
+        }
+    }
+
     /// Parses a DROP TABLE statement.
     fn parse_drop_table(&mut self) -> Result<ast::Statement> {
         self.expect(Token::Keyword(Keyword::Drop))?;
@@ -252,6 +492,60 @@ impl<'a> Parser<'a> {
         Ok(ast::Statement::DropTable { name, if_exists })
     }
 
+    /// Parses a CREATE [OR REPLACE] [MATERIALIZED] VIEW statement.
+    fn parse_create_view(&mut self) -> Result<ast::Statement> {
+        self.expect(Keyword::Create.into())?;
+        // REPLACE isn't a keyword, since it's also the replace() function.
+        let replace = self.next_is(Keyword::Or.into());
+        if replace {
+            match self.next()? {
+                Token::Ident(ident) if ident == "replace" => {}
+                token => return errinput!("expected token REPLACE, found {token}"),
+            }
+        }
+        let materialized = self.next_is(Keyword::Materialized.into());
+        if replace && materialized {
+            return errinput!("materialized views can't be replaced, drop them instead");
+        }
+        self.expect(Keyword::View.into())?;
+        let name = self.next_ident()?;
+        self.expect(Keyword::As.into())?;
+        let query = Box::new(self.parse_query()?);
+        Ok(ast::Statement::CreateView {
+            name,
+            query,
+            replace,
+            materialized,
+        })
+    }
+
+    /// Parses a DROP [MATERIALIZED] VIEW statement.
+    fn parse_drop_view(&mut self) -> Result<ast::Statement> {
+        self.expect(Keyword::Drop.into())?;
+        let materialized = self.next_is(Keyword::Materialized.into());
+        self.expect(Keyword::View.into())?;
+        let mut if_exists = false;
+        if self.next_is(Keyword::If.into()) {
+            self.expect(Keyword::Exists.into())?;
+            if_exists = true;
+        }
+        let name = self.next_ident()?;
+        Ok(ast::Statement::DropView {
+            name,
+            if_exists,
+            materialized,
+        })
+    }
+
+    /// Parses a REFRESH MATERIALIZED VIEW statement.
+    fn parse_refresh(&mut self) -> Result<ast::Statement> {
+        self.expect(Keyword::Refresh.into())?;
+        self.expect(Keyword::Materialized.into())?;
+        self.expect(Keyword::View.into())?;
+        let name = self.next_ident()?;
+        Ok(ast::Statement::RefreshMaterializedView { name })
+    }
+
     /// Parses a DELETE statement.
     fn parse_delete(&mut self) -> Result<ast::Statement> {
         self.expect(Keyword::Delete.into())?;
@@ -260,6 +554,7 @@ impl<'a> Parser<'a> {
         Ok(ast::Statement::Delete {
             table,
             r#where: self.parse_where_clause()?,
+            returning: self.parse_returning_clause()?,
         })
     }
 
@@ -269,8 +564,10 @@ impl<'a> Parser<'a> {
         self.expect(Keyword::Into.into())?;
         let table = self.next_ident()?;
 
+        // A parenthesized query isn't a column list, e.g. (SELECT 1).
         let mut columns = None;
-        if self.next_is(Token::OpenParen) {
+        if self.peek()? == Some(&Token::OpenParen) && !self.peek_query(1)? {
+            self.expect(Token::OpenParen)?;
             let columns = columns.insert(Vec::new());
             loop {
                 columns.push(self.next_ident()?);
@@ -281,6 +578,25 @@ impl<'a> Parser<'a> {
             self.expect(Token::CloseParen)?;
         }
 
+        let source = match self.peek()? {
+            Some(Token::Keyword(Keyword::Select | Keyword::With) | Token::OpenParen) => {
+                ast::InsertSource::Query(Box::new(self.parse_query()?))
+            }
+            _ => ast::InsertSource::Values(self.parse_values()?),
+        };
+        let on_conflict = self.parse_on_conflict_clause()?;
+
+        Ok(ast::Statement::Insert {
+            table,
+            columns,
+            source,
+            on_conflict,
+            returning: self.parse_returning_clause()?,
+        })
+    }
+
+    /// Parses the VALUES rows of an INSERT.
+    fn parse_values(&mut self) -> Result<Vec<Vec<ast::Expression>>> {
         self.expect(Keyword::Values.into())?;
 
         let mut values = Vec::new();
@@ -299,14 +615,55 @@ impl<'a> Parser<'a> {
                 break;
             }
         }
+        Ok(values)
+    }
 
-        Ok(ast::Statement::Insert { table, values })
+    /// Parses an ON CONFLICT clause of an INSERT, if present.
+    fn parse_on_conflict_clause(&mut self) -> Result<Option<ast::OnConflict>> {
+        if !self.next_is(Keyword::On.into()) {
+            return Ok(None);
+        }
+        self.expect(Keyword::Conflict.into())?;
+        let mut columns = Vec::new();
+        if self.next_is(Token::OpenParen) {
+            loop {
+                columns.push(self.next_ident()?);
+                if !self.next_is(Token::Comma) {
+                    break;
+                }
+            }
+            self.expect(Token::CloseParen)?;
+        }
+        self.expect(Keyword::Do.into())?;
+        let action = match self.next_is(Keyword::Nothing.into()) {
+            true => ast::ConflictAction::Nothing,
+            false => {
+                self.expect(Keyword::Update.into())?;
+                ast::ConflictAction::Update {
+                    set: self.parse_set_clause()?,
+                    r#where: self.parse_where_clause()?,
+                }
+            }
+        };
+        Ok(Some(ast::OnConflict { columns, action }))
     }
 
     /// Parses an UPDATE statement.
     fn parse_update(&mut self) -> Result<ast::Statement> {
         self.expect(Keyword::Update.into())?;
         let table = self.next_ident()?;
+        Ok(ast::Statement::Update {
+            table,
+            set: self.parse_set_clause()?,
+            r#where: self.parse_where_clause()?,
+            returning: self.parse_returning_clause()?,
+        })
+    }
+
+    /// Parses a SET clause of an UPDATE or ON CONFLICT DO UPDATE.
+    fn parse_set_clause(
+        &mut self,
+    ) -> Result<std::collections::BTreeMap<String, Option<ast::Expression>>> {
         self.expect(Keyword::Set.into())?;
         let mut set = std::collections::BTreeMap::new();
         loop {
@@ -323,38 +680,207 @@ impl<'a> Parser<'a> {
                 break;
             }
         }
-        Ok(ast::Statement::Update {
-            table,
-            set,
-            r#where: self.parse_where_clause()?,
-        })
+        Ok(set)
+    }
+
+    /// Parses a query: a SELECT statement or a set operation combining them,
+    /// optionally with WITH, ORDER BY, LIMIT and OFFSET clauses.
+    fn parse_query(&mut self) -> Result<ast::Statement> {
+        if let Some(Token::Keyword(Keyword::With)) = self.peek()? {
+            return self.parse_with();
+        }
+        let mut query = self.parse_set_operation(0)?;
+        let order_by = self.parse_order_by_clause()?;
+        let limit = self
+            .next_is(Keyword::Limit.into())
+            .then(|| self.parse_expression())
+            .transpose()?;
+        let offset = self
+            .next_is(Keyword::Offset.into())
+            .then(|| self.parse_expression())
+            .transpose()?;
+        if order_by.is_empty() && limit.is_none() && offset.is_none() {
+            return Ok(query);
+        }
+        // The clauses apply to the whole query. A parenthesized query may
+        // already have its own.
+        match &mut query {
+            ast::Statement::Select {
+                order_by: o,
+                offset: f,
+                limit: l,
+                ..
+            }
+            | ast::Statement::SetOperation {
+                order_by: o,
+                offset: f,
+                limit: l,
+                ..
+            } if o.is_empty() && f.is_none() && l.is_none() => {
+                (*o, *f, *l) = (order_by, offset, limit);
+            }
+            _ => return errinput!("multiple ORDER BY, LIMIT or OFFSET clauses"),
+        }
+        Ok(query)
     }
 
-    /// Parses a SELECT statement.
+    /// Parses a set operation (e.g. SELECT 1 UNION SELECT 2) using precedence
+    /// climbing, or a single set operation operand. Operators with a
+    /// precedence below the given minimum are left for the caller.
+    fn parse_set_operation(&mut self, min_precedence: u8) -> Result<ast::Statement> {
+        let mut left = self.parse_set_operand()?;
+        loop {
+            let operator = match self.peek()? {
+                Some(Token::Keyword(Keyword::Except)) => ast::SetOperator::Except,
+                Some(Token::Keyword(Keyword::Intersect)) => ast::SetOperator::Intersect,
+                Some(Token::Keyword(Keyword::Union)) => ast::SetOperator::Union,
+                _ => break,
+            };
+            if operator.precedence() < min_precedence {
+                break;
+            }
+            self.next()?;
+            let all = self.next_is(Keyword::All.into());
+            let right = self.parse_set_operation(operator.precedence() + 1)?;
+            left = ast::Statement::SetOperation {
+                operator,
+                all,
+                left: Box::new(left),
+                right: Box::new(right),
+                order_by: Vec::new(),
+                offset: None,
+                limit: None,
+            };
+        }
+        Ok(left)
+    }
+
+    /// Parses a set operation operand, i.e. a SELECT statement without ORDER
+    /// BY, LIMIT and OFFSET, or any parenthesized query.
+    fn parse_set_operand(&mut self) -> Result<ast::Statement> {
+        match self.peek()? {
+            Some(Token::Keyword(Keyword::Select)) => self.parse_select(),
+            Some(Token::OpenParen) => {
+                self.next()?;
+                self.parse_subquery()
+            }
+            Some(token) => errinput!("expected SELECT, found {token}"),
+            None => errinput!("unexpected end of input"),
+        }
+    }
+
+    /// Parses a SELECT statement, without ORDER BY, LIMIT and OFFSET clauses
+    /// (see parse_query).
     fn parse_select(&mut self) -> Result<ast::Statement> {
+        self.expect(Keyword::Select.into())?;
         Ok(ast::Statement::Select {
+            distinct: self.parse_distinct()?,
             select: self.parse_select_clause()?,
             from: self.parse_from_clause()?,
             r#where: self.parse_where_clause()?,
             group_by: self.parse_group_by_clause()?,
             having: self.parse_having_clause()?,
-            order_by: self.parse_order_by_clause()?,
-            limit: self
-                .next_is(Keyword::Limit.into())
-                .then(|| self.parse_expression())
-                .transpose()?,
-            offset: self
-                .next_is(Keyword::Offset.into())
-                .then(|| self.parse_expression())
-                .transpose()?,
+            order_by: Vec::new(),
+            limit: None,
+            offset: None,
         })
     }
 
-    /// Parses a SELECT clause, if present.
-    fn parse_select_clause(&mut self) -> Result<Vec<(ast::Expression, Option<String>)>> {
-        if !self.next_is(Keyword::Select.into()) {
-            return Ok(Vec::new());
+    /// Parses a WITH query, e.g. WITH RECURSIVE t (n) AS (SELECT 1 UNION ALL
+    /// SELECT n + 1 FROM t WHERE n < 10) SELECT * FROM t.
+    fn parse_with(&mut self) -> Result<ast::Statement> {
+        self.expect(Keyword::With.into())?;
+        let recursive = self.next_is(Keyword::Recursive.into());
+        let mut ctes = Vec::new();
+        loop {
+            let name = self.next_ident()?;
+            let mut columns = Vec::new();
+            if self.next_is(Token::OpenParen) {
+                loop {
+                    columns.push(self.next_ident()?);
+                    if !self.next_is(Token::Comma) {
+                        break;
+                    }
+                }
+                self.expect(Token::CloseParen)?;
+            }
+            self.expect(Keyword::As.into())?;
+            self.expect(Token::OpenParen)?;
+            let query = Box::new(self.parse_subquery()?);
+            ctes.push(ast::CommonTableExpression {
+                name,
+                columns,
+                query,
+            });
+            if !self.next_is(Token::Comma) {
+                break;
+            }
+        }
+        if let Some(Token::Keyword(Keyword::With)) = self.peek()? {
+            return errinput!("unexpected token WITH");
+        }
+        let query = Box::new(self.parse_query()?);
+        Ok(ast::Statement::With {
+            recursive,
+            ctes,
+            query,
+        })
+    }
+
+    /// Returns true if the input following an opening parenthesis is a query
+    /// rather than an expression. This looks ahead for query keywords at the
+    /// parenthesis level, which distinguishes e.g. ((SELECT 1) UNION SELECT 2)
+    /// from ((SELECT 1) + 2). The given number of tokens is skipped first,
+    /// i.e. 1 if the parenthesis hasn't been consumed yet.
+    fn peek_query(&mut self, skip: usize) -> Result<bool> {
+        let mut depth = 0;
+        for result in self.lexer.clone().skip(skip) {
+            let Ok((token, _)) = result else {
+                return Ok(false); // surface the lexer error when parsing
+            };
+            match token {
+                Token::OpenParen => depth += 1,
+                Token::CloseParen if depth == 0 => return Ok(false),
+                Token::CloseParen => depth -= 1,
+                Token::Keyword(
+                    Keyword::Except
+                    | Keyword::Intersect
+                    | Keyword::Limit
+                    | Keyword::Offset
+                    | Keyword::Order
+                    | Keyword::Select
+                    | Keyword::Union
+                    | Keyword::With,
+                ) if depth == 0 => return Ok(true),
+                _ => {}
+            }
         }
+        Ok(false)
+    }
+
+    /// Parses DISTINCT or DISTINCT ON (expressions) after SELECT, if present.
+    /// SELECT ALL is the default.
+    fn parse_distinct(&mut self) -> Result<Option<Vec<ast::Expression>>> {
+        if !self.next_is(Keyword::Distinct.into()) {
+            self.next_is(Keyword::All.into());
+            return Ok(None);
+        }
+        let mut on = Vec::new();
+        if self.next_is(Keyword::On.into()) {
+            self.expect(Token::OpenParen)?;
+            loop {
+                on.push(self.parse_expression()?);
+                if !self.next_is(Token::Comma) {
+                    break;
+                }
+            }
+            self.expect(Token::CloseParen)?;
+        }
+        Ok(Some(on))
+    }
+
+    /// Parses the expressions of a SELECT clause.
+    fn parse_select_clause(&mut self) -> Result<Vec<(ast::Expression, Option<String>)>> {
         let mut select = Vec::new();
         loop {
             let expr = self.parse_expression()?;
@@ -373,6 +899,14 @@ impl<'a> Parser<'a> {
         Ok(select)
     }
 
+    /// Parses a RETURNING clause of an INSERT, UPDATE or DELETE, if present.
+    fn parse_returning_clause(&mut self) -> Result<Vec<(ast::Expression, Option<String>)>> {
+        if !self.next_is(Keyword::Returning.into()) {
+            return Ok(Vec::new());
+        }
+        self.parse_select_clause()
+    }
+
     /// Parses a FROM clause, if present.
     fn parse_from_clause(&mut self) -> Result<Vec<ast::From>> {
         if !self.next_is(Keyword::From.into()) {
@@ -404,8 +938,17 @@ impl<'a> Parser<'a> {
         Ok(from)
     }
 
-    // Parses a FROM table.
+    // Parses a FROM table, or a derived table (SELECT ...) AS alias.
     fn parse_from_table(&mut self) -> Result<ast::From> {
+        if self.next_is(Token::OpenParen) {
+            let query = Box::new(self.parse_subquery()?);
+            self.skip(Keyword::As.into());
+            let Some(Token::Ident(_)) = self.peek()? else {
+                return errinput!("subquery in FROM must have an alias");
+            };
+            let alias = self.next_ident()?;
+            return Ok(ast::From::Subquery { query, alias });
+        }
         let name = self.next_ident()?;
         let mut alias = None;
         if self.next_is(Keyword::As.into()) || matches!(self.peek()?, Some(Token::Ident(_))) {
@@ -414,6 +957,14 @@ impl<'a> Parser<'a> {
         Ok(ast::From::Table { name, alias })
     }
 
+    /// Parses a subquery, after the opening parenthesis and including the
+    /// closing parenthesis.
+    fn parse_subquery(&mut self) -> Result<ast::Statement> {
+        let query = self.parse_query()?;
+        self.expect(Token::CloseParen)?;
+        Ok(query)
+    }
+
     // Parses a FROM JOIN type, if present.
     fn parse_from_join(&mut self) -> Result<Option<ast::JoinType>> {
         if self.next_is(Keyword::Join.into()) {
@@ -449,14 +1000,14 @@ impl<'a> Parser<'a> {
     }
 
     /// Parses a GROUP BY clause, if present.
-    fn parse_group_by_clause(&mut self) -> Result<Vec<ast::Expression>> {
+    fn parse_group_by_clause(&mut self) -> Result<Vec<ast::GroupBy>> {
         if !self.next_is(Keyword::Group.into()) {
             return Ok(Vec::new());
         }
         let mut group_by = Vec::new();
         self.expect(Keyword::By.into())?;
         loop {
-            group_by.push(self.parse_expression()?);
+            group_by.push(self.parse_group_by_element()?);
             if !self.next_is(Token::Comma) {
                 break;
             }
@@ -464,6 +1015,57 @@ impl<'a> Parser<'a> {
         Ok(group_by)
     }
 
+    /// Parses a GROUP BY element: an expression, ROLLUP (...), CUBE (...) or
+    /// GROUPING SETS (...). Each grouping set in GROUPING SETS must be in
+    /// parentheses, e.g. GROUPING SETS ((a, b), (a), ()).
+    fn parse_group_by_element(&mut self) -> Result<ast::GroupBy> {
+        // GROUPING isn't a keyword, since it's also the grouping() function.
+        let is_grouping = matches!(self.peek()?, Some(Token::Ident(ident)) if ident == "grouping");
+        if is_grouping
+            && matches!(
+                self.lexer.clone().nth(1),
+                Some(Ok((Token::Keyword(Keyword::Sets), _)))
+            )
+        {
+            self.next()?;
+            self.next()?;
+            self.expect(Token::OpenParen)?;
+            let mut sets = Vec::new();
+            loop {
+                // A set is a parenthesized list, or a bare expression.
+                match self.peek()? {
+                    Some(Token::OpenParen) => sets.push(self.parse_expression_list()?),
+                    _ => sets.push(vec![self.parse_expression()?]),
+                }
+                if !self.next_is(Token::Comma) {
+                    break;
+                }
+            }
+            self.expect(Token::CloseParen)?;
+            return Ok(ast::GroupBy::GroupingSets(sets));
+        }
+        if self.next_is(Keyword::Rollup.into()) {
+            return Ok(ast::GroupBy::Rollup(self.parse_expression_list()?));
+        }
+        if self.next_is(Keyword::Cube.into()) {
+            return Ok(ast::GroupBy::Cube(self.parse_expression_list()?));
+        }
+        Ok(ast::GroupBy::Expression(self.parse_expression()?))
+    }
+
+    /// Parses a parenthesized, possibly empty, list of expressions.
+    fn parse_expression_list(&mut self) -> Result<Vec<ast::Expression>> {
+        self.expect(Token::OpenParen)?;
+        let mut exprs = Vec::new();
+        while !self.next_is(Token::CloseParen) {
+            if !exprs.is_empty() {
+                self.expect(Token::Comma)?;
+            }
+            exprs.push(self.parse_expression()?);
+        }
+        Ok(exprs)
+    }
+
     /// Parses a HAVING clause, if present.
     fn parse_having_clause(&mut self) -> Result<Option<ast::Expression>> {
         if !self.next_is(Keyword::Having.into()) {
@@ -496,6 +1098,71 @@ impl<'a> Parser<'a> {
         Ok(order_by)
     }
 
+    /// Parses a window specification following OVER, i.e.
+    /// (PARTITION BY ... ORDER BY ... frame).
+    fn parse_window(&mut self) -> Result<ast::Window> {
+        self.expect(Token::OpenParen)?;
+        let mut partition_by = Vec::new();
+        if self.next_is(Keyword::Partition.into()) {
+            self.expect(Keyword::By.into())?;
+            loop {
+                partition_by.push(self.parse_expression()?);
+                if !self.next_is(Token::Comma) {
+                    break;
+                }
+            }
+        }
+        let order_by = self.parse_order_by_clause()?;
+        let units = self.next_if_map(|token| match token {
+            Token::Keyword(Keyword::Rows) => Some(ast::FrameUnits::Rows),
+            Token::Keyword(Keyword::Range) => Some(ast::FrameUnits::Range),
+            _ => None,
+        });
+        let frame = match units {
+            Some(units) if self.next_is(Keyword::Between.into()) => {
+                let start = self.parse_frame_bound()?;
+                self.expect(Keyword::And.into())?;
+                let end = self.parse_frame_bound()?;
+                Some(ast::WindowFrame { units, start, end })
+            }
+            Some(units) => {
+                let start = self.parse_frame_bound()?;
+                let end = ast::FrameBound::CurrentRow;
+                Some(ast::WindowFrame { units, start, end })
+            }
+            None => None,
+        };
+        self.expect(Token::CloseParen)?;
+        Ok(ast::Window {
+            partition_by,
+            order_by,
+            frame,
+        })
+    }
+
+    /// Parses a window frame bound.
+    fn parse_frame_bound(&mut self) -> Result<ast::FrameBound> {
+        if self.next_is(Keyword::Unbounded.into()) {
+            return match self.next()? {
+                Token::Keyword(Keyword::Preceding) => Ok(ast::FrameBound::UnboundedPreceding),
+                Token::Keyword(Keyword::Following) => Ok(ast::FrameBound::UnboundedFollowing),
+                token => errinput!("expected PRECEDING or FOLLOWING, found {token}"),
+            };
+        }
+        if self.next_is(Keyword::Current.into()) {
+            self.expect(Keyword::Row.into())?;
+            return Ok(ast::FrameBound::CurrentRow);
+        }
+        // Parse the offset as an arithmetic expression, such that the AND
+        // between the bounds isn't consumed as an operator.
+        let offset = Box::new(self.parse_expression_at(InfixOperator::Concat.precedence())?);
+        match self.next()? {
+            Token::Keyword(Keyword::Preceding) => Ok(ast::FrameBound::Preceding(offset)),
+            Token::Keyword(Keyword::Following) => Ok(ast::FrameBound::Following(offset)),
+            token => errinput!("expected PRECEDING or FOLLOWING, found {token}"),
+        }
+    }
+
     /// Parses an expression consisting of at least one atom operated on by any
     /// number of operators, using the precedence climbing algorithm.
     ///
@@ -514,20 +1181,19 @@ impl<'a> Parser<'a> {
         } else {
             self.parse_expression_atom()?
         };
-        // Apply any postfix operators for the left-hand atom.
-        while let Some(postfix) = self.parse_postfix_operator(min_precedence)? {
-            lhs = postfix.build(lhs)
-        }
-        // Apply any binary infix operators, parsing the right-hand operand.
-        while let Some(infix) = self.parse_infix_operator(min_precedence) {
-            let at_precedence = infix.precedence() + infix.associativity();
-            let rhs = self.parse_expression_at(at_precedence)?;
-            lhs = infix.build(lhs, rhs);
-        }
-        // Apply any postfix operators after the binary operator. Consider e.g.
-        // 1 + NULL IS NULL.
-        while let Some(postfix) = self.parse_postfix_operator(min_precedence)? {
-            lhs = postfix.build(lhs)
+        // Apply any postfix operators and binary infix operators (parsing the
+        // right-hand operand) to the left-hand side, until there are no more.
+        // These can be interleaved, consider e.g. 1 + NULL IS NULL AND TRUE.
+        loop {
+            if let Some(postfix) = self.parse_postfix_operator(min_precedence)? {
+                lhs = postfix.build(lhs);
+            } else if let Some(infix) = self.parse_infix_operator(min_precedence) {
+                let at_precedence = infix.precedence() + infix.associativity();
+                let rhs = self.parse_expression_at(at_precedence)?;
+                lhs = infix.build(lhs, rhs);
+            } else {
+                break;
+            }
         }
         Ok(lhs)
     }
@@ -537,9 +1203,20 @@ impl<'a> Parser<'a> {
     /// * A literal value.
     /// * A column name.
     /// * A function call.
+    /// * A CASE expression.
     /// * A parenthesized expression.
+    /// * A subquery, or EXISTS subquery.
     fn parse_expression_atom(&mut self) -> Result<ast::Expression> {
-        Ok(match self.next()? {
+        let mut token = self.next()?;
+        let mut position = self.position.clone().unwrap_or_default();
+        // Unreserved keywords are column names, unless they start a typed
+        // literal such as DATE '2024-01-01'.
+        if let Token::Keyword(keyword) = &token {
+            if keyword.is_unreserved() && !matches!(self.peek()?, Some(Token::String(_))) {
+                token = Token::Ident(keyword.to_string().to_lowercase());
+            }
+        }
+        Ok(match token {
             // All columns.
             Token::Asterisk => ast::Expression::All,
 
@@ -549,14 +1226,72 @@ impl<'a> Parser<'a> {
             }
             Token::Number(n) => ast::Literal::Float(n.parse()?).into(),
             Token::String(s) => ast::Literal::String(s).into(),
+            Token::HexString(s) => ast::Literal::Bytes(bytes::decode_hex(&s)?).into(),
             Token::Keyword(Keyword::True) => ast::Literal::Boolean(true).into(),
             Token::Keyword(Keyword::False) => ast::Literal::Boolean(false).into(),
-            Token::Keyword(Keyword::Infinity) => ast::Literal::Float(f32::INFINITY).into(),
-            Token::Keyword(Keyword::NaN) => ast::Literal::Float(f32::NAN).into(),
+            Token::Keyword(Keyword::Infinity) => ast::Literal::Float(f64::INFINITY).into(),
+            Token::Keyword(Keyword::NaN) => ast::Literal::Float(f64::NAN).into(),
             Token::Keyword(Keyword::Null) => ast::Literal::Null.into(),
 
+            // Bind parameters. ? parameters are numbered by position, and $n
+            // parameters explicitly (starting at 1).
+            Token::Question => {
+                self.parameters += 1;
+                ast::Expression::Parameter(self.parameters - 1)
+            }
+            Token::Parameter(n) => match n.parse::<usize>() {
+                Ok(n @ 1..) => ast::Expression::Parameter(n - 1),
+                _ => return errinput!("invalid parameter ${n}"),
+            },
+
+            // Typed date/time literal, e.g. DATE '2024-01-01'.
+            Token::Keyword(
+                keyword @ (Keyword::Date | Keyword::Time | Keyword::Timestamp | Keyword::Interval),
+            ) => {
+                let value = match self.next()? {
+                    Token::String(value) => value,
+                    token => return errinput!("expected string after {keyword}, found {token}"),
+                };
+                match keyword {
+                    Keyword::Date => ast::Literal::Date(temporal::parse_date(&value)?),
+                    Keyword::Time => ast::Literal::Time(temporal::parse_time(&value)?),
+                    Keyword::Timestamp => {
+                        ast::Literal::Timestamp(temporal::parse_timestamp(&value)?)
+                    }
+                    _ => ast::Literal::Interval(Interval::parse(&value)?),
+                }
+                .into()
+            }
+
+            // CAST(expr AS type).
+            Token::Keyword(Keyword::Cast) => {
+                self.expect(Token::OpenParen)?;
+                let expr = self.parse_expression()?;
+                self.expect(Keyword::As.into())?;
+                let data_type = self.parse_data_type()?;
+                self.expect(Token::CloseParen)?;
+                ast::Operator::Cast(Box::new(expr), data_type).into()
+            }
+
+            // CASE [operand] WHEN ... THEN ... [ELSE ...] END.
+            Token::Keyword(Keyword::Case) => self.parse_case()?,
+
+            // extract(field FROM expr), where field is e.g. year or 'year'.
+            Token::Ident(name) if name == "extract" && self.next_is(Token::OpenParen) => {
+                let field = match self.next()? {
+                    Token::Ident(field) | Token::String(field) => field,
+                    token => return errinput!("expected date/time field, found {token}"),
+                };
+                self.expect(Keyword::From.into())?;
+                let expr = self.parse_expression()?;
+                self.expect(Token::CloseParen)?;
+                let args = vec![ast::Literal::String(field).into(), expr];
+                ast::Expression::Function(name, args, false)
+            }
+
             // Function call.
             Token::Ident(name) if self.next_is(Token::OpenParen) => {
+                let distinct = self.next_is(Keyword::Distinct.into());
                 let mut args = Vec::new();
                 while !self.next_is(Token::CloseParen) {
                     if !args.is_empty() {
@@ -564,14 +1299,50 @@ impl<'a> Parser<'a> {
                     }
                     args.push(self.parse_expression()?);
                 }
-                ast::Expression::Function(name, args)
+                if self.next_is(Keyword::Over.into()) {
+                    if distinct {
+                        return errinput!("DISTINCT is not supported in window functions");
+                    }
+                    let window = self.parse_window()?;
+                    return Ok(ast::Expression::Window(name, args, Box::new(window)));
+                }
+                if self.next_is(Keyword::Within.into()) {
+                    if distinct {
+                        return errinput!("DISTINCT is not supported in ordered-set aggregates");
+                    }
+                    self.expect(Keyword::Group.into())?;
+                    self.expect(Token::OpenParen)?;
+                    let mut order_by = self.parse_order_by_clause()?;
+                    if order_by.len() != 1 {
+                        return errinput!("WITHIN GROUP requires a single ORDER BY expression");
+                    }
+                    self.expect(Token::CloseParen)?;
+                    let order = Box::new(order_by.remove(0));
+                    return Ok(ast::Expression::WithinGroup(name, args, order));
+                }
+                ast::Expression::Function(name, args, distinct)
             }
 
             // Column name, either qualified as table.column or unqualified.
             Token::Ident(table) if self.next_is(Token::Period) => {
-                ast::Expression::Column(Some(table), self.next_ident()?)
+                let column = self.next_ident()?;
+                if let Some(end) = &self.position {
+                    position.length = end.offset + end.length - position.offset;
+                }
+                ast::Expression::Column(Some(table), column, position)
+            }
+            Token::Ident(column) => ast::Expression::Column(None, column, position),
+
+            // Scalar subquery.
+            Token::OpenParen if self.peek_query(0)? => {
+                ast::Expression::Subquery(Box::new(self.parse_subquery()?))
+            }
+
+            // EXISTS (SELECT ...).
+            Token::Keyword(Keyword::Exists) => {
+                self.expect(Token::OpenParen)?;
+                ast::Expression::Exists(Box::new(self.parse_subquery()?))
             }
-            Token::Ident(column) => ast::Expression::Column(None, column),
 
             // Parenthesized expression.
             Token::OpenParen => {
@@ -584,6 +1355,32 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// Parses a CASE expression, after the CASE keyword. If an operand is
+    /// given, this is a simple CASE which compares the operand with each WHEN
+    /// value. Otherwise, it's a searched CASE which evaluates each WHEN
+    /// condition.
+    fn parse_case(&mut self) -> Result<ast::Expression> {
+        let mut operand = None;
+        if !matches!(self.peek()?, Some(Token::Keyword(Keyword::When))) {
+            operand = Some(Box::new(self.parse_expression()?));
+        }
+        let mut when = Vec::new();
+        while self.next_is(Keyword::When.into()) {
+            let condition = self.parse_expression()?;
+            self.expect(Keyword::Then.into())?;
+            when.push((condition, self.parse_expression()?));
+        }
+        if when.is_empty() {
+            return errinput!("CASE must have at least one WHEN clause");
+        }
+        let mut r#else = None;
+        if self.next_is(Keyword::Else.into()) {
+            r#else = Some(Box::new(self.parse_expression()?));
+        }
+        self.expect(Keyword::End.into())?;
+        Ok(ast::Expression::Case(operand, when, r#else))
+    }
+
     /// Parses a prefix operator, if there is one and its precedence is at least
     /// min_precedence.
     fn parse_prefix_operator(&mut self, min_precedence: Precedence) -> Option<PrefixOperator> {
@@ -605,20 +1402,24 @@ impl<'a> Parser<'a> {
             let operator = match token {
                 Token::Asterisk => InfixOperator::Multiply,
                 Token::Caret => InfixOperator::Exponentiate,
+                Token::Concat => InfixOperator::Concat,
                 Token::Equal => InfixOperator::Equal,
                 Token::GreaterThan => InfixOperator::GreaterThan,
                 Token::GreaterThanOrEqual => InfixOperator::GreaterThanOrEqual,
                 Token::Keyword(Keyword::And) => InfixOperator::And,
-                Token::Keyword(Keyword::Like) => InfixOperator::Like,
                 Token::Keyword(Keyword::Or) => InfixOperator::Or,
                 Token::LessOrGreaterThan => InfixOperator::NotEqual,
                 Token::LessThan => InfixOperator::LessThan,
                 Token::LessThanOrEqual => InfixOperator::LessThanOrEqual,
                 Token::Minus => InfixOperator::Subtract,
                 Token::NotEqual => InfixOperator::NotEqual,
+                Token::NotTilde => InfixOperator::NotRegex,
+                Token::NotTildeAsterisk => InfixOperator::NotRegexCaseInsensitive,
                 Token::Percent => InfixOperator::Remainder,
                 Token::Plus => InfixOperator::Add,
                 Token::Slash => InfixOperator::Divide,
+                Token::Tilde => InfixOperator::Regex,
+                Token::TildeAsterisk => InfixOperator::RegexCaseInsensitive,
                 _ => return None,
             };
             Some(operator).filter(|op| op.precedence() >= min_precedence)
@@ -641,7 +1442,7 @@ impl<'a> Parser<'a> {
             self.expect(Keyword::Is.into())?;
             let not = self.next_is(Keyword::Not.into());
             let value = match self.next()? {
-                Token::Keyword(Keyword::NaN) => ast::Literal::Float(f32::NAN),
+                Token::Keyword(Keyword::NaN) => ast::Literal::Float(f64::NAN),
                 Token::Keyword(Keyword::Null) => ast::Literal::Null,
                 token => return errinput!("unexpected token {token}"),
             };
@@ -652,6 +1453,88 @@ impl<'a> Parser<'a> {
             return Ok(Some(operator));
         }
 
+        // Handle [NOT] IN (...), [NOT] BETWEEN and [NOT] [I]LIKE separately,
+        // since they're multiple tokens. NOT can't otherwise follow an
+        // expression.
+        if let Some(Token::Keyword(
+            Keyword::Not | Keyword::In | Keyword::Between | Keyword::Like | Keyword::ILike,
+        )) = self.peek()?
+        {
+            // They all have the same precedence.
+            if PostfixOperator::In(Vec::new()).precedence() < min_precedence {
+                return Ok(None);
+            }
+            let not = self.next_is(Keyword::Not.into());
+            let operator = match self.next()? {
+                Token::Keyword(Keyword::In) => {
+                    self.expect(Token::OpenParen)?;
+                    if self.peek_query(0)? {
+                        let query = self.parse_subquery()?;
+                        return Ok(Some(match not {
+                            false => PostfixOperator::InSubquery(query),
+                            true => PostfixOperator::NotInSubquery(query),
+                        }));
+                    }
+                    let mut list = vec![self.parse_expression()?];
+                    while self.next_is(Token::Comma) {
+                        list.push(self.parse_expression()?);
+                    }
+                    self.expect(Token::CloseParen)?;
+                    match not {
+                        false => PostfixOperator::In(list),
+                        true => PostfixOperator::NotIn(list),
+                    }
+                }
+                Token::Keyword(Keyword::Between) => {
+                    // Parse the bounds as arithmetic expressions, such that
+                    // the AND between them isn't consumed as an operator.
+                    let at_precedence = InfixOperator::Concat.precedence();
+                    let low = self.parse_expression_at(at_precedence)?;
+                    self.expect(Keyword::And.into())?;
+                    let high = self.parse_expression_at(at_precedence)?;
+                    match not {
+                        false => PostfixOperator::Between(low, high),
+                        true => PostfixOperator::NotBetween(low, high),
+                    }
+                }
+                Token::Keyword(keyword @ (Keyword::Like | Keyword::ILike)) => {
+                    // Parse the pattern as the right-hand operand of a
+                    // left-associative operator at this precedence.
+                    let at_precedence = PostfixOperator::In(Vec::new()).precedence() + 1;
+                    let pattern = self.parse_expression_at(at_precedence)?;
+                    let mut escape = None;
+                    if self.next_is(Keyword::Escape.into()) {
+                        escape = Some(self.parse_expression_at(at_precedence)?);
+                    }
+                    let case_insensitive = keyword == Keyword::ILike;
+                    match not {
+                        false => PostfixOperator::Like(pattern, escape, case_insensitive),
+                        true => PostfixOperator::NotLike(pattern, escape, case_insensitive),
+                    }
+                }
+                token => return errinput!("expected IN, BETWEEN or LIKE, found {token}"),
+            };
+            return Ok(Some(operator));
+        }
+
+        // Handle ::type separately, since the type can be multiple tokens.
+        if let Some(Token::DoubleColon) = self.peek()? {
+            if PostfixOperator::Cast(DataType::Invalid).precedence() < min_precedence {
+                return Ok(None);
+            }
+            self.expect(Token::DoubleColon)?;
+            return Ok(Some(PostfixOperator::Cast(self.parse_data_type()?)));
+        }
+
+        // Handle COLLATE separately, since it takes a collation name.
+        if let Some(Token::Keyword(Keyword::Collate)) = self.peek()? {
+            if PostfixOperator::Collate(Collation::Binary).precedence() < min_precedence {
+                return Ok(None);
+            }
+            self.expect(Keyword::Collate.into())?;
+            return Ok(Some(PostfixOperator::Collate(self.parse_collation()?)));
+        }
+
         Ok(self.next_if_map(|token| {
             let operator = match token {
                 Token::Exclamation => PostfixOperator::Factorial,
@@ -680,7 +1563,7 @@ impl PrefixOperator {
     fn precedence(&self) -> Precedence {
         match self {
             Self::Not => 3,
-            Self::Minus | Self::Plus => 10,
+            Self::Minus | Self::Plus => 11,
         }
     }
 
@@ -703,21 +1586,25 @@ impl PrefixOperator {
 
 /// Infix operators.
 enum InfixOperator {
-    Add,                // a + b
-    And,                // a AND b
-    Divide,             // a / b
-    Equal,              // a = b
-    Exponentiate,       // a ^ b
-    GreaterThan,        // a > b
-    GreaterThanOrEqual, // a >= b
-    LessThan,           // a < b
-    LessThanOrEqual,    // a <= b
-    Like,               // a LIKE b
-    Multiply,           // a * b
-    NotEqual,           // a != b
-    Or,                 // a OR b
-    Remainder,          // a % b
-    Subtract,           // a - b
+    Add,                     // a + b
+    And,                     // a AND b
+    Concat,                  // a || b
+    Divide,                  // a / b
+    Equal,                   // a = b
+    Exponentiate,            // a ^ b
+    GreaterThan,             // a > b
+    GreaterThanOrEqual,      // a >= b
+    LessThan,                // a < b
+    LessThanOrEqual,         // a <= b
+    Multiply,                // a * b
+    NotEqual,                // a != b
+    NotRegex,                // a !~ b
+    NotRegexCaseInsensitive, // a !~* b
+    Or,                      // a OR b
+    Regex,                   // a ~ b
+    RegexCaseInsensitive,    // a ~* b
+    Remainder,               // a % b
+    Subtract,                // a - b
 }
 
 impl InfixOperator {
@@ -730,14 +1617,21 @@ impl InfixOperator {
             Self::Or => 1,
             Self::And => 2,
             // Self::Not => 3
-            Self::Equal | Self::NotEqual | Self::Like => 4, // and Self::Is
+            // and Self::Is, Self::Like, Self::In, Self::Between
+            Self::Equal
+            | Self::NotEqual
+            | Self::Regex
+            | Self::RegexCaseInsensitive
+            | Self::NotRegex
+            | Self::NotRegexCaseInsensitive => 4,
             Self::GreaterThan
             | Self::GreaterThanOrEqual
             | Self::LessThan
             | Self::LessThanOrEqual => 5,
-            Self::Add | Self::Subtract => 6,
-            Self::Multiply | Self::Divide | Self::Remainder => 7,
-            Self::Exponentiate => 8,
+            Self::Concat => 6,
+            Self::Add | Self::Subtract => 7,
+            Self::Multiply | Self::Divide | Self::Remainder => 8,
+            Self::Exponentiate => 9,
         }
     }
 
@@ -755,6 +1649,7 @@ impl InfixOperator {
         match self {
             Self::Add => ast::Operator::Add(lhs, rhs).into(),
             Self::And => ast::Operator::And(lhs, rhs).into(),
+            Self::Concat => ast::Operator::Concat(lhs, rhs).into(),
             Self::Divide => ast::Operator::Divide(lhs, rhs).into(),
             Self::Equal => ast::Operator::Equal(lhs, rhs).into(),
             Self::Exponentiate => ast::Operator::Exponentiate(lhs, rhs).into(),
@@ -762,10 +1657,17 @@ impl InfixOperator {
             Self::GreaterThanOrEqual => ast::Operator::GreaterThanOrEqual(lhs, rhs).into(),
             Self::LessThan => ast::Operator::LessThan(lhs, rhs).into(),
             Self::LessThanOrEqual => ast::Operator::LessThanOrEqual(lhs, rhs).into(),
-            Self::Like => ast::Operator::Like(lhs, rhs).into(),
             Self::Multiply => ast::Operator::Multiply(lhs, rhs).into(),
             Self::NotEqual => ast::Operator::NotEqual(lhs, rhs).into(),
+            Self::NotRegex => {
+                ast::Operator::Not(ast::Operator::Regex(lhs, rhs, false).into()).into()
+            }
+            Self::NotRegexCaseInsensitive => {
+                ast::Operator::Not(ast::Operator::Regex(lhs, rhs, true).into()).into()
+            }
             Self::Or => ast::Operator::Or(lhs, rhs).into(),
+            Self::Regex => ast::Operator::Regex(lhs, rhs, false).into(),
+            Self::RegexCaseInsensitive => ast::Operator::Regex(lhs, rhs, true).into(),
             Self::Remainder => ast::Operator::Remainder(lhs, rhs).into(),
             Self::Subtract => ast::Operator::Subtract(lhs, rhs).into(),
         }
@@ -774,9 +1676,19 @@ impl InfixOperator {
 
 /// Postfix operators.
 enum PostfixOperator {
-    Factorial,           // a!
-    Is(ast::Literal),    // a IS NULL | NAN
-    IsNot(ast::Literal), // a IS NOT NULL | NAN
+    Between(ast::Expression, ast::Expression), // a BETWEEN b AND c
+    Cast(DataType),                            // a::type
+    Collate(Collation),                        // a COLLATE collation
+    Factorial,                                 // a!
+    In(Vec<ast::Expression>),                  // a IN (b, c)
+    InSubquery(ast::Statement),                // a IN (SELECT ...)
+    Is(ast::Literal),                          // a IS NULL | NAN
+    IsNot(ast::Literal),                       // a IS NOT NULL | NAN
+    Like(ast::Expression, Option<ast::Expression>, bool), // a [I]LIKE b [ESCAPE c]
+    NotBetween(ast::Expression, ast::Expression), // a NOT BETWEEN b AND c
+    NotIn(Vec<ast::Expression>),               // a NOT IN (b, c)
+    NotInSubquery(ast::Statement),             // a NOT IN (SELECT ...)
+    NotLike(ast::Expression, Option<ast::Expression>, bool), // a NOT [I]LIKE b [ESCAPE c]
 }
 
 impl PostfixOperator {
@@ -784,7 +1696,11 @@ impl PostfixOperator {
     fn precedence(&self) -> Precedence {
         match self {
             Self::Is(_) | Self::IsNot(_) => 4,
-            Self::Factorial => 9,
+            Self::In(_) | Self::NotIn(_) | Self::Between(_, _) | Self::NotBetween(_, _) => 4,
+            Self::InSubquery(_) | Self::NotInSubquery(_) => 4,
+            Self::Like(_, _, _) | Self::NotLike(_, _, _) => 4,
+            Self::Factorial => 10,
+            Self::Cast(_) | Self::Collate(_) => 12,
         }
     }
 
@@ -792,9 +1708,29 @@ impl PostfixOperator {
     fn build(self, lhs: ast::Expression) -> ast::Expression {
         let lhs = Box::new(lhs);
         match self {
+            Self::Between(low, high) => ast::Operator::Between(lhs, low.into(), high.into()).into(),
+            Self::Cast(data_type) => ast::Operator::Cast(lhs, data_type).into(),
+            Self::Collate(collation) => ast::Operator::Collate(lhs, collation).into(),
             Self::Factorial => ast::Operator::Factorial(lhs).into(),
             Self::Is(v) => ast::Operator::Is(lhs, v).into(),
             Self::IsNot(v) => ast::Operator::Not(ast::Operator::Is(lhs, v).into()).into(),
+            Self::In(list) => ast::Operator::In(lhs, list).into(),
+            Self::InSubquery(query) => ast::Operator::InSubquery(lhs, query.into()).into(),
+            Self::Like(pattern, escape, ci) => {
+                ast::Operator::Like(lhs, pattern.into(), escape.map(Box::new), ci).into()
+            }
+            Self::NotBetween(low, high) => {
+                let between = ast::Operator::Between(lhs, low.into(), high.into());
+                ast::Operator::Not(between.into()).into()
+            }
+            Self::NotIn(list) => ast::Operator::Not(ast::Operator::In(lhs, list).into()).into(),
+            Self::NotInSubquery(query) => {
+                ast::Operator::Not(ast::Operator::InSubquery(lhs, query.into()).into()).into()
+            }
+            Self::NotLike(pattern, escape, ci) => {
+                let like = ast::Operator::Like(lhs, pattern.into(), escape.map(Box::new), ci);
+                ast::Operator::Not(like.into()).into()
+            }
         }
     }
 }
@@ -0,0 +1,69 @@
+/// Returns a " (did you mean x?)" hint with the candidate closest to the given
+/// misspelled name, or an empty string if none are close enough. Names are
+/// compared case-insensitively by edit distance, where up to a third of the
+/// characters may differ. Ties are broken alphabetically.
+pub fn did_you_mean<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> String {
+    let name = name.to_lowercase();
+    let max_distance = name.chars().count().div_ceil(3);
+    candidates
+        .into_iter()
+        .map(|candidate| (distance(&name, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| (1..=max_distance).contains(distance))
+        .min()
+        .map(|(_, candidate)| format!(" (did you mean {candidate}?)"))
+        .unwrap_or_default()
+}
+
+/// Returns the edit distance between two strings, i.e. the number of character
+/// insertions, deletions, substitutions and adjacent transpositions needed to
+/// turn one into the other (the optimal string alignment distance).
+fn distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    // The distances for the previous two rows and the current row, by prefix
+    // length of b.
+    let mut previous2 = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(previous2[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut previous2, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("select", "select"), 0);
+        assert_eq!(distance("selct", "select"), 1);
+        assert_eq!(distance("slecet", "select"), 2);
+        assert_eq!(distance("nmae", "name"), 1);
+        assert_eq!(distance("", "abc"), 3);
+        assert_eq!(distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        let candidates = ["select", "delete", "update"];
+        assert_eq!(did_you_mean("SELCT", candidates), " (did you mean select?)");
+        assert_eq!(did_you_mean("frobnicate", candidates), "");
+        assert_eq!(did_you_mean("select", candidates), "");
+        assert_eq!(
+            did_you_mean("ab", ["ac", "ab_", "aa"]),
+            " (did you mean aa?)"
+        );
+    }
+}
@@ -1,10 +1,14 @@
-use crate::common::Result;
+use crate::common::{Error, Result};
 use crate::errinput;
 use crate::sql::parser::ast;
-use crate::sql::planner::Node;
+use crate::sql::planner::{BoxedNode, Function, Node, Pattern};
 use crate::storage::tuple::Row;
+use crate::types::collation::Collation;
 use crate::types::field::{Field, Label};
+use crate::types::temporal::{self, DateTimeField};
+use crate::types::DataType;
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 
 /// An expression, made up of nested operations and values. Values are either
 /// constants or dynamic column references. Evaluates to a final value during
@@ -19,6 +23,23 @@ pub enum Expression {
     Constant(Field),
     /// A column reference. Used as row index when evaluating expressions.
     Column(usize),
+    /// A bind parameter, by 0-based index. Prepared statements replace these
+    /// with constants before execution, see Plan::bind().
+    Parameter(usize),
+    /// A column reference to the row of an enclosing query, from within a
+    /// correlated subquery, as (depth, index). The depth is the number of
+    /// queries in between, i.e. 0 for the immediately enclosing query. These
+    /// are replaced with constants before the subquery is executed.
+    OuterColumn(usize, usize),
+
+    /// A scalar subquery, which must return at most one row with a single
+    /// column. Returns its value, or NULL if it returns no rows: (SELECT a).
+    Subquery(BoxedNode),
+    /// Checks if a subquery returns any rows: EXISTS (SELECT ...).
+    Exists(BoxedNode),
+    /// Checks if a value is in the single-column result of a subquery:
+    /// a IN (SELECT b).
+    InSubquery(Box<Expression>, BoxedNode),
 
     /// Logical AND of two booleans: a AND b.
     And(Box<Expression>, Box<Expression>),
@@ -35,6 +56,10 @@ pub enum Expression {
     LessThan(Box<Expression>, Box<Expression>),
     /// Checks for the given value: IS NULL or IS NAN.
     Is(Box<Expression>, Field),
+    /// Checks if a value is in a list of values: a IN (b, c).
+    In(Box<Expression>, Vec<Expression>),
+    /// Checks if a value is within an inclusive range: a BETWEEN b AND c.
+    Between(Box<Expression>, Box<Expression>, Box<Expression>),
 
     /// Adds two numbers: a + b.
     Add(Box<Expression>, Box<Expression>),
@@ -54,11 +79,51 @@ pub enum Expression {
     Remainder(Box<Expression>, Box<Expression>),
     /// Takes the square root of a number: √a.
     SquareRoot(Box<Expression>),
+    /// Extracts a field from a date, time, timestamp or interval:
+    /// extract(year FROM a).
+    Extract(DateTimeField, Box<Expression>),
+    /// Truncates a date or timestamp to the given precision:
+    /// date_trunc('month', a).
+    DateTrunc(DateTimeField, Box<Expression>),
     /// Subtracts two numbers: a - b.
     Subtract(Box<Expression>, Box<Expression>),
 
-    // Checks if a string matches a pattern: a LIKE b.
-    Like(Box<Expression>, Box<Expression>),
+    /// Concatenates two strings or byte strings: a || b.
+    Concat(Box<Expression>, Box<Expression>),
+
+    /// Checks if a string matches a LIKE pattern, with an optional escape
+    /// character, case-insensitively if true: a [I]LIKE b [ESCAPE c].
+    Like(
+        Box<Expression>,
+        Box<Expression>,
+        Option<Box<Expression>>,
+        bool,
+    ),
+    /// Checks if a string matches a regular expression, case-insensitively if
+    /// true: a ~ b or a ~* b.
+    Regex(Box<Expression>, Box<Expression>, bool),
+    /// Checks if a string matches a constant pattern, compiled during
+    /// planning: a LIKE 'b%'.
+    Matches(Box<Expression>, Pattern),
+
+    /// Converts a value to the given type: CAST(a AS type).
+    Cast(Box<Expression>, DataType),
+    /// Converts a string to its sort key in the given collation, such that
+    /// keys compare in collation order: a COLLATE nocase. The planner wraps
+    /// the operands of comparisons and ORDER BY/GROUP BY expressions in this.
+    Collate(Box<Expression>, Collation),
+
+    /// Returns the THEN value of the first WHEN condition that is true, or the
+    /// ELSE value (NULL by default). With an operand, the WHEN values are
+    /// compared with the operand instead: CASE a WHEN b THEN c ELSE d END.
+    Case(
+        Option<Box<Expression>>,
+        Vec<(Expression, Expression)>,
+        Option<Box<Expression>>,
+    ),
+
+    /// Calls a scalar function with the given arguments: upper(a).
+    Function(Function, Vec<Expression>),
 }
 
 impl Expression {
@@ -70,14 +135,19 @@ impl Expression {
         // Precedence levels, for grouping. Matches the parser precedence.
         fn precedence(expr: &Expression) -> u8 {
             match expr {
-                Column(_) | Constant(_) | SquareRoot(_) => 11,
-                Identity(_) | Negate(_) => 10,
-                Factorial(_) => 9,
-                Exponentiate(_, _) => 8,
-                Multiply(_, _) | Divide(_, _) | Remainder(_, _) => 7,
-                Add(_, _) | Subtract(_, _) => 6,
+                Column(_) | Constant(_) | Parameter(_) | SquareRoot(_) => 12,
+                OuterColumn(_, _) | Subquery(_) | Exists(_) => 12,
+                Extract(_, _) | DateTrunc(_, _) => 12,
+                Cast(_, _) | Collate(_, _) | Expression::Function(_, _) | Case(_, _, _) => 12,
+                Identity(_) | Negate(_) => 11,
+                Factorial(_) => 10,
+                Exponentiate(_, _) => 9,
+                Multiply(_, _) | Divide(_, _) | Remainder(_, _) => 8,
+                Add(_, _) | Subtract(_, _) => 7,
+                Concat(_, _) => 6,
                 GreaterThan(_, _) | LessThan(_, _) => 5,
-                Equal(_, _) | Like(_, _) | Is(_, _) => 4,
+                Equal(_, _) | Is(_, _) | In(_, _) | InSubquery(_, _) | Between(_, _, _) => 4,
+                Like(_, _, _, _) | Regex(_, _, _) | Matches(_, _) => 4,
                 Not(_) => 3,
                 And(_, _) => 2,
                 Or(_, _) => 1,
@@ -99,6 +169,12 @@ impl Expression {
                 Label::None => format!("#{index}"),
                 label => format!("{label}"),
             },
+            Parameter(index) => format!("${}", index + 1),
+            OuterColumn(depth, index) => format!("outer{depth}.#{index}"),
+
+            Subquery(_) => "(subquery)".to_string(),
+            Exists(_) => "EXISTS (subquery)".to_string(),
+            InSubquery(expr, _) => format!("{} IN (subquery)", format(expr)),
 
             And(lhs, rhs) => format!("{} AND {}", format(lhs), format(rhs)),
             Or(lhs, rhs) => format!("{} OR {}", format(lhs), format(rhs)),
@@ -110,6 +186,23 @@ impl Expression {
             Is(expr, Field::Null) => format!("{} IS NULL", format(expr)),
             Is(expr, Field::Float(f)) if f.is_nan() => format!("{} IS NAN", format(expr)),
             Is(_, v) => panic!("unexpected IS value {v}"),
+            In(expr, list) => {
+                let list: Vec<String> = list.iter().map(|expr| expr.format(node)).collect();
+                format!("{} IN ({})", format(expr), list.join(", "))
+            }
+            Between(expr, low, high) => {
+                // The bounds are parsed as arithmetic expressions.
+                let format_bound = |expr: &Expression| match precedence(expr) {
+                    p if p < 6 => format!("({})", expr.format(node)),
+                    _ => expr.format(node),
+                };
+                format!(
+                    "{} BETWEEN {} AND {}",
+                    format(expr),
+                    format_bound(low),
+                    format_bound(high)
+                )
+            }
 
             Add(lhs, rhs) => format!("{} + {}", format(lhs), format(rhs)),
             Divide(lhs, rhs) => format!("{} / {}", format(lhs), format(rhs)),
@@ -120,9 +213,51 @@ impl Expression {
             Negate(expr) => format!("-{}", format(expr)),
             Remainder(lhs, rhs) => format!("{} % {}", format(lhs), format(rhs)),
             SquareRoot(expr) => format!("sqrt({})", format(expr)),
+            Extract(field, expr) => format!("extract({field} FROM {})", format(expr)),
+            DateTrunc(field, expr) => format!("date_trunc('{field}', {})", format(expr)),
             Subtract(lhs, rhs) => format!("{} - {}", format(lhs), format(rhs)),
 
-            Like(lhs, rhs) => format!("{} LIKE {}", format(lhs), format(rhs)),
+            Concat(lhs, rhs) => format!("{} || {}", format(lhs), format(rhs)),
+
+            Like(lhs, rhs, escape, case_insensitive) => {
+                let operator = if *case_insensitive { "ILIKE" } else { "LIKE" };
+                let mut string = format!("{} {operator} {}", format(lhs), format(rhs));
+                if let Some(escape) = escape {
+                    string += &format!(" ESCAPE {}", format(escape));
+                }
+                string
+            }
+            Regex(lhs, rhs, case_insensitive) => {
+                let operator = if *case_insensitive { "~*" } else { "~" };
+                format!("{} {operator} {}", format(lhs), format(rhs))
+            }
+            Matches(expr, pattern) => format!("{} {pattern}", format(expr)),
+
+            Cast(expr, data_type) => format!("CAST({} AS {data_type})", format(expr)),
+            Collate(expr, collation) => format!("{} COLLATE {collation}", format(expr)),
+
+            Expression::Function(function, args) => {
+                let args: Vec<String> = args.iter().map(|arg| arg.format(node)).collect();
+                format!("{function}({})", args.join(", "))
+            }
+
+            Case(operand, when, r#else) => {
+                let mut string = "CASE".to_string();
+                if let Some(operand) = operand {
+                    string += &format!(" {}", operand.format(node));
+                }
+                for (condition, then) in when {
+                    string += &format!(
+                        " WHEN {} THEN {}",
+                        condition.format(node),
+                        then.format(node)
+                    );
+                }
+                if let Some(r#else) = r#else {
+                    string += &format!(" ELSE {}", r#else.format(node));
+                }
+                string + " END"
+            }
         }
     }
 
@@ -133,6 +268,106 @@ impl Expression {
         })
     }
 
+    /// Returns the type of the expression's value, using the given plan node
+    /// to look up column types, or None if it can't be known at plan time,
+    /// e.g. for NULL, parameters, or integer division, which yields a float
+    /// when it isn't exact. Decimal arithmetic is also unknown, since the
+    /// result's scale depends on the values.
+    pub fn data_type(&self, node: &Node) -> Option<DataType> {
+        use DataType::*;
+        let arithmetic = |lhs: &Expression, rhs: &Expression| {
+            let data_type = lhs.data_type(node)?.numeric_type(rhs.data_type(node)?)?;
+            (!matches!(data_type, Decimal(_, _))).then_some(data_type)
+        };
+        match self {
+            Self::Constant(Field::Null) => None,
+            Self::Constant(value) => Some(value.get_type()),
+            Self::Column(index) => node.column_type(*index),
+            Self::Parameter(_) | Self::OuterColumn(_, _) => None,
+
+            Self::Subquery(node) => node.column_type(0),
+            Self::Exists(_)
+            | Self::InSubquery(_, _)
+            | Self::And(_, _)
+            | Self::Or(_, _)
+            | Self::Not(_)
+            | Self::Equal(_, _)
+            | Self::GreaterThan(_, _)
+            | Self::LessThan(_, _)
+            | Self::Is(_, _)
+            | Self::In(_, _)
+            | Self::Between(_, _, _)
+            | Self::Like(_, _, _, _)
+            | Self::Regex(_, _, _)
+            | Self::Matches(_, _) => Some(Bool),
+
+            Self::Add(lhs, rhs) => match (lhs.data_type(node)?, rhs.data_type(node)?) {
+                (Date, n) | (n, Date) if n.is_integer() => Some(Date),
+                (Date, Time) | (Time, Date) => Some(Timestamp),
+                (Date | Timestamp, Interval) | (Interval, Date | Timestamp) => Some(Timestamp),
+                (Time, Interval) | (Interval, Time) => Some(Time),
+                (Interval, Interval) => Some(Interval),
+                _ => arithmetic(lhs, rhs),
+            },
+            Self::Subtract(lhs, rhs) => match (lhs.data_type(node)?, rhs.data_type(node)?) {
+                (Date, Date) => Some(Int),
+                (Date, n) if n.is_integer() => Some(Date),
+                (Date | Timestamp, Interval) => Some(Timestamp),
+                (Time, Interval) => Some(Time),
+                (Interval, Interval) | (Timestamp, Timestamp) | (Time, Time) => Some(Interval),
+                _ => arithmetic(lhs, rhs),
+            },
+            Self::Multiply(lhs, rhs) => match (lhs.data_type(node)?, rhs.data_type(node)?) {
+                (Interval, n) | (n, Interval) if n.is_integer() => Some(Interval),
+                _ => arithmetic(lhs, rhs),
+            },
+            Self::Divide(lhs, rhs) => match arithmetic(lhs, rhs)? {
+                data_type if data_type.is_integer() => None,
+                data_type => Some(data_type),
+            },
+            Self::Remainder(lhs, rhs) => arithmetic(lhs, rhs),
+            Self::Exponentiate(_, _) => None,
+            Self::Factorial(expr) => expr.data_type(node).filter(DataType::is_integer),
+            Self::Identity(expr) | Self::Negate(expr) => expr.data_type(node),
+            Self::SquareRoot(expr) => match expr.data_type(node)? {
+                Float => Some(Float),
+                data_type if data_type.is_numeric() => Some(Double),
+                _ => None,
+            },
+            Self::Extract(field, _) => Some(match field {
+                DateTimeField::Second | DateTimeField::Millisecond | DateTimeField::Epoch => Double,
+                DateTimeField::Microsecond => BigInt,
+                _ => Int,
+            }),
+            Self::DateTrunc(_, _) => Some(Timestamp),
+
+            Self::Concat(lhs, rhs) => match (lhs.data_type(node), rhs.data_type(node)) {
+                (Some(Bytea), Some(Bytea)) => Some(Bytea),
+                (Some(Text), _) | (_, Some(Text)) => Some(Text),
+                _ => None,
+            },
+
+            Self::Cast(_, data_type) => Some(*data_type),
+            Self::Collate(expr, _) => expr.data_type(node),
+
+            // CASE only has a known type if all branches agree.
+            Self::Case(_, when, r#else) => {
+                let mut branches = when.iter().map(|(_, then)| then).chain(r#else.as_deref());
+                let data_type = branches.next()?.data_type(node)?;
+                branches
+                    .all(|branch| branch.data_type(node) == Some(data_type))
+                    .then_some(data_type)
+            }
+
+            Self::Function(function, args) => function.return_type(
+                &args
+                    .iter()
+                    .map(|arg| arg.data_type(node))
+                    .collect::<Vec<_>>(),
+            ),
+        }
+    }
+
     /// Evaluates an expression, returning a value. Column references look up
     /// values in the given row. If None, any Column references will panic.
     pub fn evaluate(&self, row: Option<&Row>) -> Result<Field> {
@@ -148,6 +383,18 @@ impl Expression {
                 None => panic!("can't reference column {index} with constant evaluation"),
             },
 
+            // Parameters must be bound before evaluation.
+            Self::Parameter(index) => return errinput!("unbound parameter ${}", index + 1),
+
+            // Outer columns and subqueries are resolved during execution, see
+            // execution::subquery.
+            Self::OuterColumn(depth, index) => {
+                panic!("can't reference outer column {depth}.{index} during evaluation")
+            }
+            Self::Subquery(_) | Self::Exists(_) | Self::InSubquery(_, _) => {
+                panic!("can't evaluate unresolved subquery")
+            }
+
             // Logical AND. Inputs must be boolean or NULL. NULLs generally
             // yield NULL, except the special case NULL AND false == false.
             Self::And(lhs, rhs) => match (lhs.evaluate(row)?, rhs.evaluate(row)?) {
@@ -173,19 +420,23 @@ impl Expression {
                 value => return errinput!("can't NOT {value}"),
             },
 
-            // Comparisons. Must be of same type, except floats and integers
-            // which are interchangeable. NULLs yield NULL, NaNs yield NaN.
+            // Comparisons. Must be of same type, except numbers which are
+            // interchangeable and compared after widening to a common type.
+            // NULLs yield NULL, NaNs yield NaN.
             //
             // Does not dispatch to Value.cmp() because sorting and comparisons
             // are different for f64 NaN and -0.0 values.
             #[allow(clippy::float_cmp)]
             Self::Equal(lhs, rhs) => match (lhs.evaluate(row)?, rhs.evaluate(row)?) {
                 (Boolean(lhs), Boolean(rhs)) => Boolean(lhs == rhs),
-                (Integer(lhs), Integer(rhs)) => Boolean(lhs == rhs),
-                (Integer(lhs), Float(rhs)) => Boolean(lhs as f32 == rhs),
-                (Float(lhs), Integer(rhs)) => Boolean(lhs == rhs as f32),
-                (Float(lhs), Float(rhs)) => Boolean(lhs == rhs),
+                (lhs, rhs) if lhs.is_numeric() && rhs.is_numeric() => {
+                    Boolean(lhs.partial_cmp_numeric(&rhs) == Some(Ordering::Equal))
+                }
+                (lhs, rhs) if lhs.is_temporal() && rhs.is_temporal() => {
+                    Boolean(lhs.cmp_temporal(&rhs)? == Ordering::Equal)
+                }
                 (String(lhs), String(rhs)) => Boolean(lhs == rhs),
+                (Bytes(lhs), Bytes(rhs)) => Boolean(lhs == rhs),
                 (Null, _) | (_, Null) => Null,
                 (lhs, rhs) => return errinput!("can't compare {lhs} and {rhs}"),
             },
@@ -193,11 +444,14 @@ impl Expression {
             Self::GreaterThan(lhs, rhs) => match (lhs.evaluate(row)?, rhs.evaluate(row)?) {
                 #[allow(clippy::bool_comparison)]
                 (Boolean(lhs), Boolean(rhs)) => Boolean(lhs > rhs),
-                (Integer(lhs), Integer(rhs)) => Boolean(lhs > rhs),
-                (Integer(lhs), Float(rhs)) => Boolean(lhs as f32 > rhs),
-                (Float(lhs), Integer(rhs)) => Boolean(lhs > rhs as f32),
-                (Float(lhs), Float(rhs)) => Boolean(lhs > rhs),
+                (lhs, rhs) if lhs.is_numeric() && rhs.is_numeric() => {
+                    Boolean(lhs.partial_cmp_numeric(&rhs) == Some(Ordering::Greater))
+                }
+                (lhs, rhs) if lhs.is_temporal() && rhs.is_temporal() => {
+                    Boolean(lhs.cmp_temporal(&rhs)? == Ordering::Greater)
+                }
                 (String(lhs), String(rhs)) => Boolean(lhs > rhs),
+                (Bytes(lhs), Bytes(rhs)) => Boolean(lhs > rhs),
                 (Null, _) | (_, Null) => Null,
                 (lhs, rhs) => return errinput!("can't compare {lhs} and {rhs}"),
             },
@@ -205,11 +459,14 @@ impl Expression {
             Self::LessThan(lhs, rhs) => match (lhs.evaluate(row)?, rhs.evaluate(row)?) {
                 #[allow(clippy::bool_comparison)]
                 (Boolean(lhs), Boolean(rhs)) => Boolean(lhs < rhs),
-                (Integer(lhs), Integer(rhs)) => Boolean(lhs < rhs),
-                (Integer(lhs), Float(rhs)) => Boolean((lhs as f32) < rhs),
-                (Float(lhs), Integer(rhs)) => Boolean(lhs < rhs as f32),
-                (Float(lhs), Float(rhs)) => Boolean(lhs < rhs),
+                (lhs, rhs) if lhs.is_numeric() && rhs.is_numeric() => {
+                    Boolean(lhs.partial_cmp_numeric(&rhs) == Some(Ordering::Less))
+                }
+                (lhs, rhs) if lhs.is_temporal() && rhs.is_temporal() => {
+                    Boolean(lhs.cmp_temporal(&rhs)? == Ordering::Less)
+                }
                 (String(lhs), String(rhs)) => Boolean(lhs < rhs),
+                (Bytes(lhs), Bytes(rhs)) => Boolean(lhs < rhs),
                 (Null, _) | (_, Null) => Null,
                 (lhs, rhs) => return errinput!("can't compare {lhs} and {rhs}"),
             },
@@ -217,11 +474,48 @@ impl Expression {
             Self::Is(expr, Null) => Boolean(expr.evaluate(row)? == Null),
             Self::Is(expr, Float(f)) if f.is_nan() => match expr.evaluate(row)? {
                 Float(f) => Boolean(f.is_nan()),
+                Double(f) => Boolean(f.is_nan()),
                 Null => Null,
                 v => return errinput!("IS NAN can't be used with {}", v.get_type()),
             },
             Self::Is(_, v) => panic!("invalid IS value {v}"), // enforced by parser
 
+            // IN is true if the value equals any list value. Otherwise, it's
+            // NULL if the value or any list value is NULL, else false.
+            Self::In(expr, list) => {
+                let value = expr.evaluate(row)?;
+                if value.is_null() {
+                    return Ok(Null);
+                }
+                let mut result = Boolean(false);
+                for item in list {
+                    let equal = Self::Equal(value.clone().into(), item.evaluate(row)?.into());
+                    match equal.evaluate(None)? {
+                        Boolean(true) => return Ok(Boolean(true)),
+                        Null => result = Null,
+                        _ => {}
+                    }
+                }
+                result
+            }
+
+            // BETWEEN is equivalent to a >= b AND a <= c, but only evaluates a
+            // once.
+            Self::Between(expr, low, high) => {
+                let value: Box<Expression> = expr.evaluate(row)?.into();
+                let (low, high): (Box<Expression>, Box<Expression>) =
+                    (low.evaluate(row)?.into(), high.evaluate(row)?.into());
+                let above = Self::Or(
+                    Self::GreaterThan(value.clone(), low.clone()).into(),
+                    Self::Equal(value.clone(), low).into(),
+                );
+                let below = Self::Or(
+                    Self::LessThan(value.clone(), high.clone()).into(),
+                    Self::Equal(value, high).into(),
+                );
+                Self::And(above.into(), below.into()).evaluate(None)?
+            }
+
             // Mathematical operations. Inputs must be numbers, but integers and
             // floats are interchangeable (float when mixed). NULLs yield NULL.
             // Errors on integer overflow, while floats yield infinity or NaN.
@@ -229,47 +523,129 @@ impl Expression {
             Self::Divide(lhs, rhs) => lhs.evaluate(row)?.checked_div(&rhs.evaluate(row)?)?,
             Self::Exponentiate(lhs, rhs) => lhs.evaluate(row)?.checked_pow(&rhs.evaluate(row)?)?,
             Self::Factorial(expr) => match expr.evaluate(row)? {
-                Integer(i) if i < 0 => return errinput!("can't take factorial of negative number"),
-                Integer(i) => (1..=i).try_fold(Integer(1), |p, i| p.checked_mul(&Integer(i)))?,
                 Null => Null,
-                value => return errinput!("can't take factorial of {value}"),
+                value => match value.as_i64() {
+                    Some(i) if i < 0 => {
+                        return errinput!("can't take factorial of negative number")
+                    }
+                    Some(i) => match (1..=i).try_fold(1_i64, i64::checked_mul) {
+                        Some(p) => BigInt(p).convert_numeric(value.get_type())?,
+                        None => return Err(Error::OverflowError),
+                    },
+                    None => return errinput!("can't take factorial of {value}"),
+                },
             },
             Self::Identity(expr) => match expr.evaluate(row)? {
-                v @ (Integer(_) | Float(_) | Null) => v,
+                v if v.is_numeric() || v.is_null() => v,
                 expr => return errinput!("can't take the identity of {expr}"),
             },
             Self::Multiply(lhs, rhs) => lhs.evaluate(row)?.checked_mul(&rhs.evaluate(row)?)?,
-            Self::Negate(expr) => match expr.evaluate(row)? {
-                Integer(i) => Integer(-i),
-                Float(f) => Float(-f),
-                Null => Null,
-                value => return errinput!("can't negate {value}"),
-            },
+            Self::Negate(expr) => expr.evaluate(row)?.checked_neg()?,
             Self::Remainder(lhs, rhs) => lhs.evaluate(row)?.checked_mod(&rhs.evaluate(row)?)?,
             Self::SquareRoot(expr) => match expr.evaluate(row)? {
-                Integer(i) if i < 0 => return errinput!("can't take negative square root"),
-                Integer(i) => Float((i as f32).sqrt()),
                 Float(f) => Float(f.sqrt()),
+                Double(f) => Double(f.sqrt()),
+                BigInt(i) if i < 0 => return errinput!("can't take negative square root"),
+                BigInt(i) => Double((i as f64).sqrt()),
+                Decimal(d) if d.mantissa() < 0 => {
+                    return errinput!("can't take negative square root")
+                }
+                Decimal(d) => Double(d.to_f64().sqrt()),
                 Null => Null,
-                value => return errinput!("can't take square root of {value}"),
+                value => match value.as_i64() {
+                    Some(i) if i < 0 => return errinput!("can't take negative square root"),
+                    Some(i) => Float((i as f32).sqrt()),
+                    None => return errinput!("can't take square root of {value}"),
+                },
             },
             Self::Subtract(lhs, rhs) => lhs.evaluate(row)?.checked_sub(&rhs.evaluate(row)?)?,
 
-            // LIKE pattern matching, using _ and % as single- and
-            // multi-character wildcards. Inputs must be strings. NULLs yield
-            // NULL. There's no support for escaping an _ and %.
-            Self::Like(lhs, rhs) => match (lhs.evaluate(row)?, rhs.evaluate(row)?) {
-                (String(lhs), String(rhs)) => {
-                    // We could precompile the pattern if it's constant, instead
-                    // of recompiling it for every row, but this is fine.
-                    let pattern = format!(
-                        "^{}$",
-                        regex::escape(&rhs).replace('%', ".*").replace('_', ".")
-                    );
-                    Boolean(regex::Regex::new(&pattern)?.is_match(&lhs))
+            // String concatenation. Byte strings concatenate to byte strings,
+            // otherwise one side must be a string and the other is converted
+            // to a string. NULLs yield NULL.
+            Self::Concat(lhs, rhs) => match (lhs.evaluate(row)?, rhs.evaluate(row)?) {
+                (Null, _) | (_, Null) => Null,
+                (Bytes(mut lhs), Bytes(rhs)) => {
+                    lhs.extend(rhs);
+                    Bytes(lhs)
                 }
-                (String(_), Null) | (Null, String(_)) | (Null, Null) => Null,
-                (lhs, rhs) => return errinput!("can't LIKE {lhs} and {rhs}"),
+                (lhs @ String(_), rhs) | (lhs, rhs @ String(_)) => {
+                    match (lhs.cast(DataType::Text)?, rhs.cast(DataType::Text)?) {
+                        (String(lhs), String(rhs)) => String(lhs + &rhs),
+                        (lhs, rhs) => return errinput!("can't concatenate {lhs} and {rhs}"),
+                    }
+                }
+                (lhs, rhs) => return errinput!("can't concatenate {lhs} and {rhs}"),
+            },
+
+            // Date/time functions. NULLs yield NULL.
+            Self::Extract(field, expr) => temporal::extract(*field, &expr.evaluate(row)?)?,
+            Self::DateTrunc(field, expr) => temporal::date_trunc(*field, &expr.evaluate(row)?)?,
+
+            // Scalar functions, which handle NULLs themselves.
+            Self::Function(function, args) => function.evaluate(args, row)?,
+
+            // CASE returns the first THEN value whose WHEN condition is true,
+            // where a NULL condition is not true. A simple CASE compares the
+            // operand with each WHEN value using =.
+            Self::Case(operand, when, r#else) => {
+                let operand = operand.as_ref().map(|o| o.evaluate(row)).transpose()?;
+                for (condition, then) in when {
+                    let condition = match &operand {
+                        Some(operand) => {
+                            let value = condition.evaluate(row)?;
+                            Self::Equal(operand.clone().into(), value.into()).evaluate(None)?
+                        }
+                        None => condition.evaluate(row)?,
+                    };
+                    match condition {
+                        Boolean(true) => return then.evaluate(row),
+                        Boolean(false) | Null => {}
+                        value => return errinput!("CASE condition must be boolean, got {value}"),
+                    }
+                }
+                match r#else {
+                    Some(r#else) => r#else.evaluate(row)?,
+                    None => Null,
+                }
+            }
+
+            // Explicit type conversion. Errors if the value can't be converted.
+            Self::Cast(expr, data_type) => expr.evaluate(row)?.cast(*data_type)?,
+
+            // Collation sort keys. Non-strings are returned as is.
+            Self::Collate(expr, collation) => collation.key(expr.evaluate(row)?),
+
+            // Pattern matching. Inputs must be strings, and NULLs yield NULL.
+            // Constant patterns are compiled during planning as Matches,
+            // otherwise the pattern is compiled for every row.
+            Self::Like(lhs, rhs, escape, case_insensitive) => {
+                let escape = escape.as_ref().map(|e| e.evaluate(row)).transpose()?;
+                match (lhs.evaluate(row)?, rhs.evaluate(row)?, escape) {
+                    (String(lhs), String(rhs), None) => {
+                        Boolean(Pattern::like(&rhs, None, *case_insensitive)?.is_match(&lhs))
+                    }
+                    (String(lhs), String(rhs), Some(String(escape))) => {
+                        let pattern = Pattern::like(&rhs, Some(&escape), *case_insensitive)?;
+                        Boolean(pattern.is_match(&lhs))
+                    }
+                    (String(_) | Null, String(_) | Null, None | Some(String(_) | Null)) => Null,
+                    (lhs, rhs, _) => return errinput!("can't LIKE {lhs} and {rhs}"),
+                }
+            }
+            Self::Regex(lhs, rhs, case_insensitive) => {
+                match (lhs.evaluate(row)?, rhs.evaluate(row)?) {
+                    (String(lhs), String(rhs)) => {
+                        Boolean(Pattern::regex(&rhs, *case_insensitive)?.is_match(&lhs))
+                    }
+                    (String(_) | Null, String(_) | Null) => Null,
+                    (lhs, rhs) => return errinput!("can't match {lhs} and {rhs}"),
+                }
+            }
+            Self::Matches(expr, pattern) => match expr.evaluate(row)? {
+                String(string) => Boolean(pattern.is_match(&string)),
+                Null => Null,
+                value => return errinput!("can't match {value} {pattern}"),
             },
         })
     }
@@ -283,25 +659,56 @@ impl Expression {
         match self {
             Self::Add(lhs, rhs)
             | Self::And(lhs, rhs)
+            | Self::Concat(lhs, rhs)
             | Self::Divide(lhs, rhs)
             | Self::Equal(lhs, rhs)
             | Self::Exponentiate(lhs, rhs)
             | Self::GreaterThan(lhs, rhs)
             | Self::LessThan(lhs, rhs)
-            | Self::Like(lhs, rhs)
+            | Self::Regex(lhs, rhs, _)
             | Self::Multiply(lhs, rhs)
             | Self::Or(lhs, rhs)
             | Self::Remainder(lhs, rhs)
             | Self::Subtract(lhs, rhs) => lhs.walk(visitor) && rhs.walk(visitor),
 
+            Self::Like(lhs, rhs, escape, _) => {
+                lhs.walk(visitor)
+                    && rhs.walk(visitor)
+                    && escape.as_ref().is_none_or(|e| e.walk(visitor))
+            }
+            Self::Function(_, args) => args.iter().all(|arg| arg.walk(visitor)),
+            Self::In(expr, list) => expr.walk(visitor) && list.iter().all(|e| e.walk(visitor)),
+            Self::Between(expr, low, high) => {
+                expr.walk(visitor) && low.walk(visitor) && high.walk(visitor)
+            }
+            Self::Case(operand, when, r#else) => {
+                operand.as_ref().is_none_or(|e| e.walk(visitor))
+                    && when
+                        .iter()
+                        .all(|(condition, then)| condition.walk(visitor) && then.walk(visitor))
+                    && r#else.as_ref().is_none_or(|e| e.walk(visitor))
+            }
+
             Self::Factorial(expr)
             | Self::Identity(expr)
             | Self::Is(expr, _)
             | Self::Negate(expr)
             | Self::Not(expr)
-            | Self::SquareRoot(expr) => expr.walk(visitor),
-
-            Self::Constant(_) | Self::Column(_) => true,
+            | Self::SquareRoot(expr)
+            | Self::Extract(_, expr)
+            | Self::DateTrunc(_, expr)
+            | Self::Matches(expr, _)
+            | Self::Cast(expr, _)
+            | Self::Collate(expr, _)
+            | Self::InSubquery(expr, _) => expr.walk(visitor),
+
+            // Subqueries are separate plans, and aren't walked.
+            Self::Constant(_)
+            | Self::Column(_)
+            | Self::Parameter(_)
+            | Self::OuterColumn(_, _)
+            | Self::Subquery(_)
+            | Self::Exists(_) => true,
         }
     }
 
@@ -312,6 +719,16 @@ impl Expression {
         !self.walk(&mut |e| !visitor(e))
     }
 
+    /// Returns true if the expression contains a subquery.
+    pub fn contains_subquery(&self) -> bool {
+        self.contains(&|expr| {
+            matches!(
+                expr,
+                Self::Subquery(_) | Self::Exists(_) | Self::InSubquery(_, _)
+            )
+        })
+    }
+
     /// Transforms the expression by recursively applying the given closures
     /// depth-first to each node before/after descending.
     pub fn transform(
@@ -329,16 +746,23 @@ impl Expression {
         self = match self {
             Self::Add(lhs, rhs) => Self::Add(xform(lhs)?, xform(rhs)?),
             Self::And(lhs, rhs) => Self::And(xform(lhs)?, xform(rhs)?),
+            Self::Concat(lhs, rhs) => Self::Concat(xform(lhs)?, xform(rhs)?),
             Self::Divide(lhs, rhs) => Self::Divide(xform(lhs)?, xform(rhs)?),
             Self::Equal(lhs, rhs) => Self::Equal(xform(lhs)?, xform(rhs)?),
             Self::Exponentiate(lhs, rhs) => Self::Exponentiate(xform(lhs)?, xform(rhs)?),
             Self::GreaterThan(lhs, rhs) => Self::GreaterThan(xform(lhs)?, xform(rhs)?),
             Self::LessThan(lhs, rhs) => Self::LessThan(xform(lhs)?, xform(rhs)?),
-            Self::Like(lhs, rhs) => Self::Like(xform(lhs)?, xform(rhs)?),
+            Self::Like(lhs, rhs, escape, ci) => {
+                Self::Like(xform(lhs)?, xform(rhs)?, escape.map(xform).transpose()?, ci)
+            }
+            Self::Regex(lhs, rhs, ci) => Self::Regex(xform(lhs)?, xform(rhs)?, ci),
+            Self::Matches(expr, pattern) => Self::Matches(xform(expr)?, pattern),
             Self::Multiply(lhs, rhs) => Self::Multiply(xform(lhs)?, xform(rhs)?),
             Self::Or(lhs, rhs) => Self::Or(xform(lhs)?, xform(rhs)?),
             Self::Remainder(lhs, rhs) => Self::Remainder(xform(lhs)?, xform(rhs)?),
             Self::SquareRoot(expr) => Self::SquareRoot(xform(expr)?),
+            Self::Extract(field, expr) => Self::Extract(field, xform(expr)?),
+            Self::DateTrunc(field, expr) => Self::DateTrunc(field, xform(expr)?),
             Self::Subtract(lhs, rhs) => Self::Subtract(xform(lhs)?, xform(rhs)?),
 
             Self::Factorial(expr) => Self::Factorial(xform(expr)?),
@@ -346,8 +770,44 @@ impl Expression {
             Self::Is(expr, value) => Self::Is(xform(expr)?, value),
             Self::Negate(expr) => Self::Negate(xform(expr)?),
             Self::Not(expr) => Self::Not(xform(expr)?),
-
-            expr @ (Self::Constant(_) | Self::Column(_)) => expr,
+            Self::Cast(expr, data_type) => Self::Cast(xform(expr)?, data_type),
+            Self::Collate(expr, collation) => Self::Collate(xform(expr)?, collation),
+            Self::Function(function, args) => Self::Function(
+                function,
+                args.into_iter()
+                    .map(|arg| arg.transform(before, after))
+                    .collect::<Result<_>>()?,
+            ),
+            Self::In(expr, list) => Self::In(
+                xform(expr)?,
+                list.into_iter()
+                    .map(|e| e.transform(before, after))
+                    .collect::<Result<_>>()?,
+            ),
+            Self::Between(expr, low, high) => {
+                Self::Between(xform(expr)?, xform(low)?, xform(high)?)
+            }
+            Self::Case(operand, when, r#else) => Self::Case(
+                operand.map(xform).transpose()?,
+                when.into_iter()
+                    .map(|(condition, then)| {
+                        Ok((
+                            condition.transform(before, after)?,
+                            then.transform(before, after)?,
+                        ))
+                    })
+                    .collect::<Result<_>>()?,
+                r#else.map(xform).transpose()?,
+            ),
+
+            Self::InSubquery(expr, node) => Self::InSubquery(xform(expr)?, node),
+
+            expr @ (Self::Constant(_)
+            | Self::Column(_)
+            | Self::Parameter(_)
+            | Self::OuterColumn(_, _)
+            | Self::Subquery(_)
+            | Self::Exists(_)) => expr,
         };
         self = after(self)?;
         Ok(self)
@@ -421,7 +881,7 @@ impl Expression {
     }
 
     /// Checks if an expression is a single column lookup (i.e. a disjunction of
-    /// = or IS NULL/NAN for a single column), returning the column index.
+    /// =, IN or IS NULL/NAN for a single column), returning the column index.
     pub fn is_column_lookup(&self) -> Option<usize> {
         use Expression::*;
         match &self {
@@ -436,6 +896,11 @@ impl Expression {
                 Column(c) => Some(*c),
                 _ => None,
             },
+            // IN-lists of constants can use index lookups: id IN (1, 2, 3).
+            In(expr, list) => match expr.as_ref() {
+                Column(c) if list.iter().all(|e| matches!(e, Constant(_))) => Some(*c),
+                _ => None,
+            },
             // All OR branches must be lookups on the same column:
             // id = 1 OR id = 2 OR id = 3.
             Or(lhs, rhs) => match (lhs.is_column_lookup(), rhs.is_column_lookup()) {
@@ -473,6 +938,20 @@ impl Expression {
                 }
                 expr => panic!("unexpected expression {expr:?}"),
             },
+            // Like equality, NULL and NaN list values don't match anything.
+            In(expr, list) => match *expr {
+                Column(column) => {
+                    assert_eq!(column, index, "unexpected column");
+                    list.into_iter()
+                        .map(|expr| match expr {
+                            Constant(value) => value,
+                            expr => panic!("unexpected expression {expr:?}"),
+                        })
+                        .filter(|value| !value.is_undefined())
+                        .collect()
+                }
+                expr => panic!("unexpected expression {expr:?}"),
+            },
             Or(lhs, rhs) => {
                 let mut values = lhs.into_column_values(index);
                 values.extend(rhs.into_column_values(index));
@@ -517,8 +996,10 @@ impl From<Label> for ast::Expression {
     /// Builds an ast::Expression::Column for a label. Can't be None.
     fn from(label: Label) -> Self {
         match label {
-            Label::Qualified(table, column) => ast::Expression::Column(Some(table), column),
-            Label::Unqualified(column) => ast::Expression::Column(None, column),
+            Label::Qualified(table, column) => {
+                ast::Expression::Column(Some(table), column, Default::default())
+            }
+            Label::Unqualified(column) => ast::Expression::Column(None, column, Default::default()),
             Label::None => panic!("can't convert None label to AST expression"), // shouldn't happen
         }
     }
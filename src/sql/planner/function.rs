@@ -0,0 +1,541 @@
+use super::Expression;
+use crate::common::{Error, Result};
+use crate::errinput;
+use crate::storage::tuple::Row;
+use crate::types::bytes;
+use crate::types::field::Field;
+use crate::types::temporal;
+use crate::types::DataType;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// A scalar SQL function, i.e. a function that computes a single value from
+/// the values of its arguments in one row, such as upper(name). Aggregate
+/// functions such as sum() are handled separately by the Aggregate node.
+///
+/// Functions are looked up by name via Function::lookup(), and their arguments
+/// are checked during planning by Function::check(). Arguments whose types
+/// can be known at plan time (e.g. constants and casts) are type checked, the
+/// rest are checked when the function is evaluated.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Function {
+    // String functions.
+    /// Converts a string to upper case: upper(a).
+    Upper,
+    /// Converts a string to lower case: lower(a).
+    Lower,
+    /// Returns the number of characters in a string, or bytes in a byte
+    /// string: length(a).
+    Length,
+    /// Returns part of a string or byte string, from a 1-based start position
+    /// with an optional length: substr(a, start, length).
+    Substr,
+    /// Removes spaces, or the given characters, from both ends of a string:
+    /// trim(a, chars).
+    Trim,
+    /// Replaces all occurrences of a substring: replace(a, from, to).
+    Replace,
+    /// Concatenates the text representations of its arguments, ignoring NULLs:
+    /// concat(a, b, ...).
+    Concat,
+    /// Encodes a byte string as text in the given format: encode(a, 'hex').
+    Encode,
+    /// Decodes text in the given format as a byte string: decode(a, 'hex').
+    Decode,
+
+    // Math functions.
+    /// Returns the absolute value of a number: abs(a).
+    Abs,
+    /// Rounds a number half away from zero, to the given number of fractional
+    /// digits (0 by default): round(a, digits).
+    Round,
+    /// Rounds a number down to the nearest integer: floor(a).
+    Floor,
+    /// Rounds a number up to the nearest integer: ceil(a).
+    Ceil,
+    /// Returns the natural logarithm of a number: ln(a).
+    Ln,
+    /// Raises a number to a power, like a ^ b: power(a, b).
+    Power,
+
+    // NULL handling.
+    /// Returns the first non-NULL argument: coalesce(a, b, ...).
+    Coalesce,
+    /// Returns NULL if the arguments are equal, otherwise the first argument:
+    /// nullif(a, b).
+    NullIf,
+    /// Returns the largest non-NULL argument: greatest(a, b, ...).
+    Greatest,
+    /// Returns the smallest non-NULL argument: least(a, b, ...).
+    Least,
+
+    // Date and time functions.
+    /// Returns the current timestamp: now(). Plan::bind() replaces this with
+    /// the time of execution, such that all rows see the same time.
+    Now,
+}
+
+/// The kinds of values a function argument accepts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Any,
+    Text,
+    TextOrBytes,
+    Bytes,
+    Numeric,
+    Integer,
+}
+
+impl Kind {
+    /// Returns true if the kind accepts values of the given type.
+    fn accepts(&self, data_type: DataType) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Text => data_type == DataType::Text,
+            Self::TextOrBytes => matches!(data_type, DataType::Text | DataType::Bytea),
+            Self::Bytes => data_type == DataType::Bytea,
+            Self::Numeric => data_type.is_numeric(),
+            Self::Integer => data_type.is_integer(),
+        }
+    }
+}
+
+impl std::fmt::Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Any => "any value",
+            Self::Text => "text",
+            Self::TextOrBytes => "text or bytea",
+            Self::Bytes => "bytea",
+            Self::Numeric => "a number",
+            Self::Integer => "an integer",
+        })
+    }
+}
+
+impl Function {
+    /// Looks up a function by name, or returns None if it doesn't exist.
+    pub fn lookup(name: &str) -> Option<Self> {
+        Some(match name {
+            "upper" => Self::Upper,
+            "lower" => Self::Lower,
+            "length" => Self::Length,
+            "substr" | "substring" => Self::Substr,
+            "trim" => Self::Trim,
+            "replace" => Self::Replace,
+            "concat" => Self::Concat,
+            "encode" => Self::Encode,
+            "decode" => Self::Decode,
+            "abs" => Self::Abs,
+            "round" => Self::Round,
+            "floor" => Self::Floor,
+            "ceil" | "ceiling" => Self::Ceil,
+            "ln" => Self::Ln,
+            "power" | "pow" => Self::Power,
+            "coalesce" => Self::Coalesce,
+            "nullif" => Self::NullIf,
+            "greatest" => Self::Greatest,
+            "least" => Self::Least,
+            "now" => Self::Now,
+            _ => return None,
+        })
+    }
+
+    /// Returns the minimum and maximum number of arguments, where None means
+    /// any number of arguments.
+    fn arity(&self) -> (usize, Option<usize>) {
+        match self {
+            Self::Upper | Self::Lower | Self::Length => (1, Some(1)),
+            Self::Abs | Self::Floor | Self::Ceil | Self::Ln => (1, Some(1)),
+            Self::Trim | Self::Round => (1, Some(2)),
+            Self::Substr => (2, Some(3)),
+            Self::Encode | Self::Decode | Self::Power | Self::NullIf => (2, Some(2)),
+            Self::Replace => (3, Some(3)),
+            Self::Concat | Self::Coalesce | Self::Greatest | Self::Least => (1, None),
+            Self::Now => (0, Some(0)),
+        }
+    }
+
+    /// Returns the kind of value accepted by the given argument.
+    fn parameter(&self, index: usize) -> Kind {
+        match (self, index) {
+            (Self::Upper | Self::Lower | Self::Trim | Self::Replace, _) => Kind::Text,
+            (Self::Length, _) | (Self::Substr, 0) => Kind::TextOrBytes,
+            (Self::Substr, _) | (Self::Round, 1) => Kind::Integer,
+            (Self::Encode, 0) => Kind::Bytes,
+            (Self::Encode | Self::Decode, _) => Kind::Text,
+            (Self::Abs | Self::Round | Self::Floor | Self::Ceil | Self::Ln | Self::Power, _) => {
+                Kind::Numeric
+            }
+            (
+                Self::Concat
+                | Self::Coalesce
+                | Self::NullIf
+                | Self::Greatest
+                | Self::Least
+                | Self::Now,
+                _,
+            ) => Kind::Any,
+        }
+    }
+
+    /// Returns true if the function returns NULL when any argument is NULL.
+    fn is_strict(&self) -> bool {
+        !matches!(
+            self,
+            Self::Concat | Self::Coalesce | Self::NullIf | Self::Greatest | Self::Least
+        )
+    }
+
+    /// Returns true if the function compares its arguments with each other,
+    /// which requires them to have comparable types.
+    fn compares_arguments(&self) -> bool {
+        matches!(
+            self,
+            Self::Coalesce | Self::NullIf | Self::Greatest | Self::Least
+        )
+    }
+
+    /// Checks the number of arguments and, where argument types can be known
+    /// at plan time, their types.
+    pub fn check(&self, args: &[Expression]) -> Result<()> {
+        let (min, max) = self.arity();
+        if args.len() < min || max.is_some_and(|max| args.len() > max) {
+            let expected = match max {
+                Some(max) if max == min => format!("{min}"),
+                Some(max) => format!("{min} to {max}"),
+                None => format!("at least {min}"),
+            };
+            return errinput!("{self}() takes {expected} arguments, got {}", args.len());
+        }
+        let types: Vec<Option<DataType>> = args.iter().map(data_type).collect();
+        for (i, data_type) in types.iter().enumerate() {
+            let kind = self.parameter(i);
+            if let Some(data_type) = data_type.filter(|&t| !kind.accepts(t)) {
+                return errinput!(
+                    "{self}() argument {} must be {kind}, got {data_type}",
+                    i + 1
+                );
+            }
+        }
+        if self.compares_arguments() {
+            let known: Vec<DataType> = types.into_iter().flatten().collect();
+            for pair in known.windows(2) {
+                if !comparable(pair[0], pair[1]) {
+                    return errinput!("{self}() can't compare {} and {}", pair[0], pair[1]);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the type of the function's result, if it can be known from
+    /// the given argument types.
+    pub(crate) fn return_type(&self, args: &[Option<DataType>]) -> Option<DataType> {
+        match self {
+            Self::Upper | Self::Lower | Self::Trim | Self::Replace | Self::Concat => {
+                Some(DataType::Text)
+            }
+            Self::Encode => Some(DataType::Text),
+            Self::Decode => Some(DataType::Bytea),
+            Self::Length => Some(DataType::Int),
+            Self::Substr | Self::Abs | Self::Round | Self::NullIf => args[0],
+            Self::Floor | Self::Ceil => args[0].filter(|t| !matches!(t, DataType::Decimal(_, _))),
+            Self::Ln => match args[0]? {
+                DataType::Float => Some(DataType::Float),
+                _ => Some(DataType::Double),
+            },
+            Self::Power => None,
+            Self::Now => Some(DataType::Timestamp),
+            Self::Coalesce | Self::Greatest | Self::Least => {
+                let first = args[0]?;
+                args.iter().all(|t| *t == Some(first)).then_some(first)
+            }
+        }
+    }
+
+    /// Evaluates the function with the given arguments, using the given row to
+    /// look up column references. Strict functions yield NULL if any argument
+    /// is NULL, while e.g. coalesce() only evaluates arguments until it finds
+    /// a non-NULL value.
+    pub fn evaluate(&self, args: &[Expression], row: Option<&Row>) -> Result<Field> {
+        use Field::*;
+
+        if *self == Self::Coalesce {
+            for arg in args {
+                match arg.evaluate(row)? {
+                    Null => {}
+                    value => return Ok(value),
+                }
+            }
+            return Ok(Null);
+        }
+
+        let values = args
+            .iter()
+            .map(|arg| arg.evaluate(row))
+            .collect::<Result<Vec<_>>>()?;
+        if self.is_strict() && values.iter().any(|v| v.is_null()) {
+            return Ok(Null);
+        }
+        let mut values = values.into_iter();
+        let mut next = || values.next().unwrap_or(Null);
+
+        Ok(match self {
+            Self::Upper => match next() {
+                String(s) => String(s.to_uppercase()),
+                value => return errinput!("can't convert {value} to upper case"),
+            },
+            Self::Lower => match next() {
+                String(s) => String(s.to_lowercase()),
+                value => return errinput!("can't convert {value} to lower case"),
+            },
+            Self::Length => match next() {
+                String(s) => Integer(s.chars().count() as i32),
+                Bytes(b) => Integer(b.len() as i32),
+                value => return errinput!("can't take length of {value}"),
+            },
+            Self::Substr => {
+                let (value, start) = (next(), next());
+                let Some(start) = start.as_i64() else {
+                    return errinput!("substring start must be an integer, got {start}");
+                };
+                let length = match next() {
+                    Null => None,
+                    length => match length.as_i64() {
+                        Some(l) if l < 0 => {
+                            return errinput!("negative substring length not allowed")
+                        }
+                        Some(l) => Some(l),
+                        None => {
+                            return errinput!("substring length must be an integer, got {length}")
+                        }
+                    },
+                };
+                match value {
+                    String(s) => {
+                        let chars: Vec<char> = s.chars().collect();
+                        String(substring(&chars, start, length).iter().collect())
+                    }
+                    Bytes(b) => Bytes(substring(&b, start, length).to_vec()),
+                    value => return errinput!("can't take substring of {value}"),
+                }
+            }
+            Self::Trim => match (next(), next()) {
+                (String(s), Null) => String(s.trim_matches(' ').to_string()),
+                (String(s), String(chars)) => {
+                    String(s.trim_matches(|c| chars.contains(c)).to_string())
+                }
+                (value, chars) => return errinput!("can't trim {chars} from {value}"),
+            },
+            Self::Replace => match (next(), next(), next()) {
+                (String(s), String(from), _) if from.is_empty() => String(s),
+                (String(s), String(from), String(to)) => String(s.replace(&from, &to)),
+                (value, from, to) => return errinput!("can't replace {from} with {to} in {value}"),
+            },
+            Self::Concat => String(
+                std::iter::from_fn(|| Some(next()))
+                    .take(args.len())
+                    .filter(|v| !v.is_null())
+                    .map(|v| v.to_string())
+                    .collect(),
+            ),
+            Self::Encode => match (next(), next()) {
+                (Bytes(b), String(format)) => String(bytes::encode(&b, &format)?),
+                (value, format) => return errinput!("can't encode {value} as {format}"),
+            },
+            Self::Decode => match (next(), next()) {
+                (String(s), String(format)) => Bytes(bytes::decode(&s, &format)?),
+                (value, format) => return errinput!("can't decode {value} as {format}"),
+            },
+
+            Self::Abs => match next() {
+                SmallInt(i) => SmallInt(i.checked_abs().ok_or(Error::OverflowError)?),
+                Integer(i) => Integer(i.checked_abs().ok_or(Error::OverflowError)?),
+                BigInt(i) => BigInt(i.checked_abs().ok_or(Error::OverflowError)?),
+                Float(f) => Float(f.abs()),
+                Double(f) => Double(f.abs()),
+                Decimal(d) if d.mantissa() < 0 => Decimal(d.checked_neg()?),
+                value @ Decimal(_) => value,
+                value => return errinput!("can't take absolute value of {value}"),
+            },
+            Self::Round => {
+                let value = next();
+                let digits = match next() {
+                    Null => 0,
+                    digits => match digits.as_i64() {
+                        Some(digits) => digits,
+                        None => return errinput!("round digits must be an integer, got {digits}"),
+                    },
+                };
+                match value {
+                    Float(f) => Float(round(f as f64, digits) as f32),
+                    Double(f) => Double(round(f, digits)),
+                    Decimal(d) => Decimal(d.round(digits)?),
+                    value if digits >= 0 && value.as_i64().is_some() => value,
+                    value => match value.as_i64() {
+                        Some(i) => {
+                            let rounded = crate::types::decimal::Decimal::from(i).round(digits)?;
+                            Decimal(rounded).cast(value.get_type())?
+                        }
+                        None => return errinput!("can't round {value}"),
+                    },
+                }
+            }
+            Self::Floor => match next() {
+                Float(f) => Float(f.floor()),
+                Double(f) => Double(f.floor()),
+                Decimal(d) => Decimal(d.floor()),
+                value if value.as_i64().is_some() => value,
+                value => return errinput!("can't take floor of {value}"),
+            },
+            Self::Ceil => match next() {
+                Float(f) => Float(f.ceil()),
+                Double(f) => Double(f.ceil()),
+                Decimal(d) => Decimal(d.ceil()?),
+                value if value.as_i64().is_some() => value,
+                value => return errinput!("can't take ceiling of {value}"),
+            },
+            Self::Ln => match next() {
+                value if value.as_f64().is_some_and(|f| f <= 0.0) => {
+                    return errinput!("can't take logarithm of non-positive number {value}")
+                }
+                Float(f) => Float(f.ln()),
+                value => match value.as_f64() {
+                    Some(f) => Double(f.ln()),
+                    None => return errinput!("can't take logarithm of {value}"),
+                },
+            },
+            Self::Power => next().checked_pow(&next())?,
+
+            Self::NullIf => match (next(), next()) {
+                (Null, _) => Null,
+                (value, Null) => value,
+                (value, other) => match compare(&value, &other)? {
+                    Ordering::Equal => Null,
+                    _ => value,
+                },
+            },
+            Self::Greatest | Self::Least => {
+                let wanted = match self {
+                    Self::Greatest => Ordering::Greater,
+                    _ => Ordering::Less,
+                };
+                let mut result = Null;
+                let mut numeric_type = None;
+                for value in std::iter::from_fn(|| Some(next())).take(args.len()) {
+                    if value.is_null() {
+                        continue;
+                    }
+                    if value.is_numeric() && (result.is_null() || result.is_numeric()) {
+                        numeric_type = match numeric_type {
+                            None => Some(value.get_type()),
+                            Some(t) => Field::numeric_type(&Field::new(t), &value),
+                        };
+                    }
+                    if result.is_null() || compare(&value, &result)? == wanted {
+                        result = value;
+                    }
+                }
+                match numeric_type {
+                    Some(t) => result.convert_numeric(t)?,
+                    None => result,
+                }
+            }
+            Self::Now => Timestamp(temporal::now()),
+
+            Self::Coalesce => unreachable!("coalesce is evaluated lazily"),
+        })
+    }
+}
+
+impl std::fmt::Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Upper => "upper",
+            Self::Lower => "lower",
+            Self::Length => "length",
+            Self::Substr => "substr",
+            Self::Trim => "trim",
+            Self::Replace => "replace",
+            Self::Concat => "concat",
+            Self::Encode => "encode",
+            Self::Decode => "decode",
+            Self::Abs => "abs",
+            Self::Round => "round",
+            Self::Floor => "floor",
+            Self::Ceil => "ceil",
+            Self::Ln => "ln",
+            Self::Power => "power",
+            Self::Coalesce => "coalesce",
+            Self::NullIf => "nullif",
+            Self::Greatest => "greatest",
+            Self::Least => "least",
+            Self::Now => "now",
+        })
+    }
+}
+
+/// Returns the type of an expression's value, if it can be known at plan time.
+/// Column types aren't tracked by the planner, so this only handles constants,
+/// casts and function calls.
+fn data_type(expr: &Expression) -> Option<DataType> {
+    match expr {
+        Expression::Constant(Field::Null) => None,
+        Expression::Constant(value) => Some(value.get_type()),
+        Expression::Cast(_, data_type) => Some(*data_type),
+        Expression::Function(function, args) => {
+            function.return_type(&args.iter().map(data_type).collect::<Vec<_>>())
+        }
+        _ => None,
+    }
+}
+
+/// Returns true if values of the given types can be compared with each other.
+fn comparable(lhs: DataType, rhs: DataType) -> bool {
+    use DataType::*;
+    match (lhs, rhs) {
+        (l, r) if l.is_numeric() && r.is_numeric() => true,
+        (Date, Timestamp) | (Timestamp, Date) => true,
+        (l, r) => l == r,
+    }
+}
+
+/// Compares two non-NULL values, like the comparison operators do. NaN is
+/// ordered after all other numbers.
+fn compare(lhs: &Field, rhs: &Field) -> Result<Ordering> {
+    if lhs.is_numeric() && rhs.is_numeric() {
+        return Ok(match lhs.partial_cmp_numeric(rhs) {
+            Some(ordering) => ordering,
+            None => lhs.as_f64().unwrap().total_cmp(&rhs.as_f64().unwrap()),
+        });
+    }
+    if lhs.is_temporal() && rhs.is_temporal() {
+        return lhs.cmp_temporal(rhs);
+    }
+    match (lhs, rhs) {
+        (Field::Boolean(_), Field::Boolean(_))
+        | (Field::String(_), Field::String(_))
+        | (Field::Bytes(_), Field::Bytes(_)) => Ok(lhs.cmp(rhs)),
+        (lhs, rhs) => errinput!("can't compare {lhs} and {rhs}"),
+    }
+}
+
+/// Rounds a float half away from zero to the given number of fractional
+/// digits.
+fn round(value: f64, digits: i64) -> f64 {
+    let factor = 10_f64.powi(digits.clamp(-308, 308) as i32);
+    match factor.is_finite() && (value * factor).is_finite() {
+        true => (value * factor).round() / factor,
+        false => value,
+    }
+}
+
+/// Returns the part of a string or byte string starting at the 1-based position
+/// start, with the given length if any. Positions outside of the input are
+/// ignored, such that e.g. substr('abc', 0, 2) is 'a' as in PostgreSQL.
+fn substring<T>(values: &[T], start: i64, length: Option<i64>) -> &[T] {
+    let end = length.map_or(i64::MAX, |length| start.saturating_add(length));
+    let clamp = |i: i64| (i.max(1) - 1).min(values.len() as i64) as usize;
+    &values[clamp(start)..clamp(end)]
+}
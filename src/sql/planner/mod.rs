@@ -1,10 +1,17 @@
 mod expression;
+mod function;
 mod node;
 mod optimizer;
+mod pattern;
 mod plan;
 mod planner;
 
 pub use expression::Expression;
+pub use function::Function;
 pub use node::{BoxedNode, Node};
-pub use plan::{Aggregate, Direction, Plan};
+pub use pattern::Pattern;
+pub use plan::{
+    Aggregate, ConflictAction, Direction, Frame, FrameBound, FrameUnits, Plan, Returning, Window,
+    WindowFunction,
+};
 pub use planner::Planner;
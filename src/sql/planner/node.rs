@@ -1,7 +1,7 @@
 use crate::common::Result;
-use crate::sql::planner::{Aggregate, Direction, Expression};
+use crate::sql::planner::{Aggregate, Direction, Expression, Window};
 use crate::types::field::{Field, Label};
-use crate::types::Table;
+use crate::types::{DataType, Table};
 use serde::{Deserialize, Serialize};
 use std::ops::Deref;
 
@@ -35,11 +35,33 @@ pub enum Node {
     /// Computes the given aggregate values for the given group_by buckets
     /// across all rows in the source node. The group_by columns are emitted
     /// first, followed by the aggregate columns, in the given order.
+    ///
+    /// The rows are bucketed once per grouping set, given as group_by indexes.
+    /// The group_by columns that aren't in a row's grouping set are NULL. A
+    /// plain GROUP BY has a single set with all group_by columns.
     Aggregate {
         source: BoxedNode,
         group_by: Vec<Expression>,
+        grouping_sets: Vec<Vec<usize>>,
         aggregates: Vec<Aggregate>,
     },
+    /// Discards source rows with the same values for the given expressions as
+    /// a previous row, emitting only the first row for each distinct value.
+    /// For SELECT DISTINCT, these are the visible columns, and for DISTINCT ON
+    /// the given expressions. Keeps the seen values in memory.
+    Distinct {
+        source: BoxedNode,
+        on: Vec<Expression>,
+    },
+    /// Emits the left source rows that are not in the right source (i.e.
+    /// EXCEPT). Builds an in-memory hashmap of the right source rows. If all
+    /// is false, duplicate rows are discarded, otherwise each right row
+    /// discards a single matching left row.
+    Except {
+        left: BoxedNode,
+        right: BoxedNode,
+        all: bool,
+    },
     /// Filters source rows, by discarding rows for which the predicate
     /// evaluates to false.
     Filter {
@@ -66,6 +88,15 @@ pub enum Node {
         values: Vec<Field>,
         alias: Option<String>,
     },
+    /// Emits the left source rows that are also in the right source (i.e.
+    /// INTERSECT). Builds an in-memory hashmap of the right source rows. If
+    /// all is false, duplicate rows are discarded, otherwise a row is emitted
+    /// as many times as it's in both sources.
+    Intersect {
+        left: BoxedNode,
+        right: BoxedNode,
+        all: bool,
+    },
     /// Looks up the given primary keys and emits their rows.
     KeyLookup {
         table: Table,
@@ -103,6 +134,19 @@ pub enum Node {
         expressions: Vec<Expression>,
         aliases: Vec<Label>,
     },
+    /// Evaluates a recursive CTE. Emits the anchor rows, then repeatedly
+    /// executes the recursive node with the rows emitted by the previous
+    /// iteration as the working table of the given name, until it emits no
+    /// rows. If distinct is true (i.e. UNION rather than UNION ALL), rows that
+    /// were already emitted are discarded. Errors if this doesn't terminate
+    /// within limit iterations.
+    RecursiveCte {
+        name: String,
+        anchor: BoxedNode,
+        recursive: BoxedNode,
+        distinct: bool,
+        limit: usize,
+    },
     /// Remaps source columns to the given target column index, or None to drop
     /// the column. Unspecified target columns yield Value::Null. The source →
     /// target mapping ensures a source column can only be mapped to a single
@@ -111,6 +155,18 @@ pub enum Node {
         source: BoxedNode,
         targets: Vec<Option<usize>>,
     },
+    /// Emits the left source rows that have a match in the right source, i.e.
+    /// a right row for which the predicate is true when evaluated against the
+    /// joined left and right row (any right row if there is no predicate). When
+    /// anti is true, it emits the left rows without a match instead. Only the
+    /// left columns are emitted, retaining their record IDs. Used for
+    /// decorrelated EXISTS and IN subqueries.
+    SemiJoin {
+        left: BoxedNode,
+        right: BoxedNode,
+        predicate: Option<Expression>,
+        anti: bool,
+    },
     /// A full table scan, with an optional pushed-down filter. The schema is
     /// used during plan optimization. The alias is only used for formatting.
     Scan {
@@ -118,8 +174,26 @@ pub enum Node {
         filter: Option<Expression>,
         alias: Option<String>,
     },
+    /// Emits the left source rows followed by the right source rows (i.e.
+    /// UNION). If all is false, duplicate rows are discarded, which requires
+    /// keeping the emitted rows in memory.
+    Union {
+        left: BoxedNode,
+        right: BoxedNode,
+        all: bool,
+    },
     /// A constant set of values.
     Values { rows: Vec<Vec<Expression>> },
+    /// Computes the given window functions for each source row, emitting the
+    /// source columns followed by one column per window function. Buffers the
+    /// entire row set in memory, and emits rows in the source order.
+    Window {
+        source: BoxedNode,
+        windows: Vec<Window>,
+    },
+    /// The working table of the enclosing RecursiveCte with the given name.
+    /// It's replaced by the previous iteration's rows during execution.
+    WorkingTable { name: String, columns: Vec<Label> },
 }
 
 impl Node {
@@ -138,6 +212,7 @@ impl Node {
                 ..
             } => aggregates.len() + group_by.len(),
             Self::Projection { expressions, .. } => expressions.len(),
+            Self::Window { source, windows } => source.columns() + windows.len(),
             Self::Remap { targets, .. } => targets
                 .iter()
                 .filter_map(|v| *v)
@@ -151,13 +226,23 @@ impl Node {
             }
 
             // Simple nodes just pass through the source columns.
-            Self::Filter { source, .. }
+            Self::Distinct { source, .. }
+            | Self::Filter { source, .. }
             | Self::Limit { source, .. }
             | Self::Offset { source, .. }
             | Self::Order { source, .. } => source.columns(),
 
+            // Semi joins only emit the left columns.
+            Self::SemiJoin { left, .. } => left.columns(),
+
+            // Recursive CTEs and set operations emit the anchor/left columns.
+            Self::RecursiveCte { anchor, .. } => anchor.columns(),
+            Self::Except { left, .. } | Self::Intersect { left, .. } | Self::Union { left, .. } => {
+                left.columns()
+            }
+
             // And some are trivial.
-            Self::Nothing { columns } => columns.len(),
+            Self::Nothing { columns } | Self::WorkingTable { columns, .. } => columns.len(),
             Self::Values { rows } => rows.first().map(|row| row.len()).unwrap_or(0),
         }
     }
@@ -188,6 +273,12 @@ impl Node {
                 source, group_by, ..
             } => match group_by.get(index) {
                 Some(Expression::Column(index)) => source.column_label(*index),
+                // Collated columns are bucketed by sort key, but emit the
+                // column value.
+                Some(Expression::Collate(expr, _)) => match expr.as_ref() {
+                    Expression::Column(index) => source.column_label(*index),
+                    _ => Label::None,
+                },
                 Some(_) | None => Label::None,
             },
             Self::Projection {
@@ -209,6 +300,10 @@ impl Node {
                 .position(|t| t == &Some(index))
                 .map(|i| source.column_label(i))
                 .unwrap_or(Label::None),
+            Self::Window { source, .. } => match index < source.columns() {
+                true => source.column_label(index),
+                false => Label::None,
+            },
 
             // Joins dispatch to the appropriate source.
             Self::HashJoin { left, right, .. } | Self::NestedLoopJoin { left, right, .. } => {
@@ -220,19 +315,175 @@ impl Node {
             }
 
             // Simple nodes just dispatch to the source.
-            Self::Filter { source, .. }
+            Self::Distinct { source, .. }
+            | Self::Filter { source, .. }
             | Self::Limit { source, .. }
             | Self::Offset { source, .. }
             | Self::Order { source, .. } => source.column_label(index),
+            Self::SemiJoin { left, .. } => left.column_label(index),
+            Self::RecursiveCte { anchor, .. } => anchor.column_label(index),
+            Self::Except { left, .. } | Self::Intersect { left, .. } | Self::Union { left, .. } => {
+                left.column_label(index)
+            }
 
-            // Nothing nodes contain the original columns of replaced nodes.
-            Self::Nothing { columns } => columns.get(index).cloned().unwrap_or(Label::None),
+            // Nothing nodes contain the original columns of replaced nodes,
+            // and working tables the columns of their CTE.
+            Self::Nothing { columns } | Self::WorkingTable { columns, .. } => {
+                columns.get(index).cloned().unwrap_or(Label::None)
+            }
 
             // And some don't have any names at all.
             Self::Values { .. } => Label::None,
         }
     }
 
+    /// Returns the data type of a column, if it can be inferred at plan time,
+    /// see Expression::data_type(). Used to infer bind parameter types and
+    /// the column types of set operations and materialized views.
+    pub fn column_type(&self, index: usize) -> Option<DataType> {
+        match self {
+            Self::IndexLookup { table, .. }
+            | Self::KeyLookup { table, .. }
+            | Self::Scan { table, .. } => Some(table.columns().get(index)?.get_data_type()),
+
+            Self::Aggregate {
+                source,
+                group_by,
+                aggregates,
+                ..
+            } => match group_by.get(index) {
+                Some(expr) => expr.data_type(source),
+                None => aggregates.get(index - group_by.len())?.data_type(source),
+            },
+            Self::Projection {
+                source,
+                expressions,
+                ..
+            } => expressions.get(index)?.data_type(source),
+            Self::Remap { source, targets } => {
+                let index = targets.iter().position(|t| t == &Some(index))?;
+                source.column_type(index)
+            }
+            Self::Window { source, windows } => match index < source.columns() {
+                true => source.column_type(index),
+                false => windows
+                    .get(index - source.columns())?
+                    .function
+                    .data_type(source),
+            },
+
+            Self::HashJoin { left, right, .. } | Self::NestedLoopJoin { left, right, .. } => {
+                if index < left.columns() {
+                    left.column_type(index)
+                } else {
+                    right.column_type(index - left.columns())
+                }
+            }
+
+            Self::Distinct { source, .. }
+            | Self::Filter { source, .. }
+            | Self::Limit { source, .. }
+            | Self::Offset { source, .. }
+            | Self::Order { source, .. } => source.column_type(index),
+            Self::SemiJoin { left, .. } => left.column_type(index),
+            Self::RecursiveCte { anchor, .. } => anchor.column_type(index),
+            Self::Except { left, right, .. }
+            | Self::Intersect { left, right, .. }
+            | Self::Union { left, right, .. } => {
+                left.column_type(index).or_else(|| right.column_type(index))
+            }
+
+            // Values only have a known type if all rows agree.
+            Self::Values { rows } => {
+                let empty = Node::Nothing {
+                    columns: Vec::new(),
+                };
+                let mut types = rows.iter().map(|row| row.get(index)?.data_type(&empty));
+                let data_type = types.next()??;
+                types.all(|t| t == Some(data_type)).then_some(data_type)
+            }
+
+            Self::Nothing { .. } | Self::WorkingTable { .. } => None,
+        }
+    }
+
+    /// Recursively calls the given closure on all node expressions, along with
+    /// the node whose rows the expression is evaluated against.
+    pub fn walk_expressions(&self, visitor: &mut impl FnMut(&Expression, &Node)) {
+        match self {
+            Self::Aggregate {
+                source,
+                group_by,
+                aggregates,
+                ..
+            } => {
+                group_by.iter().for_each(|expr| visitor(expr, source));
+                aggregates
+                    .iter()
+                    .filter_map(|aggregate| aggregate.expression())
+                    .for_each(|expr| visitor(expr, source));
+            }
+            Self::Distinct { source, on } => on.iter().for_each(|expr| visitor(expr, source)),
+            Self::Filter { source, predicate } => visitor(predicate, source),
+            Self::NestedLoopJoin {
+                predicate: Some(predicate),
+                ..
+            }
+            | Self::SemiJoin {
+                predicate: Some(predicate),
+                ..
+            } => visitor(predicate, self),
+            Self::Order { source, key } => key.iter().for_each(|(expr, _)| visitor(expr, source)),
+            Self::Projection {
+                source,
+                expressions,
+                ..
+            } => expressions.iter().for_each(|expr| visitor(expr, source)),
+            Self::Scan {
+                filter: Some(filter),
+                ..
+            } => visitor(filter, self),
+            Self::Values { rows } => rows.iter().flatten().for_each(|expr| visitor(expr, self)),
+            Self::Window { source, windows } => windows
+                .iter()
+                .flat_map(|window| window.expressions())
+                .for_each(|expr| visitor(expr, source)),
+            _ => {}
+        }
+        match self {
+            Self::Except { left, right, .. }
+            | Self::HashJoin { left, right, .. }
+            | Self::Intersect { left, right, .. }
+            | Self::NestedLoopJoin { left, right, .. }
+            | Self::SemiJoin { left, right, .. }
+            | Self::Union { left, right, .. } => {
+                left.walk_expressions(visitor);
+                right.walk_expressions(visitor);
+            }
+            Self::RecursiveCte {
+                anchor, recursive, ..
+            } => {
+                anchor.walk_expressions(visitor);
+                recursive.walk_expressions(visitor);
+            }
+            Self::Aggregate { source, .. }
+            | Self::Distinct { source, .. }
+            | Self::Filter { source, .. }
+            | Self::Limit { source, .. }
+            | Self::Offset { source, .. }
+            | Self::Order { source, .. }
+            | Self::Projection { source, .. }
+            | Self::Remap { source, .. }
+            | Self::Window { source, .. } => source.walk_expressions(visitor),
+            Self::IndexLookup { .. }
+            | Self::KeyLookup { .. }
+            | Self::Nothing { .. }
+            | Self::Scan { .. }
+            | Self::Values { .. }
+            | Self::WorkingTable { .. } => {}
+        }
+    }
+
     /// Recursively transforms query nodes depth-first by applying the given
     /// closures before and after descending.
     pub fn transform(
@@ -251,12 +502,23 @@ impl Node {
             Self::Aggregate {
                 source,
                 group_by,
+                grouping_sets,
                 aggregates,
             } => Self::Aggregate {
                 source: xform(source)?,
                 group_by,
+                grouping_sets,
                 aggregates,
             },
+            Self::Distinct { source, on } => Self::Distinct {
+                source: xform(source)?,
+                on,
+            },
+            Self::Except { left, right, all } => Self::Except {
+                left: xform(left)?,
+                right: xform(right)?,
+                all,
+            },
             Self::Filter { source, predicate } => Self::Filter {
                 source: xform(source)?,
                 predicate,
@@ -274,6 +536,11 @@ impl Node {
                 right_column,
                 outer,
             },
+            Self::Intersect { left, right, all } => Self::Intersect {
+                left: xform(left)?,
+                right: xform(right)?,
+                all,
+            },
             Self::Limit { source, limit } => Self::Limit {
                 source: xform(source)?,
                 limit,
@@ -306,16 +573,50 @@ impl Node {
                 expressions,
                 aliases,
             },
+            Self::RecursiveCte {
+                name,
+                anchor,
+                recursive,
+                distinct,
+                limit,
+            } => Self::RecursiveCte {
+                name,
+                anchor: xform(anchor)?,
+                recursive: xform(recursive)?,
+                distinct,
+                limit,
+            },
             Self::Remap { source, targets } => Self::Remap {
                 source: xform(source)?,
                 targets,
             },
+            Self::SemiJoin {
+                left,
+                right,
+                predicate,
+                anti,
+            } => Self::SemiJoin {
+                left: xform(left)?,
+                right: xform(right)?,
+                predicate,
+                anti,
+            },
+            Self::Union { left, right, all } => Self::Union {
+                left: xform(left)?,
+                right: xform(right)?,
+                all,
+            },
+            Self::Window { source, windows } => Self::Window {
+                source: xform(source)?,
+                windows,
+            },
 
             Self::IndexLookup { .. }
             | Self::KeyLookup { .. }
             | Self::Nothing { .. }
             | Self::Scan { .. }
-            | Self::Values { .. } => self,
+            | Self::Values { .. }
+            | Self::WorkingTable { .. } => self,
         };
         self = after(self)?;
         Ok(self)
@@ -329,6 +630,13 @@ impl Node {
         after: &impl Fn(Expression) -> Result<Expression>,
     ) -> Result<Self> {
         Ok(match self {
+            Self::Distinct { source, on } => {
+                let on = on
+                    .into_iter()
+                    .map(|expr| expr.transform(before, after))
+                    .collect::<Result<_>>()?;
+                Self::Distinct { source, on }
+            }
             Self::Filter {
                 source,
                 mut predicate,
@@ -350,6 +658,20 @@ impl Node {
                     outer,
                 }
             }
+            Self::SemiJoin {
+                left,
+                right,
+                predicate: Some(predicate),
+                anti,
+            } => {
+                let predicate = Some(predicate.transform(before, after)?);
+                Self::SemiJoin {
+                    left,
+                    right,
+                    predicate,
+                    anti,
+                }
+            }
             Self::Order { source, mut key } => {
                 key = key
                     .into_iter()
@@ -385,6 +707,27 @@ impl Node {
                     filter,
                 }
             }
+            Self::Aggregate {
+                source,
+                mut group_by,
+                grouping_sets,
+                mut aggregates,
+            } => {
+                group_by = group_by
+                    .into_iter()
+                    .map(|expr| expr.transform(before, after))
+                    .collect::<Result<_>>()?;
+                aggregates = aggregates
+                    .into_iter()
+                    .map(|aggregate| aggregate.transform(before, after))
+                    .collect::<Result<_>>()?;
+                Self::Aggregate {
+                    source,
+                    group_by,
+                    grouping_sets,
+                    aggregates,
+                }
+            }
             Self::Values { mut rows } => {
                 rows = rows
                     .into_iter()
@@ -397,10 +740,21 @@ impl Node {
                 // .try_collect()?;
                 Self::Values { rows }
             }
+            Self::Window {
+                source,
+                mut windows,
+            } => {
+                windows = windows
+                    .into_iter()
+                    .map(|window| window.transform(before, after))
+                    .collect::<Result<_>>()?;
+                Self::Window { source, windows }
+            }
 
-            Self::Aggregate { .. }
+            Self::Except { .. }
             | Self::HashJoin { .. }
             | Self::IndexLookup { .. }
+            | Self::Intersect { .. }
             | Self::KeyLookup { .. }
             | Self::Limit { .. }
             | Self::NestedLoopJoin {
@@ -408,8 +762,36 @@ impl Node {
             }
             | Self::Nothing { .. }
             | Self::Offset { .. }
+            | Self::RecursiveCte { .. }
             | Self::Remap { .. }
-            | Self::Scan { filter: None, .. } => self,
+            | Self::Scan { filter: None, .. }
+            | Self::SemiJoin {
+                predicate: None, ..
+            }
+            | Self::Union { .. }
+            | Self::WorkingTable { .. } => self,
         })
     }
+
+    /// Returns true if the node references columns of an enclosing query, i.e.
+    /// if it's a correlated subquery.
+    pub fn is_correlated(&self) -> bool {
+        self.references_outer(0)
+    }
+
+    /// Returns true if the node references columns of a query more than the
+    /// given depth above it, including via nested subqueries.
+    fn references_outer(&self, depth: usize) -> bool {
+        let mut found = false;
+        self.walk_expressions(&mut |expr, _| {
+            found |= expr.contains(&|expr| match expr {
+                Expression::OuterColumn(d, _) => *d >= depth,
+                Expression::Subquery(node)
+                | Expression::Exists(node)
+                | Expression::InSubquery(_, node) => node.references_outer(depth + 1),
+                _ => false,
+            })
+        });
+        found
+    }
 }
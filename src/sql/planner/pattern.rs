@@ -0,0 +1,141 @@
+use crate::common::{Error, Result};
+use crate::errinput;
+use crate::types::field::Field;
+use serde::{Deserialize, Serialize};
+
+/// A compiled string pattern, for LIKE, ILIKE, ~ and ~* matching. The planner
+/// compiles constant patterns once when building the expression, instead of
+/// for every row during evaluation.
+///
+/// Only the pattern source is serialized, and the regex is recompiled on
+/// deserialization.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(into = "PatternSource", try_from = "PatternSource")]
+pub struct Pattern {
+    source: PatternSource,
+    regex: regex::Regex,
+}
+
+/// The source of a pattern, from which the regex is compiled.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct PatternSource {
+    syntax: Syntax,
+    pattern: String,
+    escape: Option<char>,
+    case_insensitive: bool,
+}
+
+/// A pattern syntax.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+enum Syntax {
+    /// A LIKE pattern, where _ and % match a single character and any number
+    /// of characters, and must match the entire string.
+    Like,
+    /// A regular expression, which matches any part of the string.
+    Regex,
+}
+
+impl Pattern {
+    /// Compiles a LIKE pattern, using the given escape string to match a
+    /// literal _ or %. The escape string must be empty (no escaping) or a
+    /// single character. If case_insensitive is true, this is an ILIKE pattern.
+    pub fn like(pattern: &str, escape: Option<&str>, case_insensitive: bool) -> Result<Self> {
+        let escape = escape.unwrap_or_default();
+        if escape.chars().count() > 1 {
+            return errinput!("invalid escape string {}", Field::from(escape));
+        }
+        let escape = escape.chars().next();
+        Self::compile(PatternSource {
+            syntax: Syntax::Like,
+            pattern: pattern.to_string(),
+            escape,
+            case_insensitive,
+        })
+    }
+
+    /// Compiles a regular expression. If case_insensitive is true, this is a
+    /// ~* pattern.
+    pub fn regex(pattern: &str, case_insensitive: bool) -> Result<Self> {
+        Self::compile(PatternSource {
+            syntax: Syntax::Regex,
+            pattern: pattern.to_string(),
+            escape: None,
+            case_insensitive,
+        })
+    }
+
+    /// Compiles a pattern source into a regex.
+    fn compile(source: PatternSource) -> Result<Self> {
+        let regex = match source.syntax {
+            Syntax::Like => {
+                let mut regex = String::from("^");
+                let mut chars = source.pattern.chars();
+                while let Some(c) = chars.next() {
+                    match c {
+                        c if Some(c) == source.escape => match chars.next() {
+                            Some(c) => regex.push_str(&regex::escape(&c.to_string())),
+                            None => {
+                                return errinput!("LIKE pattern can't end with escape character")
+                            }
+                        },
+                        '%' => regex.push_str(".*"),
+                        '_' => regex.push('.'),
+                        c => regex.push_str(&regex::escape(&c.to_string())),
+                    }
+                }
+                regex.push('$');
+                regex
+            }
+            Syntax::Regex => source.pattern.clone(),
+        };
+        let regex = regex::RegexBuilder::new(&regex)
+            .case_insensitive(source.case_insensitive)
+            .dot_matches_new_line(source.syntax == Syntax::Like)
+            .build()
+            .map_err(|err| Error::InvalidInput(format!("invalid pattern: {err}")))?;
+        Ok(Self { source, regex })
+    }
+
+    /// Returns true if the string matches the pattern.
+    pub fn is_match(&self, string: &str) -> bool {
+        self.regex.is_match(string)
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+/// Formats the pattern as an operator and pattern string, e.g. LIKE 'a%'.
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let source = &self.source;
+        let operator = match (source.syntax, source.case_insensitive) {
+            (Syntax::Like, false) => "LIKE",
+            (Syntax::Like, true) => "ILIKE",
+            (Syntax::Regex, false) => "~",
+            (Syntax::Regex, true) => "~*",
+        };
+        write!(f, "{operator} {}", Field::from(source.pattern.as_str()))?;
+        if let Some(escape) = source.escape {
+            write!(f, " ESCAPE {}", Field::from(escape.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Pattern> for PatternSource {
+    fn from(pattern: Pattern) -> Self {
+        pattern.source
+    }
+}
+
+impl TryFrom<PatternSource> for Pattern {
+    type Error = Error;
+
+    fn try_from(source: PatternSource) -> Result<Self> {
+        Self::compile(source)
+    }
+}
@@ -1,13 +1,19 @@
 use crate::common::Result;
+use crate::errinput;
 use crate::sql::engine::{Catalog, Transaction};
 use crate::sql::execution;
 use crate::sql::execution::ExecutionResult;
 use crate::sql::parser::ast;
 use crate::sql::planner::expression::Expression;
 use crate::sql::planner::optimizer::OPTIMIZERS;
-use crate::sql::planner::{BoxedNode, Node, Planner};
-use crate::types::Table;
+use crate::sql::planner::{BoxedNode, Function, Node, Planner};
+use crate::types::collation::Collation;
+use crate::types::field::{Field, Label};
+use crate::types::temporal;
+use crate::types::{DataType, Table, View};
+use itertools::Itertools as _;
 use serde::{Deserialize, Serialize};
+use std::ops::Deref;
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Plan {
@@ -17,15 +23,42 @@ pub enum Plan {
     /// A DROP TABLE plan. Drops the given table. Errors if the table does not
     /// exist, unless if_exists is true.
     DropTable { table: String, if_exists: bool },
+    /// A CREATE VIEW plan. Stores the view in the catalog. Errors if a table
+    /// or view of the same name exists, unless replace is true and it's a view.
+    CreateView { view: View, replace: bool },
+    /// A CREATE MATERIALIZED VIEW plan. Stores the view in the catalog, and
+    /// creates a table of the same name with the given column names holding
+    /// the rows from source. Column types are inferred from the rows.
+    CreateMaterializedView {
+        view: View,
+        source: BoxedNode,
+        columns: Vec<String>,
+    },
+    /// A DROP VIEW plan. Drops the given view, including the table of a
+    /// materialized view. Errors if the view is of the other kind, or does
+    /// not exist unless if_exists is true.
+    DropView {
+        view: String,
+        if_exists: bool,
+        materialized: bool,
+    },
+    /// A REFRESH MATERIALIZED VIEW plan. Replaces the rows of the view's
+    /// table with the rows from source.
+    RefreshMaterializedView { table: Table, source: BoxedNode },
     /// A DELETE plan. Deletes rows in table that match the rows from source.
     /// primary_key specifies the primary key column index in the source rows.
     Delete {
         table: String,
         // primary_key: usize,
         source: BoxedNode,
+        returning: Option<Returning>,
     },
     /// An INSERT plan. Inserts rows from source (typically a Values node) into table.
-    Insert { table: Table, source: BoxedNode },
+    Insert {
+        table: Table,
+        source: BoxedNode,
+        returning: Option<Returning>,
+    },
     /// An UPDATE plan. Updates rows in table that match the rows from source,
     /// where primary_key specifies the primary key column index in the source
     /// rows. The given column/expression pairs specify the row updates to make,
@@ -36,12 +69,50 @@ pub enum Plan {
         // primary_key: usize,
         source: BoxedNode,
         expressions: Vec<(usize, Expression)>,
+        returning: Option<Returning>,
+    },
+    /// An UPSERT plan, i.e. INSERT ... ON CONFLICT. Inserts rows from source
+    /// into table like Insert, but rows that conflict with an existing row on
+    /// the target constraint take the conflict action instead. The table's
+    /// constraints are given as sorted column indexes, and the target is an
+    /// index into them, or None for any constraint. Conflicts on non-target
+    /// constraints are errors.
+    Upsert {
+        table: Table,
+        source: BoxedNode,
+        constraints: Vec<Vec<usize>>,
+        target: Option<usize>,
+        action: Box<ConflictAction>,
+        returning: Option<Returning>,
     },
     /// A SELECT plan. Recursively executes the query plan tree and returns the
     /// resulting rows.
     Select(BoxedNode),
 }
 
+/// The action to take when an upserted row conflicts with an existing row.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ConflictAction {
+    /// Skip the inserted row.
+    Nothing,
+    /// Update the existing row with the given column/expression pairs, if the
+    /// predicate holds. They're evaluated on the existing row followed by the
+    /// inserted (excluded) row.
+    Update {
+        expressions: Vec<(usize, Expression)>,
+        predicate: Option<Expression>,
+    },
+}
+
+/// A RETURNING clause of a DML plan. The expressions are evaluated on each
+/// affected table row: the inserted or updated row, or the deleted row. The
+/// results are returned like a SELECT with the given column labels.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Returning {
+    pub expressions: Vec<Expression>,
+    pub labels: Vec<Label>,
+}
+
 impl Plan {
     /// Builds a plan from an AST statement.
     pub fn build(statement: ast::Statement, catalog: &impl Catalog) -> Result<Self> {
@@ -57,53 +128,746 @@ impl Plan {
     pub fn optimize(self) -> Result<Self> {
         let optimize = |node| OPTIMIZERS.iter().try_fold(node, |node, (_, opt)| opt(node));
         Ok(match self {
-            Self::CreateTable { .. } | Self::DropTable { .. } => self,
-            Self::Delete { table, source } => Self::Delete {
+            Self::CreateTable { .. }
+            | Self::DropTable { .. }
+            | Self::CreateView { .. }
+            | Self::DropView { .. } => self,
+            Self::CreateMaterializedView {
+                view,
+                source,
+                columns,
+            } => Self::CreateMaterializedView {
+                view,
+                source: optimize(source)?,
+                columns,
+            },
+            Self::RefreshMaterializedView { table, source } => Self::RefreshMaterializedView {
                 table,
                 source: optimize(source)?,
             },
-            Self::Insert { table, source } => Self::Insert {
+            Self::Delete {
+                table,
+                source,
+                returning,
+            } => Self::Delete {
                 table,
                 source: optimize(source)?,
+                returning,
+            },
+            Self::Insert {
+                table,
+                source,
+                returning,
+            } => Self::Insert {
+                table,
+                source: optimize(source)?,
+                returning,
             },
             Self::Update {
                 table,
                 source,
                 expressions,
+                returning,
             } => Self::Update {
                 table,
                 source: optimize(source)?,
                 expressions,
+                returning,
+            },
+            Self::Upsert {
+                table,
+                source,
+                constraints,
+                target,
+                action,
+                returning,
+            } => Self::Upsert {
+                table,
+                source: optimize(source)?,
+                constraints,
+                target,
+                action,
+                returning,
             },
             Self::Select(root) => Self::Select(optimize(root)?),
         })
     }
+
+    /// Returns the data types of the plan's bind parameters, by index. A type
+    /// is inferred when a parameter is inserted into or assigned to a column,
+    /// or compared with one. Otherwise it's None, and any value is accepted.
+    pub fn parameters(&self) -> Vec<Option<DataType>> {
+        let mut types = Vec::new();
+        let source = match self {
+            // Views can't have parameters.
+            Self::CreateTable { .. }
+            | Self::DropTable { .. }
+            | Self::CreateView { .. }
+            | Self::CreateMaterializedView { .. }
+            | Self::DropView { .. }
+            | Self::RefreshMaterializedView { .. } => return types,
+            Self::Delete { source, .. } | Self::Select(source) => source,
+            Self::Insert { table, source, .. } | Self::Upsert { table, source, .. } => {
+                if let Node::Values { rows } = source.deref() {
+                    for row in rows {
+                        for (expr, column) in row.iter().zip(table.columns()) {
+                            if let Expression::Parameter(index) = expr {
+                                let data_type = column.get_data_type();
+                                infer_parameter(&mut types, *index, Some(data_type));
+                            }
+                        }
+                    }
+                }
+                source
+            }
+            Self::Update {
+                table,
+                source,
+                expressions,
+                ..
+            } => {
+                for (column, expr) in expressions {
+                    if let Expression::Parameter(index) = expr {
+                        let data_type = table.get_column(*column).get_data_type();
+                        infer_parameter(&mut types, *index, Some(data_type));
+                    }
+                    infer_parameters(expr, source, &mut types);
+                }
+                source
+            }
+        };
+        // DO UPDATE expressions are evaluated on the existing and inserted rows.
+        if let Self::Upsert { table, action, .. } = self {
+            if let ConflictAction::Update {
+                expressions,
+                predicate,
+            } = action.as_ref()
+            {
+                let scan = || Node::Scan {
+                    table: table.clone(),
+                    filter: None,
+                    alias: None,
+                };
+                let input = Node::NestedLoopJoin {
+                    left: scan().into(),
+                    right: scan().into(),
+                    predicate: None,
+                    outer: false,
+                };
+                for (column, expr) in expressions {
+                    if let Expression::Parameter(index) = expr {
+                        let data_type = table.get_column(*column).get_data_type();
+                        infer_parameter(&mut types, *index, Some(data_type));
+                    }
+                    infer_parameters(expr, &input, &mut types);
+                }
+                if let Some(predicate) = predicate {
+                    infer_parameters(predicate, &input, &mut types);
+                }
+            }
+        }
+        // RETURNING expressions are evaluated on the affected table rows.
+        match self {
+            Self::Delete {
+                source,
+                returning: Some(returning),
+                ..
+            }
+            | Self::Update {
+                source,
+                returning: Some(returning),
+                ..
+            } => {
+                for expr in &returning.expressions {
+                    infer_parameters(expr, source, &mut types);
+                }
+            }
+            Self::Insert {
+                table,
+                returning: Some(returning),
+                ..
+            }
+            | Self::Upsert {
+                table,
+                returning: Some(returning),
+                ..
+            } => {
+                let input = Node::Scan {
+                    table: table.clone(),
+                    filter: None,
+                    alias: None,
+                };
+                for expr in &returning.expressions {
+                    infer_parameters(expr, &input, &mut types);
+                }
+            }
+            _ => {}
+        }
+        source.walk_expressions(&mut |expr, input| infer_parameters(expr, input, &mut types));
+        types
+    }
+
+    /// Binds values to the plan's parameters, replacing them with constants.
+    /// Values are bound as is, see PreparedStatement for type checks. Calls to
+    /// now() are bound to the current time, so this must be called right
+    /// before every execution, also for plans without parameters.
+    pub fn bind(self, values: &[Field]) -> Result<Self> {
+        let now = Field::Timestamp(temporal::now());
+        let bind = |expr| bind_expression(expr, values, &now);
+        let bind_node = |node| bind_node(node, values, &now);
+        let bind_returning = |returning: Option<Returning>| -> Result<Option<Returning>> {
+            returning
+                .map(
+                    |Returning {
+                         expressions,
+                         labels,
+                     }| {
+                        let expressions = expressions
+                            .into_iter()
+                            .map(|expr| expr.transform(&Ok, &bind))
+                            .collect::<Result<_>>()?;
+                        Ok(Returning {
+                            expressions,
+                            labels,
+                        })
+                    },
+                )
+                .transpose()
+        };
+        Ok(match self {
+            Self::CreateTable { .. }
+            | Self::DropTable { .. }
+            | Self::CreateView { .. }
+            | Self::DropView { .. } => self,
+            Self::CreateMaterializedView {
+                view,
+                source,
+                columns,
+            } => Self::CreateMaterializedView {
+                view,
+                source: bind_node(source)?,
+                columns,
+            },
+            Self::RefreshMaterializedView { table, source } => Self::RefreshMaterializedView {
+                table,
+                source: bind_node(source)?,
+            },
+            Self::Delete {
+                table,
+                source,
+                returning,
+            } => Self::Delete {
+                table,
+                source: bind_node(source)?,
+                returning: bind_returning(returning)?,
+            },
+            Self::Insert {
+                table,
+                source,
+                returning,
+            } => Self::Insert {
+                table,
+                source: bind_node(source)?,
+                returning: bind_returning(returning)?,
+            },
+            Self::Update {
+                table,
+                source,
+                expressions,
+                returning,
+            } => Self::Update {
+                table,
+                source: bind_node(source)?,
+                expressions: expressions
+                    .into_iter()
+                    .map(|(column, expr)| Ok((column, expr.transform(&Ok, &bind)?)))
+                    .collect::<Result<_>>()?,
+                returning: bind_returning(returning)?,
+            },
+            Self::Upsert {
+                table,
+                source,
+                constraints,
+                target,
+                action,
+                returning,
+            } => Self::Upsert {
+                table,
+                source: bind_node(source)?,
+                constraints,
+                target,
+                action: match *action {
+                    ConflictAction::Nothing => ConflictAction::Nothing,
+                    ConflictAction::Update {
+                        expressions,
+                        predicate,
+                    } => ConflictAction::Update {
+                        expressions: expressions
+                            .into_iter()
+                            .map(|(column, expr)| Ok((column, expr.transform(&Ok, &bind)?)))
+                            .collect::<Result<_>>()?,
+                        predicate: predicate
+                            .map(|expr| expr.transform(&Ok, &bind))
+                            .transpose()?,
+                    },
+                }
+                .into(),
+                returning: bind_returning(returning)?,
+            },
+            Self::Select(root) => Self::Select(bind_node(root)?),
+        })
+    }
+}
+
+/// Binds values to the parameters of an expression, and the given time to
+/// calls to now(), including any subqueries.
+fn bind_expression(expr: Expression, values: &[Field], now: &Field) -> Result<Expression> {
+    Ok(match expr {
+        Expression::Parameter(index) => match values.get(index) {
+            Some(value) => Expression::Constant(value.clone()),
+            None => return errinput!("no value for parameter ${}", index + 1),
+        },
+        Expression::Function(Function::Now, _) => Expression::Constant(now.clone()),
+        Expression::Subquery(node) => Expression::Subquery(bind_node(node, values, now)?),
+        Expression::Exists(node) => Expression::Exists(bind_node(node, values, now)?),
+        Expression::InSubquery(expr, node) => {
+            Expression::InSubquery(expr, bind_node(node, values, now)?)
+        }
+        expr => expr,
+    })
+}
+
+/// Binds values to the parameters of all expressions in a node tree, see
+/// bind_expression().
+fn bind_node(node: BoxedNode, values: &[Field], now: &Field) -> Result<BoxedNode> {
+    let bind = |expr| bind_expression(expr, values, now);
+    let node = node
+        .inner
+        .transform(&|node| node.transform_expressions(&Ok, &bind), &Ok)?;
+    Ok(node.into())
+}
+
+/// Infers bind parameter types from comparisons with columns in the given
+/// expression, where columns refer to rows of the given input node.
+fn infer_parameters(expr: &Expression, input: &Node, types: &mut Vec<Option<DataType>>) {
+    // Strips a collation, which the planner adds to comparison operands.
+    fn uncollate(expr: &Expression) -> &Expression {
+        match expr {
+            Expression::Collate(expr, _) => expr,
+            expr => expr,
+        }
+    }
+    let infer = |types: &mut Vec<Option<DataType>>, param: &Expression, other: &Expression| {
+        if let (Expression::Parameter(index), Expression::Column(column)) =
+            (uncollate(param), uncollate(other))
+        {
+            infer_parameter(types, *index, input.column_type(*column));
+        }
+    };
+    expr.walk(&mut |expr| {
+        match expr {
+            Expression::Parameter(index) => infer_parameter(types, *index, None),
+            Expression::Equal(lhs, rhs)
+            | Expression::GreaterThan(lhs, rhs)
+            | Expression::LessThan(lhs, rhs) => {
+                infer(types, lhs, rhs);
+                infer(types, rhs, lhs);
+            }
+            Expression::In(expr, list) => list.iter().for_each(|item| infer(types, item, expr)),
+            Expression::Between(expr, low, high) => {
+                infer(types, low, expr);
+                infer(types, high, expr);
+            }
+            // Subqueries are separate plans, infer from their expressions.
+            Expression::Subquery(node)
+            | Expression::Exists(node)
+            | Expression::InSubquery(_, node) => {
+                node.walk_expressions(&mut |expr, input| infer_parameters(expr, input, types))
+            }
+            _ => {}
+        }
+        true
+    });
+}
+
+/// Records a parameter's data type, unless it's already known.
+fn infer_parameter(types: &mut Vec<Option<DataType>>, index: usize, data_type: Option<DataType>) {
+    if types.len() <= index {
+        types.resize(index + 1, None);
+    }
+    if types[index].is_none() {
+        types[index] = data_type;
+    }
 }
 
 /// An aggregate function.
 #[allow(dead_code)]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Aggregate {
+    /// Estimates the number of distinct values with a HyperLogLog sketch.
+    ApproxCountDistinct(Expression),
+    /// Estimates the value at the given fraction (0 to 1) of the ordered
+    /// values with a t-digest.
+    ApproxPercentile(Expression, f64),
+    /// Collects the values into an array, encoded as a text array literal,
+    /// e.g. {1,2,3}.
+    ArrayAgg(Expression),
     Average(Expression),
+    BoolAnd(Expression),
+    BoolOr(Expression),
     Count(Expression),
+    /// Aggregates only distinct values, e.g. COUNT(DISTINCT x). Values are
+    /// compared by the given collation, but aggregated as is.
+    Distinct(Box<Aggregate>, Collation),
+    /// GROUPING() of the given GROUP BY expression indexes: a bitmask of the
+    /// expressions that aren't grouped by in the row's grouping set, with the
+    /// first as the most significant bit.
+    Grouping(Vec<usize>),
     Max(Expression),
     Min(Expression),
+    /// The value at the given fraction (0 to 1) of the ordered values,
+    /// interpolating between adjacent values, i.e. PERCENTILE_CONT.
+    PercentileCont(Expression, f64, Direction),
+    /// The first ordered value whose position is at or after the given
+    /// fraction (0 to 1) of the values, i.e. PERCENTILE_DISC.
+    PercentileDisc(Expression, f64, Direction),
+    StdDevPop(Expression),
+    StdDevSamp(Expression),
+    /// Concatenates string values, separated by the given separator.
+    StringAgg(Expression, String),
     Sum(Expression),
+    VarPop(Expression),
+    VarSamp(Expression),
 }
 
 #[allow(dead_code)]
 impl Aggregate {
+    /// Returns the aggregate's input expression, if any.
+    pub fn expression(&self) -> Option<&Expression> {
+        match self {
+            Self::ApproxCountDistinct(expr)
+            | Self::ApproxPercentile(expr, _)
+            | Self::ArrayAgg(expr)
+            | Self::Average(expr)
+            | Self::BoolAnd(expr)
+            | Self::BoolOr(expr)
+            | Self::Count(expr)
+            | Self::Max(expr)
+            | Self::Min(expr)
+            | Self::PercentileCont(expr, _, _)
+            | Self::PercentileDisc(expr, _, _)
+            | Self::StdDevPop(expr)
+            | Self::StdDevSamp(expr)
+            | Self::StringAgg(expr, _)
+            | Self::Sum(expr)
+            | Self::VarPop(expr)
+            | Self::VarSamp(expr) => Some(expr),
+            Self::Distinct(aggregate, _) => aggregate.expression(),
+            Self::Grouping(_) => None,
+        }
+    }
+
+    /// Returns the type of the aggregate's result, evaluating its input
+    /// expression against the given source node, or None if it can't be known
+    /// at plan time. AVG is a DOUBLE, although exact averages of integers
+    /// yield a BIGINT, and sums and averages of decimals are unknown since
+    /// their scale depends on the values.
+    pub fn data_type(&self, source: &Node) -> Option<DataType> {
+        let input = || self.expression()?.data_type(source);
+        match self {
+            Self::ApproxCountDistinct(_) | Self::Count(_) => Some(DataType::BigInt),
+            Self::ApproxPercentile(_, _)
+            | Self::PercentileCont(_, _, _)
+            | Self::StdDevPop(_)
+            | Self::StdDevSamp(_)
+            | Self::VarPop(_)
+            | Self::VarSamp(_) => Some(DataType::Double),
+            Self::Average(_) => input()
+                .filter(|t| t.is_numeric() && !matches!(t, DataType::Decimal(_, _)))
+                .map(|_| DataType::Double),
+            Self::ArrayAgg(_) | Self::StringAgg(_, _) => Some(DataType::Text),
+            Self::BoolAnd(_) | Self::BoolOr(_) => Some(DataType::Bool),
+            Self::Distinct(aggregate, _) => aggregate.data_type(source),
+            Self::Grouping(_) => Some(DataType::Int),
+            Self::Max(_) | Self::Min(_) | Self::PercentileDisc(_, _, _) => input(),
+            Self::Sum(_) => match input()? {
+                data_type if data_type.is_integer() => Some(DataType::BigInt),
+                data_type @ (DataType::Float | DataType::Double) => Some(data_type),
+                _ => None,
+            },
+        }
+    }
+
+    /// Transforms the aggregate's input expression with the given closures,
+    /// see Expression::transform().
+    pub fn transform(
+        self,
+        before: &impl Fn(Expression) -> Result<Expression>,
+        after: &impl Fn(Expression) -> Result<Expression>,
+    ) -> Result<Self> {
+        Ok(match self {
+            Self::ApproxCountDistinct(expr) => {
+                Self::ApproxCountDistinct(expr.transform(before, after)?)
+            }
+            Self::ApproxPercentile(expr, fraction) => {
+                Self::ApproxPercentile(expr.transform(before, after)?, fraction)
+            }
+            Self::ArrayAgg(expr) => Self::ArrayAgg(expr.transform(before, after)?),
+            Self::Average(expr) => Self::Average(expr.transform(before, after)?),
+            Self::BoolAnd(expr) => Self::BoolAnd(expr.transform(before, after)?),
+            Self::BoolOr(expr) => Self::BoolOr(expr.transform(before, after)?),
+            Self::Count(expr) => Self::Count(expr.transform(before, after)?),
+            Self::Distinct(aggregate, collation) => {
+                Self::Distinct(aggregate.transform(before, after)?.into(), collation)
+            }
+            Self::Grouping(indexes) => Self::Grouping(indexes),
+            Self::Max(expr) => Self::Max(expr.transform(before, after)?),
+            Self::Min(expr) => Self::Min(expr.transform(before, after)?),
+            Self::PercentileCont(expr, fraction, direction) => {
+                Self::PercentileCont(expr.transform(before, after)?, fraction, direction)
+            }
+            Self::PercentileDisc(expr, fraction, direction) => {
+                Self::PercentileDisc(expr.transform(before, after)?, fraction, direction)
+            }
+            Self::StdDevPop(expr) => Self::StdDevPop(expr.transform(before, after)?),
+            Self::StdDevSamp(expr) => Self::StdDevSamp(expr.transform(before, after)?),
+            Self::StringAgg(expr, separator) => {
+                Self::StringAgg(expr.transform(before, after)?, separator)
+            }
+            Self::Sum(expr) => Self::Sum(expr.transform(before, after)?),
+            Self::VarPop(expr) => Self::VarPop(expr.transform(before, after)?),
+            Self::VarSamp(expr) => Self::VarSamp(expr.transform(before, after)?),
+        })
+    }
+
     fn format(&self, node: &Node) -> String {
         match self {
+            Self::ApproxCountDistinct(expr) => {
+                format!("approx_count_distinct({})", expr.format(node))
+            }
+            Self::ApproxPercentile(expr, fraction) => {
+                format!("approx_percentile({}, {fraction})", expr.format(node))
+            }
+            Self::ArrayAgg(expr) => format!("array_agg({})", expr.format(node)),
             Self::Average(expr) => format!("avg({})", expr.format(node)),
+            Self::BoolAnd(expr) => format!("bool_and({})", expr.format(node)),
+            Self::BoolOr(expr) => format!("bool_or({})", expr.format(node)),
             Self::Count(expr) => format!("count({})", expr.format(node)),
+            Self::Distinct(aggregate, collation) => {
+                let format = aggregate.format(node).replacen('(', "(DISTINCT ", 1);
+                match (collation, aggregate.expression()) {
+                    (Collation::Binary, _) | (_, None) => format,
+                    (collation, Some(expr)) => {
+                        let expr = expr.format(node);
+                        format.replacen(&expr, &format!("{expr} COLLATE {collation}"), 1)
+                    }
+                }
+            }
+            Self::Grouping(indexes) => format!("grouping({})", indexes.iter().join(", ")),
             Self::Max(expr) => format!("max({})", expr.format(node)),
             Self::Min(expr) => format!("min({})", expr.format(node)),
+            Self::PercentileCont(expr, fraction, direction)
+            | Self::PercentileDisc(expr, fraction, direction) => {
+                let name = match self {
+                    Self::PercentileCont(..) => "percentile_cont",
+                    _ => "percentile_disc",
+                };
+                let direction = match direction {
+                    Direction::Ascending => "",
+                    Direction::Descending => " DESC",
+                };
+                format!(
+                    "{name}({fraction}) WITHIN GROUP (ORDER BY {}{direction})",
+                    expr.format(node)
+                )
+            }
+            Self::StdDevPop(expr) => format!("stddev_pop({})", expr.format(node)),
+            Self::StdDevSamp(expr) => format!("stddev_samp({})", expr.format(node)),
+            Self::StringAgg(expr, separator) => {
+                format!("string_agg({}, '{separator}')", expr.format(node))
+            }
             Self::Sum(expr) => format!("sum({})", expr.format(node)),
+            Self::VarPop(expr) => format!("var_pop({})", expr.format(node)),
+            Self::VarSamp(expr) => format!("var_samp({})", expr.format(node)),
         }
     }
 }
 
+/// A window function call, computed over the rows of its partition (rows
+/// with the same partition_by values) ordered by order_by.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Window {
+    pub function: WindowFunction,
+    pub partition_by: Vec<Expression>,
+    pub order_by: Vec<(Expression, Direction)>,
+    pub frame: Frame,
+}
+
+impl Window {
+    /// Transforms the window's expressions with the given closures, see
+    /// Expression::transform().
+    pub fn transform(
+        self,
+        before: &impl Fn(Expression) -> Result<Expression>,
+        after: &impl Fn(Expression) -> Result<Expression>,
+    ) -> Result<Self> {
+        Ok(Self {
+            function: self.function.transform(before, after)?,
+            partition_by: self
+                .partition_by
+                .into_iter()
+                .map(|expr| expr.transform(before, after))
+                .collect::<Result<_>>()?,
+            order_by: self
+                .order_by
+                .into_iter()
+                .map(|(expr, dir)| Ok((expr.transform(before, after)?, dir)))
+                .collect::<Result<_>>()?,
+            frame: self.frame,
+        })
+    }
+
+    /// Returns the window's expressions, i.e. function arguments and
+    /// partition and order keys.
+    pub fn expressions(&self) -> impl Iterator<Item = &Expression> {
+        self.function
+            .expressions()
+            .into_iter()
+            .chain(&self.partition_by)
+            .chain(self.order_by.iter().map(|(expr, _)| expr))
+    }
+}
+
+/// A window function.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WindowFunction {
+    /// The 1-based row number within the partition.
+    RowNumber,
+    /// The row number of the row's first peer (rows with equal order_by
+    /// values), i.e. with gaps.
+    Rank,
+    /// The number of distinct peer groups up to and including the row, i.e.
+    /// without gaps.
+    DenseRank,
+    /// The expression value of the row offset rows before the current row in
+    /// the partition, or the default (evaluated on the current row) if none.
+    Lag {
+        expression: Expression,
+        offset: usize,
+        default: Expression,
+    },
+    /// Like Lag, but offset rows after the current row.
+    Lead {
+        expression: Expression,
+        offset: usize,
+        default: Expression,
+    },
+    /// The expression value of the first row in the frame, or NULL if empty.
+    FirstValue(Expression),
+    /// The expression value of the last row in the frame, or NULL if empty.
+    LastValue(Expression),
+    /// An aggregate function over the rows in the frame.
+    Aggregate(Aggregate),
+}
+
+impl WindowFunction {
+    /// Returns the function's argument expressions.
+    pub fn expressions(&self) -> Vec<&Expression> {
+        match self {
+            Self::RowNumber | Self::Rank | Self::DenseRank => Vec::new(),
+            Self::Lag {
+                expression,
+                default,
+                ..
+            }
+            | Self::Lead {
+                expression,
+                default,
+                ..
+            } => vec![expression, default],
+            Self::FirstValue(expr) | Self::LastValue(expr) => vec![expr],
+            Self::Aggregate(aggregate) => aggregate.expression().into_iter().collect(),
+        }
+    }
+
+    /// Returns the type of the function's result, evaluating its arguments
+    /// against the given source node, or None if it can't be known.
+    pub fn data_type(&self, source: &Node) -> Option<DataType> {
+        match self {
+            Self::RowNumber | Self::Rank | Self::DenseRank => Some(DataType::BigInt),
+            Self::Lag { expression, .. }
+            | Self::Lead { expression, .. }
+            | Self::FirstValue(expression)
+            | Self::LastValue(expression) => expression.data_type(source),
+            Self::Aggregate(aggregate) => aggregate.data_type(source),
+        }
+    }
+
+    /// Transforms the function's argument expressions with the given closures,
+    /// see Expression::transform().
+    pub fn transform(
+        self,
+        before: &impl Fn(Expression) -> Result<Expression>,
+        after: &impl Fn(Expression) -> Result<Expression>,
+    ) -> Result<Self> {
+        Ok(match self {
+            Self::RowNumber | Self::Rank | Self::DenseRank => self,
+            Self::Lag {
+                expression,
+                offset,
+                default,
+            } => Self::Lag {
+                expression: expression.transform(before, after)?,
+                offset,
+                default: default.transform(before, after)?,
+            },
+            Self::Lead {
+                expression,
+                offset,
+                default,
+            } => Self::Lead {
+                expression: expression.transform(before, after)?,
+                offset,
+                default: default.transform(before, after)?,
+            },
+            Self::FirstValue(expr) => Self::FirstValue(expr.transform(before, after)?),
+            Self::LastValue(expr) => Self::LastValue(expr.transform(before, after)?),
+            Self::Aggregate(aggregate) => Self::Aggregate(aggregate.transform(before, after)?),
+        })
+    }
+}
+
+/// A window frame, i.e. the rows of the partition that FirstValue, LastValue
+/// and aggregate window functions are computed over for each row.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Frame {
+    pub units: FrameUnits,
+    pub start: FrameBound,
+    pub end: FrameBound,
+}
+
+/// Window frame units.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum FrameUnits {
+    /// Offsets are row counts, and the current row is just that row.
+    Rows,
+    /// Offsets are distances from the current row's order_by value, which
+    /// requires a single order_by expression, and the current row includes
+    /// its peers.
+    Range,
+}
+
+/// A window frame bound. Offsets are non-negative row counts for ROWS frames,
+/// and numbers or intervals for RANGE frames.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum FrameBound {
+    UnboundedPreceding,
+    Preceding(Field),
+    CurrentRow,
+    Following(Field),
+    UnboundedFollowing,
+}
+
 /// A sort order direction.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Direction {
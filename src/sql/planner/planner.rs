@@ -1,25 +1,51 @@
 use crate::common::Result;
+use crate::config::config::RECURSION_LIMIT;
 use crate::errinput;
 use crate::sql::engine::Catalog;
-use crate::sql::parser::ast;
 use crate::sql::parser::ast::Statement;
+use crate::sql::parser::{ast, did_you_mean, Parser};
 use crate::sql::planner::plan::remap_sources;
-use crate::sql::planner::{Aggregate, Expression, Node, Plan};
+use crate::sql::planner::{
+    Aggregate, BoxedNode, ConflictAction, Direction, Expression, Frame, FrameBound, FrameUnits,
+    Function, Node, Pattern, Plan, Returning, Window, WindowFunction,
+};
+use crate::types::collation::Collation;
 use crate::types::field::{Field, Label};
-use crate::types::{Column, Table};
+use crate::types::temporal::DateTimeField;
+use crate::types::{Column, DataType, Table, View};
 use itertools::Itertools as _;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::rc::Rc;
 
 /// Builds a query plan from a parsed SQL abstract syntax
 /// tree, referencing the catalog for schema information.
 pub struct Planner<'a, C: Catalog> {
     catalog: &'a C,
+    /// The maximum number of iterations of a recursive CTE.
+    recursion_limit: usize,
+    /// The views currently being expanded, to detect self-references.
+    views: RefCell<Vec<String>>,
+    /// The views referenced by the outermost view being expanded.
+    dependencies: RefCell<BTreeSet<String>>,
 }
 
 impl<'a, C: Catalog> Planner<'a, C> {
     /// Creates a new planner.
     pub fn new(catalog: &'a C) -> Self {
-        Self { catalog }
+        Self {
+            catalog,
+            recursion_limit: RECURSION_LIMIT,
+            views: RefCell::default(),
+            dependencies: RefCell::default(),
+        }
+    }
+
+    /// Sets the maximum number of iterations of recursive CTEs, after which
+    /// their execution errors.
+    pub fn with_recursion_limit(mut self, limit: usize) -> Self {
+        self.recursion_limit = limit;
+        self
     }
 
     /// Builds a query plan from a parsed AST statement.
@@ -34,25 +60,46 @@ impl<'a, C: Catalog> Planner<'a, C> {
                 table: name,
                 if_exists,
             }),
-            Delete { table, r#where } => self.build_delete(table, r#where),
-            Insert { table, values } => self.build_insert(table, values),
+            CreateView {
+                name,
+                query,
+                replace,
+                materialized,
+            } => self.build_create_view(name, *query, replace, materialized),
+            DropView {
+                name,
+                if_exists,
+                materialized,
+            } => Ok(Plan::DropView {
+                view: name,
+                if_exists,
+                materialized,
+            }),
+            RefreshMaterializedView { name } => self.build_refresh_materialized_view(name),
+            Delete {
+                table,
+                r#where,
+                returning,
+            } => self.build_delete(table, r#where, returning),
+            Insert {
+                table,
+                columns,
+                source,
+                on_conflict,
+                returning,
+            } => self.build_insert(table, columns, source, on_conflict, returning),
             Update {
                 table,
                 set,
                 r#where,
-            } => self.build_update(table, set, r#where),
-            Select {
-                select,
-                from,
-                r#where,
-                group_by,
-                having,
-                order_by,
-                offset,
-                limit,
-            } => self.build_select(
-                select, from, r#where, group_by, having, order_by, offset, limit,
-            ),
+                returning,
+            } => self.build_update(table, set, r#where, returning),
+            statement @ (Select { .. } | SetOperation { .. } | With { .. }) => Ok(Plan::Select(
+                self.build_query(statement, None, &HashMap::new())?.into(),
+            )),
+            Prepare { .. } | Execute { .. } | Deallocate { .. } => {
+                errinput!("prepared statements must be handled by the session")
+            }
             _ => {
                 panic!("Statement either invalid or not yet implemented.")
             }
@@ -73,69 +120,485 @@ impl<'a, C: Catalog> Planner<'a, C> {
     /// This will build an Aggregate node for SUM(a), COUNT(*), MAX(c) bucketed
     /// by b % 10. The SELECT can look up up SUM(a) and COUNT(*) to compute the
     /// division, and HAVING can look up b % 10 to compute the predicate.
+    ///
+    /// GROUP BY ROLLUP, CUBE and GROUPING SETS are expanded to grouping sets
+    /// over the distinct GROUP BY expressions, which the node buckets by in a
+    /// single pass.
     fn build_aggregate(
         &self,
         source: Node,
-        mut group_by: Vec<ast::Expression>,
+        group_by: Vec<ast::GroupBy>,
         mut aggregates: Vec<ast::Expression>,
         scope: &mut Scope,
     ) -> Result<Node> {
+        for expr in &aggregates {
+            let args = match expr {
+                ast::Expression::Function(_, args, _) => args.iter().collect_vec(),
+                ast::Expression::WithinGroup(_, args, order) => {
+                    args.iter().chain([&order.0]).collect_vec()
+                }
+                _ => Vec::new(),
+            };
+            if args
+                .into_iter()
+                .any(|arg| arg.contains(&Self::is_aggregate_function))
+            {
+                return errinput!("aggregate functions can't be nested");
+            }
+        }
+        let (group_by, grouping_sets) = Self::build_grouping_sets(group_by)?;
+
         // Construct a child scope with the group_by and aggregate AST
-        // expressions, for lookups. Discard duplicate expressions.
+        // expressions, for lookups. Discard duplicate aggregates (the group_by
+        // expressions are already unique).
         let mut child_scope = scope.spawn();
-        group_by.retain(|expr| child_scope.add_aggregate(expr, scope).is_some());
+        for expr in &group_by {
+            child_scope.add_aggregate(expr, scope);
+        }
         aggregates.retain(|expr| child_scope.add_aggregate(expr, scope).is_some());
 
-        // Build the node from the remaining unique expressions.
-        let group_by = group_by
+        // Build the node from the remaining unique expressions. GROUP BY
+        // expressions with a collation are bucketed by their sort key.
+        let aggregates = aggregates
             .into_iter()
-            .map(|expr| Self::build_expression(expr, scope))
+            .map(|expr| match expr {
+                ast::Expression::Function(name, args, distinct) if name == "grouping" => {
+                    Self::build_grouping(args, distinct, &group_by)
+                }
+                expr => self.build_aggregate_function(expr, scope),
+            })
             .try_collect()?;
-        let aggregates = aggregates
+        let group_by = group_by
             .into_iter()
-            .map(|expr| Self::build_aggregate_function(expr, scope))
+            .map(|expr| self.build_collated(expr, scope))
             .try_collect()?;
 
         *scope = child_scope;
         Ok(Node::Aggregate {
             source: source.into(),
             group_by,
+            grouping_sets,
             aggregates,
         })
     }
 
+    /// Expands GROUP BY elements into grouping sets, i.e. the cross product
+    /// of each element's grouping sets. Returns the distinct grouping
+    /// expressions, and the grouping sets as indexes into them. A plain GROUP
+    /// BY yields a single set with all expressions, and no GROUP BY a single
+    /// empty set.
+    fn build_grouping_sets(
+        group_by: Vec<ast::GroupBy>,
+    ) -> Result<(Vec<ast::Expression>, Vec<Vec<usize>>)> {
+        let mut sets: Vec<Vec<ast::Expression>> = vec![Vec::new()];
+        for element in group_by {
+            let element_sets = match element {
+                ast::GroupBy::Expression(expr) => vec![vec![expr]],
+                ast::GroupBy::Rollup(exprs) => (0..=exprs.len())
+                    .rev()
+                    .map(|len| exprs[..len].to_vec())
+                    .collect(),
+                ast::GroupBy::Cube(exprs) => {
+                    if exprs.len() > 12 {
+                        return errinput!("CUBE is limited to 12 expressions");
+                    }
+                    // Enumerate subsets from all expressions to none, with
+                    // the first expression as the most significant bit.
+                    let len = exprs.len();
+                    (0..1usize << len)
+                        .rev()
+                        .map(|mask| {
+                            (0..len)
+                                .filter(|i| mask & (1 << (len - 1 - i)) != 0)
+                                .map(|i| exprs[i].clone())
+                                .collect()
+                        })
+                        .collect()
+                }
+                ast::GroupBy::GroupingSets(sets) => sets,
+            };
+            sets = sets
+                .into_iter()
+                .cartesian_product(element_sets)
+                .map(|(set, element_set)| set.into_iter().chain(element_set).collect())
+                .collect();
+            if sets.len() > 4096 {
+                return errinput!("too many grouping sets");
+            }
+        }
+
+        let exprs = sets.iter().flatten().unique().cloned().collect_vec();
+        let sets = sets
+            .into_iter()
+            .map(|set| {
+                set.iter()
+                    .map(|expr| exprs.iter().position(|e| e == expr).unwrap())
+                    .unique()
+                    .collect()
+            })
+            .collect();
+        Ok((exprs, sets))
+    }
+
+    /// Builds a GROUPING() function, whose arguments must be GROUP BY
+    /// expressions.
+    fn build_grouping(
+        args: Vec<ast::Expression>,
+        distinct: bool,
+        group_by: &[ast::Expression],
+    ) -> Result<Aggregate> {
+        if distinct {
+            return errinput!("DISTINCT is not allowed in grouping");
+        }
+        if args.is_empty() || args.len() > 31 {
+            return errinput!("grouping takes 1 to 31 arguments");
+        }
+        let indexes = args
+            .iter()
+            .map(|arg| match group_by.iter().position(|expr| expr == arg) {
+                Some(index) => Ok(index),
+                None => errinput!("grouping arguments must be GROUP BY expressions"),
+            })
+            .try_collect()?;
+        Ok(Aggregate::Grouping(indexes))
+    }
+
     /// Builds an aggregate function from an AST expression.
-    fn build_aggregate_function(expr: ast::Expression, scope: &Scope) -> Result<Aggregate> {
-        let ast::Expression::Function(name, mut args) = expr else {
-            panic!("aggregate expression must be function");
+    fn build_aggregate_function(&self, expr: ast::Expression, scope: &Scope) -> Result<Aggregate> {
+        let (name, mut args, distinct) = match expr {
+            ast::Expression::Function(name, args, distinct) => (name, args, distinct),
+            ast::Expression::WithinGroup(name, args, order) => {
+                return self.build_ordered_set_aggregate(name, args, *order, scope)
+            }
+            _ => panic!("aggregate expression must be function"),
         };
+        if name == "percentile_cont" || name == "percentile_disc" {
+            return errinput!("{name} requires WITHIN GROUP (ORDER BY ...)");
+        }
+        // string_agg() takes a constant separator as its second argument, and
+        // approx_percentile() a constant fraction.
+        let mut separator = String::new();
+        let mut fraction = 0.0;
+        if name == "string_agg" {
+            if args.len() != 2 {
+                return errinput!("{name} takes 2 arguments");
+            }
+            separator = match self.evaluate_constant(args.remove(1))? {
+                Field::String(separator) => separator,
+                Field::Null => String::new(),
+                value => return errinput!("invalid string_agg separator {value}"),
+            };
+        }
+        if name == "approx_percentile" {
+            if args.len() != 2 {
+                return errinput!("{name} takes 2 arguments");
+            }
+            fraction = self.build_fraction(&name, args.remove(1))?;
+        }
         if args.len() != 1 {
             return errinput!("{name} takes 1 argument");
         }
-        if args[0].contains(&|expr| Self::is_aggregate_function(expr)) {
-            return errinput!("aggregate functions can't be nested");
-        }
+        // DISTINCT compares values by the argument's collation, if any.
+        let collation = scope.collation(&args[0]).unwrap_or_default();
         // Special-case COUNT(*) since expressions don't support tuples.
         let expr = match (name.as_str(), args.remove(0)) {
+            ("count", ast::Expression::All) if distinct => {
+                return errinput!("count(DISTINCT *) is not supported")
+            }
             ("count", ast::Expression::All) => Expression::Constant(Field::Boolean(true)),
-            (_, arg) => Self::build_expression(arg, scope)?,
+            (_, arg) => self.build_expression(arg, scope)?,
         };
-        Ok(match name.as_str() {
+        let aggregate = match name.as_str() {
+            "approx_count_distinct" => Aggregate::ApproxCountDistinct(expr),
+            "approx_percentile" => Aggregate::ApproxPercentile(expr, fraction),
+            "array_agg" => Aggregate::ArrayAgg(expr),
             "avg" => Aggregate::Average(expr),
+            "bool_and" => Aggregate::BoolAnd(expr),
+            "bool_or" => Aggregate::BoolOr(expr),
             "count" => Aggregate::Count(expr),
+            "median" => Aggregate::PercentileCont(expr, 0.5, Direction::Ascending),
             "min" => Aggregate::Min(expr),
             "max" => Aggregate::Max(expr),
+            "stddev" | "stddev_samp" => Aggregate::StdDevSamp(expr),
+            "stddev_pop" => Aggregate::StdDevPop(expr),
+            "string_agg" => Aggregate::StringAgg(expr, separator),
             "sum" => Aggregate::Sum(expr),
+            "variance" | "var_samp" => Aggregate::VarSamp(expr),
+            "var_pop" => Aggregate::VarPop(expr),
             name => return errinput!("unknown aggregate function {name}"),
+        };
+        Ok(match distinct {
+            true => Aggregate::Distinct(aggregate.into(), collation),
+            false => aggregate,
         })
     }
 
+    /// Builds an ordered-set aggregate function, i.e. f(args) WITHIN GROUP
+    /// (ORDER BY expr).
+    fn build_ordered_set_aggregate(
+        &self,
+        name: String,
+        mut args: Vec<ast::Expression>,
+        (expr, direction): (ast::Expression, ast::Direction),
+        scope: &Scope,
+    ) -> Result<Aggregate> {
+        if name != "percentile_cont" && name != "percentile_disc" {
+            return errinput!("unknown ordered-set aggregate function {name}");
+        }
+        if args.len() != 1 {
+            return errinput!("{name} takes 1 argument");
+        }
+        let fraction = self.build_fraction(&name, args.remove(0))?;
+        let expr = self.build_expression(expr, scope)?;
+        Ok(match name.as_str() {
+            "percentile_cont" => Aggregate::PercentileCont(expr, fraction, direction.into()),
+            _ => Aggregate::PercentileDisc(expr, fraction, direction.into()),
+        })
+    }
+
+    /// Builds a constant percentile fraction, which must be between 0 and 1.
+    fn build_fraction(&self, name: &str, expr: ast::Expression) -> Result<f64> {
+        let fraction = match self.evaluate_constant(expr)? {
+            value if value.is_numeric() => value.as_double().unwrap(),
+            value => return errinput!("invalid {name} fraction {value}"),
+        };
+        if !(0.0..=1.0).contains(&fraction) {
+            return errinput!("{name} fraction must be between 0 and 1");
+        }
+        Ok(fraction)
+    }
+
     /// Checks whether a given AST expression is an aggregate function.
     fn is_aggregate_function(expr: &ast::Expression) -> bool {
-        if let ast::Expression::Function(name, _) = expr {
-            return ["avg", "count", "max", "min", "sum"].contains(&name.as_str());
+        match expr {
+            ast::Expression::Function(name, _, _) => [
+                "approx_count_distinct",
+                "approx_percentile",
+                "array_agg",
+                "avg",
+                "bool_and",
+                "bool_or",
+                "count",
+                "grouping",
+                "max",
+                "median",
+                "min",
+                "percentile_cont",
+                "percentile_disc",
+                "stddev",
+                "stddev_pop",
+                "stddev_samp",
+                "string_agg",
+                "sum",
+                "var_pop",
+                "var_samp",
+                "variance",
+            ]
+            .contains(&name.as_str()),
+            ast::Expression::WithinGroup(_, _, _) => true,
+            _ => false,
         }
-        false
+    }
+
+    /// Builds a Window node computing the given window functions. Source
+    /// columns are passed through, and a column is appended to the scope for
+    /// each window function, for lookups from SELECT and ORDER BY. These are
+    /// hidden, so they're only emitted when projected.
+    fn build_window(
+        &self,
+        source: Node,
+        windows: Vec<ast::Expression>,
+        scope: &mut Scope,
+    ) -> Result<Node> {
+        let windows = windows.into_iter().unique().collect_vec();
+        let built: Vec<Window> = windows
+            .iter()
+            .map(|expr| self.build_window_function(expr.clone(), scope))
+            .try_collect()?;
+        // RANGE offsets are added to and subtracted from the ORDER BY value,
+        // so it must be numeric or temporal. If its type isn't known here,
+        // it's checked during execution.
+        for window in &built {
+            let is_offset = |bound: &FrameBound| {
+                matches!(bound, FrameBound::Preceding(_) | FrameBound::Following(_))
+            };
+            let frame = &window.frame;
+            if frame.units != FrameUnits::Range
+                || !(is_offset(&frame.start) || is_offset(&frame.end))
+            {
+                continue;
+            }
+            match window.order_by[0].0.data_type(&source) {
+                Some(data_type)
+                    if data_type != DataType::Invalid
+                        && !data_type.is_numeric()
+                        && !data_type.is_temporal() =>
+                {
+                    return errinput!(
+                        "RANGE with offset requires a numeric or temporal ORDER BY value, got {data_type}"
+                    )
+                }
+                _ => {}
+            }
+        }
+        for expr in windows {
+            let index = scope.add_column(Label::None);
+            scope.windows.insert(expr, index);
+            scope.hidden.insert(index);
+        }
+        Ok(Node::Window {
+            source: source.into(),
+            windows: built,
+        })
+    }
+
+    /// Builds a window function from an AST expression.
+    fn build_window_function(&self, expr: ast::Expression, scope: &Scope) -> Result<Window> {
+        let ast::Expression::Window(name, mut args, window) = expr else {
+            panic!("window expression must be window function");
+        };
+        let ast::Window {
+            partition_by,
+            order_by,
+            frame,
+        } = *window;
+        let is_window = |expr: &ast::Expression| matches!(expr, ast::Expression::Window(_, _, _));
+        if args
+            .iter()
+            .chain(&partition_by)
+            .chain(order_by.iter().map(|(expr, _)| expr))
+            .any(|expr| expr.contains(&is_window))
+        {
+            return errinput!("window functions can't be nested");
+        }
+
+        let function = match (name.as_str(), args.len()) {
+            ("row_number", 0) => WindowFunction::RowNumber,
+            ("rank", 0) => WindowFunction::Rank,
+            ("dense_rank", 0) => WindowFunction::DenseRank,
+            ("row_number" | "rank" | "dense_rank", _) => {
+                return errinput!("{name} takes no arguments")
+            }
+            ("lag" | "lead", 1..=3) => {
+                let mut args = args.into_iter();
+                let expression = self.build_expression(args.next().unwrap(), scope)?;
+                let offset = match args.next() {
+                    Some(offset) => {
+                        let offset = self.evaluate_constant(offset)?;
+                        match offset.as_i64() {
+                            Some(n) if n >= 0 => n as usize,
+                            _ => return errinput!("invalid {name} offset {offset}"),
+                        }
+                    }
+                    None => 1,
+                };
+                let default = match args.next() {
+                    Some(default) => self.build_expression(default, scope)?,
+                    None => Expression::Constant(Field::Null),
+                };
+                match name.as_str() {
+                    "lag" => WindowFunction::Lag {
+                        expression,
+                        offset,
+                        default,
+                    },
+                    _ => WindowFunction::Lead {
+                        expression,
+                        offset,
+                        default,
+                    },
+                }
+            }
+            ("lag" | "lead", _) => return errinput!("{name} takes 1 to 3 arguments"),
+            ("first_value" | "last_value", 1) => {
+                let expr = self.build_expression(args.remove(0), scope)?;
+                match name.as_str() {
+                    "first_value" => WindowFunction::FirstValue(expr),
+                    _ => WindowFunction::LastValue(expr),
+                }
+            }
+            ("first_value" | "last_value", _) => return errinput!("{name} takes 1 argument"),
+            _ => {
+                let function = ast::Expression::Function(name.clone(), args, false);
+                if !Self::is_aggregate_function(&function) || name == "grouping" {
+                    return errinput!("unknown window function {name}");
+                }
+                WindowFunction::Aggregate(self.build_aggregate_function(function, scope)?)
+            }
+        };
+
+        let frame = match frame {
+            Some(frame) => self.build_frame(frame, order_by.len())?,
+            // Without ORDER BY all rows are peers, so this is the whole
+            // partition.
+            None => Frame {
+                units: FrameUnits::Range,
+                start: FrameBound::UnboundedPreceding,
+                end: FrameBound::CurrentRow,
+            },
+        };
+        Ok(Window {
+            function,
+            partition_by: partition_by
+                .into_iter()
+                .map(|expr| self.build_collated(expr, scope))
+                .try_collect()?,
+            order_by: order_by
+                .into_iter()
+                .map(|(expr, dir)| Ok((self.build_collated(expr, scope)?, dir.into())))
+                .collect::<Result<_>>()?,
+            frame,
+        })
+    }
+
+    /// Builds a window frame, evaluating and checking its offsets. RANGE
+    /// offsets are distances between ORDER BY values, so they require a
+    /// single ORDER BY expression.
+    fn build_frame(&self, frame: ast::WindowFrame, order_by: usize) -> Result<Frame> {
+        let units = match frame.units {
+            ast::FrameUnits::Rows => FrameUnits::Rows,
+            ast::FrameUnits::Range => FrameUnits::Range,
+        };
+        if frame.start == ast::FrameBound::UnboundedFollowing {
+            return errinput!("frame start can't be UNBOUNDED FOLLOWING");
+        }
+        if frame.end == ast::FrameBound::UnboundedPreceding {
+            return errinput!("frame end can't be UNBOUNDED PRECEDING");
+        }
+        let build = |bound: ast::FrameBound| -> Result<FrameBound> {
+            let (offset, preceding) = match bound {
+                ast::FrameBound::UnboundedPreceding => return Ok(FrameBound::UnboundedPreceding),
+                ast::FrameBound::CurrentRow => return Ok(FrameBound::CurrentRow),
+                ast::FrameBound::UnboundedFollowing => return Ok(FrameBound::UnboundedFollowing),
+                ast::FrameBound::Preceding(offset) => (offset, true),
+                ast::FrameBound::Following(offset) => (offset, false),
+            };
+            if units == FrameUnits::Range && order_by != 1 {
+                return errinput!("RANGE with offset requires exactly one ORDER BY column");
+            }
+            let offset = self.evaluate_constant(*offset)?;
+            let valid = match units {
+                FrameUnits::Rows => offset.as_i64().is_some_and(|n| n >= 0),
+                FrameUnits::Range => match offset {
+                    Field::Interval(_) => true,
+                    _ => offset
+                        .partial_cmp_numeric(&Field::Integer(0))
+                        .is_some_and(|o| o.is_ge()),
+                },
+            };
+            if !valid {
+                return errinput!("invalid frame offset {offset}");
+            }
+            Ok(match preceding {
+                true => FrameBound::Preceding(offset),
+                false => FrameBound::Following(offset),
+            })
+        };
+        Ok(Frame {
+            units,
+            start: build(frame.start)?,
+            end: build(frame.end)?,
+        })
     }
 
     /// Builds a CREATE TABLE plan.
@@ -147,17 +610,31 @@ impl<'a, C: Catalog> Planner<'a, C> {
                     .into_iter()
                     .map(|c| {
                         let nullable = c.nullable.unwrap_or(false);
-                        Ok(Column::new(
+                        let mut column = Column::new(
                             &c.name,
                             c.datatype,
                             nullable,
                             match c.default {
-                                Some(expr) => Some(Self::evaluate_constant(expr)?),
+                                Some(expr) => {
+                                    Some(self.evaluate_constant(expr)?.coerce(c.datatype)?)
+                                }
                                 None if nullable => Some(Field::Null),
                                 None => None,
                             },
                             None,
-                        ))
+                        );
+                        column.set_primary_key(c.primary_key);
+                        column.set_unique(c.unique);
+                        if let Some(collation) = c.collation {
+                            if c.datatype != DataType::Text {
+                                return errinput!(
+                                    "collation {collation} requires a TEXT column, got {}",
+                                    c.datatype
+                                );
+                            }
+                            column.set_collation(collation);
+                        }
+                        Ok(column)
                     })
                     .collect::<Result<_>>()?,
             )
@@ -165,43 +642,339 @@ impl<'a, C: Catalog> Planner<'a, C> {
         Ok(Plan::CreateTable { schema: table })
     }
 
+    /// Builds a CREATE VIEW plan. The query is built to validate it, but only
+    /// its SQL text is stored, and it's expanded where the view is referenced.
+    /// A materialized view also stores the query's rows in a table.
+    fn build_create_view(
+        &self,
+        name: String,
+        query: Statement,
+        replace: bool,
+        materialized: bool,
+    ) -> Result<Plan> {
+        let mut view = View {
+            name,
+            query: query.to_string(),
+            materialized,
+            dependencies: BTreeSet::new(),
+        };
+        let source = self.build_view(&view)?;
+        view.dependencies = self.dependencies.take();
+        if !Plan::Select(source.clone().into()).parameters().is_empty() {
+            return errinput!("views can't have parameters");
+        }
+        let columns = Self::column_names(&source);
+        if let Some(column) = columns.iter().duplicates().next() {
+            return errinput!("duplicate column {column} in view {}", view.name);
+        }
+        if !materialized {
+            return Ok(Plan::CreateView { view, replace });
+        }
+        Ok(Plan::CreateMaterializedView {
+            view,
+            source: source.into(),
+            columns,
+        })
+    }
+
+    /// Builds a REFRESH MATERIALIZED VIEW plan, which recomputes the view's
+    /// query into its table.
+    fn build_refresh_materialized_view(&self, name: String) -> Result<Plan> {
+        let view = match self.catalog.get_view(&name)? {
+            Some(view) if view.materialized => view,
+            Some(_) => return errinput!("{name} is not a materialized view"),
+            None => return errinput!("materialized view {name} does not exist"),
+        };
+        let table = self.catalog.must_get_table(&name)?;
+        let source = self.build_view(&view)?;
+        if source.columns() != table.col_count() {
+            let (size, n) = (table.col_count(), source.columns());
+            return errinput!("materialized view {name} has {size} columns, but its query has {n}");
+        }
+        Ok(Plan::RefreshMaterializedView {
+            table,
+            source: source.into(),
+        })
+    }
+
+    /// Builds a view's query from its SQL text. It can't reference enclosing
+    /// queries or CTEs, nor the view itself (directly or via other views).
+    fn build_view(&self, view: &View) -> Result<Node> {
+        if self.views.borrow().contains(&view.name) {
+            return errinput!("view {} references itself", view.name);
+        }
+        let query = Parser::new(&view.query).parse()?;
+        self.views.borrow_mut().push(view.name.clone());
+        let node = self.build_query(query, None, &HashMap::new());
+        self.views.borrow_mut().pop();
+        node
+    }
+
+    /// Records a view referenced by the outermost view being expanded, if any,
+    /// as one of its dependencies.
+    fn add_dependency(&self, view: &str) {
+        if self.views.borrow().len() == 1 {
+            self.dependencies.borrow_mut().insert(view.to_string());
+        }
+    }
+
+    /// Fetches the table modified by a DML statement. Views can't be modified.
+    fn must_get_writable_table(&self, name: &str) -> Result<Table> {
+        match self.catalog.get_view(name)? {
+            Some(view) if view.materialized => errinput!("can't modify materialized view {name}"),
+            Some(_) => errinput!("can't modify view {name}"),
+            None => self.catalog.must_get_table(name),
+        }
+    }
+
     /// Builds a DELETE plan.
-    fn build_delete(&self, table: String, r#where: Option<ast::Expression>) -> Result<Plan> {
-        let table = self.catalog.must_get_table(&table)?;
+    fn build_delete(
+        &self,
+        table: String,
+        r#where: Option<ast::Expression>,
+        returning: Vec<(ast::Expression, Option<String>)>,
+    ) -> Result<Plan> {
+        let table = self.must_get_writable_table(&table)?;
         let scope = Scope::from_table(&table)?;
         let filter = r#where
-            .map(|expr| Self::build_expression(expr, &scope))
+            .map(|expr| self.build_expression(expr, &scope))
             .transpose()?;
         Ok(Plan::Delete {
             table: table.name().to_string(),
-            source: Node::Scan {
-                table,
-                alias: None,
-                filter,
-            }
-            .into(),
+            returning: self.build_returning(&table, returning)?,
+            source: Self::build_scan(table, filter).into(),
         })
     }
 
     /// Builds an INSERT plan.
-    fn build_insert(&self, table: String, values: Vec<Vec<ast::Expression>>) -> Result<Plan> {
-        let table = self.catalog.must_get_table(&table)?;
-        let scope = Scope::new();
+    ///
+    /// Values are given for the listed columns, or the leading table columns
+    /// if there is no column list. The remaining columns are filled with their
+    /// default values. The source rows are emitted in table column order,
+    /// either as VALUES rows or via a projection of the query's rows. With an
+    /// ON CONFLICT clause, an Upsert plan is built instead.
+    fn build_insert(
+        &self,
+        table: String,
+        columns: Option<Vec<String>>,
+        source: ast::InsertSource,
+        on_conflict: Option<ast::OnConflict>,
+        returning: Vec<(ast::Expression, Option<String>)>,
+    ) -> Result<Plan> {
+        let table = self.must_get_writable_table(&table)?;
 
-        let mut rows = Vec::new();
-        for exprs in values {
-            let mut row = Vec::new();
-            for expr in exprs {
-                row.push(Self::build_expression(expr, &scope)?);
+        // Map the source columns to table column indexes.
+        let explicit = columns.is_some();
+        let targets = match columns {
+            Some(columns) => {
+                let scope = Scope::from_table(&table)?;
+                let mut targets = Vec::with_capacity(columns.len());
+                for column in columns {
+                    let index = scope.lookup_column(None, &column)?;
+                    if targets.contains(&index) {
+                        return errinput!("column {column} given multiple times");
+                    }
+                    targets.push(index);
+                }
+                targets
+            }
+            None => (0..table.col_count()).collect(),
+        };
+
+        // Arranges source values in table column order, adding defaults.
+        let fill = |values: Vec<Expression>| -> Result<Vec<Expression>> {
+            if values.len() > targets.len() {
+                return errinput!("INSERT has more values than target columns");
+            }
+            if explicit && values.len() < targets.len() {
+                return errinput!("INSERT has more target columns than values");
+            }
+            let mut row = vec![None; table.col_count()];
+            for (value, index) in values.into_iter().zip(&targets) {
+                row[*index] = Some(value);
+            }
+            row.into_iter()
+                .enumerate()
+                .map(|(index, value)| match value {
+                    Some(value) => Ok(value),
+                    None => Self::build_default(table.get_column(index)),
+                })
+                .collect()
+        };
+
+        let source = match source {
+            ast::InsertSource::Values(values) => {
+                let scope = Scope::new();
+                let mut rows = Vec::with_capacity(values.len());
+                for exprs in values {
+                    let row = exprs
+                        .into_iter()
+                        .map(|expr| self.build_expression(expr, &scope))
+                        .try_collect()?;
+                    rows.push(fill(row)?);
+                }
+                Node::Values { rows }
             }
-            rows.push(row);
+            ast::InsertSource::Query(query) => {
+                let node = self.build_query(*query, None, &HashMap::new())?;
+                let expressions = fill((0..node.columns()).map(Expression::Column).collect())?;
+                let identity = expressions.len() == node.columns()
+                    && expressions
+                        .iter()
+                        .enumerate()
+                        .all(|(i, expr)| expr == &Expression::Column(i));
+                match identity {
+                    true => node,
+                    false => Node::Projection {
+                        source: node.into(),
+                        aliases: vec![Label::None; expressions.len()],
+                        expressions,
+                    },
+                }
+            }
+        };
+        let returning = self.build_returning(&table, returning)?;
+        match on_conflict {
+            Some(on_conflict) => self.build_upsert(table, source, on_conflict, returning),
+            None => Ok(Plan::Insert {
+                table,
+                source: source.into(),
+                returning,
+            }),
         }
-        Ok(Plan::Insert {
+    }
+
+    /// Builds an UPSERT plan, i.e. INSERT ... ON CONFLICT, for the given
+    /// INSERT table and source.
+    ///
+    /// The conflict target must match the primary key or a unique column.
+    /// Without a target, DO NOTHING handles conflicts on any constraint. The
+    /// DO UPDATE expressions are evaluated on the existing row followed by the
+    /// inserted row, which is referenced via the table name "excluded".
+    fn build_upsert(
+        &self,
+        table: Table,
+        source: Node,
+        on_conflict: ast::OnConflict,
+        returning: Option<Returning>,
+    ) -> Result<Plan> {
+        let ast::OnConflict { columns, action } = on_conflict;
+        let mut scope = Scope::from_table(&table)?;
+
+        // Collect the table's constraints, as sorted column indexes.
+        let mut constraints = Vec::new();
+        let primary_key = (0..table.col_count())
+            .filter(|i| table.get_column(*i).is_primary_key())
+            .collect_vec();
+        if !primary_key.is_empty() {
+            constraints.push(primary_key);
+        }
+        for index in 0..table.col_count() {
+            if table.get_column(index).is_unique() && !constraints.contains(&vec![index]) {
+                constraints.push(vec![index]);
+            }
+        }
+        if constraints.is_empty() {
+            return errinput!(
+                "table {} has no primary key or unique constraint",
+                table.name()
+            );
+        }
+
+        let mut target = None;
+        if !columns.is_empty() {
+            let mut columns = columns
+                .iter()
+                .map(|column| scope.lookup_column(None, column))
+                .collect::<Result<Vec<_>>>()?;
+            columns.sort();
+            columns.dedup();
+            let Some(index) = constraints.iter().position(|c| c == &columns) else {
+                return errinput!(
+                    "no primary key or unique constraint matches ON CONFLICT columns"
+                );
+            };
+            target = Some(index);
+        }
+
+        let action = match action {
+            ast::ConflictAction::Nothing => ConflictAction::Nothing,
+            ast::ConflictAction::Update { .. } if target.is_none() => {
+                return errinput!("ON CONFLICT DO UPDATE requires a conflict target");
+            }
+            ast::ConflictAction::Update { set, r#where } => {
+                scope.add_excluded(&table)?;
+                let predicate = r#where
+                    .map(|expr| self.build_expression(expr, &scope))
+                    .transpose()?;
+                let mut expressions = Vec::with_capacity(set.len());
+                for (column, expr) in set {
+                    let index = scope.lookup_column(Some(table.name()), &column)?;
+                    let expr = match expr {
+                        Some(expr) => self.build_expression(expr, &scope)?,
+                        None => Self::build_default(table.get_column(index))?,
+                    };
+                    expressions.push((index, expr));
+                }
+                ConflictAction::Update {
+                    expressions,
+                    predicate,
+                }
+            }
+        };
+
+        Ok(Plan::Upsert {
             table,
-            source: Node::Values { rows }.into(),
+            source: source.into(),
+            constraints,
+            target,
+            action: action.into(),
+            returning,
         })
     }
 
+    /// Builds a RETURNING clause, if any, evaluated on rows of the given
+    /// table. Column references are labeled like SELECT columns.
+    fn build_returning(
+        &self,
+        table: &Table,
+        returning: Vec<(ast::Expression, Option<String>)>,
+    ) -> Result<Option<Returning>> {
+        if returning.is_empty() {
+            return Ok(None);
+        }
+        let scope = Scope::from_table(table)?;
+        let mut expressions = Vec::with_capacity(returning.len());
+        let mut labels = Vec::with_capacity(returning.len());
+        for (expr, alias) in returning {
+            if expr == ast::Expression::All {
+                expressions.extend((0..table.col_count()).map(Expression::Column));
+                labels.extend(scope.columns.iter().cloned());
+                continue;
+            }
+            let expr = self.build_expression(expr, &scope)?;
+            labels.push(match (&expr, alias) {
+                (_, Some(alias)) => Label::Unqualified(alias),
+                (Expression::Column(index), None) => scope.columns[*index].clone(),
+                (_, None) => Label::None,
+            });
+            expressions.push(expr);
+        }
+        Ok(Some(Returning {
+            expressions,
+            labels,
+        }))
+    }
+
+    /// Builds a column's default value, for omitted INSERT columns and
+    /// SET column = DEFAULT.
+    fn build_default(column: &Column) -> Result<Expression> {
+        match column.default() {
+            Some(default) => Ok(Expression::Constant(default.clone())),
+            None => errinput!("column {} has no default value", column.get_name()),
+        }
+    }
+
     /// Collects aggregate functions from SELECT, HAVING, and ORDER BY clauses.
     fn collect_aggregates(
         select: &[(ast::Expression, Option<String>)],
@@ -218,6 +991,23 @@ impl<'a, C: Catalog> Planner<'a, C> {
         aggregates
     }
 
+    /// Collects window functions from SELECT and ORDER BY clauses.
+    fn collect_windows(
+        select: &[(ast::Expression, Option<String>)],
+        order_by: &[(ast::Expression, ast::Direction)],
+    ) -> Vec<ast::Expression> {
+        let select = select.iter().map(|(expr, _)| expr);
+        let order_by = order_by.iter().map(|(expr, _)| expr);
+        let mut windows = Vec::new();
+        for expr in select.chain(order_by) {
+            expr.collect(
+                &|expr| matches!(expr, ast::Expression::Window(_, _, _)),
+                &mut windows,
+            )
+        }
+        windows
+    }
+
     /// Builds hidden columns for a projection to pass through columns that are
     /// used by downstream nodes. Consider e.g.:
     ///
@@ -233,12 +1023,14 @@ impl<'a, C: Catalog> Planner<'a, C> {
     fn build_select_hidden(
         &self,
         having: &Option<ast::Expression>,
+        distinct_on: &[ast::Expression],
         order_by: &[(ast::Expression, ast::Direction)],
         scope: &Scope,
         child_scope: &mut Scope,
     ) -> Vec<Expression> {
         let mut hidden = Vec::new();
-        for expr in having.iter().chain(order_by.iter().map(|(expr, _)| expr)) {
+        let order_by = order_by.iter().map(|(expr, _)| expr);
+        for expr in having.iter().chain(distinct_on).chain(order_by) {
             expr.walk(&mut |expr| {
                 // If this is an aggregate or GROUP BY expression that isn't
                 // already available in the child scope, add a hidden column.
@@ -249,10 +1041,18 @@ impl<'a, C: Catalog> Planner<'a, C> {
                         return true;
                     }
                 }
+                // Likewise for window functions.
+                if let Some(&index) = scope.windows.get(expr) {
+                    if !child_scope.windows.contains_key(expr) {
+                        child_scope.add_passthrough(scope, index, true);
+                        hidden.push(Expression::Column(index));
+                    }
+                    return true;
+                }
 
                 // Look for column references that don't exist post-projection,
                 // but that do exist in the parent, and add hidden columns.
-                let ast::Expression::Column(table, column) = expr else {
+                let ast::Expression::Column(table, column, _) = expr else {
                     return true;
                 };
                 if child_scope.lookup_column(table.as_deref(), column).is_ok() {
@@ -273,7 +1073,7 @@ impl<'a, C: Catalog> Planner<'a, C> {
 
     /// Builds an expression from an AST expression, looking up columns and
     /// aggregate expressions in the scope.
-    pub fn build_expression(expr: ast::Expression, scope: &Scope) -> Result<Expression> {
+    pub fn build_expression(&self, expr: ast::Expression, scope: &Scope) -> Result<Expression> {
         use Expression::*;
 
         // Look up aggregate functions or GROUP BY expressions. These were added
@@ -281,10 +1081,22 @@ impl<'a, C: Catalog> Planner<'a, C> {
         if let Some(index) = scope.lookup_aggregate(&expr) {
             return Ok(Column(index));
         }
+        // Likewise for window functions, added when building the Window node.
+        if let Some(&index) = scope.windows.get(&expr) {
+            return Ok(Column(index));
+        }
 
         // Helper for building a boxed expression.
         let build = |expr: Box<ast::Expression>| -> Result<Box<Expression>> {
-            Ok(Box::new(Self::build_expression(*expr, scope)?))
+            Ok(Box::new(self.build_expression(*expr, scope)?))
+        };
+
+        // Helper for building the boxed operands of a binary comparison.
+        type Operands = (Box<Expression>, Box<Expression>);
+        let compare = |lhs: Box<ast::Expression>, rhs: Box<ast::Expression>| -> Result<Operands> {
+            let mut operands = self.build_comparison(vec![*lhs, *rhs], scope)?.into_iter();
+            let (lhs, rhs) = (operands.next().unwrap(), operands.next().unwrap());
+            Ok((Box::new(lhs), Box::new(rhs)))
         };
 
         Ok(match expr {
@@ -295,29 +1107,92 @@ impl<'a, C: Catalog> Planner<'a, C> {
             ast::Expression::Literal(l) => Constant(match l {
                 ast::Literal::Null => Field::Null,
                 ast::Literal::Boolean(b) => Field::Boolean(b),
-                ast::Literal::Integer(i) => Field::Integer(i),
-                ast::Literal::Float(f) => Field::Float(f),
+                // Integer literals are INT unless they need 64 bits.
+                ast::Literal::Integer(i) => match i32::try_from(i) {
+                    Ok(i) => Field::Integer(i),
+                    Err(_) => Field::BigInt(i),
+                },
+                // Float literals are REAL unless that would lose precision,
+                // i.e. the literal's digits don't survive a 32-bit round trip.
+                ast::Literal::Float(f) => match f as f32 {
+                    r if !f.is_finite() || r.to_string().parse() == Ok(f) => Field::Float(r),
+                    _ => Field::Double(f),
+                },
                 ast::Literal::String(s) => Field::String(s),
+                ast::Literal::Bytes(b) => Field::Bytes(b),
+                ast::Literal::Date(d) => Field::Date(d),
+                ast::Literal::Time(t) => Field::Time(t),
+                ast::Literal::Timestamp(t) => Field::Timestamp(t),
+                ast::Literal::Interval(i) => Field::Interval(i),
             }),
-            ast::Expression::Column(table, name) => {
-                Column(scope.lookup_column(table.as_deref(), &name)?)
+            // Columns that aren't in scope may be correlated references to
+            // the columns of an enclosing query.
+            ast::Expression::Column(table, name, position) => {
+                match scope.lookup_column(table.as_deref(), &name) {
+                    Ok(index) => Column(index),
+                    Err(err) => match scope.lookup_outer_column(table.as_deref(), &name) {
+                        Some((depth, index)) => OuterColumn(depth, index),
+                        None => return Err(position.locate(err)),
+                    },
+                }
+            }
+            ast::Expression::Parameter(index) => Parameter(index),
+            ast::Expression::Subquery(query) => Subquery(self.build_subquery(*query, scope, true)?),
+            ast::Expression::Exists(query) => Exists(self.build_subquery(*query, scope, false)?),
+            ast::Expression::Function(name, _, true) => {
+                return errinput!("DISTINCT is not allowed in non-aggregate function {name}")
+            }
+            ast::Expression::Window(name, _, _) => {
+                return errinput!("window function {name} can only be used in SELECT and ORDER BY")
             }
-            ast::Expression::Function(name, mut args) => match (name.as_str(), args.len()) {
+            ast::Expression::WithinGroup(name, _, _) => {
+                return errinput!("aggregate function {name} is not allowed here")
+            }
+            ast::Expression::Function(name, mut args, false) => match (name.as_str(), args.len()) {
                 // NB: aggregate functions are processed above.
                 ("sqrt", 1) => SquareRoot(build(Box::new(args.remove(0)))?),
-                (name, n) => return errinput!("unknown function {name} with {n} arguments"),
+                ("extract", 2) => {
+                    let field = Self::build_date_time_field(args.remove(0))?;
+                    Extract(field, build(Box::new(args.remove(0)))?)
+                }
+                ("date_trunc", 2) => {
+                    let field = Self::build_date_time_field(args.remove(0))?;
+                    DateTrunc(field, build(Box::new(args.remove(0)))?)
+                }
+                (name, _) => self.build_function(name, args, scope)?,
             },
+            ast::Expression::Case(operand, when, r#else) => Case(
+                operand.map(build).transpose()?,
+                when.into_iter()
+                    .map(|(condition, then)| {
+                        Ok((
+                            self.build_expression(condition, scope)?,
+                            self.build_expression(then, scope)?,
+                        ))
+                    })
+                    .collect::<Result<_>>()?,
+                r#else.map(build).transpose()?,
+            ),
             ast::Expression::Operator(op) => match op {
                 ast::Operator::And(lhs, rhs) => And(build(lhs)?, build(rhs)?),
                 ast::Operator::Not(expr) => Not(build(expr)?),
                 ast::Operator::Or(lhs, rhs) => Or(build(lhs)?, build(rhs)?),
 
-                ast::Operator::Equal(lhs, rhs) => Equal(build(lhs)?, build(rhs)?),
-                ast::Operator::GreaterThan(lhs, rhs) => GreaterThan(build(lhs)?, build(rhs)?),
-                ast::Operator::GreaterThanOrEqual(lhs, rhs) => Or(
-                    GreaterThan(build(lhs.clone())?, build(rhs.clone())?).into(),
-                    Equal(build(lhs)?, build(rhs)?).into(),
-                ),
+                ast::Operator::Equal(lhs, rhs) => {
+                    let (lhs, rhs) = compare(lhs, rhs)?;
+                    Equal(lhs, rhs)
+                }
+                ast::Operator::GreaterThan(lhs, rhs) => {
+                    let (lhs, rhs) = compare(lhs, rhs)?;
+                    GreaterThan(lhs, rhs)
+                }
+                ast::Operator::GreaterThanOrEqual(lhs, rhs) => {
+                    let (lhs, rhs) = compare(lhs, rhs)?;
+                    Or(
+                        GreaterThan(lhs.clone(), rhs.clone()).into(),
+                        Equal(lhs, rhs).into(),
+                    )
+                }
                 ast::Operator::Is(expr, literal) => {
                     let expr = build(expr)?;
                     let value = match literal {
@@ -325,79 +1200,418 @@ impl<'a, C: Catalog> Planner<'a, C> {
                         ast::Literal::Float(f) if f.is_nan() => Field::Float(f as f32),
                         value => panic!("invalid IS value {value:?}"), // enforced by parser
                     };
-                    Is(expr, value)
+                    Is(expr, value)
+                }
+                ast::Operator::LessThan(lhs, rhs) => {
+                    let (lhs, rhs) = compare(lhs, rhs)?;
+                    LessThan(lhs, rhs)
+                }
+                ast::Operator::LessThanOrEqual(lhs, rhs) => {
+                    let (lhs, rhs) = compare(lhs, rhs)?;
+                    Or(
+                        LessThan(lhs.clone(), rhs.clone()).into(),
+                        Equal(lhs, rhs).into(),
+                    )
+                }
+                ast::Operator::Like(lhs, rhs, escape, case_insensitive) => {
+                    let (lhs, rhs) = (build(lhs)?, build(rhs)?);
+                    let escape = escape.map(build).transpose()?;
+                    // Compile constant patterns once, instead of for every row.
+                    match (rhs.as_ref(), escape.as_deref()) {
+                        (Constant(Field::String(pattern)), None) => {
+                            Matches(lhs, Pattern::like(pattern, None, case_insensitive)?)
+                        }
+                        (
+                            Constant(Field::String(pattern)),
+                            Some(Constant(Field::String(escape))),
+                        ) => Matches(lhs, Pattern::like(pattern, Some(escape), case_insensitive)?),
+                        _ => Like(lhs, rhs, escape, case_insensitive),
+                    }
+                }
+                ast::Operator::Regex(lhs, rhs, case_insensitive) => {
+                    let (lhs, rhs) = (build(lhs)?, build(rhs)?);
+                    match rhs.as_ref() {
+                        Constant(Field::String(pattern)) => {
+                            Matches(lhs, Pattern::regex(pattern, case_insensitive)?)
+                        }
+                        _ => Regex(lhs, rhs, case_insensitive),
+                    }
+                }
+                ast::Operator::In(expr, list) => {
+                    let operands = std::iter::once(*expr).chain(list).collect();
+                    let mut operands = self.build_comparison(operands, scope)?;
+                    In(operands.remove(0).into(), operands)
+                }
+                // The operand's collation also applies to the subquery values.
+                ast::Operator::InSubquery(expr, query) => {
+                    let expr = self.build_comparison(vec![*expr], scope)?.remove(0);
+                    InSubquery(expr.into(), self.build_subquery(*query, scope, true)?)
+                }
+                ast::Operator::Between(expr, low, high) => {
+                    let operands = vec![*expr, *low, *high];
+                    let mut operands = self.build_comparison(operands, scope)?.into_iter();
+                    let mut next = || Box::new(operands.next().unwrap());
+                    Between(next(), next(), next())
+                }
+                ast::Operator::NotEqual(lhs, rhs) => {
+                    let (lhs, rhs) = compare(lhs, rhs)?;
+                    Not(Equal(lhs, rhs).into())
+                }
+
+                ast::Operator::Add(lhs, rhs) => Add(build(lhs)?, build(rhs)?),
+                ast::Operator::Divide(lhs, rhs) => Divide(build(lhs)?, build(rhs)?),
+                ast::Operator::Exponentiate(lhs, rhs) => Exponentiate(build(lhs)?, build(rhs)?),
+                ast::Operator::Factorial(expr) => Factorial(build(expr)?),
+                ast::Operator::Identity(expr) => Identity(build(expr)?),
+                ast::Operator::Remainder(lhs, rhs) => Remainder(build(lhs)?, build(rhs)?),
+                ast::Operator::Multiply(lhs, rhs) => Multiply(build(lhs)?, build(rhs)?),
+                ast::Operator::Negate(expr) => Negate(build(expr)?),
+                ast::Operator::Subtract(lhs, rhs) => Subtract(build(lhs)?, build(rhs)?),
+
+                ast::Operator::Concat(lhs, rhs) => Concat(build(lhs)?, build(rhs)?),
+
+                ast::Operator::Cast(expr, data_type) => Cast(build(expr)?, data_type),
+                // COLLATE only affects comparisons and ordering, where the
+                // operands are built via build_comparison() and
+                // build_collated(). Otherwise, it returns the value as is.
+                ast::Operator::Collate(expr, _) => return self.build_expression(*expr, scope),
+            },
+        })
+    }
+
+    /// Builds an UPDATE plan.
+    fn build_update(
+        &self,
+        table: String,
+        set: BTreeMap<String, Option<ast::Expression>>,
+        r#where: Option<ast::Expression>,
+        returning: Vec<(ast::Expression, Option<String>)>,
+    ) -> Result<Plan> {
+        let table = self.must_get_writable_table(&table)?;
+        let scope = Scope::from_table(&table)?;
+        let filter = r#where
+            .map(|expr| self.build_expression(expr, &scope))
+            .transpose()?;
+        let mut expressions = Vec::with_capacity(set.len());
+        for (column, expr) in set {
+            let index = scope.lookup_column(None, &column)?;
+            let expr = match expr {
+                Some(expr) => self.build_expression(expr, &scope)?,
+                None => Self::build_default(table.get_column(index))?,
+            };
+            expressions.push((index, expr));
+        }
+        Ok(Plan::Update {
+            table: table.clone(),
+            returning: self.build_returning(&table, returning)?,
+            source: Self::build_scan(table, filter).into(),
+            expressions,
+        })
+    }
+
+    /// Builds a query node, i.e. a SELECT, set operation or WITH query. For
+    /// subqueries, the scope of the enclosing query is given as outer, and
+    /// its columns can be referenced. The given CTEs can be referenced in
+    /// FROM clauses.
+    fn build_query(
+        &self,
+        statement: Statement,
+        outer: Option<&Scope>,
+        ctes: &HashMap<String, Rc<Cte>>,
+    ) -> Result<Node> {
+        match statement {
+            Statement::Select {
+                distinct,
+                select,
+                from,
+                r#where,
+                group_by,
+                having,
+                order_by,
+                offset,
+                limit,
+            } => self.build_select(
+                distinct, select, from, r#where, group_by, having, order_by, offset, limit, outer,
+                ctes,
+            ),
+            Statement::SetOperation {
+                operator,
+                all,
+                left,
+                right,
+                order_by,
+                offset,
+                limit,
+            } => {
+                let left = self.build_query(*left, outer, ctes)?;
+                let right = self.build_query(*right, outer, ctes)?;
+                let node = Self::build_set_operation(operator, all, left, right)?;
+                // ORDER BY can reference the result columns, which are named
+                // by the left query.
+                let mut scope = Scope::with_outer(outer);
+                scope.ctes.clone_from(ctes);
+                for i in 0..node.columns() {
+                    scope.add_column(node.column_label(i));
+                }
+                let node = self.build_order(node, order_by, &scope)?;
+                self.build_offset_limit(node, offset, limit)
+            }
+            Statement::With {
+                recursive,
+                ctes: definitions,
+                query,
+            } => {
+                let mut ctes = ctes.clone();
+                let mut names = HashSet::new();
+                for definition in definitions {
+                    if !names.insert(definition.name.clone()) {
+                        return errinput!("duplicate CTE name {}", definition.name);
+                    }
+                    // Build the CTE to validate it and resolve its columns. It
+                    // is built again wherever it's referenced.
+                    let (_, columns) = self.build_cte(&definition, recursive, outer, &ctes)?;
+                    let name = definition.name.clone();
+                    let cte = Cte::Query {
+                        definition,
+                        recursive,
+                        columns,
+                        ctes: ctes.clone(),
+                    };
+                    ctes.insert(name, Rc::new(cte));
                 }
-                ast::Operator::LessThan(lhs, rhs) => LessThan(build(lhs)?, build(rhs)?),
-                ast::Operator::LessThanOrEqual(lhs, rhs) => Or(
-                    LessThan(build(lhs.clone())?, build(rhs.clone())?).into(),
-                    Equal(build(lhs)?, build(rhs)?).into(),
-                ),
-                ast::Operator::Like(lhs, rhs) => Like(build(lhs)?, build(rhs)?),
-                ast::Operator::NotEqual(lhs, rhs) => Not(Equal(build(lhs)?, build(rhs)?).into()),
+                self.build_query(*query, outer, &ctes)
+            }
+            statement => errinput!("expected SELECT query, got {statement}"),
+        }
+    }
 
-                ast::Operator::Add(lhs, rhs) => Add(build(lhs)?, build(rhs)?),
-                ast::Operator::Divide(lhs, rhs) => Divide(build(lhs)?, build(rhs)?),
-                ast::Operator::Exponentiate(lhs, rhs) => Exponentiate(build(lhs)?, build(rhs)?),
-                ast::Operator::Factorial(expr) => Factorial(build(expr)?),
-                ast::Operator::Identity(expr) => Identity(build(expr)?),
-                ast::Operator::Remainder(lhs, rhs) => Remainder(build(lhs)?, build(rhs)?),
-                ast::Operator::Multiply(lhs, rhs) => Multiply(build(lhs)?, build(rhs)?),
-                ast::Operator::Negate(expr) => Negate(build(expr)?),
-                ast::Operator::Subtract(lhs, rhs) => Subtract(build(lhs)?, build(rhs)?),
-            },
+    /// Builds a set operation node. Both sides must have the same number of
+    /// columns, and compatible column types where these are known. Numeric
+    /// columns are widened to their common type, e.g. INT and BIGINT to
+    /// BIGINT, by casting the narrower side, such that equal values compare
+    /// and deduplicate as equal and the result has a single type per column.
+    fn build_set_operation(
+        operator: ast::SetOperator,
+        all: bool,
+        left: Node,
+        right: Node,
+    ) -> Result<Node> {
+        let types = Self::check_set_operation(operator, &left, &right)?;
+        let left = Self::cast_columns(left, &types).into();
+        let right = Self::cast_columns(right, &types).into();
+        Ok(match operator {
+            ast::SetOperator::Except => Node::Except { left, right, all },
+            ast::SetOperator::Intersect => Node::Intersect { left, right, all },
+            ast::SetOperator::Union => Node::Union { left, right, all },
         })
     }
 
-    /// Builds an UPDATE plan.
-    fn build_update(
-        &self,
-        table: String,
-        set: BTreeMap<String, Option<ast::Expression>>,
-        r#where: Option<ast::Expression>,
-    ) -> Result<Plan> {
-        let table = self.catalog.must_get_table(&table)?;
-        let scope = Scope::from_table(&table)?;
-        let filter = r#where
-            .map(|expr| Self::build_expression(expr, &scope))
-            .transpose()?;
-        let mut expressions = Vec::with_capacity(set.len());
-        for (column, expr) in set {
-            let index = scope.lookup_column(None, &column)?;
-            let expr = match expr {
-                Some(expr) => Self::build_expression(expr, &scope)?,
-                None => match &table.get_column(index).default() {
-                    Some(default) => Expression::Constant((*default).clone()),
-                    None => return errinput!("column {column} has no default value"),
+    /// Checks that the sides of a set operation have matching columns, see
+    /// build_set_operation(). Returns the common type of each column, or None
+    /// if neither side's type is known.
+    fn check_set_operation(
+        operator: ast::SetOperator,
+        left: &Node,
+        right: &Node,
+    ) -> Result<Vec<Option<DataType>>> {
+        if left.columns() != right.columns() {
+            let (l, r) = (left.columns(), right.columns());
+            return errinput!(
+                "{operator} queries must have the same number of columns, got {l} and {r}"
+            );
+        }
+        (0..left.columns())
+            .map(|i| match (left.column_type(i), right.column_type(i)) {
+                (Some(l), Some(r)) => match l.unify(r) {
+                    Some(data_type) => Ok(Some(data_type)),
+                    None => {
+                        let n = i + 1;
+                        errinput!("{operator} types {l} and {r} can't be matched for column {n}")
+                    }
                 },
-            };
-            expressions.push((index, expr));
+                (l, r) => Ok(l.or(r)),
+            })
+            .collect()
+    }
+
+    /// Casts the columns of a set operation side to the given common types,
+    /// where the column's type is known and differs. The node is returned
+    /// as is if no casts are needed.
+    fn cast_columns(node: Node, types: &[Option<DataType>]) -> Node {
+        let casts: Vec<_> = types
+            .iter()
+            .enumerate()
+            .map(|(i, data_type)| {
+                data_type.filter(|data_type| node.column_type(i).is_some_and(|t| t != *data_type))
+            })
+            .collect();
+        if casts.iter().all(Option::is_none) {
+            return node;
         }
-        Ok(Plan::Update {
-            table: table.clone(),
-            source: Node::Scan {
-                table,
-                alias: None,
-                filter,
-            }
-            .into(),
+        let (expressions, aliases) = casts
+            .into_iter()
+            .enumerate()
+            .map(|(i, cast)| match cast {
+                Some(data_type) => (
+                    Expression::Cast(Expression::Column(i).into(), data_type),
+                    node.column_label(i),
+                ),
+                None => (Expression::Column(i), Label::None),
+            })
+            .unzip();
+        Node::Projection {
+            source: node.into(),
             expressions,
+            aliases,
+        }
+    }
+
+    /// Builds a common table expression, returning its node and column names.
+    ///
+    /// In WITH RECURSIVE, a UNION query whose right-hand side references the
+    /// CTE itself is recursive. The reference reads a working table, and the
+    /// sides are combined in a RecursiveCte node. Consider e.g.:
+    ///
+    /// WITH RECURSIVE t (n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t WHERE n < 3) SELECT n FROM t
+    ///
+    /// The non-recursive term SELECT 1 emits 1, and the recursive term is then
+    /// executed repeatedly with the previous iteration's rows in t, emitting 2
+    /// and 3 before it runs out of rows.
+    fn build_cte(
+        &self,
+        cte: &ast::CommonTableExpression,
+        recursive: bool,
+        outer: Option<&Scope>,
+        ctes: &HashMap<String, Rc<Cte>>,
+    ) -> Result<(Node, Vec<String>)> {
+        let name = &cte.name;
+        match (*cte.query).clone() {
+            Statement::SetOperation {
+                operator: ast::SetOperator::Union,
+                all,
+                left,
+                right,
+                order_by,
+                offset: None,
+                limit: None,
+            } if recursive && order_by.is_empty() => {
+                let anchor = self.build_query(*left, outer, ctes)?;
+                let columns = Self::cte_columns(cte, &anchor)?;
+                let mut ctes = ctes.clone();
+                let working_table = Cte::WorkingTable {
+                    columns: columns.clone(),
+                };
+                ctes.insert(name.clone(), Rc::new(working_table));
+                let term = self.build_query(*right, outer, &ctes)?;
+
+                // If the term doesn't reference the working table, this is
+                // just a UNION.
+                let referenced = std::cell::Cell::new(false);
+                term.clone().transform(
+                    &|node| {
+                        if matches!(&node, Node::WorkingTable { name: n, .. } if n == name) {
+                            referenced.set(true);
+                        }
+                        Ok(node)
+                    },
+                    &Ok,
+                )?;
+                if !referenced.get() {
+                    let node =
+                        Self::build_set_operation(ast::SetOperator::Union, all, anchor, term)?;
+                    return Ok((node, columns));
+                }
+                let types = Self::check_set_operation(ast::SetOperator::Union, &anchor, &term)?;
+                let node = Node::RecursiveCte {
+                    name: name.clone(),
+                    anchor: Self::cast_columns(anchor, &types).into(),
+                    recursive: Self::cast_columns(term, &types).into(),
+                    distinct: !all,
+                    limit: self.recursion_limit,
+                };
+                Ok((node, columns))
+            }
+            query => {
+                let node = self.build_query(query, outer, ctes)?;
+                let columns = Self::cte_columns(cte, &node)?;
+                Ok((node, columns))
+            }
+        }
+    }
+
+    /// Returns the column names of a CTE, either explicit ones or those of its
+    /// (non-recursive) query node.
+    fn cte_columns(cte: &ast::CommonTableExpression, node: &Node) -> Result<Vec<String>> {
+        match cte.columns.len() {
+            0 => Ok(Self::column_names(node)),
+            n if n == node.columns() => Ok(cte.columns.clone()),
+            n => {
+                let (name, size) = (&cte.name, node.columns());
+                errinput!("CTE {name} must have {size} column names, got {n}")
+            }
+        }
+    }
+
+    /// Returns the column names of a node used as a derived table. Unnamed
+    /// columns are named by position as column1, column2, etc.
+    fn column_names(node: &Node) -> Vec<String> {
+        (0..node.columns())
+            .map(|i| match node.column_label(i) {
+                Label::Qualified(_, name) | Label::Unqualified(name) => name,
+                Label::None => format!("column{}", i + 1),
+            })
+            .collect()
+    }
+
+    /// Builds a derived table from a source node, i.e. a projection of its
+    /// columns labeled with the given table and column names, and adds it to
+    /// the scope.
+    fn build_derived_table(
+        source: Node,
+        table: &str,
+        columns: &[String],
+        scope: &mut Scope,
+    ) -> Result<Node> {
+        scope.add_derived_table(table, columns)?;
+        Ok(Node::Projection {
+            expressions: (0..columns.len()).map(Expression::Column).collect(),
+            aliases: columns
+                .iter()
+                .map(|name| Label::Qualified(table.to_string(), name.clone()))
+                .collect(),
+            source: source.into(),
         })
     }
 
-    /// Builds a SELECT plan.
+    /// Builds a subquery node from a SELECT statement, which can reference
+    /// columns of the enclosing query's scope. Scalar and IN subqueries must
+    /// return a single column.
+    fn build_subquery(&self, query: Statement, outer: &Scope, single: bool) -> Result<BoxedNode> {
+        let node = self.build_query(query, Some(outer), &outer.ctes)?;
+        if single && node.columns() != 1 {
+            return errinput!("subquery must return 1 column, got {}", node.columns());
+        }
+        Ok(node.into())
+    }
+
+    /// Builds a SELECT query node.
     #[allow(clippy::too_many_arguments)]
     fn build_select(
         &self,
+        distinct: Option<Vec<ast::Expression>>,
         mut select: Vec<(ast::Expression, Option<String>)>,
         from: Vec<ast::From>,
         r#where: Option<ast::Expression>,
-        group_by: Vec<ast::Expression>,
-        having: Option<ast::Expression>,
+        group_by: Vec<ast::GroupBy>,
+        mut having: Option<ast::Expression>,
         order_by: Vec<(ast::Expression, ast::Direction)>,
         offset: Option<ast::Expression>,
         limit: Option<ast::Expression>,
-    ) -> Result<Plan> {
-        let mut scope = Scope::new();
+        outer: Option<&Scope>,
+        ctes: &HashMap<String, Rc<Cte>>,
+    ) -> Result<Node> {
+        let mut scope = Scope::with_outer(outer);
+        scope.ctes.clone_from(ctes);
 
         // Build FROM clause.
         let mut node = if !from.is_empty() {
@@ -430,11 +1644,8 @@ impl<'a, C: Catalog> Planner<'a, C> {
 
         // Build WHERE clause
         if let Some(r#where) = r#where {
-            let predicate = Self::build_expression(r#where, &scope)?;
-            node = Node::Filter {
-                source: node.into(),
-                predicate,
-            };
+            let predicate = self.build_expression(r#where, &scope)?;
+            node = Self::build_filter(node, predicate);
         }
 
         // Build aggregate functions and GROUP BY clause.
@@ -443,6 +1654,16 @@ impl<'a, C: Catalog> Planner<'a, C> {
             node = self.build_aggregate(node, group_by, aggregates, &mut scope)?;
         }
 
+        // Build window functions. These are computed after aggregation, so
+        // HAVING must be applied before them rather than after the projection.
+        let windows = Self::collect_windows(&select, &order_by);
+        if !windows.is_empty() {
+            if let Some(having) = having.take() {
+                node = self.build_having(node, having, &scope)?;
+            }
+            node = self.build_window(node, windows, &mut scope)?;
+        }
+
         // Build SELECT clause. We can omit this for a trivial SELECT *.
         if select.as_slice() != [(ast::Expression::All, None)] {
             // Prepare the post-projection scope.
@@ -452,12 +1673,15 @@ impl<'a, C: Catalog> Planner<'a, C> {
             let mut expressions = Vec::with_capacity(select.len());
             let mut aliases = Vec::with_capacity(select.len());
             for (expr, alias) in select {
-                expressions.push(Self::build_expression(expr, &scope)?);
+                expressions.push(self.build_expression(expr, &scope)?);
                 aliases.push(Label::from(alias));
             }
 
-            // Add hidden columns for HAVING and ORDER BY columns not in SELECT.
-            let hidden = self.build_select_hidden(&having, &order_by, &scope, &mut child_scope);
+            // Add hidden columns for HAVING, DISTINCT ON and ORDER BY columns
+            // not in SELECT.
+            let distinct_on = distinct.as_deref().unwrap_or_default();
+            let hidden =
+                self.build_select_hidden(&having, distinct_on, &order_by, &scope, &mut child_scope);
             aliases.extend(std::iter::repeat(Label::None).take(hidden.len()));
             expressions.extend(hidden);
 
@@ -471,33 +1695,109 @@ impl<'a, C: Catalog> Planner<'a, C> {
 
         // Build HAVING clause
         if let Some(having) = having {
-            if scope.aggregates.is_empty() {
-                return errinput!("HAVING requires GROUP BY or aggregate function");
+            node = self.build_having(node, having, &scope)?;
+        }
+
+        // Build ORDER BY clause, then DISTINCT, such that DISTINCT ON emits the
+        // first row of each group in ORDER BY order.
+        if let Some(on) = &distinct {
+            if order_by
+                .iter()
+                .take(on.len())
+                .any(|(expr, _)| !on.contains(expr))
+            {
+                return errinput!(
+                    "DISTINCT ON expressions must match initial ORDER BY expressions"
+                );
             }
-            let predicate = Self::build_expression(having, &scope)?;
-            node = Node::Filter {
-                source: node.into(),
-                predicate,
-            };
         }
+        node = self.build_order(node, order_by, &scope)?;
+        if let Some(on) = distinct {
+            node = self.build_distinct(node, on, &scope)?;
+        }
+        node = self.build_offset_limit(node, offset, limit)?;
 
-        // Build ORDER BY clause.
-        if !order_by.is_empty() {
-            let key = order_by
-                .into_iter()
-                .map(|(expr, dir)| Ok((Self::build_expression(expr, &scope)?, dir.into())))
-                .collect::<Result<_>>()?;
-            node = Node::Order {
+        // Remove any hidden columns before emitting the result.
+        if let Some(targets) = scope.remap_hidden() {
+            node = Node::Remap {
                 source: node.into(),
-                key,
-            };
+                targets,
+            }
+        }
+
+        Ok(node)
+    }
+
+    /// Builds a HAVING clause.
+    fn build_having(&self, node: Node, having: ast::Expression, scope: &Scope) -> Result<Node> {
+        if scope.aggregates.is_empty() {
+            return errinput!("HAVING requires GROUP BY or aggregate function");
+        }
+        let predicate = self.build_expression(having, scope)?;
+        Ok(Node::Filter {
+            source: node.into(),
+            predicate,
+        })
+    }
+
+    /// Builds an ORDER BY clause, if any.
+    fn build_order(
+        &self,
+        node: Node,
+        order_by: Vec<(ast::Expression, ast::Direction)>,
+        scope: &Scope,
+    ) -> Result<Node> {
+        if order_by.is_empty() {
+            return Ok(node);
         }
+        let key = order_by
+            .into_iter()
+            .map(|(expr, dir)| Ok((self.build_collated(expr, scope)?, dir.into())))
+            .collect::<Result<_>>()?;
+        Ok(Node::Order {
+            source: node.into(),
+            key,
+        })
+    }
+
+    /// Builds a DISTINCT node. Rows are compared by the DISTINCT ON
+    /// expressions, or all visible columns if none are given. Like GROUP BY,
+    /// values are compared by their collation.
+    fn build_distinct(&self, node: Node, on: Vec<ast::Expression>, scope: &Scope) -> Result<Node> {
+        let on = match on.is_empty() {
+            true => (0..node.columns())
+                .filter(|index| !scope.hidden.contains(index))
+                .map(|index| match scope.collations.get(&index) {
+                    Some(&collation) if collation != Collation::Binary => {
+                        Expression::Collate(Expression::Column(index).into(), collation)
+                    }
+                    _ => Expression::Column(index),
+                })
+                .collect(),
+            false => on
+                .into_iter()
+                .map(|expr| self.build_collated(expr, scope))
+                .try_collect()?,
+        };
+        Ok(Node::Distinct {
+            source: node.into(),
+            on,
+        })
+    }
 
+    /// Builds OFFSET and LIMIT clauses, if any.
+    fn build_offset_limit(
+        &self,
+        mut node: Node,
+        offset: Option<ast::Expression>,
+        limit: Option<ast::Expression>,
+    ) -> Result<Node> {
         // Build OFFSET clause.
         if let Some(offset) = offset {
-            let offset = match Self::evaluate_constant(offset)? {
-                Field::Integer(offset) if offset >= 0 => offset as usize,
-                offset => return errinput!("invalid offset {offset}"),
+            let offset = self.evaluate_count("OFFSET", offset)?;
+            let offset = match offset.as_i64() {
+                Some(n) if n >= 0 => n as usize,
+                _ => return errinput!("invalid offset {offset}"),
             };
             node = Node::Offset {
                 source: node.into(),
@@ -507,25 +1807,185 @@ impl<'a, C: Catalog> Planner<'a, C> {
 
         // Build LIMIT clause.
         if let Some(limit) = limit {
-            let limit = match Self::evaluate_constant(limit)? {
-                Field::Integer(limit) if limit >= 0 => limit as usize,
-                limit => return errinput!("invalid limit {limit}"),
+            let limit = self.evaluate_count("LIMIT", limit)?;
+            let limit = match limit.as_i64() {
+                Some(n) if n >= 0 => n as usize,
+                _ => return errinput!("invalid limit {limit}"),
             };
             node = Node::Limit {
                 source: node.into(),
                 limit,
             }
         }
+        Ok(node)
+    }
 
-        // Remove any hidden columns before emitting the result.
-        if let Some(targets) = scope.remap_hidden() {
-            node = Node::Remap {
-                source: node.into(),
-                targets,
+    /// Evaluates a LIMIT or OFFSET expression, which must be constant. Bind
+    /// parameters aren't supported, since the plan depends on the value.
+    fn evaluate_count(&self, clause: &str, expr: ast::Expression) -> Result<Field> {
+        let expr = self.build_expression(expr, &Scope::new())?;
+        if expr.contains(&|expr| matches!(expr, Expression::Parameter(_))) {
+            return errinput!("{clause} can't use bind parameters");
+        }
+        if expr.contains_subquery() {
+            return errinput!("subqueries are not allowed in constant expressions");
+        }
+        expr.evaluate(None)
+    }
+
+    /// Builds a filter node for a WHERE predicate. Top-level conjuncts with
+    /// correlated [NOT] EXISTS or IN subqueries are decorrelated into semi or
+    /// anti joins where possible, instead of executing the subquery per row.
+    fn build_filter(mut source: Node, predicate: Expression) -> Node {
+        let mut conjuncts = Vec::new();
+        let mut semi_joins = Vec::new();
+        for expr in Self::split_conjuncts(predicate) {
+            match Self::build_semi_join(&expr, source.columns()) {
+                Some(semi_join) => semi_joins.push(semi_join),
+                None => conjuncts.push(expr),
+            }
+        }
+        if let Some(predicate) = Expression::and_vec(conjuncts) {
+            source = Node::Filter {
+                source: source.into(),
+                predicate,
+            };
+        }
+        for (right, predicate, anti) in semi_joins {
+            source = Node::SemiJoin {
+                left: source.into(),
+                right: right.into(),
+                predicate,
+                anti,
+            };
+        }
+        source
+    }
+
+    /// Splits an expression into its top-level AND conjuncts.
+    fn split_conjuncts(expr: Expression) -> Vec<Expression> {
+        match expr {
+            Expression::And(lhs, rhs) => {
+                let mut conjuncts = Self::split_conjuncts(*lhs);
+                conjuncts.extend(Self::split_conjuncts(*rhs));
+                conjuncts
             }
+            expr => vec![expr],
+        }
+    }
+
+    /// Attempts to decorrelate a WHERE conjunct with a correlated subquery
+    /// into a semi join (EXISTS, IN) or anti join (NOT EXISTS) with a source
+    /// of the given width, returning the right node, join predicate, and anti
+    /// flag. NOT IN isn't decorrelated, since its NULL semantics differ.
+    ///
+    /// This is possible when the subquery is a projection of a filter, where
+    /// only the filter's top-level conjuncts reference the outer query. These
+    /// are pulled up into the join predicate, with outer column references
+    /// mapped to the left columns and subquery columns to the right columns.
+    /// For IN, the join predicate also compares the value with the projected
+    /// expression. Consider e.g.:
+    ///
+    /// SELECT * FROM a WHERE a.x IN (SELECT b.x FROM b WHERE b.id = a.id AND b.y > 0)
+    ///
+    /// This is built as a semi join of a with b filtered by b.y > 0, on the
+    /// predicate b.id = a.id AND a.x = b.x.
+    fn build_semi_join(
+        expr: &Expression,
+        left_size: usize,
+    ) -> Option<(Node, Option<Expression>, bool)> {
+        let (node, lhs, anti) = match expr {
+            Expression::Exists(node) => (node, None, false),
+            Expression::Not(expr) => match expr.as_ref() {
+                Expression::Exists(node) => (node, None, true),
+                _ => return None,
+            },
+            Expression::InSubquery(lhs, node) => (node, Some(lhs.as_ref()), false),
+            _ => return None,
+        };
+        // Uncorrelated subqueries are only executed once anyway.
+        if !node.is_correlated() || lhs.is_some_and(Expression::contains_subquery) {
+            return None;
+        }
+
+        // Peel off the projection and filter, if any.
+        let (mut right, projection) = match node.inner.as_ref().clone() {
+            Node::Projection {
+                source,
+                mut expressions,
+                ..
+            } => (*source.inner, Some(expressions.remove(0))),
+            node => (node, None),
+        };
+        let mut conjuncts = Vec::new();
+        if let Node::Filter { source, predicate } = right {
+            conjuncts = Self::split_conjuncts(predicate);
+            right = *source.inner;
+        }
+
+        // Pull up conjuncts that only reference the immediately enclosing
+        // query, and join the rest as a filter.
+        let is_outer = |expr: &Expression| matches!(expr, Expression::OuterColumn(0, _));
+        let (pulled, remaining): (Vec<_>, Vec<_>) = conjuncts
+            .into_iter()
+            .partition(|expr| expr.contains(&is_outer));
+        let pullable = |expr: &Expression| {
+            !expr.contains_subquery()
+                && !expr.contains(&|expr| matches!(expr, Expression::OuterColumn(1.., _)))
+        };
+        if !pulled.iter().all(pullable) || !projection.iter().all(pullable) {
+            return None;
+        }
+        if let Some(predicate) = Expression::and_vec(remaining) {
+            right = Node::Filter {
+                source: right.into(),
+                predicate,
+            };
+        }
+        if right.is_correlated() {
+            return None;
         }
 
-        Ok(Plan::Select(node.into()))
+        // Build the join predicate over the joined left and right rows.
+        let join = |expr: Expression| {
+            let xform = |expr| match expr {
+                Expression::Column(index) => Expression::Column(left_size + index),
+                Expression::OuterColumn(0, index) => Expression::Column(index),
+                expr => expr,
+            };
+            expr.transform(&|expr| Ok(xform(expr)), &Ok).unwrap() // never fails
+        };
+        let mut predicate = pulled.into_iter().map(join).collect_vec();
+        if let Some(lhs) = lhs {
+            let rhs = join(projection.unwrap_or(Expression::Column(0)));
+            let rhs = match lhs {
+                Expression::Collate(_, collation) => Expression::Collate(rhs.into(), *collation),
+                _ => rhs,
+            };
+            predicate.push(Expression::Equal(lhs.clone().into(), rhs.into()));
+        }
+        Some((right, Expression::and_vec(predicate), anti))
+    }
+
+    /// Builds a scan of a DELETE or UPDATE table with the given WHERE filter.
+    /// Since the storage scan evaluates its filter without a transaction,
+    /// predicates with subqueries are built as a filter node instead.
+    fn build_scan(table: Table, filter: Option<Expression>) -> Node {
+        match filter {
+            Some(predicate) if predicate.contains_subquery() => {
+                let scan = Node::Scan {
+                    table,
+                    alias: None,
+                    filter: None,
+                };
+                Self::build_filter(scan, predicate)
+            }
+            filter => Node::Scan {
+                table,
+                alias: None,
+                filter,
+            },
+        }
     }
 
     /// Builds a FROM clause consisting of one or more items. Each item is
@@ -557,18 +2017,74 @@ impl<'a, C: Catalog> Planner<'a, C> {
     fn build_from(&self, from: ast::From, parent_scope: &mut Scope) -> Result<Node> {
         // Each from item is built in its own scope, such that a join node only
         // sees the columns of its children. It's then merged into the parent.
-        let mut scope = Scope::new();
+        let mut scope = Scope::with_outer(parent_scope.outer);
+        scope.ctes.clone_from(&parent_scope.ctes);
 
         let node = match from {
-            // A full table scan.
-            ast::From::Table { name, alias } => {
-                let table = self.catalog.must_get_table(&name)?;
-                scope.add_table(&table, alias.as_deref())?;
-                Node::Scan {
-                    table,
-                    alias,
-                    filter: None,
+            // A CTE reference, which is built as a derived table named by the
+            // alias or CTE name. CTEs shadow tables.
+            ast::From::Table { name, alias } if scope.ctes.contains_key(&name) => {
+                let (source, columns) = match scope.ctes[&name].as_ref() {
+                    Cte::Query {
+                        definition,
+                        recursive,
+                        columns,
+                        ctes,
+                    } => {
+                        let (node, _) =
+                            self.build_cte(definition, *recursive, scope.outer, ctes)?;
+                        (node, columns.clone())
+                    }
+                    Cte::WorkingTable { columns } => {
+                        let labels = columns
+                            .iter()
+                            .map(|column| Label::Qualified(name.clone(), column.clone()))
+                            .collect();
+                        let node = Node::WorkingTable {
+                            name: name.clone(),
+                            columns: labels,
+                        };
+                        (node, columns.clone())
+                    }
+                };
+                let table = alias.unwrap_or(name);
+                Self::build_derived_table(source, &table, &columns, &mut scope)?
+            }
+
+            // A view reference, which is expanded into the view's query and
+            // built as a derived table named by the alias or view name.
+            // Materialized views are scanned like tables. Otherwise, a full
+            // table scan.
+            ast::From::Table { name, alias } => match self.catalog.get_view(&name)? {
+                Some(view) if !view.materialized => {
+                    self.add_dependency(&name);
+                    let source = self.build_view(&view)?;
+                    let columns = Self::column_names(&source);
+                    let table = alias.unwrap_or(name);
+                    Self::build_derived_table(source, &table, &columns, &mut scope)?
+                }
+                view => {
+                    if view.is_some() {
+                        self.add_dependency(&name);
+                    }
+                    let table = self.catalog.must_get_table(&name)?;
+                    scope.add_table(&table, alias.as_deref())?;
+                    Node::Scan {
+                        table,
+                        alias,
+                        filter: None,
+                    }
                 }
+            },
+
+            // A derived table. It can't reference other FROM items, but can
+            // reference enclosing queries if this is a subquery. Its columns
+            // are qualified by the alias, and unnamed columns are named by
+            // position as column1, column2, etc.
+            ast::From::Subquery { query, alias } => {
+                let source = self.build_query(*query, scope.outer, &scope.ctes)?;
+                let columns = Self::column_names(&source);
+                Self::build_derived_table(source, &alias, &columns, &mut scope)?
             }
 
             // A two-way join. The left or right nodes may be chained joins.
@@ -590,7 +2106,7 @@ impl<'a, C: Catalog> Planner<'a, C> {
 
                 // Build the join node.
                 let predicate = predicate
-                    .map(|e| Self::build_expression(e, &scope))
+                    .map(|e| self.build_expression(e, &scope))
                     .transpose()?;
                 let outer = r#type.is_outer();
                 let mut node = Node::NestedLoopJoin {
@@ -620,9 +2136,97 @@ impl<'a, C: Catalog> Planner<'a, C> {
         Ok(node)
     }
 
-    /// Builds and evaluates a constant AST expression. Errors on column refs.
-    fn evaluate_constant(expr: ast::Expression) -> Result<Field> {
-        Self::build_expression(expr, &Scope::new())?.evaluate(None)
+    /// Builds and evaluates a constant AST expression. Errors on column refs
+    /// and subqueries, which can't be executed during planning.
+    pub fn evaluate_constant(&self, expr: ast::Expression) -> Result<Field> {
+        let expr = self.build_expression(expr, &Scope::new())?;
+        if expr.contains_subquery() {
+            return errinput!("subqueries are not allowed in constant expressions");
+        }
+        expr.evaluate(None)
+    }
+
+    /// Builds a call to a scalar function from the function registry, checking
+    /// its arguments.
+    fn build_function(
+        &self,
+        name: &str,
+        args: Vec<ast::Expression>,
+        scope: &Scope,
+    ) -> Result<Expression> {
+        let Some(function) = Function::lookup(name) else {
+            return errinput!("unknown function {name} with {} arguments", args.len());
+        };
+        let args = args
+            .into_iter()
+            .map(|arg| self.build_expression(arg, scope))
+            .collect::<Result<Vec<_>>>()?;
+        function.check(&args)?;
+        Ok(Expression::Function(function, args))
+    }
+
+    /// Builds an expression that's compared or ordered by its collation, i.e.
+    /// an ORDER BY or GROUP BY expression. If the expression has a non-binary
+    /// collation, it's converted to the collation's sort key.
+    fn build_collated(&self, expr: ast::Expression, scope: &Scope) -> Result<Expression> {
+        let collation = scope.collation(&expr);
+        let expr = self.build_expression(expr, scope)?;
+        Ok(match collation {
+            Some(collation) if collation != Collation::Binary => {
+                Expression::Collate(expr.into(), collation)
+            }
+            _ => expr,
+        })
+    }
+
+    /// Builds the operands of a comparison. If an operand has a non-binary
+    /// collation, all operands are converted to the collation's sort keys. An
+    /// explicit COLLATE takes precedence over a column's collation, and the
+    /// operands can't have different collations of the same kind.
+    fn build_comparison(
+        &self,
+        operands: Vec<ast::Expression>,
+        scope: &Scope,
+    ) -> Result<Vec<Expression>> {
+        let explicit = operands
+            .iter()
+            .filter_map(|expr| match expr {
+                ast::Expression::Operator(ast::Operator::Collate(_, collation)) => Some(*collation),
+                _ => None,
+            })
+            .unique()
+            .collect_vec();
+        let implicit = operands
+            .iter()
+            .filter_map(|expr| scope.collation(expr))
+            .unique()
+            .collect_vec();
+        let collation = match (explicit.as_slice(), implicit.as_slice()) {
+            ([collation], _) | ([], [collation]) => *collation,
+            ([], []) => Collation::Binary,
+            ([lhs, rhs, ..], _) | ([], [lhs, rhs, ..]) => {
+                return errinput!("conflicting collations {lhs} and {rhs}")
+            }
+        };
+        operands
+            .into_iter()
+            .map(|expr| {
+                let expr = self.build_expression(expr, scope)?;
+                Ok(match collation {
+                    Collation::Binary => expr,
+                    collation => Expression::Collate(expr.into(), collation),
+                })
+            })
+            .collect()
+    }
+
+    /// Builds a date/time field for extract() or date_trunc(), which must be
+    /// given as a string constant such as 'year'.
+    fn build_date_time_field(expr: ast::Expression) -> Result<DateTimeField> {
+        match expr {
+            ast::Expression::Literal(ast::Literal::String(field)) => field.as_str().try_into(),
+            expr => errinput!("expected date/time field, got {expr:?}"),
+        }
     }
 }
 
@@ -637,7 +2241,7 @@ impl<'a, C: Catalog> Planner<'a, C> {
 /// scope is used to resolve column names to column indexes, which are placed in
 /// the plan and used during execution.
 #[derive(Default)]
-pub struct Scope {
+pub struct Scope<'a> {
     /// The currently visible columns. If empty, only constant expressions can
     /// be used (no column references).
     columns: Vec<Label>,
@@ -654,13 +2258,27 @@ pub struct Scope {
     /// expressions in downstream SELECT, HAVING, and ORDER BY clauses. If the
     /// node contains an (inner) Aggregate node, this is never empty.
     aggregates: HashMap<ast::Expression, usize>,
+    /// Index of window function expressions to column indexes. This is used
+    /// to track output columns of Window nodes and look them up from SELECT
+    /// and ORDER BY clauses.
+    windows: HashMap<ast::Expression, usize>,
     /// Hidden columns. These are used to pass e.g. ORDER BY and HAVING
     /// expressions through SELECT projection nodes if the expressions aren't
     /// already projected. They should be removed before emitting results.
     hidden: HashSet<usize>,
+    /// Column collations, either from the table schema or an explicit COLLATE
+    /// in a projection. Columns without an entry use the binary collation.
+    collations: HashMap<usize, Collation>,
+    /// The scope of the enclosing query, if this is a subquery. Columns that
+    /// aren't found in this scope are looked up in the outer scopes, as
+    /// correlated column references.
+    outer: Option<&'a Scope<'a>>,
+    /// Common table expressions that can be referenced by name in FROM
+    /// clauses, including those of enclosing queries.
+    ctes: HashMap<String, Rc<Cte>>,
 }
 
-impl Scope {
+impl<'a> Scope<'a> {
     /// Creates a new, empty scope.
     pub fn new() -> Self {
         Self {
@@ -669,7 +2287,19 @@ impl Scope {
             qualified: HashMap::new(),
             unqualified: HashMap::new(),
             aggregates: HashMap::new(),
+            windows: HashMap::new(),
             hidden: HashSet::new(),
+            collations: HashMap::new(),
+            outer: None,
+            ctes: HashMap::new(),
+        }
+    }
+
+    /// Creates a new, empty scope for a subquery of the given outer scope.
+    fn with_outer(outer: Option<&'a Scope<'a>>) -> Self {
+        Self {
+            outer,
+            ..Self::new()
         }
     }
 
@@ -682,8 +2312,9 @@ impl Scope {
 
     /// Creates a new child scope that inherits from the parent scope.
     pub fn spawn(&self) -> Self {
-        let mut child = Scope::new();
+        let mut child = Scope::with_outer(self.outer);
         child.tables.clone_from(&self.tables); // retain table names
+        child.ctes.clone_from(&self.ctes);
         child
     }
 
@@ -695,10 +2326,41 @@ impl Scope {
             return errinput!("duplicate table name {name}");
         }
         for column in table.columns() {
-            self.add_column(Label::Qualified(
+            let index = self.add_column(Label::Qualified(
                 name.to_string(),
                 column.get_name().to_string(),
             ));
+            if column.collation() != Collation::Binary {
+                self.collations.insert(index, column.collation());
+            }
+        }
+        self.tables.insert(name.to_string());
+        Ok(())
+    }
+
+    /// Adds the pseudo-table "excluded" for ON CONFLICT DO UPDATE, with the
+    /// given table's columns. Its columns can only be referenced by qualified
+    /// name, so unqualified names refer to the original table.
+    fn add_excluded(&mut self, table: &Table) -> Result<()> {
+        let start = self.columns.len();
+        self.add_table(table, Some("excluded"))?;
+        for indexes in self.unqualified.values_mut() {
+            indexes.retain(|index| *index < start);
+        }
+        Ok(())
+    }
+
+    /// Adds a derived table to the scope, with the given column names. The
+    /// name must be unique, and so must the column names.
+    fn add_derived_table(&mut self, name: &str, columns: &[String]) -> Result<()> {
+        if self.tables.contains(name) {
+            return errinput!("duplicate table name {name}");
+        }
+        if let Some(column) = columns.iter().duplicates().next() {
+            return errinput!("duplicate column {column} in subquery {name}");
+        }
+        for column in columns {
+            self.add_column(Label::Qualified(name.to_string(), column.clone()));
         }
         self.tables.insert(name.to_string());
         Ok(())
@@ -734,7 +2396,8 @@ impl Scope {
         }
         if let Some(table) = table {
             if !self.tables.contains(table) {
-                return errinput!("unknown table {table}");
+                let hint = did_you_mean(table, self.tables.iter().map(String::as_str));
+                return errinput!("unknown table {table}{hint}");
             }
             if let Some(index) = self.qualified.get(&(table.to_string(), name.to_string())) {
                 return Ok(*index);
@@ -751,7 +2414,32 @@ impl Scope {
                 fmtname()
             );
         }
-        errinput!("unknown column {}", fmtname())
+        let hint = match table {
+            Some(table) => did_you_mean(
+                name,
+                self.qualified
+                    .keys()
+                    .filter(|(t, _)| t == table)
+                    .map(|(_, column)| column.as_str()),
+            ),
+            None => did_you_mean(name, self.unqualified.keys().map(String::as_str)),
+        };
+        errinput!("unknown column {}{hint}", fmtname())
+    }
+
+    /// Looks up a column in the scopes of enclosing queries, returning the
+    /// query depth (0 for the immediately enclosing query) and column index.
+    fn lookup_outer_column(&self, table: Option<&str>, name: &str) -> Option<(usize, usize)> {
+        let mut outer = self.outer;
+        let mut depth = 0;
+        while let Some(scope) = outer {
+            if let Ok(index) = scope.lookup_column(table, name) {
+                return Some((depth, index));
+            }
+            outer = scope.outer;
+            depth += 1;
+        }
+        None
     }
 
     /// Adds an aggregate expression to the scope, returning the new column
@@ -765,7 +2453,7 @@ impl Scope {
         // If this is a simple column reference (i.e. GROUP BY foo), pass
         // through the column label from the parent scope for lookups.
         let mut label = Label::None;
-        if let ast::Expression::Column(table, column) = expr {
+        if let ast::Expression::Column(table, column, _) = expr {
             // Ignore errors, they will be emitted when building the expression.
             if let Ok(index) = parent.lookup_column(table.as_deref(), column.as_str()) {
                 label = parent.columns[index].clone();
@@ -773,6 +2461,9 @@ impl Scope {
         }
         let index = self.add_column(label);
         self.aggregates.insert(expr.clone(), index);
+        if let Some(collation) = parent.collation(expr) {
+            self.collations.insert(index, collation);
+        }
         Some(index)
     }
 
@@ -782,15 +2473,43 @@ impl Scope {
         self.aggregates.get(expr).copied()
     }
 
+    /// Looks up the collation of an expression, if any. This is either an
+    /// explicit COLLATE, or the collation of a column or GROUP BY expression.
+    fn collation(&self, expr: &ast::Expression) -> Option<Collation> {
+        if let ast::Expression::Operator(ast::Operator::Collate(_, collation)) = expr {
+            return Some(*collation);
+        }
+        let index = self.lookup_aggregate(expr).or_else(|| match expr {
+            ast::Expression::Column(table, column, _) => {
+                self.lookup_column(table.as_deref(), column).ok()
+            }
+            _ => None,
+        });
+        match (index, expr) {
+            (Some(index), _) => self.collations.get(&index).copied(),
+            // Fall back to the outer scopes for correlated column references.
+            (None, ast::Expression::Column(_, _, _)) => self.outer?.collation(expr),
+            (None, _) => None,
+        }
+    }
+
     /// Adds a column that passes through a column from the parent scope,
     /// retaining its properties. If hide is true, the column is hidden.
     fn add_passthrough(&mut self, parent: &Scope, parent_index: usize, hide: bool) -> usize {
         let index = self.add_column(parent.columns[parent_index].clone());
+        if let Some(collation) = parent.collations.get(&parent_index) {
+            self.collations.insert(index, *collation);
+        }
         for (expr, i) in &parent.aggregates {
             if *i == parent_index {
                 self.aggregates.entry(expr.clone()).or_insert(index);
             }
         }
+        for (expr, i) in &parent.windows {
+            if *i == parent_index {
+                self.windows.entry(expr.clone()).or_insert(index);
+            }
+        }
         if hide || parent.hidden.contains(&parent_index) {
             self.hidden.insert(index);
         }
@@ -814,6 +2533,12 @@ impl Scope {
         }
         self.hidden
             .extend(scope.hidden.into_iter().map(|index| index + offset));
+        self.collations.extend(
+            scope
+                .collations
+                .into_iter()
+                .map(|(index, collation)| (index + offset, collation)),
+        );
         Ok(())
     }
 
@@ -830,19 +2555,25 @@ impl Scope {
             let mut label = Label::None;
             if let Some(alias) = alias {
                 label = Label::Unqualified(alias.clone());
-            } else if let ast::Expression::Column(table, column) = expr {
+            } else if let ast::Expression::Column(table, column, _) = expr {
                 // Ignore errors, they will be surfaced in build_expression().
                 if let Ok(index) = self.lookup_column(table.as_deref(), column.as_str()) {
                     label = self.columns[index].clone();
                 }
             }
             let index = child.add_column(label);
+            if let Some(collation) = self.collation(expr) {
+                child.collations.insert(index, collation);
+            }
             // If this is an aggregate query, then all projected expressions
             // must also be aggregates by definition (an aggregate node can only
             // emit aggregate functions or GROUP BY expressions).
             if !self.aggregates.is_empty() {
                 child.aggregates.entry(expr.clone()).or_insert(index);
             }
+            if self.windows.contains_key(expr) {
+                child.windows.entry(expr.clone()).or_insert(index);
+            }
         }
         child
     }
@@ -875,6 +2606,8 @@ impl Scope {
             .for_each(|(_, vec)| vec.retain(|i| !hidden.contains(i)));
         self.unqualified.retain(|_, vec| !vec.is_empty());
         self.aggregates.retain(|_, index| !hidden.contains(index));
+        self.windows.retain(|_, index| !hidden.contains(index));
+        self.collations.retain(|index, _| !hidden.contains(index));
         Some(hidden)
     }
 
@@ -896,3 +2629,18 @@ impl Scope {
         Some(targets)
     }
 }
+
+/// A common table expression that can be referenced by name in a scope.
+enum Cte {
+    /// A WITH query, which is built wherever it's referenced. Retains the CTEs
+    /// visible to its definition, and its column names.
+    Query {
+        definition: ast::CommonTableExpression,
+        recursive: bool,
+        columns: Vec<String>,
+        ctes: HashMap<String, Rc<Cte>>,
+    },
+    /// A recursive CTE referenced from its own recursive term, which reads the
+    /// working table, i.e. the rows emitted by the previous iteration.
+    WorkingTable { columns: Vec<String> },
+}
@@ -386,7 +386,7 @@ fn test_update_expression() {
         .execute("INSERT INTO test VALUES (0, NULL, 0), (1, 1, 0), (2, 2, 0)")
         .select_expect(
             "SELECT * FROM test",
-            &with_schema("0, 0, 0 ; 1, 1, 0 ; 2, 2, 0"),
+            &with_schema("0, NULL, 0 ; 1, 1, 0 ; 2, 2, 0"),
         );
 
     // UPDATE can evaluate constant expressions
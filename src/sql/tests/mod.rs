@@ -1,3 +1,5 @@
 #[cfg(test)]
 mod lab3_student_tests;
+#[cfg(test)]
+mod sql_tests;
 mod utility;
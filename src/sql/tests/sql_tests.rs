@@ -0,0 +1,2119 @@
+use crate::sql::engine::{Catalog, Engine, Local, Session, StatementResult};
+use crate::sql::tests::utility::{create_storage_engine, handle, SqlStudentRunner};
+use crate::types::field::Field;
+use crate::types::temporal;
+use crate::types::DataType;
+
+// ============================== Numeric Types ===============================
+
+#[test]
+fn test_numeric_types() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .execute(
+            "CREATE TABLE numbers ( \
+                id INT PRIMARY KEY, \
+                small SMALLINT, \
+                big BIGINT, \
+                single REAL, \
+                wide DOUBLE PRECISION \
+            )",
+        )
+        .execute("INSERT INTO numbers VALUES (1, 7, 9000000000, 0.5, 0.1)")
+        .execute("INSERT INTO numbers VALUES (2, -7, 1, 1.5, 3.141592653589793)")
+        .select_expect(
+            "SELECT * FROM numbers",
+            "numbers.id, numbers.small, numbers.big, numbers.single, numbers.wide ; \
+                1, 7, 9000000000, 0.5, 0.1 ; \
+                2, -7, 1, 1.5, 3.141592653589793",
+        )
+        // Mixed arithmetic widens to the wider type.
+        .select_expect(
+            "SELECT big + small, single * wide FROM numbers WHERE id = 1",
+            " , ; 9000000007, 0.05",
+        )
+        .select_expect(
+            "SELECT id FROM numbers WHERE big > 2147483647",
+            "numbers.id ; 1",
+        )
+        // Numbers of different types with the same value are the same group.
+        .execute("INSERT INTO numbers VALUES (3, 1, 1, 1.0, 1.0)")
+        .select_expect(
+            "SELECT COUNT(DISTINCT CASE WHEN id = 2 THEN big ELSE small END), \
+                COUNT(DISTINCT CASE WHEN id = 2 THEN big ELSE wide END) \
+                FROM numbers WHERE id > 1",
+            " , ; 1, 1",
+        )
+        .select_expect(
+            "SELECT COUNT(*) FROM numbers WHERE id > 1 \
+                GROUP BY CASE WHEN id = 2 THEN big ELSE wide END",
+            " ; 2",
+        );
+}
+
+#[test]
+fn test_sum_widens_to_bigint() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE TABLE ints (id INT PRIMARY KEY, value INT)")
+        .execute("INSERT INTO ints VALUES (1, 2147483647)")
+        .execute("INSERT INTO ints VALUES (2, 2147483647)")
+        .select_expect(
+            "SELECT SUM(value), COUNT(value) FROM ints",
+            " , ; 4294967294, 2",
+        )
+        .select_expect("SELECT AVG(value) FROM ints", " ; 2147483647");
+}
+
+// ============================== Temporal Types ==============================
+
+#[test]
+fn test_temporal_types() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .execute(
+            "CREATE TABLE events ( \
+                id INT PRIMARY KEY, \
+                day DATE, \
+                starts TIME, \
+                happened TIMESTAMP, \
+                length INTERVAL \
+            )",
+        )
+        .execute(
+            "INSERT INTO events VALUES (1, DATE '2024-01-31', TIME '09:30', \
+                TIMESTAMP '2024-01-31 09:30:00', INTERVAL '1 hour 30 minutes')",
+        )
+        .execute(
+            "INSERT INTO events VALUES (2, DATE '2024-03-01', TIME '23:00:00.5', \
+                TIMESTAMP '2024-03-01 23:00:00.5', INTERVAL '2 days')",
+        )
+        .select_expect(
+            "SELECT * FROM events",
+            "events.id, events.day, events.starts, events.happened, events.length ; \
+                1, 2024-01-31, 09:30:00, 2024-01-31 09:30:00, 01:30:00 ; \
+                2, 2024-03-01, 23:00:00.5, 2024-03-01 23:00:00.5, 2 days",
+        )
+        // Interval arithmetic, with months clamped to the end of the month.
+        .select_expect(
+            "SELECT day + 1, day + INTERVAL '1 month', happened + length, starts + length \
+                FROM events WHERE id = 1",
+            " , , , ; 2024-02-01, 2024-02-29 00:00:00, 2024-01-31 11:00:00, 11:00:00",
+        )
+        .select_expect(
+            "SELECT DATE '2024-03-01' - day, happened - TIMESTAMP '2024-01-01' \
+                FROM events WHERE id = 1",
+            " , ; 30, 30 days 09:30:00",
+        )
+        .select_expect(
+            "SELECT id FROM events WHERE happened > DATE '2024-02-01'",
+            "events.id ; 2",
+        )
+        .select_expect(
+            "SELECT extract(year FROM day), extract(hour FROM starts), \
+                date_trunc('month', happened) FROM events WHERE id = 2",
+            " , , ; 2024, 23, 2024-03-01 00:00:00",
+        )
+        .select_expect("SELECT now() > TIMESTAMP '2024-01-01'", " ; true");
+
+    // The type names can also be used as column names.
+    SqlStudentRunner::new(&engine)
+        .execute(
+            "CREATE TABLE logs ( \
+                id INT PRIMARY KEY, \
+                date DATE, \
+                timestamp TIMESTAMP, \
+                interval INTERVAL \
+            )",
+        )
+        .execute(
+            "INSERT INTO logs (id, date, timestamp, interval) \
+                VALUES (1, '2024-01-02', '2024-01-02 03:00', '1 hour')",
+        )
+        .execute("UPDATE logs SET date = date + 1 WHERE logs.date = DATE '2024-01-02'")
+        .select_expect(
+            "SELECT date, timestamp + interval AS ends FROM logs ORDER BY date",
+            "logs.date, ends ; 2024-01-03, 2024-01-02 04:00:00",
+        );
+
+    // Results must be within years 1 to 9999.
+    let mut session = engine.session();
+    let error = |session: &mut Session<'_, _>, sql| session.execute(sql).unwrap_err().to_string();
+    assert!(error(
+        &mut session,
+        "SELECT TIMESTAMP '2024-01-01 00:00' + INTERVAL '100000000 years'"
+    )
+    .starts_with("invalid input: timestamp out of range"));
+    assert!(error(
+        &mut session,
+        "SELECT DATE '2024-01-01' + 2000000000 + INTERVAL '1 day'"
+    )
+    .starts_with("invalid input: date out of range"));
+    assert!(error(&mut session, "SELECT DATE '9999-12-31' + 1")
+        .starts_with("invalid input: date out of range"));
+    assert!(
+        error(&mut session, "SELECT DATE '0001-01-01' - INTERVAL '1 day'")
+            .starts_with("invalid input: timestamp out of range")
+    );
+}
+
+// ============================== Decimal Types ===============================
+
+#[test]
+fn test_decimal_type() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE TABLE prices (id INT PRIMARY KEY, price DECIMAL(8, 2), rate NUMERIC)")
+        .execute("INSERT INTO prices VALUES (1, 0.1, 3)")
+        .execute("INSERT INTO prices VALUES (2, 0.2, 2)")
+        .execute("INSERT INTO prices VALUES (3, 19.999, -1)")
+        // Values are rounded to the column scale.
+        .select_expect(
+            "SELECT * FROM prices",
+            "prices.id, prices.price, prices.rate ; \
+                1, 0.10, 3 ; \
+                2, 0.20, 2 ; \
+                3, 20.00, -1",
+        )
+        // Arithmetic is exact, and division rounds to at least 6 digits.
+        .select_expect(
+            "SELECT price + 0.2, price * rate, price / 3 FROM prices WHERE id = 1",
+            " , , ; 0.30, 0.30, 0.033333",
+        )
+        .select_expect(
+            "SELECT SUM(price), AVG(price) FROM prices",
+            " , ; 20.30, 6.766667",
+        )
+        // Decimals compare with integers and floats.
+        .select_expect(
+            "SELECT id FROM prices WHERE price = 0.1 OR price > 19",
+            "prices.id ; 1 ; 3",
+        )
+        .execute("UPDATE prices SET price = price * 2 + 0.005 WHERE id = 2")
+        .select_expect(
+            "SELECT price FROM prices WHERE id = 2",
+            "prices.price ; 0.41",
+        );
+
+    let mut session = engine.session();
+    assert!(session
+        .execute("INSERT INTO prices VALUES (4, 1000000, 0)")
+        .is_err());
+    assert!(session
+        .execute("CREATE TABLE invalid (id INT PRIMARY KEY, value DECIMAL(2, 3))")
+        .is_err());
+}
+
+// ================================ Byte Strings ==============================
+
+#[test]
+fn test_bytea_type() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE TABLE blobs (id INT PRIMARY KEY, name TEXT, data BYTEA, hash BLOB)")
+        .execute("INSERT INTO blobs VALUES (1, 'beef', x'DEADBEEF', x'')")
+        .execute("INSERT INTO blobs VALUES (2, 'text', decode('aGk=', 'base64'), x'00ff')")
+        .select_expect(
+            "SELECT * FROM blobs",
+            "blobs.id, blobs.name, blobs.data, blobs.hash ; \
+                1, beef, \\xdeadbeef, \\x ; \
+                2, text, \\x6869, \\x00ff",
+        )
+        .select_expect(
+            "SELECT length(data), substr(data, 2, 2), encode(data, 'base64') \
+                FROM blobs WHERE id = 1",
+            " , , ; 4, \\xadbe, 3q2+7w==",
+        )
+        .select_expect(
+            "SELECT encode(data, 'escape'), length(name), substr(name, 2) \
+                FROM blobs WHERE id = 2",
+            " , , ; hi, 4, ext",
+        )
+        .select_expect(
+            "SELECT id FROM blobs WHERE data = x'deadbeef' OR hash > x'00'",
+            "blobs.id ; 1 ; 2",
+        );
+}
+
+// =========================== Casts and Coercion =============================
+
+#[test]
+fn test_cast() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .select_expect(
+            "SELECT CAST('42' AS INT) + 1, '1.5'::DOUBLE * 2, CAST(2.5 AS INT), -7.5::BIGINT",
+            " , , , ; 43, 3, 3, -8",
+        )
+        .select_expect(
+            "SELECT CAST(3 AS TEXT), 1.25::DECIMAL(4, 1), 'yes'::BOOL, CAST(0 AS BOOLEAN)",
+            " , , , ; 3, 1.3, true, false",
+        )
+        .select_expect(
+            "SELECT '2024-02-29'::DATE + 1, CAST(TIMESTAMP '2024-01-31 09:30' AS DATE), \
+                CAST(DATE '2024-01-31' AS TEXT)",
+            " , , ; 2024-03-01, 2024-01-31, 2024-01-31",
+        )
+        .select_expect(
+            "SELECT CAST('\\x0aff' AS BYTEA), CAST(NULL AS INT)",
+            " , ; \\x0aff, NULL",
+        );
+
+    let mut session = engine.session();
+    assert!(session.execute("SELECT CAST('abc' AS INT)").is_err());
+    assert!(session.execute("SELECT CAST(100000 AS SMALLINT)").is_err());
+    assert!(session
+        .execute("SELECT CAST(DATE '2024-01-01' AS INT)")
+        .is_err());
+}
+
+#[test]
+fn test_write_coercion() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .execute(
+            "CREATE TABLE coerced ( \
+                id INT PRIMARY KEY, \
+                big BIGINT, \
+                day DATE DEFAULT '2024-01-01', \
+                label TEXT \
+            )",
+        )
+        .execute("INSERT INTO coerced VALUES ('1', 7, '2024-05-06', 3.5)")
+        .select_expect(
+            "SELECT * FROM coerced",
+            "coerced.id, coerced.big, coerced.day, coerced.label ; 1, 7, 2024-05-06, 3.5",
+        )
+        .execute("UPDATE coerced SET big = '9000000000', label = DATE '2024-01-02'")
+        .select_expect(
+            "SELECT big, label FROM coerced",
+            "coerced.big, coerced.label ; 9000000000, 2024-01-02",
+        );
+
+    let mut session = engine.session();
+    assert!(session
+        .execute("INSERT INTO coerced VALUES (2, TRUE, '2024-01-01', 'x')")
+        .is_err());
+    assert!(session
+        .execute("INSERT INTO coerced VALUES (2, 1, 'tomorrow', 'x')")
+        .is_err());
+}
+
+// ============================= Scalar Functions =============================
+
+#[test]
+fn test_scalar_functions() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE TABLE people (id INT PRIMARY KEY, name TEXT, nick TEXT, score DOUBLE)")
+        .execute("INSERT INTO people VALUES (1, '  Alice ', '', -2.5)")
+        .execute("INSERT INTO people VALUES (2, 'bob', 'bobby', 7.25)")
+        .select_expect(
+            "SELECT upper(trim(name)), lower(name) = '  alice ', length(trim(name)), \
+                substr(trim(name), 2, 3) \
+                FROM people WHERE id = 1",
+            " , , , ; ALICE, true, 5, lic",
+        )
+        .select_expect(
+            "SELECT replace(name, 'b', 'B'), trim('xxhixx', 'x'), concat(name, '-', nick, id) \
+                FROM people WHERE id = 2",
+            " , , ; BoB, hi, bob-bobby2",
+        )
+        .select_expect(
+            "SELECT concat(nullif(nick, ''), id), coalesce(nullif(nick, ''), trim(name), 'x'), \
+                nullif(id, 1) \
+                FROM people ORDER BY id",
+            " , , ; 1, Alice, NULL ; bobby2, bobby, 2",
+        )
+        .select_expect(
+            "SELECT abs(score), round(score), floor(score), ceil(score) FROM people ORDER BY id",
+            " , , , ; 2.5, -3, -3, -2 ; 7.25, 7, 7, 8",
+        )
+        .select_expect(
+            "SELECT round(3.14159, 2), round(1250, -2), abs(-7), power(2, 10), ln(1)",
+            " , , , , ; 3.14, 1300, 7, 1024, 0",
+        )
+        .select_expect(
+            "SELECT round(CAST(2.345 AS DECIMAL(5,3)), 2), floor(CAST(-1.5 AS DECIMAL(3,1)))",
+            " , ; 2.35, -2",
+        )
+        .select_expect(
+            "SELECT ceil(CAST('-1.5' AS DECIMAL(38,19)) * CAST('1' AS DECIMAL(38,19)))",
+            " ; -1",
+        )
+        .select_expect(
+            "SELECT greatest(1, 2.5, NULL), least(3, 2, 7), greatest('a', 'b'), least(NULL, NULL)",
+            " , , , ; 2.5, 2, b, NULL",
+        )
+        .select_expect(
+            "SELECT upper(NULL), abs(NULL), substr('abc', NULL), coalesce(NULL, NULL)",
+            " , , , ; NULL, NULL, NULL, NULL",
+        );
+
+    // Arity and types are checked when planning. Column types are checked
+    // when evaluating.
+    let mut session = engine.session();
+    assert!(session.execute("SELECT upper('a', 'b')").is_err());
+    assert!(session.execute("SELECT coalesce()").is_err());
+    assert!(session.execute("SELECT replace('a', 'b')").is_err());
+    assert!(session.execute("SELECT upper(1)").is_err());
+    assert!(session.execute("SELECT abs('x')").is_err());
+    assert!(session.execute("SELECT round(1.5, 1.5)").is_err());
+    assert!(session.execute("SELECT lower(length('abc'))").is_err());
+    assert!(session.execute("SELECT greatest(1, 'a')").is_err());
+    assert!(session.execute("SELECT ln(0)").is_err());
+    assert!(session.execute("SELECT upper(id) FROM people").is_err());
+    assert!(session.execute("SELECT frobnicate(1)").is_err());
+}
+
+// ======================== CASE, IN and BETWEEN ==============================
+
+#[test]
+fn test_case_in_between() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE TABLE grades (id INT PRIMARY KEY, name TEXT, score INT)")
+        .execute("INSERT INTO grades VALUES (1, 'ann', 95), (2, 'ben', 72), (3, 'cal', 40)")
+        .select_expect(
+            "SELECT name, CASE WHEN score >= 90 THEN 'A' WHEN score >= 70 THEN 'B' ELSE 'F' END \
+                FROM grades ORDER BY id",
+            "grades.name, ; ann, A ; ben, B ; cal, F",
+        )
+        .select_expect(
+            "SELECT CASE id WHEN 1 THEN 'one' WHEN 2 THEN 'two' END FROM grades ORDER BY id",
+            " ; one ; two ; NULL",
+        )
+        .select_expect(
+            "SELECT id FROM grades WHERE name IN ('ann', 'cal', 'zed') ORDER BY id",
+            "grades.id ; 1 ; 3",
+        )
+        .select_expect(
+            "SELECT id FROM grades WHERE id NOT IN (1, 2) AND score + 10 IN (50, 60)",
+            "grades.id ; 3",
+        )
+        .select_expect(
+            "SELECT id FROM grades WHERE score BETWEEN 70 AND 100 - 5 ORDER BY id",
+            "grades.id ; 1 ; 2",
+        )
+        .select_expect(
+            "SELECT id FROM grades WHERE score NOT BETWEEN 50 AND 90",
+            "grades.id ; 1 ; 3",
+        )
+        // Three-valued logic: NULL operands yield NULL, unless IN finds a
+        // match or a CASE branch is taken.
+        .select_expect(
+            "SELECT 1 IN (2, NULL), 1 IN (1, NULL), NULL IN (1), 1 NOT IN (2, NULL), \
+                NULL BETWEEN 1 AND 2, 3 BETWEEN 1 AND NULL, 0 BETWEEN 1 AND NULL",
+            " , , , , , , ; NULL, true, NULL, NULL, NULL, NULL, false",
+        )
+        .select_expect(
+            "SELECT CASE WHEN NULL THEN 1 ELSE 2 END, CASE NULL WHEN NULL THEN 1 ELSE 2 END",
+            " , ; 2, 2",
+        );
+
+    let mut session = engine.session();
+    assert!(session.execute("SELECT CASE WHEN 1 THEN 2 END").is_err());
+    assert!(session.execute("SELECT CASE ELSE 1 END").is_err());
+    assert!(session.execute("SELECT 1 IN ()").is_err());
+    assert!(session.execute("SELECT 1 BETWEEN 2").is_err());
+}
+
+// ============================= Pattern Matching =============================
+
+#[test]
+fn test_pattern_matching() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE TABLE files (id INT PRIMARY KEY, name TEXT, pattern TEXT)")
+        .execute("INSERT INTO files VALUES (1, 'report_2024.pdf', 'report%')")
+        .execute("INSERT INTO files VALUES (2, 'Report-final.PDF', '%.pdf')")
+        .execute("INSERT INTO files VALUES (3, '100% done.txt', '1__!%%')")
+        .select_expect(
+            "SELECT id FROM files WHERE name LIKE 'report!_%' ESCAPE '!'",
+            "files.id ; 1",
+        )
+        .select_expect(
+            "SELECT id FROM files WHERE name NOT LIKE '%!%%' ESCAPE '!' ORDER BY id",
+            "files.id ; 1 ; 2",
+        )
+        .select_expect(
+            "SELECT id FROM files WHERE name ILIKE 'report%' ORDER BY id",
+            "files.id ; 1 ; 2",
+        )
+        .select_expect(
+            "SELECT id FROM files WHERE name NOT ILIKE '%.PDF'",
+            "files.id ; 3",
+        )
+        .select_expect(
+            "SELECT id FROM files WHERE name ~ '^[a-z]+_[0-9]{4}' OR name ~* 'FINAL'",
+            "files.id ; 1 ; 2",
+        )
+        // !~ and !~* negate the regex match, and have the same precedence.
+        .select_expect(
+            "SELECT id FROM files WHERE name !~ 'pdf' AND name !~* 'TXT'",
+            "files.id ; 2",
+        )
+        .select_expect(
+            "SELECT 'abc' !~ 'z', 'abc' !~ 'b', 'ABC' !~* 'b', 'abc'!~*'z' = true, NULL !~ 'a'",
+            " , , , , ; true, false, false, true, NULL",
+        )
+        // Dynamic patterns are compiled per row.
+        .select_expect(
+            "SELECT id, name LIKE pattern ESCAPE '!', name ILIKE pattern FROM files ORDER BY id",
+            "files.id, , ; 1, true, true ; 2, false, true ; 3, true, false",
+        )
+        .select_expect(
+            "SELECT NULL LIKE 'a', 'a' LIKE NULL, 'a' ~ NULL, 'a_c' LIKE 'a\\_c' ESCAPE ''",
+            " , , , ; NULL, NULL, NULL, false",
+        );
+
+    let mut session = engine.session();
+    assert!(session.execute("SELECT 'a' LIKE 'a!' ESCAPE '!'").is_err());
+    assert!(session.execute("SELECT 'a' LIKE 'a' ESCAPE 'ab'").is_err());
+    assert!(session.execute("SELECT 'a' ~ '('").is_err());
+    assert!(session.execute("SELECT 1 LIKE 'a'").is_err());
+}
+
+// ====================== Concatenation and Collations ========================
+
+#[test]
+fn test_concat_and_collations() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE TABLE users (id INT PRIMARY KEY, name TEXT COLLATE nocase, city TEXT)")
+        .execute("INSERT INTO users VALUES (1, 'alice', 'Zürich')")
+        .execute("INSERT INTO users VALUES (2, 'Bob', 'zagreb')")
+        .execute("INSERT INTO users VALUES (3, 'ALICE', 'Aarhus')")
+        .execute("INSERT INTO users VALUES (4, 'carol', 'Ängelholm')")
+        .select_expect(
+            "SELECT id, name || '@' || city FROM users WHERE id = 1",
+            "users.id, ; 1, alice@Zürich",
+        )
+        .select_expect(
+            "SELECT 'a' || NULL, NULL || 1, 1 + 2 || 'x' || true, x'01' || x'02', 'a' || 'b' = 'ab'",
+            " , , , , ; NULL, NULL, 3xtrue, \\x0102, true",
+        )
+        // Comparisons, ORDER BY and GROUP BY use the column collation, unless
+        // overridden with COLLATE.
+        .select_expect(
+            "SELECT id FROM users WHERE name = 'ALICE' ORDER BY id",
+            "users.id ; 1 ; 3",
+        )
+        .select_expect(
+            "SELECT id FROM users WHERE name COLLATE binary = 'ALICE'",
+            "users.id ; 3",
+        )
+        .select_expect(
+            "SELECT id FROM users WHERE name IN ('BOB', 'Carol') ORDER BY id",
+            "users.id ; 2 ; 4",
+        )
+        .select_expect(
+            "SELECT name FROM users ORDER BY name, id",
+            "users.name ; alice ; ALICE ; Bob ; carol",
+        )
+        .select_expect(
+            "SELECT name, count(*) FROM users GROUP BY name ORDER BY name",
+            "users.name, ; alice, 2 ; Bob, 1 ; carol, 1",
+        )
+        // DISTINCT aggregates compare values by collation too, but aggregate
+        // the first value seen.
+        .select_expect(
+            "SELECT count(DISTINCT name), count(DISTINCT name COLLATE binary), \
+                string_agg(DISTINCT name, '/') FROM users",
+            " , , ; 3, 4, alice/Bob/carol",
+        )
+        .select_expect(
+            "SELECT city FROM users ORDER BY city",
+            "users.city ; Aarhus ; Zürich ; zagreb ; Ängelholm",
+        )
+        .select_expect(
+            "SELECT city FROM users ORDER BY city COLLATE unicode",
+            "users.city ; Aarhus ; Ängelholm ; zagreb ; Zürich",
+        )
+        .select_expect(
+            "SELECT id FROM users WHERE city COLLATE nocase BETWEEN 'a' AND 'b'",
+            "users.id ; 3",
+        );
+
+    let mut session = engine.session();
+    assert!(session
+        .execute("SELECT id FROM users WHERE name COLLATE nocase = city COLLATE binary")
+        .is_err());
+    assert!(session
+        .execute("CREATE TABLE bad (id INT PRIMARY KEY COLLATE nocase)")
+        .is_err());
+    assert!(session.execute("SELECT 'a' COLLATE latin1").is_err());
+    assert!(session.execute("SELECT 1 || 2").is_err());
+}
+
+// ================================== Scripts =================================
+
+#[test]
+fn test_scripts() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+    let mut session = engine.session();
+
+    let results = session.execute_script(
+        "-- The schema.
+        CREATE TABLE notes (id INT PRIMARY KEY, body TEXT); /* A block
+            comment /* with a nested comment */ and a ; */
+        INSERT INTO notes VALUES (1, 'a;b -- not a comment');;
+        INSERT INTO notes VALUES (2, 'c') -- no trailing semicolon",
+        false,
+    );
+    assert_eq!(results.len(), 3);
+    assert!(matches!(
+        results[0],
+        Ok(StatementResult::CreateTable { .. })
+    ));
+    assert!(matches!(
+        results[2],
+        Ok(StatementResult::Insert { count: 1, .. })
+    ));
+
+    // Execution stops at the first error, unless continuing. Parse errors skip
+    // to the next statement.
+    let results = session.execute_script(
+        "INSERT INTO notes VALUES (3, 'd'); SELECT FROM; INSERT INTO notes VALUES (4, 'e')",
+        false,
+    );
+    assert_eq!(results.len(), 2);
+    assert!(results[0].is_ok() && results[1].is_err());
+
+    let results = session.execute_script(
+        "SELECT 1 +; INSERT INTO nope VALUES (1); SELECT 1 # 2; INSERT INTO notes VALUES (5, 'f')",
+        true,
+    );
+    assert_eq!(results.len(), 4);
+    assert!(results[..3].iter().all(|result| result.is_err()));
+    assert!(results[3].is_ok());
+
+    let mut results = session.execute_script(
+        "SELECT id, body = 'a;b -- not a comment' FROM notes ORDER BY id",
+        false,
+    );
+    handle(
+        results.remove(0).unwrap(),
+        "notes.id, ; 1, true ; 2, false ; 3, false ; 5, false",
+    );
+
+    assert!(session.execute("SELECT 1 /* unterminated").is_err());
+    assert!(session.execute_script("", false).is_empty());
+}
+
+// =========================== Prepared Statements ============================
+
+#[test]
+fn test_prepared_statements() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+    let mut session = engine.session();
+    session
+        .execute("CREATE TABLE items (id INT PRIMARY KEY, name TEXT, price BIGINT)")
+        .unwrap();
+
+    // Parameter types are inferred from the columns they're inserted into or
+    // compared with, and values are type-checked.
+    let insert = session
+        .prepare("INSERT INTO items VALUES (?, ?, ?)")
+        .unwrap();
+    assert_eq!(
+        insert.parameters(),
+        [
+            Some(DataType::Int),
+            Some(DataType::Text),
+            Some(DataType::BigInt)
+        ]
+    );
+    for (id, name, price) in [(1, "apple", 3), (2, "pear", 5), (3, "plum", 2)] {
+        let values = [Field::Integer(id), Field::from(name), Field::Integer(price)];
+        insert.execute(&values).unwrap();
+    }
+    let values = [Field::Integer(4), Field::Integer(4), Field::Integer(4)];
+    assert!(insert.execute(&values).is_err());
+    assert!(insert.execute(&[Field::Integer(4)]).is_err());
+
+    // Values are bound as constants, so they can't inject SQL.
+    let select = session
+        .prepare("SELECT name FROM items WHERE price > $1 OR name = $2 ORDER BY id")
+        .unwrap();
+    assert_eq!(
+        select.parameters(),
+        [Some(DataType::BigInt), Some(DataType::Text)]
+    );
+    handle(
+        select
+            .execute(&[Field::Integer(2), Field::from("x' OR 1=1 --")])
+            .unwrap(),
+        "items.name ; apple ; pear",
+    );
+    handle(
+        select
+            .execute(&[Field::Integer(4), Field::from("plum")])
+            .unwrap(),
+        "items.name ; pear ; plum",
+    );
+
+    // Untyped parameters accept any value.
+    let select = session.prepare("SELECT ? + 1, $1").unwrap();
+    assert_eq!(select.parameters(), [None]);
+    handle(select.execute(&[Field::Integer(1)]).unwrap(), ", ; 2, 1");
+
+    // The session can still execute statements while a statement is prepared.
+    let update = session
+        .prepare("UPDATE items SET price = ? WHERE id = ?")
+        .unwrap();
+    session.execute("DELETE FROM items WHERE id = 3").unwrap();
+    update
+        .execute(&[Field::Integer(9), Field::Integer(1)])
+        .unwrap();
+
+    // PREPARE, EXECUTE and DEALLOCATE are also available in SQL.
+    session
+        .execute("PREPARE cheap (BIGINT) AS SELECT name FROM items WHERE price < $1")
+        .unwrap();
+    handle(
+        session.execute("EXECUTE cheap (6)").unwrap(),
+        "items.name ; pear",
+    );
+    // String literals are parsed as the parameter type.
+    handle(
+        session.execute("EXECUTE cheap ('6')").unwrap(),
+        "items.name ; pear",
+    );
+    assert_eq!(
+        session
+            .execute("EXECUTE cheap ('six')")
+            .unwrap_err()
+            .to_string(),
+        "invalid input: parameter $1 expects bigint, got 'six'"
+    );
+    assert!(session.execute("PREPARE cheap AS SELECT 1").is_err());
+    assert!(session
+        .execute("PREPARE nested AS EXECUTE cheap (1)")
+        .is_err());
+    session.execute("DEALLOCATE cheap").unwrap();
+    assert!(session.execute("EXECUTE cheap (6)").is_err());
+
+    // Unbound parameters can't be evaluated.
+    assert!(session.execute("SELECT ?").is_err());
+    assert!(session.execute("SELECT $0").is_err());
+    assert_eq!(
+        session
+            .execute("PREPARE page AS SELECT id FROM items LIMIT ?")
+            .unwrap_err()
+            .to_string(),
+        "invalid input: LIMIT can't use bind parameters"
+    );
+
+    // Plans are reused until the schema changes, which only DDL statements
+    // do. Statements are then planned again, and see the new schema. now() is
+    // evaluated at execution time.
+    let version = || engine.begin().unwrap().schema_version();
+    let before = version();
+    let values = [Field::Integer(5), Field::from("fig"), Field::Integer(1)];
+    insert.execute(&values).unwrap();
+    update
+        .execute(&[Field::Integer(8), Field::Integer(5)])
+        .unwrap();
+    assert_eq!(version(), before);
+    session
+        .execute("CREATE TABLE events (id INT PRIMARY KEY, name TEXT, d DATE)")
+        .unwrap();
+    assert!(version() > before);
+    session
+        .execute("INSERT INTO events VALUES (1, 'a', '2024-01-02')")
+        .unwrap();
+    session
+        .execute("PREPARE on_day AS SELECT id FROM events WHERE d = $1")
+        .unwrap();
+    handle(
+        session.execute("EXECUTE on_day ('2024-01-02')").unwrap(),
+        "events.id ; 1",
+    );
+    let select = session
+        .prepare("SELECT * FROM events WHERE id = ?")
+        .unwrap();
+    let insert = session
+        .prepare("INSERT INTO events VALUES (?, ?, ?)")
+        .unwrap();
+    session.execute("DROP TABLE events").unwrap();
+    assert_eq!(
+        select
+            .execute(&[Field::Integer(1)])
+            .unwrap_err()
+            .to_string(),
+        "invalid input: No table with name events exists."
+    );
+    session
+        .execute("CREATE TABLE events (id INT PRIMARY KEY, score BIGINT)")
+        .unwrap();
+    let values = [
+        Field::Integer(2),
+        Field::from("b"),
+        Field::from("2024-01-03"),
+    ];
+    assert_eq!(
+        insert.execute(&values).unwrap_err().to_string(),
+        "invalid input: INSERT has more values than target columns"
+    );
+    session.execute("INSERT INTO events VALUES (2, 7)").unwrap();
+    handle(
+        select.execute(&[Field::Integer(2)]).unwrap(),
+        "events.id, events.score ; 2, 7",
+    );
+    let now = session.prepare("SELECT now()").unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    let after = temporal::now();
+    let StatementResult::Select { mut rows, .. } = now.execute(&[]).unwrap() else {
+        panic!("expected select result");
+    };
+    assert!(rows.remove(0).get_field(0).unwrap() >= Field::Timestamp(after));
+}
+
+// ============================== Error Messages ==============================
+
+#[test]
+fn test_error_messages() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+    let mut session = engine.session();
+    session
+        .execute("CREATE TABLE users (id INT PRIMARY KEY, name TEXT)")
+        .unwrap();
+    let error = |session: &mut Session<'_, _>, sql| session.execute(sql).unwrap_err().to_string();
+    // Parse and lexer errors are located, with a snippet of the input line.
+    assert_eq!(
+        error(&mut session, "SELECT id FROM users WHERE"),
+        [
+            "invalid input: unexpected end of input at line 1, column 27",
+            "  SELECT id FROM users WHERE",
+            "                            ^",
+        ]
+        .join("\n")
+    );
+    assert_eq!(
+        error(&mut session, "SELECT 'abc"),
+        [
+            "invalid input: unexpected end of string literal at line 1, column 8",
+            "  SELECT 'abc",
+            "         ^^^^",
+        ]
+        .join("\n")
+    );
+
+    // Misspelled keywords, columns and tables suggest the closest name.
+    assert_eq!(
+        error(&mut session, "SELCT * FROM users"),
+        [
+            "invalid input: unexpected token selct (did you mean SELECT?) at line 1, column 1",
+            "  SELCT * FROM users",
+            "  ^^^^^",
+        ]
+        .join("\n")
+    );
+    assert_eq!(
+        error(&mut session, "INSERT INTO users VALUSE (1, 'a')"),
+        [
+            "invalid input: expected token VALUES, found valuse (did you mean VALUES?) at line 1, column 19",
+            "  INSERT INTO users VALUSE (1, 'a')",
+            "                    ^^^^^^",
+        ]
+        .join("\n")
+    );
+    assert_eq!(
+        error(&mut session, "SELECT id,\n  usres.name\nFROM users"),
+        [
+            "invalid input: unknown table usres (did you mean users?) at line 2, column 3",
+            "    usres.name",
+            "    ^^^^^^^^^^",
+        ]
+        .join("\n")
+    );
+    assert_eq!(
+        error(&mut session, "SELECT id FROM users WHERE nmae = 'x'"),
+        [
+            "invalid input: unknown column nmae (did you mean name?) at line 1, column 28",
+            "  SELECT id FROM users WHERE nmae = 'x'",
+            "                             ^^^^",
+        ]
+        .join("\n")
+    );
+
+    // Scripts locate errors within the whole script.
+    let results = session.execute_script("SELECT 1;\nSELECT 1 # 2", true);
+    assert_eq!(
+        results[1].as_ref().unwrap_err().to_string(),
+        [
+            "invalid input: unexpected character # at line 2, column 10",
+            "  SELECT 1 # 2",
+            "           ^",
+        ]
+        .join("\n")
+    );
+}
+
+// ================================ Subqueries ================================
+
+#[test]
+fn test_subqueries() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE TABLE depts (id INT PRIMARY KEY, name TEXT)")
+        .execute("CREATE TABLE emps (id INT PRIMARY KEY, name TEXT, dept INT, salary INT)")
+        .execute("INSERT INTO depts VALUES (1, 'eng'), (2, 'ops'), (3, 'hr')")
+        .execute("INSERT INTO emps VALUES (1, 'ann', 1, 100), (2, 'ben', 1, 80)")
+        .execute("INSERT INTO emps VALUES (3, 'cal', 2, 90), (4, 'dan', 0, 70)")
+        // Uncorrelated subqueries are executed once. Scalar subqueries without
+        // rows yield NULL.
+        .select_expect(
+            "SELECT name FROM emps WHERE salary > (SELECT avg(salary) FROM emps) ORDER BY id",
+            "emps.name ; ann ; cal",
+        )
+        .select_expect(
+            "SELECT (SELECT id FROM depts WHERE id > 10), EXISTS (SELECT 1 FROM depts WHERE id = 3), \
+                NOT EXISTS (SELECT 1 FROM depts), 'ANN' COLLATE nocase IN (SELECT name FROM emps)",
+            " , , , ; NULL, true, false, true",
+        )
+        .select_expect(
+            "SELECT name FROM depts WHERE id IN (SELECT dept FROM emps) ORDER BY id",
+            "depts.name ; eng ; ops",
+        )
+        // NOT IN yields NULL rather than true if the subquery returns a NULL.
+        .select_expect(
+            "SELECT name FROM depts WHERE id NOT IN (SELECT dept FROM emps)",
+            "depts.name ; hr",
+        )
+        .select_expect(
+            "SELECT count(*) FROM depts WHERE id NOT IN \
+                (SELECT CASE WHEN dept = 0 THEN NULL ELSE dept END FROM emps)",
+            " ; 0",
+        )
+        // Correlated subqueries can reference columns of enclosing queries.
+        .select_expect(
+            "SELECT name, (SELECT name FROM depts WHERE depts.id = emps.dept) FROM emps ORDER BY id",
+            "emps.name, ; ann, eng ; ben, eng ; cal, ops ; dan, NULL",
+        )
+        .select_expect(
+            "SELECT name FROM depts WHERE (SELECT count(*) FROM emps WHERE dept = depts.id) = 2",
+            "depts.name ; eng",
+        )
+        .select_expect(
+            "SELECT name FROM emps e WHERE salary IN \
+                (SELECT max(salary) FROM emps WHERE dept = e.dept) ORDER BY name",
+            "emps.name ; ann ; cal ; dan",
+        )
+        .select_expect(
+            "SELECT d.name, (SELECT count(*) FROM emps WHERE salary >= \
+                (SELECT min(salary) FROM emps e2 WHERE e2.dept = d.id)) FROM depts d ORDER BY d.id",
+            "depts.name, ; eng, 3 ; ops, 2 ; hr, 0",
+        )
+        // Correlated EXISTS, NOT EXISTS and IN are decorrelated into semi and
+        // anti joins.
+        .select_expect(
+            "SELECT name FROM depts WHERE EXISTS \
+                (SELECT * FROM emps WHERE emps.dept = depts.id AND salary > 85) ORDER BY id",
+            "depts.name ; eng ; ops",
+        )
+        .select_expect(
+            "SELECT name FROM depts WHERE NOT EXISTS (SELECT 1 FROM emps WHERE dept = depts.id)",
+            "depts.name ; hr",
+        )
+        .select_expect(
+            "SELECT name FROM depts WHERE 'ann' IN (SELECT name FROM emps WHERE dept = depts.id)",
+            "depts.name ; eng",
+        )
+        .select_expect(
+            "SELECT count(*) FROM depts WHERE EXISTS (SELECT 1 FROM emps WHERE dept = depts.id) \
+                AND id > 1",
+            " ; 1",
+        )
+        // Derived tables must have an alias, which qualifies their columns.
+        // Unnamed columns are named by position.
+        .select_expect(
+            "SELECT s.dept, s.total FROM \
+                (SELECT dept, sum(salary) AS total FROM emps GROUP BY dept) AS s \
+                WHERE s.total > 85 ORDER BY s.dept",
+            "s.dept, s.total ; 1, 180 ; 2, 90",
+        )
+        .select_expect(
+            "SELECT d.name, s.n FROM depts d \
+                JOIN (SELECT dept, count(*) AS n FROM emps GROUP BY dept) s ON s.dept = d.id \
+                ORDER BY d.name",
+            "depts.name, s.n ; eng, 2 ; ops, 1",
+        )
+        .select_expect(
+            "SELECT * FROM (SELECT 1 + 1, 'x' AS y) t",
+            "t.column1, t.y ; 2, x",
+        )
+        .select_expect(
+            "SELECT count(*) FROM (SELECT * FROM emps WHERE salary > 75) rich",
+            " ; 3",
+        )
+        // DELETE and UPDATE can use subqueries too.
+        .execute("DELETE FROM depts WHERE NOT EXISTS (SELECT 1 FROM emps WHERE emps.dept = depts.id)")
+        .execute("DELETE FROM emps WHERE dept IN (SELECT id FROM depts WHERE name = 'ops')")
+        .execute(
+            "UPDATE emps SET salary = (SELECT id * 10 FROM depts WHERE depts.id = emps.dept) \
+                WHERE dept > 0",
+        )
+        .select_expect(
+            "SELECT (SELECT count(*) FROM depts), name, salary FROM emps ORDER BY id",
+            " , emps.name, emps.salary ; 2, ann, 10 ; 2, ben, 10 ; 2, dan, 70",
+        );
+
+    let mut session = engine.session();
+    let error = |session: &mut Session<'_, _>, sql| session.execute(sql).unwrap_err().to_string();
+    assert_eq!(
+        error(&mut session, "SELECT (SELECT id, name FROM depts)"),
+        "invalid input: subquery must return 1 column, got 2"
+    );
+    assert_eq!(
+        error(&mut session, "SELECT (SELECT id FROM depts)"),
+        "invalid input: subquery returned more than one row"
+    );
+    assert!(error(&mut session, "SELECT * FROM (SELECT id FROM depts)")
+        .starts_with("invalid input: subquery in FROM must have an alias"));
+    assert_eq!(
+        error(&mut session, "SELECT * FROM (SELECT id, id FROM depts) s"),
+        "invalid input: duplicate column id in subquery s"
+    );
+    assert_eq!(
+        error(&mut session, "SELECT 1 LIMIT (SELECT 1)"),
+        "invalid input: subqueries are not allowed in constant expressions"
+    );
+    assert!(session
+        .execute("SELECT * FROM depts ORDER BY (SELECT count(*) FROM emps WHERE dept = depts.id)")
+        .is_err());
+    assert!(session
+        .execute("SELECT * FROM depts d, (SELECT * FROM emps WHERE dept = d.id) e")
+        .is_err());
+}
+
+// ================================ CTEs ================================
+
+#[test]
+fn test_ctes() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE TABLE emps (id INT PRIMARY KEY, name TEXT, manager INT, salary INT)")
+        .execute("INSERT INTO emps VALUES (1, 'ann', 0, 100), (2, 'ben', 1, 80)")
+        .execute("INSERT INTO emps VALUES (3, 'cal', 1, 90), (4, 'dan', 3, 70), (5, 'eve', 4, 60)")
+        .execute("CREATE TABLE edges (src INT PRIMARY KEY, dst INT)")
+        .execute("INSERT INTO edges VALUES (1, 2), (2, 3), (3, 1)")
+        // CTEs are referenced like tables, and can reference earlier CTEs.
+        .select_expect(
+            "WITH rich AS (SELECT name, salary FROM emps WHERE salary > 75) \
+                SELECT * FROM rich ORDER BY salary",
+            "rich.name, rich.salary ; ben, 80 ; cal, 90 ; ann, 100",
+        )
+        .select_expect(
+            "WITH a (n, s) AS (SELECT id, salary FROM emps), b AS (SELECT n FROM a WHERE s < 75) \
+                SELECT b.n, x.n FROM b, b AS x WHERE x.n > b.n",
+            "b.n, x.n ; 4, 5",
+        )
+        .select_expect(
+            "WITH totals AS (SELECT manager, count(*) AS reports FROM emps GROUP BY manager) \
+                SELECT e.name, t.reports FROM emps e JOIN totals t ON t.manager = e.id \
+                ORDER BY e.name",
+            "emps.name, t.reports ; ann, 2 ; cal, 1 ; dan, 1",
+        )
+        // CTEs shadow tables, and are visible in subqueries.
+        .select_expect(
+            "WITH emps AS (SELECT 1 AS id) SELECT * FROM emps",
+            "emps.id ; 1",
+        )
+        .select_expect(
+            "WITH m AS (SELECT manager FROM emps) \
+                SELECT count(*) FROM emps WHERE id IN (SELECT manager FROM m)",
+            " ; 3",
+        )
+        .select_expect(
+            "SELECT * FROM (WITH t AS (SELECT 2 AS x) SELECT x * 2 AS y FROM t) s",
+            "s.y ; 4",
+        )
+        // Recursive CTEs iterate until the recursive term emits no rows.
+        .select_expect(
+            "WITH RECURSIVE t (n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t WHERE n < 100) \
+                SELECT count(*), sum(n) FROM t",
+            " , ; 100, 5050",
+        )
+        .select_expect(
+            "WITH RECURSIVE chain (id, name, depth) AS ( \
+                SELECT id, name, 0 FROM emps WHERE manager = 0 \
+                UNION ALL \
+                SELECT e.id, e.name, c.depth + 1 FROM emps e JOIN chain c ON e.manager = c.id) \
+                SELECT name, depth FROM chain ORDER BY depth, name",
+            "chain.name, chain.depth ; ann, 0 ; ben, 1 ; cal, 1 ; dan, 2 ; eve, 3",
+        )
+        // UNION discards duplicate rows, which terminates cyclic traversals.
+        .select_expect(
+            "WITH RECURSIVE reach (node) AS (SELECT 1 UNION \
+                SELECT edges.dst FROM edges JOIN reach ON edges.src = reach.node) \
+                SELECT count(*) FROM reach",
+            " ; 3",
+        );
+
+    let mut session = engine.session();
+    let error = |session: &mut Session<'_, _>, sql| session.execute(sql).unwrap_err().to_string();
+    assert_eq!(
+        error(
+            &mut session,
+            "WITH RECURSIVE reach (node) AS (SELECT 1 UNION ALL \
+                SELECT edges.dst FROM edges JOIN reach ON edges.src = reach.node) \
+                SELECT count(*) FROM reach"
+        ),
+        "invalid input: recursive query reach exceeded 1000 iterations"
+    );
+    session.set_recursion_limit(10);
+    assert_eq!(
+        error(
+            &mut session,
+            "WITH RECURSIVE t (n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t WHERE n < 20) \
+                SELECT * FROM t"
+        ),
+        "invalid input: recursive query t exceeded 10 iterations"
+    );
+    assert_eq!(
+        error(&mut session, "WITH t (a, b) AS (SELECT 1) SELECT * FROM t"),
+        "invalid input: CTE t must have 1 column names, got 2"
+    );
+    assert_eq!(
+        error(
+            &mut session,
+            "WITH t AS (SELECT 1), t AS (SELECT 2) SELECT * FROM t"
+        ),
+        "invalid input: duplicate CTE name t"
+    );
+    assert_eq!(
+        error(
+            &mut session,
+            "WITH RECURSIVE t (n) AS (SELECT 1 UNION SELECT n, n FROM t) SELECT * FROM t"
+        ),
+        "invalid input: UNION queries must have the same number of columns, got 1 and 2"
+    );
+    assert_eq!(
+        error(
+            &mut session,
+            "WITH RECURSIVE t (n) AS (SELECT 1 UNION ALL \
+                SELECT n + 1 FROM t WHERE n < (SELECT max(n) FROM t)) SELECT * FROM t"
+        ),
+        "invalid input: recursive reference to t can't be in a subquery"
+    );
+    // Without RECURSIVE, a CTE can't reference itself.
+    assert!(session
+        .execute("WITH t AS (SELECT 1 FROM t) SELECT * FROM t")
+        .is_err());
+    assert!(session
+        .execute("WITH t (n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t) SELECT * FROM t")
+        .is_err());
+}
+
+// ============================== Set Operations ==============================
+
+#[test]
+fn test_set_operations() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE TABLE a (id INT PRIMARY KEY, n INT, s TEXT)")
+        .execute("INSERT INTO a VALUES (1, 1, 'x'), (2, 2, 'y'), (3, 2, 'y'), (4, 3, 'z')")
+        .execute("CREATE TABLE b (id INT PRIMARY KEY, n INT, s TEXT)")
+        .execute("INSERT INTO b VALUES (1, 2, 'y'), (2, 3, 'z'), (3, 3, 'z'), (4, 4, 'w')")
+        // UNION discards duplicates, UNION ALL keeps them. Columns are named
+        // by the left query.
+        .select_expect(
+            "SELECT n, s FROM a UNION SELECT n, s FROM b ORDER BY n",
+            "a.n, a.s ; 1, x ; 2, y ; 3, z ; 4, w",
+        )
+        .select_expect(
+            "SELECT count(*) FROM (SELECT n FROM a UNION ALL SELECT n FROM b) u",
+            " ; 8",
+        )
+        .select_expect(
+            "SELECT count(*) FROM (SELECT n FROM a UNION SELECT n FROM b) u",
+            " ; 4",
+        )
+        // INTERSECT ALL emits a row as many times as it's in both sides.
+        .select_expect("SELECT n FROM a INTERSECT SELECT n FROM b ORDER BY n", "a.n ; 2 ; 3")
+        .select_expect(
+            "SELECT count(*), sum(n) FROM (SELECT n FROM b INTERSECT ALL                 SELECT n FROM a UNION ALL SELECT 3) i",
+            " , ; 3, 8",
+        )
+        // EXCEPT ALL discards a left row for each right row.
+        .select_expect(
+            "SELECT n FROM a EXCEPT SELECT n FROM b ORDER BY n",
+            "a.n ; 1",
+        )
+        .select_expect(
+            "SELECT n FROM a EXCEPT ALL SELECT n FROM b WHERE n < 3 ORDER BY n",
+            "a.n ; 1 ; 2 ; 3",
+        )
+        .select_expect(
+            "SELECT count(*) FROM (SELECT n FROM a EXCEPT ALL SELECT n FROM b WHERE n < 3) e",
+            " ; 3",
+        )
+        // INTERSECT binds tighter than UNION and EXCEPT, which are evaluated
+        // left to right. Parentheses override this.
+        .select_expect(
+            "SELECT n FROM a UNION SELECT 5 INTERSECT SELECT 4 ORDER BY n",
+            "a.n ; 1 ; 2 ; 3",
+        )
+        .select_expect(
+            "SELECT count(*) FROM (SELECT n FROM a EXCEPT SELECT 1 EXCEPT SELECT 2) e",
+            " ; 1",
+        )
+        .select_expect(
+            "SELECT count(*) FROM (SELECT n FROM a EXCEPT (SELECT 1 EXCEPT SELECT 1)) e",
+            " ; 3",
+        )
+        // ORDER BY and LIMIT apply to the entire result, unless parenthesized.
+        .select_expect(
+            "(SELECT n FROM a ORDER BY n DESC LIMIT 1) UNION ALL SELECT n FROM b                 ORDER BY n DESC LIMIT 2",
+            "a.n ; 4 ; 3",
+        )
+        .select_expect(
+            "SELECT id FROM a WHERE n IN (SELECT 1 UNION SELECT n FROM b WHERE s = 'z')                 ORDER BY id",
+            "a.id ; 1 ; 4",
+        )
+        // Numeric columns are widened to their common type, so equal values
+        // of different types are duplicates.
+        .execute("CREATE TABLE c (id INT PRIMARY KEY, big BIGINT, d DECIMAL(4, 1))")
+        .execute("INSERT INTO c VALUES (1, 3, 2.5), (2, 5, 1.0)")
+        .select_expect(
+            "SELECT n FROM a UNION SELECT big FROM c ORDER BY n",
+            "a.n ; 1 ; 2 ; 3 ; 5",
+        )
+        .select_expect(
+            "SELECT n FROM a UNION SELECT CAST(1 AS BIGINT) ORDER BY n",
+            "a.n ; 1 ; 2 ; 3",
+        )
+        .select_expect(
+            "SELECT n FROM a INTERSECT SELECT CAST(2 AS BIGINT)",
+            "a.n ; 2",
+        )
+        .select_expect(
+            "SELECT d FROM c UNION SELECT n FROM a ORDER BY d",
+            "c.d ; 1.0 ; 2.0 ; 2.5 ; 3.0",
+        );
+
+    let mut session = engine.session();
+    let error = |session: &mut Session<'_, _>, sql| session.execute(sql).unwrap_err().to_string();
+    assert_eq!(
+        error(&mut session, "SELECT n FROM a UNION SELECT 'one'"),
+        "invalid input: UNION types int and varchar can't be matched for column 1"
+    );
+    assert_eq!(
+        error(&mut session, "SELECT n FROM a UNION SELECT n, s FROM b"),
+        "invalid input: UNION queries must have the same number of columns, got 1 and 2"
+    );
+    assert_eq!(
+        error(&mut session, "SELECT n FROM a INTERSECT SELECT s FROM b"),
+        "invalid input: INTERSECT types int and varchar can't be matched for column 1"
+    );
+    assert!(error(
+        &mut session,
+        "(SELECT n FROM a UNION SELECT n FROM b ORDER BY 1) ORDER BY 1"
+    )
+    .starts_with("invalid input: multiple ORDER BY, LIMIT or OFFSET clauses at line 1"));
+}
+
+// ================================= DISTINCT =================================
+
+#[test]
+fn test_distinct() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE TABLE emps (id INT PRIMARY KEY, dept TEXT COLLATE NOCASE, name TEXT, salary INT)")
+        .execute("INSERT INTO emps VALUES (1, 'eng', 'ann', 100), (2, 'ENG', 'ben', 120)")
+        .execute("INSERT INTO emps VALUES (3, 'ops', 'cal', 90), (4, 'ops', 'dan', 90), (5, 'hr', 'eve', 70)")
+        // SELECT DISTINCT discards duplicate rows before LIMIT, comparing
+        // values by their collation.
+        .select_expect(
+            "SELECT DISTINCT salary FROM emps ORDER BY salary DESC LIMIT 3",
+            "emps.salary ; 120 ; 100 ; 90",
+        )
+        .select_expect(
+            "SELECT count(*) FROM (SELECT DISTINCT salary FROM emps) d",
+            " ; 4",
+        )
+        .select_expect(
+            "SELECT count(*) FROM (SELECT DISTINCT dept FROM emps) d",
+            " ; 3",
+        )
+        .select_expect(
+            "SELECT count(*) FROM (SELECT DISTINCT * FROM emps) d",
+            " ; 5",
+        )
+        // DISTINCT ON emits the first row of each group in ORDER BY order,
+        // and can use columns that aren't selected.
+        .select_expect(
+            "SELECT DISTINCT ON (dept) dept, name FROM emps ORDER BY dept, salary DESC, name",
+            "emps.dept, emps.name ; ENG, ben ; hr, eve ; ops, cal",
+        )
+        .select_expect(
+            "SELECT DISTINCT ON (dept) name FROM emps ORDER BY dept, salary, name",
+            "emps.name ; ann ; eve ; cal",
+        )
+        .select_expect(
+            "SELECT count(*) FROM (SELECT DISTINCT ON (salary > 95) id FROM emps) d",
+            " ; 2",
+        )
+        // Aggregates can aggregate only distinct values, separately for each
+        // group.
+        .select_expect(
+            "SELECT count(DISTINCT salary), sum(DISTINCT salary), count(salary), \
+                avg(DISTINCT salary) FROM emps",
+            " , , , ; 4, 380, 5, 95",
+        )
+        .select_expect(
+            "SELECT dept, count(DISTINCT salary), sum(salary) FROM emps GROUP BY dept ORDER BY dept",
+            "emps.dept, , ; eng, 2, 220 ; hr, 1, 70 ; ops, 1, 180",
+        )
+        .select_expect(
+            "SELECT count(DISTINCT salary) FROM emps HAVING count(DISTINCT salary) > count(salary) - 2",
+            " ; 4",
+        );
+
+    let mut session = engine.session();
+    let error = |session: &mut Session<'_, _>, sql| session.execute(sql).unwrap_err().to_string();
+    assert_eq!(
+        error(
+            &mut session,
+            "SELECT DISTINCT ON (dept) name FROM emps ORDER BY salary"
+        ),
+        "invalid input: DISTINCT ON expressions must match initial ORDER BY expressions"
+    );
+    assert_eq!(
+        error(&mut session, "SELECT sqrt(DISTINCT salary) FROM emps"),
+        "invalid input: DISTINCT is not allowed in non-aggregate function sqrt"
+    );
+    assert_eq!(
+        error(&mut session, "SELECT count(DISTINCT *) FROM emps"),
+        "invalid input: count(DISTINCT *) is not supported"
+    );
+}
+
+// ================================== INSERT ==================================
+
+#[test]
+fn test_insert_columns_and_select() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .execute(
+            "CREATE TABLE items (id INT PRIMARY KEY, name TEXT DEFAULT 'none', \
+                qty INT DEFAULT 1, price INT DEFAULT 0)",
+        )
+        .execute("CREATE TABLE archive (id INT PRIMARY KEY, name TEXT, total INT DEFAULT -1)")
+        // Omitted columns use their default, and listed columns can be in any
+        // order. Without a column list, trailing columns can be omitted.
+        .execute("INSERT INTO items (id, qty) VALUES (1, 5)")
+        .execute("INSERT INTO items (price, id) VALUES (20, 2), (30, 3)")
+        .execute("INSERT INTO items VALUES (4, 'four')")
+        .select_expect(
+            "SELECT * FROM items ORDER BY id",
+            "items.id, items.name, items.qty, items.price ; \
+                1, none, 5, 0 ; 2, none, 1, 20 ; 3, none, 1, 30 ; 4, four, 1, 0",
+        )
+        // INSERT ... SELECT inserts the rows of any query.
+        .execute("INSERT INTO archive (name, id) SELECT name, id FROM items WHERE qty > 1")
+        .execute("INSERT INTO archive SELECT id + 10, name, qty * price FROM items WHERE price > 0")
+        .execute("INSERT INTO archive (id, name) SELECT 20, 'x' UNION ALL SELECT 21, 'y'")
+        .execute(
+            "INSERT INTO archive (id, name) WITH big AS (SELECT * FROM items WHERE id > 3) \
+                SELECT id + 100, name FROM big",
+        )
+        .select_expect(
+            "SELECT * FROM archive ORDER BY id",
+            "archive.id, archive.name, archive.total ; 1, none, -1 ; 12, none, 20 ; \
+                13, none, 30 ; 20, x, -1 ; 21, y, -1 ; 104, four, -1",
+        )
+        .select_expect("SELECT count(*) FROM archive", " ; 6")
+        // A table can be loaded from itself.
+        .execute("INSERT INTO items (id, qty) SELECT id + 10, qty FROM items")
+        .select_expect("SELECT count(*), sum(qty) FROM items", " , ; 8, 16");
+
+    let mut session = engine.session();
+    session
+        .execute("CREATE TABLE strict (id INT PRIMARY KEY, v INT)")
+        .unwrap();
+    let error = |session: &mut Session<'_, _>, sql| session.execute(sql).unwrap_err().to_string();
+    assert_eq!(
+        error(&mut session, "INSERT INTO items (id, id) VALUES (1, 1)"),
+        "invalid input: column id given multiple times"
+    );
+    assert_eq!(
+        error(&mut session, "INSERT INTO items (id, qty) VALUES (9)"),
+        "invalid input: INSERT has more target columns than values"
+    );
+    assert_eq!(
+        error(&mut session, "INSERT INTO items (id) SELECT 9, 1"),
+        "invalid input: INSERT has more values than target columns"
+    );
+    assert_eq!(
+        error(&mut session, "INSERT INTO strict (id) VALUES (1)"),
+        "invalid input: column v has no default value"
+    );
+
+    // Omitted nullable columns are NULL, which is stored as such for both
+    // fixed-width and variable-length columns, rather than as e.g. 0, '' or
+    // 1970-01-01. UPDATE and upserts can write NULLs too.
+    let mut runner = SqlStudentRunner::new(&engine);
+    runner
+        .execute(
+            "CREATE TABLE nulls (id INT PRIMARY KEY, a INT DEFAULT 7, b TEXT NULL, \
+                c INT NULL, d DATE NULL, e BYTEA NULL)",
+        )
+        .execute("INSERT INTO nulls (id) VALUES (1)")
+        .execute("INSERT INTO nulls (id, b, c, d, e) VALUES (2, '', 0, '1970-01-01', x'')")
+        .select_expect(
+            "SELECT * FROM nulls ORDER BY id",
+            "nulls.id, nulls.a, nulls.b, nulls.c, nulls.d, nulls.e ; \
+                1, 7, NULL, NULL, NULL, NULL ; 2, 7, , 0, 1970-01-01, \\x",
+        )
+        .select_expect(
+            "SELECT id FROM nulls WHERE c IS NULL OR b IS NULL",
+            "nulls.id ; 1",
+        )
+        .execute("UPDATE nulls SET b = 'x', c = NULL, d = NULL WHERE id = 2")
+        .execute(
+            "INSERT INTO nulls (id, b) VALUES (1, 'y') \
+                ON CONFLICT (id) DO UPDATE SET a = NULL, b = excluded.b",
+        )
+        .select_expect(
+            "SELECT * FROM nulls ORDER BY id",
+            "nulls.id, nulls.a, nulls.b, nulls.c, nulls.d, nulls.e ; \
+                1, NULL, y, NULL, NULL, NULL ; 2, 7, x, NULL, NULL, \\x",
+        );
+}
+
+// =============================== ON CONFLICT ================================
+
+#[test]
+fn test_upsert() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE TABLE stock (id INT PRIMARY KEY, sku TEXT UNIQUE, qty INT, note TEXT DEFAULT 'new')")
+        .execute("INSERT INTO stock VALUES (1, 'a', 10, 'x'), (2, 'b', 20, 'x')")
+        // DO NOTHING skips conflicting rows, on any constraint without a target.
+        .execute("INSERT INTO stock VALUES (1, 'z', 0, 'y'), (3, 'c', 30, 'y') ON CONFLICT DO NOTHING")
+        .execute("INSERT INTO stock VALUES (4, 'b', 0, 'y') ON CONFLICT DO NOTHING")
+        .execute("INSERT INTO stock VALUES (4, 'd', 40, 'y'), (5, 'd', 0, 'y') ON CONFLICT (sku) DO NOTHING")
+        .select_expect(
+            "SELECT * FROM stock ORDER BY id",
+            "stock.id, stock.sku, stock.qty, stock.note ; 1, a, 10, x ; 2, b, 20, x ; \
+                3, c, 30, y ; 4, d, 40, y",
+        )
+        .select_expect("SELECT count(*) FROM stock", " ; 4")
+        // DO UPDATE updates the existing row, referencing the inserted row
+        // as excluded. Unqualified names refer to the existing row.
+        .execute(
+            "INSERT INTO stock (id, sku, qty) VALUES (1, 'a', 5), (5, 'e', 50) \
+                ON CONFLICT (id) DO UPDATE SET qty = stock.qty + excluded.qty, note = DEFAULT",
+        )
+        .execute(
+            "INSERT INTO stock VALUES (9, 'b', 7, 'z') \
+                ON CONFLICT (sku) DO UPDATE SET qty = qty * excluded.qty, note = excluded.note",
+        )
+        // The WHERE clause skips the update unless it holds.
+        .execute(
+            "INSERT INTO stock VALUES (3, 'c', 1, 'w'), (4, 'd', 1, 'w') \
+                ON CONFLICT (id) DO UPDATE SET note = excluded.note WHERE qty > 35",
+        )
+        .select_expect(
+            "SELECT * FROM stock ORDER BY id",
+            "stock.id, stock.sku, stock.qty, stock.note ; 1, a, 15, new ; 2, b, 140, z ; \
+                3, c, 30, y ; 4, d, 40, w ; 5, e, 50, new",
+        )
+        .select_expect("SELECT count(*) FROM stock", " ; 5")
+        // INSERT ... SELECT can upsert too.
+        .execute(
+            "INSERT INTO stock SELECT id, sku, 1, 'q' FROM stock WHERE id < 3 \
+                ON CONFLICT (id) DO UPDATE SET qty = excluded.qty",
+        )
+        .select_expect("SELECT sum(qty) FROM stock", " ; 122");
+
+    let mut session = engine.session();
+    session
+        .execute("CREATE TABLE plain (id INT, v INT)")
+        .unwrap();
+    let error = |session: &mut Session<'_, _>, sql| session.execute(sql).unwrap_err().to_string();
+    assert_eq!(
+        error(
+            &mut session,
+            "INSERT INTO plain VALUES (1, 1) ON CONFLICT DO NOTHING"
+        ),
+        "invalid input: table plain has no primary key or unique constraint"
+    );
+    assert_eq!(
+        error(
+            &mut session,
+            "INSERT INTO stock VALUES (1, 'a', 1, 'x') ON CONFLICT (qty) DO NOTHING"
+        ),
+        "invalid input: no primary key or unique constraint matches ON CONFLICT columns"
+    );
+    assert_eq!(
+        error(
+            &mut session,
+            "INSERT INTO stock VALUES (1, 'a', 1, 'x') ON CONFLICT DO UPDATE SET qty = 1"
+        ),
+        "invalid input: ON CONFLICT DO UPDATE requires a conflict target"
+    );
+    assert_eq!(
+        error(
+            &mut session,
+            "INSERT INTO stock VALUES (1, 'a', 1, 'x'), (1, 'a', 2, 'x') \
+                ON CONFLICT (id) DO UPDATE SET qty = excluded.qty"
+        ),
+        "invalid input: ON CONFLICT DO UPDATE can't affect a row twice"
+    );
+    assert_eq!(
+        error(
+            &mut session,
+            "INSERT INTO stock VALUES (1, 'a', 1, 'x') ON CONFLICT (id) DO UPDATE SET sku = 'b'"
+        ),
+        "invalid input: ON CONFLICT DO UPDATE conflicts with another row"
+    );
+    assert_eq!(
+        error(
+            &mut session,
+            "INSERT INTO stock VALUES (7, 'a', 1, 'x') ON CONFLICT (id) DO NOTHING"
+        ),
+        "invalid input: INSERT conflicts with an existing row"
+    );
+    assert!(error(
+        &mut session,
+        "INSERT INTO stock VALUES (1, 'a', 1, 'x') ON CONFLICT (id) DO UPDATE SET qty = other.qty"
+    )
+    .starts_with("invalid input: unknown table other"));
+}
+
+// ================================ RETURNING =================================
+
+#[test]
+fn test_returning() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE TABLE items (id INT PRIMARY KEY, name TEXT DEFAULT 'none', qty INT)")
+        .execute("INSERT INTO items VALUES (1, 'a', 10)")
+        // INSERT returns the inserted rows, including defaults.
+        .select_expect(
+            "INSERT INTO items (id, qty) VALUES (2, 20), (3, 30) RETURNING *",
+            "items.id, items.name, items.qty ; 2, none, 20 ; 3, none, 30",
+        )
+        .select_expect(
+            "INSERT INTO items SELECT id + 10, name, qty FROM items WHERE id = 1 \
+                RETURNING id, qty * 2 AS twice, upper(name)",
+            "items.id, twice, ; 11, 20, A",
+        )
+        // UPDATE returns the updated rows.
+        .select_expect(
+            "UPDATE items SET qty = qty + 1 WHERE id < 3 RETURNING id, qty",
+            "items.id, items.qty ; 1, 11 ; 2, 21",
+        )
+        // DELETE returns the deleted rows. Uncorrelated subqueries are
+        // evaluated before the table is modified.
+        .select_expect(
+            "DELETE FROM items WHERE id > 10 RETURNING name, (SELECT count(*) FROM items) AS n",
+            "items.name, n ; a, 4",
+        )
+        .select_expect("SELECT count(*) FROM items", " ; 3")
+        // Upserts return both inserted and updated rows.
+        .select_expect(
+            "INSERT INTO items VALUES (3, 'c', 1), (4, 'd', 40) \
+                ON CONFLICT (id) DO UPDATE SET name = excluded.name RETURNING id, name, qty",
+            "items.id, items.name, items.qty ; 3, c, 30 ; 4, d, 40",
+        )
+        .select_expect(
+            "INSERT INTO items VALUES (4, 'x', 0), (5, 'e', 50) ON CONFLICT DO NOTHING \
+                RETURNING id",
+            "items.id ; 5",
+        )
+        // Correlated subqueries are evaluated per row.
+        .select_expect(
+            "UPDATE items SET qty = 0 WHERE id = 5 \
+                RETURNING id, (SELECT max(i.qty) FROM items i WHERE i.id < items.id) AS prev",
+            "items.id, prev ; 5, 40",
+        );
+
+    let mut session = engine.session();
+    let error = |session: &mut Session<'_, _>, sql| session.execute(sql).unwrap_err().to_string();
+    assert!(error(&mut session, "DELETE FROM items RETURNING missing")
+        .starts_with("invalid input: unknown column missing"));
+    assert!(error(
+        &mut session,
+        "INSERT INTO items VALUES (9, 'z', 9) RETURNING * AS x"
+    )
+    .starts_with("invalid input: can't alias *"));
+}
+
+// ============================= Window functions =============================
+
+#[test]
+fn test_window_functions() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE TABLE emp (id INT PRIMARY KEY, dept TEXT, salary INT)")
+        .execute(
+            "INSERT INTO emp VALUES (1, 'a', 100), (2, 'a', 200), (3, 'a', 200), \
+                (4, 'b', 50), (5, 'b', 300), (6, 'c', 10)",
+        )
+        // Ranking functions, where peers share a rank.
+        .select_expect(
+            "SELECT id, row_number() OVER (PARTITION BY dept ORDER BY salary DESC) AS rn, \
+                rank() OVER (PARTITION BY dept ORDER BY salary DESC) AS r, \
+                dense_rank() OVER (ORDER BY salary DESC) AS dr FROM emp ORDER BY id",
+            "emp.id, rn, r, dr ; 1, 3, 3, 3 ; 2, 1, 1, 2 ; 3, 2, 1, 2 ; 4, 2, 2, 4 ; \
+                5, 1, 1, 1 ; 6, 1, 1, 5",
+        )
+        // lag() and lead(), with offsets and defaults.
+        .select_expect(
+            "SELECT id, lag(salary) OVER (ORDER BY id) AS prev, \
+                lead(salary, 2, 0) OVER (ORDER BY id) AS next2 FROM emp ORDER BY id",
+            "emp.id, prev, next2 ; 1, NULL, 200 ; 2, 100, 50 ; 3, 200, 300 ; 4, 200, 10 ; \
+                5, 50, 0 ; 6, 300, 0",
+        )
+        // Aggregates default to a frame from the partition start to the
+        // current row and its peers, or the whole partition without ORDER BY.
+        .select_expect(
+            "SELECT id, sum(salary) OVER (ORDER BY salary) AS running, \
+                count(*) OVER () AS total FROM emp ORDER BY id",
+            "emp.id, running, total ; 1, 160, 6 ; 2, 560, 6 ; 3, 560, 6 ; 4, 60, 6 ; \
+                5, 860, 6 ; 6, 10, 6",
+        )
+        // ROWS frames count rows.
+        .select_expect(
+            "SELECT id, sum(salary) OVER (ORDER BY id ROWS BETWEEN 1 PRECEDING AND 1 FOLLOWING) \
+                AS s FROM emp ORDER BY id",
+            "emp.id, s ; 1, 300 ; 2, 500 ; 3, 450 ; 4, 550 ; 5, 360 ; 6, 310",
+        )
+        // RANGE frames use distances between ORDER BY values.
+        .select_expect(
+            "SELECT id, count(*) OVER (ORDER BY salary RANGE BETWEEN 50 PRECEDING AND \
+                100 FOLLOWING) AS c, count(*) OVER (ORDER BY salary DESC RANGE 50 PRECEDING) \
+                AS d FROM emp ORDER BY id",
+            "emp.id, c, d ; 1, 4, 1 ; 2, 3, 2 ; 3, 3, 2 ; 4, 3, 2 ; 5, 1, 1 ; 6, 3, 2",
+        )
+        // Offsets beyond the order type's range extend to the partition edge.
+        .select_expect(
+            "SELECT id, sum(salary) OVER (ORDER BY salary RANGE BETWEEN 2147483647 PRECEDING \
+                AND 2147483647 FOLLOWING) AS s, count(*) OVER (ORDER BY salary DESC \
+                RANGE BETWEEN CURRENT ROW AND 2147483647 FOLLOWING) AS c \
+                FROM emp WHERE id < 3 ORDER BY id",
+            "emp.id, s, c ; 1, 300, 1 ; 2, 300, 2",
+        )
+        .select_expect(
+            "SELECT id, first_value(id) OVER (PARTITION BY dept ORDER BY salary) AS f, \
+                last_value(id) OVER (PARTITION BY dept ORDER BY salary \
+                ROWS BETWEEN CURRENT ROW AND UNBOUNDED FOLLOWING) AS l FROM emp ORDER BY id",
+            "emp.id, f, l ; 1, 1, 3 ; 2, 1, 3 ; 3, 1, 3 ; 4, 4, 5 ; 5, 4, 5 ; 6, 6, 6",
+        )
+        // Window functions are computed after GROUP BY and HAVING, and can use
+        // aggregates.
+        .select_expect(
+            "SELECT dept, sum(salary) AS total, rank() OVER (ORDER BY sum(salary) DESC) AS r, \
+                sum(sum(salary)) OVER () AS grand FROM emp GROUP BY dept \
+                HAVING count(*) > 1 ORDER BY dept",
+            "emp.dept, total, r, grand ; a, 500, 1, 850 ; b, 350, 2, 850",
+        )
+        // Window functions can be used in ORDER BY without being selected.
+        .select_expect(
+            "SELECT id FROM emp ORDER BY row_number() OVER (ORDER BY salary DESC, id DESC) \
+                LIMIT 3",
+            "emp.id ; 5 ; 3 ; 2",
+        )
+        .select_expect(
+            "SELECT * FROM emp ORDER BY rank() OVER (ORDER BY salary) LIMIT 1",
+            "emp.id, emp.dept, emp.salary ; 6, c, 10",
+        )
+        // Top row per partition, via a derived table.
+        .select_expect(
+            "SELECT id FROM (SELECT id, row_number() OVER (PARTITION BY dept \
+                ORDER BY salary DESC) AS rn FROM emp) t WHERE rn = 1 ORDER BY id",
+            "t.id ; 2 ; 5 ; 6",
+        );
+
+    // The frame keywords can also be used as column names.
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE TABLE frames (id INT PRIMARY KEY, range INT, rows INT, current INT)")
+        .execute("INSERT INTO frames VALUES (1, 10, 1, 5), (2, 20, 2, 6)")
+        .select_expect(
+            "SELECT current, sum(rows) OVER (ORDER BY range ROWS BETWEEN CURRENT ROW \
+                AND 1 FOLLOWING) AS s FROM frames ORDER BY frames.range",
+            "frames.current, s ; 5, 3 ; 6, 2",
+        );
+
+    let mut session = engine.session();
+    let error = |session: &mut Session<'_, _>, sql| session.execute(sql).unwrap_err().to_string();
+    assert!(error(
+        &mut session,
+        "SELECT id FROM emp WHERE row_number() OVER () > 1"
+    )
+    .starts_with(
+        "invalid input: window function row_number can only be used in SELECT and ORDER BY"
+    ));
+    assert!(error(&mut session, "SELECT rank(id) OVER () FROM emp")
+        .starts_with("invalid input: rank takes no arguments"));
+    assert!(error(&mut session, "SELECT foo(id) OVER () FROM emp")
+        .starts_with("invalid input: unknown window function foo"));
+    assert!(error(
+        &mut session,
+        "SELECT rank() OVER (ORDER BY rank() OVER ()) FROM emp"
+    )
+    .starts_with("invalid input: window functions can't be nested"));
+    assert!(
+        error(&mut session, "SELECT count(DISTINCT id) OVER () FROM emp")
+            .starts_with("invalid input: DISTINCT is not supported in window functions")
+    );
+    assert!(error(
+        &mut session,
+        "SELECT sum(id) OVER (ORDER BY dept, id RANGE 1 PRECEDING) FROM emp"
+    )
+    .starts_with("invalid input: RANGE with offset requires exactly one ORDER BY column"));
+    // RANGE offsets need a numeric or temporal ORDER BY value, which is
+    // checked when planning, e.g. when preparing a statement.
+    assert_eq!(
+        session
+            .prepare("SELECT sum(id) OVER (ORDER BY dept RANGE 1 PRECEDING) FROM emp")
+            .err()
+            .unwrap()
+            .to_string(),
+        "invalid input: RANGE with offset requires a numeric or temporal ORDER BY value, got varchar"
+    );
+    assert!(error(
+        &mut session,
+        "SELECT sum(id) OVER (ORDER BY id > 2 RANGE BETWEEN CURRENT ROW AND 1 FOLLOWING) FROM emp"
+    )
+    .starts_with(
+        "invalid input: RANGE with offset requires a numeric or temporal ORDER BY value, got bool"
+    ));
+    session
+        .execute("SELECT sum(id) OVER (ORDER BY dept RANGE UNBOUNDED PRECEDING) FROM emp")
+        .unwrap();
+    assert!(error(
+        &mut session,
+        "SELECT sum(id) OVER (ROWS BETWEEN UNBOUNDED FOLLOWING AND CURRENT ROW) FROM emp"
+    )
+    .starts_with("invalid input: frame start can't be UNBOUNDED FOLLOWING"));
+}
+
+// ============================== Grouping sets ===============================
+
+#[test]
+fn test_grouping_sets() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE TABLE sales (id INT PRIMARY KEY, region TEXT, product TEXT, amount INT)")
+        .execute(
+            "INSERT INTO sales VALUES (1, 'east', 'a', 10), (2, 'east', 'b', 20), \
+                (3, 'west', 'a', 30), (4, 'west', 'a', 40)",
+        )
+        // ROLLUP emits subtotals per region and a grand total, and GROUPING()
+        // flags the columns that were rolled up.
+        .select_expect(
+            "SELECT region, product, sum(amount) AS total, grouping(region, product) AS g \
+                FROM sales GROUP BY ROLLUP (region, product) ORDER BY region, product",
+            "sales.region, sales.product, total, g ; NULL, NULL, 100, 3 ; east, NULL, 30, 1 ; \
+                east, a, 10, 0 ; east, b, 20, 0 ; west, NULL, 70, 1 ; west, a, 70, 0",
+        )
+        // CUBE emits all combinations.
+        .select_expect(
+            "SELECT region, product, count(*) AS c FROM sales GROUP BY CUBE (region, product) \
+                ORDER BY region, product",
+            "sales.region, sales.product, c ; NULL, NULL, 4 ; NULL, a, 3 ; NULL, b, 1 ; \
+                east, NULL, 2 ; east, a, 1 ; east, b, 1 ; west, NULL, 2 ; west, a, 2",
+        )
+        // GROUPING SETS are given explicitly, and GROUPING() can be used in
+        // HAVING.
+        .select_expect(
+            "SELECT region, product, count(*) AS c FROM sales \
+                GROUP BY GROUPING SETS ((region), (product), ()) \
+                HAVING grouping(region) = 0 ORDER BY region",
+            "sales.region, sales.product, c ; east, NULL, 2 ; west, NULL, 2",
+        )
+        // Plain GROUP BY expressions are combined with each grouping set.
+        .select_expect(
+            "SELECT region, product, max(amount) AS m FROM sales \
+                GROUP BY region, ROLLUP (product) ORDER BY region, product",
+            "sales.region, sales.product, m ; east, NULL, 20 ; east, a, 10 ; east, b, 20 ; \
+                west, NULL, 40 ; west, a, 40",
+        )
+        // Empty grouping sets emit a row for empty input.
+        .select_expect(
+            "SELECT region, count(*) AS c FROM sales WHERE id > 10 GROUP BY ROLLUP (region)",
+            "sales.region, c ; NULL, 0",
+        );
+
+    let mut session = engine.session();
+    let error = |session: &mut Session<'_, _>, sql| session.execute(sql).unwrap_err().to_string();
+    assert!(error(
+        &mut session,
+        "SELECT grouping(product) FROM sales GROUP BY ROLLUP (region)"
+    )
+    .starts_with("invalid input: grouping arguments must be GROUP BY expressions"));
+    assert!(error(&mut session, "SELECT grouping(region) FROM sales")
+        .starts_with("invalid input: grouping arguments must be GROUP BY expressions"));
+    assert!(
+        error(&mut session, "SELECT grouping(region) OVER () FROM sales")
+            .starts_with("invalid input: unknown window function grouping")
+    );
+}
+
+// ========================= Statistical aggregates ==========================
+
+#[test]
+fn test_statistical_aggregates() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    // NULLs are derived in a CTE, such that the same rows can be aggregated
+    // with and without them.
+    let with = "WITH n AS (SELECT id, grp, nullif(x, 0) AS x, \
+        CASE WHEN id IN (4, 6) THEN NULL ELSE flag END AS flag, nullif(name, '') AS name FROM t)";
+
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE TABLE t (id INT PRIMARY KEY, grp TEXT, x INT, flag BOOLEAN, name TEXT)")
+        .execute(
+            "INSERT INTO t VALUES (1, 'a', 1, TRUE, 'p'), (2, 'a', 3, TRUE, 'q'), \
+                (3, 'a', 0, FALSE, ''), (4, 'b', 10, TRUE, 'r'), (5, 'b', 20, FALSE, 's'), \
+                (6, 'b', 30, TRUE, 't')",
+        )
+        // NULLs are skipped.
+        .select_expect(
+            &format!(
+                "{with} SELECT grp, variance(x) AS v, stddev(x) AS sd, bool_and(flag) AS ba, \
+                    bool_or(flag) AS bo, string_agg(name, '-') AS names FROM n GROUP BY grp \
+                    ORDER BY grp"
+            ),
+            "n.grp, v, sd, ba, bo, names ; a, 2, 1.4142135623730951, false, true, p-q ; \
+                b, 100, 10, false, false, r-s-t",
+        )
+        // Population variants, DISTINCT, and single rows.
+        .select_expect(
+            "SELECT string_agg(DISTINCT grp, '') AS g, var_pop(x) AS vp, var_samp(x) AS vs, \
+                stddev_pop(x) AS sp FROM t WHERE id = 1",
+            "g, vp, vs, sp ; a, 0, NULL, 0",
+        )
+        // percentile_cont interpolates, percentile_disc picks an input value.
+        .select_expect(
+            &format!(
+                "{with} SELECT percentile_cont(0.1) WITHIN GROUP (ORDER BY x) AS c1, \
+                    percentile_cont(0.25) WITHIN GROUP (ORDER BY x DESC) AS c2, \
+                    percentile_disc(0.5) WITHIN GROUP (ORDER BY x) AS d1, \
+                    percentile_disc(0.1) WITHIN GROUP (ORDER BY x DESC) AS d2, median(x) AS m \
+                    FROM n"
+            ),
+            "c1, c2, d1, d2, m ; 1.8, 20, 10, 30, 10",
+        )
+        // Empty input yields NULL.
+        .select_expect(
+            "SELECT median(x) AS m, string_agg(name, '-') AS s, bool_or(flag) AS b FROM t \
+                WHERE id > 10",
+            "m, s, b ; NULL, NULL, NULL",
+        )
+        // They can be used as window functions.
+        .select_expect(
+            &format!(
+                "{with} SELECT id, string_agg(name, '') OVER (ORDER BY id) AS s FROM n \
+                    WHERE id <= 3 ORDER BY id"
+            ),
+            "n.id, s ; 1, p ; 2, pq ; 3, pq",
+        );
+
+    let mut session = engine.session();
+    let error = |session: &mut Session<'_, _>, sql| session.execute(sql).unwrap_err().to_string();
+    assert!(error(&mut session, "SELECT percentile_cont(0.5) FROM t")
+        .starts_with("invalid input: percentile_cont requires WITHIN GROUP (ORDER BY ...)"));
+    assert!(error(
+        &mut session,
+        "SELECT percentile_cont(2) WITHIN GROUP (ORDER BY x) FROM t"
+    )
+    .starts_with("invalid input: percentile_cont fraction must be between 0 and 1"));
+    assert!(error(
+        &mut session,
+        "SELECT percentile_cont(0.5) WITHIN GROUP (ORDER BY name) FROM t"
+    )
+    .starts_with("invalid input: can't interpolate"));
+    assert!(error(
+        &mut session,
+        "SELECT sum(x) WITHIN GROUP (ORDER BY x) FROM t"
+    )
+    .starts_with("invalid input: unknown ordered-set aggregate function sum"));
+    assert!(error(&mut session, "SELECT bool_and(x) FROM t")
+        .starts_with("invalid input: can't aggregate 1 as a boolean"));
+    assert!(error(&mut session, "SELECT string_agg(name) FROM t")
+        .starts_with("invalid input: string_agg takes 2 arguments"));
+    assert!(error(&mut session, "SELECT array_agg(name, ',') FROM t")
+        .starts_with("invalid input: array_agg takes 1 argument"));
+
+    // array_agg collects values into a text array literal, skipping NULLs.
+    // Elements are quoted if needed. Checked by value, since the test runner
+    // splits rows by comma.
+    let mut column = |sql: &str| match session.execute(sql).unwrap() {
+        StatementResult::Select { rows, .. } => rows
+            .into_iter()
+            .map(|row| row.get_field(0).unwrap())
+            .collect::<Vec<_>>(),
+        result => panic!("unexpected result {result:?}"),
+    };
+    assert_eq!(
+        column(&format!(
+            "{with} SELECT array_agg(name) FROM n GROUP BY grp ORDER BY grp"
+        )),
+        [Field::from("{p,q}"), Field::from("{r,s,t}")]
+    );
+    assert_eq!(
+        column("SELECT array_agg(x) FROM t WHERE grp = 'a'"),
+        [Field::from("{1,3,0}")]
+    );
+    assert_eq!(
+        column("SELECT array_agg(DISTINCT grp) FROM t"),
+        [Field::from("{a,b}")]
+    );
+    assert_eq!(
+        column("SELECT array_agg(x) FROM t WHERE id > 10"),
+        [Field::Null]
+    );
+    assert_eq!(
+        column(
+            "WITH v AS (SELECT 'a b' AS v UNION ALL SELECT '' UNION ALL SELECT 'null' \
+                UNION ALL SELECT 'x\"y\\z' UNION ALL SELECT '{1}') SELECT array_agg(v) FROM v"
+        ),
+        [Field::from(r#"{"a b","","null","x\"y\\z","{1}"}"#)]
+    );
+    assert_eq!(
+        column(&format!(
+            "{with} SELECT array_agg(name) OVER (ORDER BY id) FROM n WHERE id <= 3 ORDER BY id"
+        )),
+        [
+            Field::from("{p}"),
+            Field::from("{p,q}"),
+            Field::from("{p,q}")
+        ]
+    );
+}
+
+// ========================= Approximate aggregates ==========================
+
+#[test]
+fn test_approximate_aggregates() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    // The sketches are exact for small inputs.
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE TABLE t (id INT PRIMARY KEY, grp TEXT, x INT)")
+        .execute(
+            "INSERT INTO t VALUES (1, 'a', 1), (2, 'a', 5), (3, 'a', 3), (4, 'b', 10), \
+                (5, 'b', 10), (6, 'c', 7), (7, 'c', 8), (8, 'c', 9)",
+        )
+        .select_expect(
+            "SELECT approx_count_distinct(grp) AS g, approx_count_distinct(x) AS x, \
+                approx_percentile(x, 0.5) AS p FROM t",
+            "g, x, p ; 3, 7, 7.5",
+        )
+        .select_expect(
+            "SELECT grp, approx_count_distinct(x) AS c, approx_percentile(x, 0.5) AS p, \
+                approx_percentile(x, 1) AS mx FROM t GROUP BY grp ORDER BY grp",
+            "t.grp, c, p, mx ; a, 3, 3, 5 ; b, 1, 10, 10 ; c, 3, 8, 9",
+        )
+        .select_expect(
+            "SELECT approx_count_distinct(x) AS c, approx_percentile(x, 0.5) AS p FROM t \
+                WHERE id > 10",
+            "c, p ; 0, NULL",
+        );
+
+    let mut session = engine.session();
+    let error = |session: &mut Session<'_, _>, sql| session.execute(sql).unwrap_err().to_string();
+    assert!(error(&mut session, "SELECT approx_percentile(x) FROM t")
+        .starts_with("invalid input: approx_percentile takes 2 arguments"));
+    assert!(
+        error(&mut session, "SELECT approx_percentile(x, 1.5) FROM t")
+            .starts_with("invalid input: approx_percentile fraction must be between 0 and 1")
+    );
+    assert!(
+        error(&mut session, "SELECT approx_percentile(grp, 0.5) FROM t")
+            .starts_with("invalid input: can't compute approx_percentile of 'a'")
+    );
+}
+
+// ================================== Views ===================================
+#[test]
+fn test_views() {
+    let storage_engine = create_storage_engine();
+    let engine = Local::new(storage_engine);
+
+    // Views are expanded when referenced, so they see later changes.
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE TABLE t (id INT PRIMARY KEY, grp TEXT, x INT)")
+        .execute("INSERT INTO t VALUES (1, 'a', 1), (2, 'a', 5), (3, 'b', 10)")
+        .execute("CREATE VIEW v AS SELECT grp, sum(x) AS total FROM t GROUP BY grp")
+        .select_expect(
+            "SELECT * FROM v ORDER BY grp",
+            "v.grp, v.total ; a, 6 ; b, 10",
+        )
+        .execute("INSERT INTO t VALUES (4, 'b', 2)")
+        .select_expect(
+            "SELECT w.total FROM v AS w WHERE w.grp = 'b'",
+            "w.total ; 12",
+        )
+        // Views can reference other views, and be joined.
+        .execute("CREATE VIEW big AS SELECT grp FROM v WHERE total > 10")
+        .select_expect(
+            "SELECT t.id, big.grp FROM t JOIN big ON t.grp = big.grp ORDER BY t.id",
+            "t.id, big.grp ; 3, b ; 4, b",
+        )
+        .execute("CREATE OR REPLACE VIEW v AS SELECT grp, count(*) AS total FROM t GROUP BY grp")
+        .select_expect("SELECT * FROM big", "big.grp ; ")
+        .execute("DROP VIEW big")
+        .execute("DROP VIEW IF EXISTS big");
+
+    // Materialized views store their rows until refreshed.
+    SqlStudentRunner::new(&engine)
+        .execute("CREATE MATERIALIZED VIEW m AS SELECT grp, max(x) AS mx FROM t GROUP BY grp")
+        .select_expect("SELECT * FROM m ORDER BY grp", "m.grp, m.mx ; a, 5 ; b, 10")
+        .execute("INSERT INTO t VALUES (5, 'c', 7)")
+        .select_expect("SELECT * FROM m ORDER BY grp", "m.grp, m.mx ; a, 5 ; b, 10")
+        .execute("REFRESH MATERIALIZED VIEW m")
+        .select_expect(
+            "SELECT * FROM m ORDER BY grp",
+            "m.grp, m.mx ; a, 5 ; b, 10 ; c, 7",
+        )
+        .execute("DROP MATERIALIZED VIEW m")
+        .execute("CREATE TABLE m (id INT PRIMARY KEY)")
+        // Column types are inferred from the query, e.g. AVG is a DOUBLE.
+        .execute("CREATE MATERIALIZED VIEW m2 AS SELECT count(*) AS c, avg(x) AS a FROM t")
+        .select_expect("SELECT * FROM m2", "m2.c, m2.a ; 5, 5")
+        .execute("INSERT INTO t VALUES (6, 'c', 3)")
+        .execute("REFRESH MATERIALIZED VIEW m2")
+        .select_expect("SELECT * FROM m2", "m2.c, m2.a ; 6, 4.666666666666667")
+        // NULLs are stored, and typed by the query where known.
+        .execute("CREATE MATERIALIZED VIEW m3 AS SELECT nullif(x, 3) AS n, NULL AS u FROM t WHERE id = 6")
+        .select_expect("SELECT * FROM m3", "m3.n, m3.u ; NULL, NULL");
+
+    let mut session = engine.session();
+    let error = |session: &mut Session<'_, _>, sql| session.execute(sql).unwrap_err().to_string();
+    session
+        .execute("CREATE MATERIALIZED VIEW mv AS SELECT id FROM t")
+        .unwrap();
+    assert!(error(&mut session, "CREATE VIEW v AS SELECT 1")
+        .starts_with("invalid input: View 'v' already exists"));
+    assert!(error(&mut session, "CREATE VIEW t AS SELECT 1")
+        .starts_with("invalid input: Table 't' already exists"));
+    assert!(error(&mut session, "CREATE TABLE v (id INT PRIMARY KEY)")
+        .starts_with("invalid input: View 'v' already exists"));
+    assert!(
+        error(&mut session, "CREATE OR REPLACE VIEW v AS SELECT * FROM v")
+            .starts_with("invalid input: view v references itself")
+    );
+    assert!(
+        error(&mut session, "CREATE VIEW w AS SELECT 1 AS a, 2 AS a")
+            .starts_with("invalid input: duplicate column a in view w")
+    );
+    assert!(error(&mut session, "CREATE VIEW w AS SELECT ?")
+        .starts_with("invalid input: views can't have parameters"));
+    assert!(error(
+        &mut session,
+        "CREATE OR REPLACE MATERIALIZED VIEW mv AS SELECT 1"
+    )
+    .starts_with("invalid input: materialized views can't be replaced"));
+    assert!(error(&mut session, "INSERT INTO v VALUES ('c', 1)")
+        .starts_with("invalid input: can't modify view v"));
+    assert!(error(&mut session, "DELETE FROM mv")
+        .starts_with("invalid input: can't modify materialized view mv"));
+    assert!(error(&mut session, "DROP TABLE mv")
+        .starts_with("invalid input: mv is a materialized view, use DROP MATERIALIZED VIEW"));
+    assert!(error(&mut session, "DROP VIEW mv")
+        .starts_with("invalid input: mv is a materialized view, use DROP MATERIALIZED VIEW"));
+    assert!(error(&mut session, "DROP MATERIALIZED VIEW v")
+        .starts_with("invalid input: v is not a materialized view, use DROP VIEW"));
+    assert!(error(&mut session, "REFRESH MATERIALIZED VIEW v")
+        .starts_with("invalid input: v is not a materialized view"));
+    assert!(error(&mut session, "DROP VIEW w").starts_with("invalid input: View w does not exist"));
+
+    // A failed refresh keeps the existing rows. Integer division yields a
+    // float when it isn't exact, which can't be stored in the INT column.
+    session
+        .execute("CREATE MATERIALIZED VIEW half AS SELECT max(x) / 2 AS h FROM t")
+        .unwrap();
+    session
+        .execute("INSERT INTO t VALUES (7, 'c', 11)")
+        .unwrap();
+    assert!(error(&mut session, "REFRESH MATERIALIZED VIEW half").starts_with("invalid input"));
+    handle(session.execute("SELECT * FROM half").unwrap(), "half.h ; 5");
+
+    // Views can't be dropped while other views reference them, including
+    // via subqueries and from materialized views.
+    session
+        .execute("CREATE VIEW v1 AS SELECT id FROM t")
+        .unwrap();
+    session
+        .execute("CREATE VIEW v2 AS SELECT 1 AS one WHERE EXISTS (SELECT * FROM v1)")
+        .unwrap();
+    session
+        .execute("CREATE MATERIALIZED VIEW m4 AS SELECT * FROM v2")
+        .unwrap();
+    session
+        .execute("CREATE VIEW v3 AS SELECT * FROM m4")
+        .unwrap();
+    assert_eq!(
+        error(&mut session, "DROP VIEW IF EXISTS v1"),
+        "invalid input: can't drop v1, view v2 depends on it"
+    );
+    assert_eq!(
+        error(&mut session, "DROP VIEW v2"),
+        "invalid input: can't drop v2, view m4 depends on it"
+    );
+    assert_eq!(
+        error(&mut session, "DROP MATERIALIZED VIEW m4"),
+        "invalid input: can't drop m4, view v3 depends on it"
+    );
+    handle(session.execute("SELECT * FROM v3").unwrap(), "v3.one ; 1");
+    // Replacing a view updates its dependencies.
+    session
+        .execute("CREATE OR REPLACE VIEW v3 AS SELECT 1")
+        .unwrap();
+    for sql in ["DROP MATERIALIZED VIEW m4", "DROP VIEW v2", "DROP VIEW v1"] {
+        session.execute(sql).unwrap();
+    }
+}
@@ -36,7 +36,7 @@ VALUES
     (104, 41.7500, -87.5800, FALSE, '3345 E 75th St, Chicago, IL'),
     (105, 41.8200, -87.6800, TRUE, '9807 W Madison St, Chicago, IL');
 
-INSERT INTO data_officerallegation (crid, officer_id, allegation_description)
+INSERT INTO data_officerallegation (id, crid, officer_id, allegation_description)
 VALUES
     (0, 101, 2, 'Neglecting to follow proper arrest procedures.'),
     (1, 102, 4, 'Racial profiling incident.'),
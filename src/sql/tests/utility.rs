@@ -63,13 +63,13 @@ impl<'a> SqlStudentRunner<'a> {
     /// Execute the given "testscripts/`script_name`.sql" script, typically to perform
     /// table initialization/setup tasks. (Hence the name.)
     ///
-    /// Note that statements in the .sql scripts must end in semicolons.
+    /// Note that statements in the .sql scripts must be separated by semicolons.
     pub(crate) fn initialize(&mut self, script_name: &str) -> &mut Self {
         let contents = open_script(script_name).unwrap();
-        parse_script(&contents).iter().for_each(|statement| {
-            let session = &mut self.execution.borrow_mut();
-            session.execute(statement).unwrap();
-        });
+        let session = self.execution.get_mut();
+        for result in session.execute_script(&contents, false) {
+            result.unwrap();
+        }
         self
     }
 }
@@ -126,11 +126,3 @@ pub fn open_script(script_name: &str) -> Result<String, Error> {
     buf_reader.read_to_string(&mut contents)?;
     Ok(contents)
 }
-
-pub fn parse_script(script: &str) -> Vec<&str> {
-    script
-        .split(";")
-        .map(|statement| statement.trim())
-        .filter(|statement| !statement.is_empty())
-        .collect()
-}
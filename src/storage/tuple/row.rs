@@ -83,7 +83,8 @@ impl Row {
             .get_mut(index)
             .ok_or_else(|| Error::OutOfBounds)?;
 
-        match field.get_type() == new.get_type() {
+        // NULL can replace, and be replaced by, a value of any type.
+        match field.get_type() == new.get_type() || field.is_null() || new.is_null() {
             true => {
                 *field = new;
                 Ok(())
@@ -118,13 +119,17 @@ impl Row {
 
     /// Serializes the Row's header and data into a byte-stream, structured as follows:
     ///
-    /// | variable length field offset map | field data in bytes |
-    ///                 ^                               ^
-    ///     a text field's `stored_offset` points       |
-    ///     here, which stores the field's offset into here
+    /// | null bitmap | variable length field offset map | field data in bytes |
+    ///                             ^                               ^
+    ///                 a text field's `stored_offset` points       |
+    ///                 here, which stores the field's offset into here
     ///
     ///   a fixed length field's stored_offset is to the offset from the start of
     ///   the field data portion (possibly not the beginning of the byte stream!)
+    ///
+    ///   the null bitmap has one bit per column, set if the column is NULL. A
+    ///   NULL fixed length field is stored as zeroes, and a NULL variable length
+    ///   field is empty.
     pub fn serialize(&self, schema: &Table) -> Result<Vec<u8>> {
         ////////////////////////////// Begin: Students Implement  //////////////////////////////
 
@@ -141,7 +146,7 @@ impl Row {
         // First pass: Calculate offsets for variable-length fields
         for (i, column) in schema.columns().iter().enumerate() {
             match column.get_data_type() {
-                DataType::Text => {
+                DataType::Text | DataType::Bytea => {
                     variable_field_offsets.push(running_offset);
                     // todo(eyoon): This should be incremented by the schema column size, not the field size
                     running_offset += self.values.get(i).unwrap().get_size();
@@ -151,12 +156,20 @@ impl Row {
         }
 
         // Calculate total buffer size and initialize it
-        let header_size = 2 * variable_field_offsets.len() as u16;
+        let bitmap_size = null_bitmap_size(schema);
+        let header_size = bitmap_size as u16 + 2 * variable_field_offsets.len() as u16;
         let e2e_size_bytes = header_size + running_offset;
         let mut data = vec![0; e2e_size_bytes as usize];
 
+        // Write the null bitmap to the buffer
+        for (i, value) in self.values.iter().enumerate() {
+            if value.is_null() {
+                data[i / 8] |= 1 << (i % 8);
+            }
+        }
+
         // Write header data to the buffer
-        let mut cursor = 0_usize;
+        let mut cursor = bitmap_size;
         for offset in variable_field_offsets.iter() {
             let dst = offset + header_size;
             let offset_bytes = dst.to_le_bytes();
@@ -166,13 +179,21 @@ impl Row {
         }
 
         // Write field data to the buffer
-        let mut var_cursor =
-            schema.fixed_field_size_bytes() as usize + 2 * variable_field_offsets.len();
+        let mut var_cursor = schema.fixed_field_size_bytes() as usize + header_size as usize;
         for (i, column) in schema.columns().iter().enumerate() {
-            let field_bytes = self.values.get(i).unwrap().serialize();
+            let field_bytes = match (self.values.get(i).unwrap(), column.get_data_type()) {
+                // NULLs are recorded in the bitmap, and stored as zeroes.
+                (Field::Null, DataType::Text | DataType::Bytea) => vec![],
+                (Field::Null, data_type) => vec![0; data_type.length_bytes() as usize],
+                // Decimals are stored as a mantissa at the column's scale.
+                (Field::Decimal(d), DataType::Decimal(_, scale)) => {
+                    Field::Decimal(d.rescale(scale)?).serialize()
+                }
+                (field, _) => field.serialize(),
+            };
             let num_bytes = field_bytes.len();
             match column.get_data_type() {
-                DataType::Text => {
+                DataType::Text | DataType::Bytea => {
                     data[var_cursor..(var_cursor + num_bytes)].copy_from_slice(&field_bytes);
                     var_cursor += num_bytes;
                 }
@@ -189,22 +210,31 @@ impl Row {
 
     /// Deserializes a byte stream into a Row object.
     ///
-    /// `bytes` contains u16 offsets for variable-length fields, followed
-    /// by fixed-length fields, with variable-length fields at the end.
+    /// `bytes` contains a null bitmap and u16 offsets for variable-length
+    /// fields, followed by fixed-length fields, with variable-length fields at
+    /// the end.
     pub fn deserialize(bytes: Vec<u8>, schema: &Table) -> Self {
+        if bytes.is_empty() {
+            return Self::default();
+        }
+        let bitmap_size = null_bitmap_size(schema);
+
         // Get the offsets of the variable length text fields, if any exist.
         let variable_field_offsets: Vec<u16> = (0..schema.variable_length_fields())
-            .map(|i| u16::from_le_bytes([bytes[2 * i], bytes[(2 * i) + 1]]))
+            .map(|i| bitmap_size + 2 * i)
+            .map(|i| u16::from_le_bytes([bytes[i], bytes[i + 1]]))
             .collect();
 
         // The first byte in `bytes` of the field data
-        let field_data_start = variable_field_offsets.len() * 2;
+        let field_data_start = bitmap_size + variable_field_offsets.len() * 2;
 
         let values = schema
             .columns()
             .iter()
-            .map(|column| match column.get_data_type() {
-                DataType::Text => {
+            .enumerate()
+            .map(|(i, column)| match column.get_data_type() {
+                _ if bytes[i / 8] & (1 << (i % 8)) != 0 => Field::Null,
+                datatype @ (DataType::Text | DataType::Bytea) => {
                     // Get the index into the variable length field offset array.
                     let offset_index = column.stored_offset() as usize;
                     let start = *variable_field_offsets.get(offset_index).unwrap() as usize;
@@ -215,7 +245,7 @@ impl Row {
                     };
 
                     // todo(eyoon): update deserialize based on chnages to to_bytes
-                    Field::deserialize(&bytes[start..end], DataType::Text)
+                    Field::deserialize(&bytes[start..end], datatype)
                 }
                 datatype => {
                     // Get the offset of the field in the byte stream.
@@ -229,4 +259,9 @@ impl Row {
         Self { values }
     }
 }
+
+/// Returns the size in bytes of a row's null bitmap, with one bit per column.
+fn null_bitmap_size(schema: &Table) -> usize {
+    schema.col_count().div_ceil(8)
+}
 // eof  ‎‎‎‎
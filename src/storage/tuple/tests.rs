@@ -1,6 +1,7 @@
 use super::*;
 use crate::common::utility::create_table_definition;
 use crate::types::field::Field;
+use crate::types::{DataType, Table};
 use std::sync::Arc;
 
 #[test]
@@ -78,3 +79,58 @@ pub fn test_int_serialization() {
         .enumerate()
         .for_each(|(i, field)| assert_eq!(row2.get_field(i).unwrap(), *field));
 }
+
+#[test]
+pub fn test_variable_length_serialization() {
+    let schema = Table::builder()
+        .name("test")
+        .column("id", DataType::Int, false, None, None)
+        .column("data", DataType::Bytea, false, None, None)
+        .column("name", DataType::Text, false, None, None)
+        .column("hash", DataType::Bytea, false, None, None)
+        .build();
+    assert_eq!(schema.variable_length_fields(), 3);
+
+    let fields = vec![
+        Field::from(7),
+        Field::Bytes(vec![0xde, 0xad, 0xbe, 0xef]),
+        Field::from("name"),
+        Field::Bytes(vec![]),
+    ];
+    let row = Row::from(fields.clone());
+    let row2 = Row::from_tuple(row.to_tuple(&schema).unwrap(), &schema).unwrap();
+    assert_eq!(row2, row);
+}
+
+#[test]
+pub fn test_null_serialization() {
+    // More than 8 columns, such that the null bitmap spans two bytes.
+    let mut builder = Table::builder();
+    builder.name("test");
+    for i in 0..9 {
+        let data_type = match i % 3 {
+            0 => DataType::Int,
+            1 => DataType::Text,
+            _ => DataType::Date,
+        };
+        builder.column(&format!("c{i}"), data_type, true, None, None);
+    }
+    let schema = builder.build();
+
+    // NULLs round-trip for both fixed-width and variable-length columns, and
+    // are distinct from zero values.
+    let fields = vec![
+        Field::Null,
+        Field::Null,
+        Field::Null,
+        Field::from(0),
+        Field::from(""),
+        Field::Date(0),
+        Field::from(7),
+        Field::from("seven"),
+        Field::Null,
+    ];
+    let row = Row::from(fields);
+    let row2 = Row::from_tuple(row.to_tuple(&schema).unwrap(), &schema).unwrap();
+    assert_eq!(row2, row);
+}
@@ -0,0 +1,172 @@
+//! Binary string encodings for BYTEA values, as used by hex literals such as
+//! x'deadbeef' and the encode() and decode() SQL functions.
+
+use crate::common::Result;
+use crate::errinput;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes bytes in the given format: hex, base64 or escape.
+pub fn encode(bytes: &[u8], format: &str) -> Result<String> {
+    Ok(match format.to_lowercase().as_str() {
+        "hex" => encode_hex(bytes),
+        "base64" => encode_base64(bytes),
+        "escape" => encode_escape(bytes),
+        format => return errinput!("unknown encoding {format}"),
+    })
+}
+
+/// Decodes a string in the given format: hex, base64 or escape.
+pub fn decode(string: &str, format: &str) -> Result<Vec<u8>> {
+    match format.to_lowercase().as_str() {
+        "hex" => decode_hex(string),
+        "base64" => decode_base64(string),
+        "escape" => decode_escape(string),
+        format => errinput!("unknown encoding {format}"),
+    }
+}
+
+/// Encodes bytes as lowercase hex digits.
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Decodes pairs of hex digits, ignoring whitespace between pairs.
+pub fn decode_hex(string: &str) -> Result<Vec<u8>> {
+    let digits: Vec<char> = string.chars().filter(|c| !c.is_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return errinput!("invalid hex string {string}: odd number of digits");
+    }
+    digits
+        .chunks(2)
+        .map(|pair| match (pair[0].to_digit(16), pair[1].to_digit(16)) {
+            (Some(high), Some(low)) => Ok((high * 16 + low) as u8),
+            _ => errinput!("invalid hex digit in {string}"),
+        })
+        .collect()
+}
+
+/// Encodes bytes as padded base64.
+pub fn encode_base64(bytes: &[u8]) -> String {
+    let mut string = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0, |group, (i, &b)| group | ((b as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => {
+                    string.push(BASE64_ALPHABET[((group >> (18 - 6 * i)) & 0x3f) as usize] as char)
+                }
+                false => string.push('='),
+            }
+        }
+    }
+    string
+}
+
+/// Decodes padded base64, ignoring whitespace.
+pub fn decode_base64(string: &str) -> Result<Vec<u8>> {
+    let chars: Vec<u8> = string
+        .bytes()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    if !chars.len().is_multiple_of(4) {
+        return errinput!("invalid base64 string {string}");
+    }
+    let mut bytes = Vec::with_capacity(chars.len() / 4 * 3);
+    for (n, chunk) in chars.chunks(4).enumerate() {
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && n != chars.len() / 4 - 1) {
+            return errinput!("invalid base64 string {string}");
+        }
+        let mut group = 0;
+        for &c in &chunk[..4 - padding] {
+            let Some(value) = BASE64_ALPHABET.iter().position(|&a| a == c) else {
+                return errinput!("invalid base64 character {}", c as char);
+            };
+            group = (group << 6) | value as u32;
+        }
+        group <<= 6 * padding;
+        bytes.extend(&group.to_be_bytes()[1..4 - padding]);
+    }
+    Ok(bytes)
+}
+
+/// Encodes bytes as text, escaping backslashes as \\ and non-printable bytes
+/// as octal \nnn.
+fn encode_escape(bytes: &[u8]) -> String {
+    let mut string = String::new();
+    for &b in bytes {
+        match b {
+            b'\\' => string.push_str("\\\\"),
+            0x20..=0x7e => string.push(b as char),
+            b => string.push_str(&format!("\\{b:03o}")),
+        }
+    }
+    string
+}
+
+/// Decodes text escaped with \\ and octal \nnn sequences.
+fn decode_escape(string: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut rest = string.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        rest = tail;
+        if b != b'\\' {
+            bytes.push(b);
+        } else if rest.first() == Some(&b'\\') {
+            bytes.push(b'\\');
+            rest = &rest[1..];
+        } else {
+            let octal = rest.get(..3).and_then(|o| std::str::from_utf8(o).ok());
+            match octal.and_then(|o| u8::from_str_radix(o, 8).ok()) {
+                Some(value) => bytes.push(value),
+                None => return errinput!("invalid escape sequence in {string}"),
+            }
+            rest = &rest[3..];
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() -> Result<()> {
+        assert_eq!(encode_hex(&[0xde, 0xad, 0xbe, 0xef]), "deadbeef");
+        assert_eq!(decode_hex("DEAD beef")?, vec![0xde, 0xad, 0xbe, 0xef]);
+        assert!(decode_hex("abc").is_err());
+        assert!(decode_hex("zz").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_base64() -> Result<()> {
+        for (bytes, string) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(encode_base64(bytes.as_bytes()), string);
+            assert_eq!(decode_base64(string)?, bytes.as_bytes());
+        }
+        assert!(decode_base64("Zg=").is_err());
+        assert!(decode_base64("Zg==Zg==").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_escape() -> Result<()> {
+        let bytes = b"a\\b\x00\xff";
+        assert_eq!(encode(bytes, "escape")?, "a\\\\b\\000\\377");
+        assert_eq!(decode("a\\\\b\\000\\377", "escape")?, bytes);
+        assert!(decode("\\9", "escape").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+//! String collations, which determine how strings are compared and ordered by
+//! e.g. comparison operators, ORDER BY and GROUP BY. A collation is given either
+//! for a column in CREATE TABLE or for an expression via COLLATE.
+
+use crate::common::Result;
+use crate::errinput;
+use crate::types::field::Field;
+use serde::{Deserialize, Serialize};
+
+/// Accented lowercase Latin letters, and the base letters they fold to (by
+/// position) for the Unicode collation's primary ordering.
+const ACCENTED: &str = "àáâãäåçèéêëìíîïñòóôõöøùúûüýÿ\
+    āăąćĉċčďđēĕėęěĝğġģĥħĩīĭįıĵķĺļľŀłńņňōŏőŕŗřśŝşšţťŧũūŭůűųŵŷźżž";
+const UNACCENTED: &str = "aaaaaaceeeeiiiinoooooouuuuyy\
+    aaaccccddeeeeegggghhiiiiijklllllnnnooorrrsssstttuuuuuuwyzzz";
+
+/// A string collation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Collation {
+    /// Compares strings by their UTF-8 bytes. This is the default.
+    #[default]
+    Binary,
+    /// Compares strings case-insensitively, e.g. 'abc' = 'ABC'.
+    NoCase,
+    /// Orders strings alphabetically, ignoring case and accents, such that
+    /// e.g. 'Äpfel' < 'apple' < 'banana'. Strings which only differ by case or
+    /// accents are ordered by their bytes, and are not equal.
+    Unicode,
+}
+
+impl Collation {
+    /// Looks up a collation by name.
+    pub fn lookup(name: &str) -> Result<Self> {
+        Ok(match name.to_lowercase().as_str() {
+            "binary" | "c" => Self::Binary,
+            "nocase" => Self::NoCase,
+            "unicode" => Self::Unicode,
+            name => return errinput!("unknown collation {name}"),
+        })
+    }
+
+    /// Returns a sort key for the value, such that keys compare (and hash) in
+    /// collation order. Non-string values are returned as is. An index on a
+    /// collated column should store these keys.
+    pub fn key(&self, value: Field) -> Field {
+        let Field::String(string) = value else {
+            return value;
+        };
+        match self {
+            Self::Binary => Field::String(string),
+            Self::NoCase => Field::String(string.to_lowercase()),
+            Self::Unicode => {
+                // The primary key is the case- and accent-folded string, with
+                // ties broken by the original string. The NUL separator sorts
+                // before any other character, so prefixes sort first.
+                let mut key = String::with_capacity(string.len() * 2 + 1);
+                for c in string.chars().flat_map(char::to_lowercase) {
+                    match c {
+                        // Drop combining diacritical marks, e.g. from NFD input.
+                        '\u{0300}'..='\u{036f}' => {}
+                        'ß' => key.push_str("ss"),
+                        'æ' => key.push_str("ae"),
+                        'œ' => key.push_str("oe"),
+                        c => key.push(Self::unaccent(c)),
+                    }
+                }
+                key.push('\0');
+                key.push_str(&string);
+                Field::String(key)
+            }
+        }
+    }
+
+    /// Folds an accented lowercase Latin letter to its base letter.
+    fn unaccent(c: char) -> char {
+        if c.is_ascii() {
+            return c;
+        }
+        ACCENTED
+            .chars()
+            .position(|a| a == c)
+            .and_then(|i| UNACCENTED.chars().nth(i))
+            .unwrap_or(c)
+    }
+}
+
+impl std::fmt::Display for Collation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Binary => "binary",
+            Self::NoCase => "nocase",
+            Self::Unicode => "unicode",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unaccent_table() {
+        assert_eq!(ACCENTED.chars().count(), UNACCENTED.chars().count());
+    }
+
+    #[test]
+    fn test_lookup() -> Result<()> {
+        assert_eq!(Collation::lookup("NOCASE")?, Collation::NoCase);
+        assert_eq!(Collation::lookup("c")?, Collation::Binary);
+        assert!(Collation::lookup("latin1").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_key() {
+        let sorted = |collation: Collation, strings: &[&'static str]| {
+            let mut strings = strings.to_vec();
+            strings.sort_by_key(|s| collation.key(Field::from(*s)));
+            strings
+        };
+        let strings = ["banana", "Äpfel", "apple", "Apple", "Zebra", "über", "Ubel"];
+        assert_eq!(
+            sorted(Collation::Binary, &strings),
+            ["Apple", "Ubel", "Zebra", "apple", "banana", "Äpfel", "über"]
+        );
+        assert_eq!(
+            sorted(Collation::NoCase, &strings),
+            ["apple", "Apple", "banana", "Ubel", "Zebra", "Äpfel", "über"]
+        );
+        assert_eq!(
+            sorted(Collation::Unicode, &strings),
+            ["Äpfel", "Apple", "apple", "banana", "Ubel", "über", "Zebra"]
+        );
+
+        // Strings that differ only in case are equal under NoCase, but not
+        // under Unicode. Prefixes sort first.
+        let key = |c: Collation, s: &str| c.key(Field::from(s));
+        assert_eq!(key(Collation::NoCase, "ABC"), key(Collation::NoCase, "abc"));
+        assert_ne!(
+            key(Collation::Unicode, "ABC"),
+            key(Collation::Unicode, "abc")
+        );
+        assert!(key(Collation::Unicode, "Ab") < key(Collation::Unicode, "abc"));
+        assert_eq!(
+            key(Collation::Unicode, "Straße"),
+            Field::from("strasse\0Straße")
+        );
+
+        // Non-strings are returned as is.
+        assert_eq!(Collation::NoCase.key(Field::Integer(1)), Field::Integer(1));
+    }
+}
@@ -0,0 +1,427 @@
+//! Exact fixed-point decimal numbers, used for DECIMAL(p, s) columns.
+//!
+//! A decimal is stored as an integer mantissa and a scale, i.e. the number of
+//! fractional digits: 12.34 is mantissa 1234 with scale 2. Arithmetic is exact,
+//! except for division which rounds to a fixed number of fractional digits.
+//! Rounding is half away from zero, and overflow of the 128-bit mantissa
+//! errors.
+
+use crate::common::{Error, Result};
+use crate::errinput;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// The maximum number of digits in a decimal, which fits in an i128.
+pub const MAX_PRECISION: u8 = 38;
+
+/// The minimum number of fractional digits in the result of a division.
+const MIN_DIVISION_SCALE: u8 = 6;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u8,
+}
+
+impl Decimal {
+    pub fn new(mantissa: i128, scale: u8) -> Self {
+        Self { mantissa, scale }
+    }
+
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    pub fn scale(&self) -> u8 {
+        self.scale
+    }
+
+    /// Parses a decimal number such as -12.340, keeping trailing zeros.
+    pub fn parse(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidInput(format!("invalid decimal '{s}'"));
+        let (negative, digits) = match s.trim() {
+            s if s.starts_with('-') => (true, &s[1..]),
+            s => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if integer.is_empty() && fraction.is_empty()
+            || !integer
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        let scale: u8 = fraction.len().try_into().map_err(|_| invalid())?;
+        if scale > MAX_PRECISION {
+            return Err(invalid());
+        }
+        let mantissa: i128 = format!("{integer}{fraction}")
+            .parse()
+            .map_err(|_| invalid())?;
+        Ok(Self::new(
+            if negative { -mantissa } else { mantissa },
+            scale,
+        ))
+    }
+
+    /// Converts a float to a decimal with the given scale, rounding as needed.
+    /// Errors on NaN and infinity.
+    pub fn from_f64(value: f64, scale: u8) -> Result<Self> {
+        if !value.is_finite() {
+            return errinput!("can't convert {value} to decimal");
+        }
+        Self::parse(&format!("{value:.*}", scale as usize)).or(Err(Error::OverflowError))
+    }
+
+    pub fn to_f64(self) -> f64 {
+        // Go via the decimal representation to get the closest float.
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    /// Returns the decimal as an f64 if it can be represented exactly, i.e. if
+    /// it's an integer or a fraction with a power of two denominator, and has
+    /// at most 53 significant bits.
+    pub fn to_exact_f64(self) -> Option<f64> {
+        // m / 10^s = (m / 5^s) / 2^s, which is exact if 5^s divides m.
+        let decimal = self.normalize();
+        let power = 5_i128.pow(decimal.scale as u32);
+        if decimal.mantissa % power != 0 {
+            return None;
+        }
+        let numerator = decimal.mantissa / power;
+        if numerator.unsigned_abs() > 1 << f64::MANTISSA_DIGITS {
+            return None;
+        }
+        Some(numerator as f64 / 2_f64.powi(decimal.scale as i32))
+    }
+
+    /// Returns the number of significant digits left of the decimal point.
+    fn integer_digits(&self) -> u8 {
+        let integer = (self.mantissa / pow10(self.scale)).unsigned_abs();
+        integer.checked_ilog10().map_or(0, |d| d as u8 + 1)
+    }
+
+    /// Changes the scale, rounding half away from zero if digits are dropped.
+    pub fn rescale(self, scale: u8) -> Result<Self> {
+        if scale > MAX_PRECISION {
+            return Err(Error::OverflowError);
+        }
+        let mantissa = match scale.cmp(&self.scale) {
+            Ordering::Equal => self.mantissa,
+            Ordering::Greater => self
+                .mantissa
+                .checked_mul(pow10(scale - self.scale))
+                .ok_or(Error::OverflowError)?,
+            Ordering::Less => div_round(self.mantissa, pow10(self.scale - scale)),
+        };
+        Ok(Self::new(mantissa, scale))
+    }
+
+    /// Rescales the decimal to fit a DECIMAL(precision, scale) column,
+    /// erroring if there are too many integer digits.
+    pub fn fit(self, precision: u8, scale: u8) -> Result<Self> {
+        let decimal = self.rescale(scale)?;
+        if decimal.integer_digits() > precision - scale {
+            return errinput!("{self} doesn't fit in decimal({precision},{scale})");
+        }
+        Ok(decimal)
+    }
+
+    /// Rescales two decimals to the same scale.
+    fn align(self, other: Self) -> Result<(Self, Self)> {
+        let scale = self.scale.max(other.scale);
+        Ok((self.rescale(scale)?, other.rescale(scale)?))
+    }
+
+    pub fn checked_add(self, other: Self) -> Result<Self> {
+        let (lhs, rhs) = self.align(other)?;
+        let mantissa = lhs.mantissa.checked_add(rhs.mantissa);
+        Ok(Self::new(mantissa.ok_or(Error::OverflowError)?, lhs.scale))
+    }
+
+    pub fn checked_sub(self, other: Self) -> Result<Self> {
+        let (lhs, rhs) = self.align(other)?;
+        let mantissa = lhs.mantissa.checked_sub(rhs.mantissa);
+        Ok(Self::new(mantissa.ok_or(Error::OverflowError)?, lhs.scale))
+    }
+
+    /// Multiplies two decimals. The result scale is the sum of the input
+    /// scales, rounded down to MAX_PRECISION if necessary.
+    pub fn checked_mul(self, other: Self) -> Result<Self> {
+        let mantissa = self
+            .mantissa
+            .checked_mul(other.mantissa)
+            .ok_or(Error::OverflowError)?;
+        let scale = self.scale + other.scale;
+        match scale > MAX_PRECISION {
+            true => Self::new(mantissa, scale).rescale(MAX_PRECISION),
+            false => Ok(Self::new(mantissa, scale)),
+        }
+    }
+
+    /// Divides two decimals. The result has at least MIN_DIVISION_SCALE
+    /// fractional digits, or more if the inputs have more, up to
+    /// MAX_PRECISION. It's rounded, and has fewer fractional digits if the
+    /// mantissa would overflow otherwise.
+    pub fn checked_div(self, other: Self) -> Result<Self> {
+        if other.mantissa == 0 {
+            return Err(Error::InvalidData("Division by zero".to_string()));
+        }
+        let scale = self.scale.max(other.scale).max(MIN_DIVISION_SCALE);
+        let scale = scale.min(MAX_PRECISION);
+        // (a / 10^sa) / (b / 10^sb) = (a * 10^(s + sb - sa) / b) / 10^s. The
+        // shifted dividend may not fit in an i128, so this is computed by long
+        // division, one fractional digit at a time. s >= sa, so the shift is
+        // non-negative.
+        let shift = scale + other.scale - self.scale;
+        let (dividend, divisor) = (self.mantissa.unsigned_abs(), other.mantissa.unsigned_abs());
+        let (mut quotient, mut remainder) = (dividend / divisor, dividend % divisor);
+        let mut digits = 0;
+        while digits < shift {
+            // The next digit is remainder * 10 / divisor, which is below 10
+            // since remainder < divisor. remainder * 10 itself may overflow,
+            // so it's accumulated modulo the divisor instead.
+            let (mut digit, mut next) = (0, 0u128);
+            for _ in 0..10 {
+                match next >= divisor - remainder {
+                    true => (digit, next) = (digit + 1, next - (divisor - remainder)),
+                    false => next += remainder,
+                }
+            }
+            let q = quotient.checked_mul(10).and_then(|q| q.checked_add(digit));
+            let Some(q) = q.filter(|&q| q <= i128::MAX as u128) else {
+                break;
+            };
+            (quotient, remainder) = (q, next);
+            digits += 1;
+        }
+        if remainder >= divisor - remainder {
+            quotient = quotient.checked_add(1).ok_or(Error::OverflowError)?;
+        }
+        let mut mantissa = i128::try_from(quotient).or(Err(Error::OverflowError))?;
+        if (self.mantissa < 0) != (other.mantissa < 0) {
+            mantissa = -mantissa;
+        }
+        // Missing digits are dropped from the scale, unless they're integer
+        // digits.
+        let scale = scale
+            .checked_sub(shift - digits)
+            .ok_or(Error::OverflowError)?;
+        Ok(Self::new(mantissa, scale))
+    }
+
+    pub fn checked_rem(self, other: Self) -> Result<Self> {
+        let (lhs, rhs) = self.align(other)?;
+        let mantissa = lhs.mantissa.checked_rem(rhs.mantissa);
+        Ok(Self::new(mantissa.ok_or(Error::OverflowError)?, lhs.scale))
+    }
+
+    /// Raises the decimal to an integer power, by repeated squaring.
+    pub fn checked_pow(self, exponent: u32) -> Result<Self> {
+        let (mut result, mut base, mut exponent) = (Self::from(1), self, exponent);
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.checked_mul(base)?;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.checked_mul(base)?.normalize();
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn checked_neg(self) -> Result<Self> {
+        let mantissa = self.mantissa.checked_neg().ok_or(Error::OverflowError)?;
+        Ok(Self::new(mantissa, self.scale))
+    }
+
+    /// Rounds to the given number of fractional digits, half away from zero.
+    /// Negative digits round to the left of the decimal point, e.g. rounding
+    /// 1250 to -2 digits yields 1300.
+    pub fn round(self, digits: i64) -> Result<Self> {
+        if digits >= 0 {
+            return self.rescale(digits.min(MAX_PRECISION as i64) as u8);
+        }
+        let integer = self.rescale(0)?.mantissa;
+        let Some(factor) = 10_i128.checked_pow(digits.unsigned_abs().min(u32::MAX as u64) as u32)
+        else {
+            return Ok(Self::new(0, 0));
+        };
+        let mantissa = div_round(integer, factor).checked_mul(factor);
+        Ok(Self::new(mantissa.ok_or(Error::OverflowError)?, 0))
+    }
+
+    /// Returns the largest integer less than or equal to the decimal.
+    pub fn floor(self) -> Self {
+        Self::new(self.mantissa.div_euclid(pow10(self.scale)), 0)
+    }
+
+    /// Returns the smallest integer greater than or equal to the decimal.
+    pub fn ceil(self) -> Result<Self> {
+        let floor = self.floor();
+        match self.mantissa.rem_euclid(pow10(self.scale)) == 0 {
+            true => Ok(floor),
+            false => Ok(Self::new(
+                floor.mantissa.checked_add(1).ok_or(Error::OverflowError)?,
+                0,
+            )),
+        }
+    }
+
+    /// Returns the decimal with trailing fractional zeros removed, such that
+    /// equal numbers have the same representation.
+    fn normalize(self) -> Self {
+        let mut decimal = self;
+        while decimal.scale > 0 && decimal.mantissa % 10 == 0 {
+            decimal = Self::new(decimal.mantissa / 10, decimal.scale - 1);
+        }
+        decimal
+    }
+}
+
+/// Returns 10^exponent. The exponent must be at most MAX_PRECISION.
+fn pow10(exponent: u8) -> i128 {
+    10_i128.pow(exponent as u32)
+}
+
+/// Divides two integers, rounding half away from zero.
+fn div_round(dividend: i128, divisor: i128) -> i128 {
+    let (quotient, remainder) = (dividend / divisor, dividend % divisor);
+    if remainder.unsigned_abs() >= divisor.unsigned_abs() - remainder.unsigned_abs() {
+        quotient + dividend.signum() * divisor.signum()
+    } else {
+        quotient
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Self {
+        Self::new(value as i128, 0)
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl std::hash::Hash for Decimal {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        let decimal = self.normalize();
+        decimal.mantissa.hash(state);
+        decimal.scale.hash(state);
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self.align(*other) {
+            Ok((lhs, rhs)) => lhs.mantissa.cmp(&rhs.mantissa),
+            // Aligning can only overflow if the integer parts differ wildly.
+            Err(_) => self.to_f64().total_cmp(&other.to_f64()),
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let digits = format!(
+            "{:0>1$}",
+            self.mantissa.unsigned_abs(),
+            self.scale as usize + 1
+        );
+        let (integer, fraction) = digits.split_at(digits.len() - self.scale as usize);
+        match fraction.is_empty() {
+            true => write!(f, "{sign}{integer}"),
+            false => write!(f, "{sign}{integer}.{fraction}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_display() -> Result<()> {
+        for s in [
+            "0",
+            "12.34",
+            "-0.05",
+            "1000.000",
+            "99999999999999999999.999999",
+        ] {
+            assert_eq!(Decimal::parse(s)?.to_string(), s);
+        }
+        assert_eq!(Decimal::parse(".5")?.to_string(), "0.5");
+        assert!(Decimal::parse("1.2.3").is_err());
+        assert!(Decimal::parse("-").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_arithmetic() -> Result<()> {
+        let d = |s| Decimal::parse(s).unwrap();
+        assert_eq!(d("0.1").checked_add(d("0.2"))?.to_string(), "0.3");
+        assert_eq!(d("1.50").checked_sub(d("2"))?.to_string(), "-0.50");
+        assert_eq!(d("1.5").checked_mul(d("1.5"))?.to_string(), "2.25");
+        assert_eq!(d("2").checked_div(d("3"))?.to_string(), "0.666667");
+        assert_eq!(d("-1").checked_div(d("8"))?.to_string(), "-0.125000");
+        assert_eq!(d("10.5").checked_rem(d("3"))?.to_string(), "1.5");
+        assert!(d("1").checked_div(d("0.00")).is_err());
+        // Division doesn't overflow when the result fits, but keeps fewer
+        // fractional digits if necessary.
+        let one = d("1.00000000000000000000");
+        assert_eq!(one.checked_div(one)?.to_string(), "1.00000000000000000000");
+        let third = d("100000000000000000.00000000000000000000").checked_div(d("3"))?;
+        assert_eq!(third.to_string(), "33333333333333333.33333333333333333333");
+        let third = d("3").checked_div(d("0.000000000000000000000000000000000009"))?;
+        assert_eq!(third.to_string(), "333333333333333333333333333333333333.33");
+        assert!(d("10").checked_div(Decimal::new(1, 38)).is_err());
+        let divisor = Decimal::new(99999999999999999999999999999999999999, 38);
+        assert_eq!(
+            d("1").checked_div(divisor)?.to_string(),
+            "1.00000000000000000000000000000000000001"
+        );
+        let max = Decimal::new(i128::MAX, 0);
+        assert_eq!(max.checked_add(d("1")), Err(Error::OverflowError));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rounding() -> Result<()> {
+        let d = |s| Decimal::parse(s).unwrap();
+        assert_eq!(d("2.345").rescale(2)?.to_string(), "2.35");
+        assert_eq!(d("-2.345").rescale(2)?.to_string(), "-2.35");
+        assert_eq!(d("2.344").rescale(2)?.to_string(), "2.34");
+        assert_eq!(d("123.456").fit(5, 2)?.to_string(), "123.46");
+        assert!(d("1234.5").fit(5, 2).is_err());
+        assert_eq!(d("1.0"), d("1.000"));
+        assert_eq!(d("2.345").round(1)?.to_string(), "2.3");
+        assert_eq!(d("-1250").round(-2)?.to_string(), "-1300");
+        assert_eq!(d("1250").round(-40)?.to_string(), "0");
+        assert_eq!(d("-2.5").floor().to_string(), "-3");
+        assert_eq!(d("-2.5").ceil()?.to_string(), "-2");
+        assert_eq!(d("2.00").ceil()?.to_string(), "2");
+        assert_eq!(d("-2.125").to_exact_f64(), Some(-2.125));
+        assert_eq!(d("1.000").to_exact_f64(), Some(1.0));
+        assert_eq!(d("0.1").to_exact_f64(), None);
+        let small = Decimal::new(-15 * 10_i128.pow(37), 38);
+        assert_eq!(small.floor().to_string(), "-2");
+        assert_eq!(small.ceil()?.to_string(), "-1");
+        assert_eq!(Decimal::new(i128::MAX, 38).ceil()?.to_string(), "2");
+        Ok(())
+    }
+}
@@ -1,6 +1,9 @@
 use crate::common::{Error, Result};
-use crate::errinput;
+use crate::types::bytes;
+use crate::types::decimal::{self, Decimal};
+use crate::types::temporal::{self, Interval};
 use crate::types::DataType;
+use crate::{errdata, errinput};
 use serde::{Deserialize, Serialize};
 use std::ops::{Add, Div, Mul, Rem, Sub};
 
@@ -8,35 +11,67 @@ use std::ops::{Add, Div, Mul, Rem, Sub};
 pub enum Field {
     Null,
     Boolean(bool),
+    SmallInt(i16),
     Integer(i32),
+    BigInt(i64),
     Float(f32),
+    Double(f64),
+    Decimal(Decimal),
+    Date(i32),
+    Time(i64),
+    Timestamp(i64),
+    Interval(Interval),
     String(String),
+    Bytes(Vec<u8>),
+}
+
+/// The exact value of a number, such that numbers of different types compare
+/// and hash equal if they have the same value, e.g. INT 1, BIGINT 1 and DOUBLE
+/// 1.0. Used for GROUP BY, DISTINCT, set operations and the like.
+#[derive(PartialEq, Eq, Hash)]
+enum NumericKey {
+    /// An integral value, including integral floats and decimals.
+    Integer(i128),
+    /// A non-integral float, or a decimal that's exactly representable as
+    /// one, as f64 bits. NaNs are canonicalized.
+    Float(u64),
+    /// A decimal that isn't exactly representable as an f64.
+    Decimal(Decimal),
+}
+
+impl NumericKey {
+    fn from_f64(f: f64) -> Self {
+        match f {
+            f if f.is_nan() => Self::Float(f64::NAN.to_bits()),
+            // Also maps -0.0 to 0.
+            f if f.fract() == 0.0 && f.abs() < 2_f64.powi(127) => Self::Integer(f as i128),
+            f => Self::Float(f.to_bits()),
+        }
+    }
 }
 
 impl PartialEq for Field {
     fn eq(&self, other: &Field) -> bool {
-        match self {
-            Field::Null => match other {
-                Field::Null => true,
-                _ => false,
-            },
-            Field::Boolean(b) => match other {
-                Field::Boolean(b2) => b == b2,
-                _ => false,
-            },
-            Field::Integer(i) => match other {
-                Field::Integer(i2) => i == i2,
-                _ => false,
-            },
+        if let (Some(lhs), Some(rhs)) = (self.numeric_key(), other.numeric_key()) {
+            return lhs == rhs;
+        }
+        match (self, other) {
+            (Field::Null, Field::Null) => true,
+            (Field::Boolean(b), Field::Boolean(b2)) => b == b2,
+            (Field::SmallInt(i), Field::SmallInt(i2)) => i == i2,
+            (Field::Integer(i), Field::Integer(i2)) => i == i2,
+            (Field::BigInt(i), Field::BigInt(i2)) => i == i2,
             // match on NaN as well as equality
-            Field::Float(f) => match other {
-                Field::Float(f2) => (f == f2) || (f.is_nan() && f2.is_nan()),
-                _ => false,
-            },
-            Field::String(s) => match other {
-                Field::String(s2) => s == s2,
-                _ => false,
-            },
+            (Field::Float(f), Field::Float(f2)) => (f == f2) || (f.is_nan() && f2.is_nan()),
+            (Field::Double(f), Field::Double(f2)) => (f == f2) || (f.is_nan() && f2.is_nan()),
+            (Field::Decimal(d), Field::Decimal(d2)) => d == d2,
+            (Field::Date(d), Field::Date(d2)) => d == d2,
+            (Field::Time(t), Field::Time(t2)) => t == t2,
+            (Field::Timestamp(t), Field::Timestamp(t2)) => t == t2,
+            (Field::Interval(i), Field::Interval(i2)) => i == i2,
+            (Field::String(s), Field::String(s2)) => s == s2,
+            (Field::Bytes(b), Field::Bytes(b2)) => b == b2,
+            _ => false,
         }
     }
 }
@@ -45,10 +80,17 @@ impl Eq for Field {} // implement Eq trait for Field, uses PartialEq
 
 impl std::hash::Hash for Field {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        // Numbers of different types hash equal if they have the same value.
+        if let Some(key) = self.numeric_key() {
+            return key.hash(state);
+        }
+        core::mem::discriminant(self).hash(state);
         match self {
             Field::Null => 0.hash(state),
             Field::Boolean(b) => b.hash(state),
+            Field::SmallInt(i) => i.hash(state),
             Field::Integer(i) => i.hash(state),
+            Field::BigInt(i) => i.hash(state),
             Field::Float(f) => {
                 if f.is_nan() {
                     0.hash(state);
@@ -56,7 +98,20 @@ impl std::hash::Hash for Field {
                     f.to_bits().hash(state);
                 }
             }
+            Field::Double(f) => {
+                if f.is_nan() {
+                    0.hash(state);
+                } else {
+                    f.to_bits().hash(state);
+                }
+            }
+            Field::Decimal(d) => d.hash(state),
+            Field::Date(d) => d.hash(state),
+            Field::Time(t) => t.hash(state),
+            Field::Timestamp(t) => t.hash(state),
+            Field::Interval(i) => i.hash(state),
             Field::String(s) => s.hash(state),
+            Field::Bytes(b) => b.hash(state),
         }
     }
 }
@@ -64,27 +119,43 @@ impl std::hash::Hash for Field {
 // for use in sorting
 impl Ord for Field {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // NaN sorts after all other floats.
+        fn cmp_float(f: f64, f2: f64) -> std::cmp::Ordering {
+            match (f.is_nan(), f2.is_nan()) {
+                (true, true) => std::cmp::Ordering::Equal,
+                (true, false) => std::cmp::Ordering::Greater,
+                (false, true) => std::cmp::Ordering::Less,
+                (false, false) => f.partial_cmp(&f2).unwrap_or(std::cmp::Ordering::Equal),
+            }
+        }
+
         match (self, other) {
             (Field::Null, Field::Null) => std::cmp::Ordering::Equal,
-            (Field::Null, _) => std::cmp::Ordering::Less,
-            (_, Field::Null) => std::cmp::Ordering::Greater,
             (Field::Boolean(b), Field::Boolean(b2)) => b.cmp(b2),
+            (Field::SmallInt(i), Field::SmallInt(i2)) => i.cmp(i2),
             (Field::Integer(i), Field::Integer(i2)) => i.cmp(i2),
-
-            (Field::Float(f), Field::Float(f2)) => match (f.is_nan(), f2.is_nan()) {
-                (true, true) => std::cmp::Ordering::Equal,
-                (true, false) => std::cmp::Ordering::Greater,
-                (false, true) => std::cmp::Ordering::Less,
-                (false, false) => f.partial_cmp(f2).unwrap_or(std::cmp::Ordering::Equal),
-            },
+            (Field::BigInt(i), Field::BigInt(i2)) => i.cmp(i2),
+            (Field::Float(f), Field::Float(f2)) => cmp_float(*f as f64, *f2 as f64),
+            (Field::Double(f), Field::Double(f2)) => cmp_float(*f, *f2),
+            (Field::Decimal(d), Field::Decimal(d2)) => d.cmp(d2),
+            (Field::Date(d), Field::Date(d2)) => d.cmp(d2),
+            (Field::Time(t), Field::Time(t2)) => t.cmp(t2),
+            (Field::Timestamp(t), Field::Timestamp(t2)) => t.cmp(t2),
+            (Field::Interval(i), Field::Interval(i2)) => i.cmp(i2),
             (Field::String(s), Field::String(s2)) => s.cmp(s2),
-            (Field::Boolean(_), _) => std::cmp::Ordering::Less,
-            (Field::Integer(_), Field::Boolean(_)) => std::cmp::Ordering::Greater,
-            (Field::Integer(_), _) => std::cmp::Ordering::Less,
-            (Field::Float(_), Field::Boolean(_)) => std::cmp::Ordering::Greater,
-            (Field::Float(_), Field::Integer(_)) => std::cmp::Ordering::Greater,
-            (Field::Float(_), _) => std::cmp::Ordering::Less,
-            (Field::String(_), _) => std::cmp::Ordering::Greater,
+            (Field::Bytes(b), Field::Bytes(b2)) => b.cmp(b2),
+            // Numbers of different types are ordered by value, consistently
+            // with equality, and with ties broken by type.
+            (lhs, rhs) if lhs.is_numeric() && rhs.is_numeric() => match lhs == rhs {
+                true => std::cmp::Ordering::Equal,
+                false => match lhs.partial_cmp_numeric(rhs) {
+                    Some(ordering) if ordering.is_ne() => ordering,
+                    _ => cmp_float(lhs.as_f64().unwrap(), rhs.as_f64().unwrap())
+                        .then(lhs.type_rank().cmp(&rhs.type_rank())),
+                },
+            },
+            // Values of different types are ordered by type.
+            (lhs, rhs) => lhs.type_rank().cmp(&rhs.type_rank()),
         }
     }
 }
@@ -146,9 +217,18 @@ impl std::fmt::Display for Field {
             Self::Null => f.write_str("NULL"),
             Self::Boolean(true) => f.write_str("TRUE"),
             Self::Boolean(false) => f.write_str("FALSE"),
+            Self::SmallInt(integer) => integer.fmt(f),
             Self::Integer(integer) => integer.fmt(f),
+            Self::BigInt(integer) => integer.fmt(f),
             Self::Float(float) => write!(f, "{float:?}"),
+            Self::Double(double) => write!(f, "{double:?}"),
+            Self::Decimal(decimal) => decimal.fmt(f),
+            Self::Date(date) => write!(f, "DATE '{}'", temporal::format_date(*date)),
+            Self::Time(time) => write!(f, "TIME '{}'", temporal::format_time(*time)),
+            Self::Timestamp(ts) => write!(f, "TIMESTAMP '{}'", temporal::format_timestamp(*ts)),
+            Self::Interval(interval) => write!(f, "INTERVAL '{interval}'"),
             Self::String(string) => write!(f, "'{}'", string.escape_debug()),
+            Self::Bytes(b) => write!(f, "x'{}'", bytes::encode_hex(b)),
         }
     }
 }
@@ -159,12 +239,30 @@ impl From<f32> for Field {
     }
 }
 
+impl From<f64> for Field {
+    fn from(v: f64) -> Self {
+        Field::Double(v)
+    }
+}
+
+impl From<i16> for Field {
+    fn from(v: i16) -> Self {
+        Field::SmallInt(v)
+    }
+}
+
 impl From<i32> for Field {
     fn from(v: i32) -> Self {
         Field::Integer(v)
     }
 }
 
+impl From<i64> for Field {
+    fn from(v: i64) -> Self {
+        Field::BigInt(v)
+    }
+}
+
 impl From<String> for Field {
     fn from(v: String) -> Self {
         Field::String(v)
@@ -188,9 +286,18 @@ impl Field {
     pub fn new(d: DataType) -> Field {
         match d {
             DataType::Bool => Field::from(false),
+            DataType::SmallInt => Field::from(0i16),
             DataType::Int => Field::from(0i32),
-            DataType::Float => Field::from(0.0),
+            DataType::BigInt => Field::from(0i64),
+            DataType::Float => Field::from(0.0f32),
+            DataType::Double => Field::from(0.0f64),
+            DataType::Decimal(_, scale) => Field::Decimal(Decimal::new(0, scale)),
+            DataType::Date => Field::Date(0),
+            DataType::Time => Field::Time(0),
+            DataType::Timestamp => Field::Timestamp(0),
+            DataType::Interval => Field::Interval(Interval::default()),
             DataType::Text => Field::from("".to_string()),
+            DataType::Bytea => Field::Bytes(Vec::new()),
             DataType::Invalid => Field::Null,
         }
     }
@@ -198,9 +305,18 @@ impl Field {
         match self {
             Field::Null => DataType::Invalid,
             Field::Boolean(_) => DataType::Bool,
+            Field::SmallInt(_) => DataType::SmallInt,
             Field::Integer(_) => DataType::Int,
+            Field::BigInt(_) => DataType::BigInt,
             Field::Float(_) => DataType::Float,
+            Field::Double(_) => DataType::Double,
+            Field::Decimal(d) => DataType::Decimal(decimal::MAX_PRECISION, d.scale()),
+            Field::Date(_) => DataType::Date,
+            Field::Time(_) => DataType::Time,
+            Field::Timestamp(_) => DataType::Timestamp,
+            Field::Interval(_) => DataType::Interval,
             Field::String(_) => DataType::Text,
+            Field::Bytes(_) => DataType::Bytea,
         }
     }
     // size in bytes
@@ -208,156 +324,527 @@ impl Field {
         match self {
             Field::Null => 0,
             Field::Boolean(_) => 1,
+            Field::SmallInt(_) => 2,
             Field::Integer(_) => 4,
+            Field::BigInt(_) => 8,
             Field::Float(_) => 4,
+            Field::Double(_) => 8,
+            Field::Decimal(_) => 16,
+            Field::Date(_) => 4,
+            Field::Time(_) => 8,
+            Field::Timestamp(_) => 8,
+            Field::Interval(_) => 16,
             Field::String(s) => s.len() as u16,
+            Field::Bytes(b) => b.len() as u16,
         }
     }
     pub fn to_string(&self) -> String {
         match self {
             Field::Null => "NULL".to_string(),
             Field::Boolean(b) => b.to_string(),
+            Field::SmallInt(i) => i.to_string(),
             Field::Integer(i) => i.to_string(),
+            Field::BigInt(i) => i.to_string(),
             Field::Float(f) => f.to_string(),
+            Field::Double(f) => f.to_string(),
+            Field::Decimal(d) => d.to_string(),
+            Field::Date(d) => temporal::format_date(*d),
+            Field::Time(t) => temporal::format_time(*t),
+            Field::Timestamp(t) => temporal::format_timestamp(*t),
+            Field::Interval(i) => i.to_string(),
             Field::String(s) => s.clone(),
+            Field::Bytes(b) => format!("\\x{}", bytes::encode_hex(b)),
         }
     }
-    pub fn checked_add(&self, other: &Field) -> Result<Field> {
+
+    /// The position of the value's type in the cross-type sort order.
+    fn type_rank(&self) -> u8 {
+        match self {
+            Field::Null => 0,
+            Field::Boolean(_) => 1,
+            Field::SmallInt(_) => 2,
+            Field::Integer(_) => 3,
+            Field::BigInt(_) => 4,
+            Field::Decimal(_) => 5,
+            Field::Float(_) => 6,
+            Field::Double(_) => 7,
+            Field::Date(_) => 8,
+            Field::Time(_) => 9,
+            Field::Timestamp(_) => 10,
+            Field::Interval(_) => 11,
+            Field::String(_) => 12,
+            Field::Bytes(_) => 13,
+        }
+    }
+
+    /// Returns true if the value is an integer, decimal or floating point
+    /// number.
+    pub fn is_numeric(&self) -> bool {
+        self.get_type().is_numeric()
+    }
+
+    /// Returns true if the value is a date, time, timestamp or interval.
+    pub fn is_temporal(&self) -> bool {
+        self.get_type().is_temporal()
+    }
+
+    /// Compares two dates, times, timestamps or intervals. Dates and
+    /// timestamps can be compared with each other, with the date taken as
+    /// midnight. Errors for other type combinations.
+    pub fn cmp_temporal(&self, other: &Field) -> Result<std::cmp::Ordering> {
         use Field::*;
-        match (&self, other) {
-            (Integer(lhs), Integer(rhs)) => match lhs.checked_add(*rhs) {
-                Some(v) => Ok(Integer(v)),
-                None => Result::from(Error::OverflowError),
+        Ok(match (self, other) {
+            (Date(lhs), Date(rhs)) => lhs.cmp(rhs),
+            (Time(lhs), Time(rhs)) => lhs.cmp(rhs),
+            (Timestamp(lhs), Timestamp(rhs)) => lhs.cmp(rhs),
+            (Date(lhs), Timestamp(rhs)) => temporal::date_to_timestamp(*lhs)?.cmp(rhs),
+            (Timestamp(lhs), Date(rhs)) => lhs.cmp(&temporal::date_to_timestamp(*rhs)?),
+            (Interval(lhs), Interval(rhs)) => lhs.cmp(rhs),
+            (lhs, rhs) => return errinput!("can't compare {lhs} and {rhs}"),
+        })
+    }
+
+    /// Returns the exact value of a number, or None for other values.
+    fn numeric_key(&self) -> Option<NumericKey> {
+        Some(match self {
+            Field::SmallInt(i) => NumericKey::Integer(*i as i128),
+            Field::Integer(i) => NumericKey::Integer(*i as i128),
+            Field::BigInt(i) => NumericKey::Integer(*i as i128),
+            Field::Float(f) => NumericKey::from_f64(*f as f64),
+            Field::Double(f) => NumericKey::from_f64(*f),
+            Field::Decimal(d) => match d.to_exact_f64() {
+                Some(f) => NumericKey::from_f64(f),
+                None if d.floor() == *d => NumericKey::Integer(d.floor().mantissa()),
+                None => NumericKey::Decimal(*d),
             },
-            (Integer(lhs), Float(rhs)) => {
-                let result = (*lhs as f32) + rhs;
-                Ok(Float(result))
+            _ => return None,
+        })
+    }
+
+    /// Returns the value as an i64, if it is an integer of any width.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Field::SmallInt(i) => Some(*i as i64),
+            Field::Integer(i) => Some(*i as i64),
+            Field::BigInt(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as an f64, if it is a number of any type.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Field::SmallInt(i) => Some(*i as f64),
+            Field::Integer(i) => Some(*i as f64),
+            Field::BigInt(i) => Some(*i as f64),
+            Field::Float(f) => Some(*f as f64),
+            Field::Double(f) => Some(*f),
+            Field::Decimal(d) => Some(d.to_f64()),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a decimal, if it is an integer, decimal or finite
+    /// REAL. REALs are converted via their shortest decimal representation.
+    fn as_decimal(&self) -> Option<Decimal> {
+        match self {
+            Field::Decimal(d) => Some(*d),
+            Field::Float(f) => Decimal::parse(&f.to_string()).ok(),
+            value => value.as_i64().map(Decimal::from),
+        }
+    }
+
+    /// Returns the value as an f64 for use as a DOUBLE. REAL values are widened
+    /// via their shortest decimal representation, such that e.g. REAL 0.1
+    /// becomes DOUBLE 0.1 rather than 0.10000000149011612.
+    pub fn as_double(&self) -> Option<f64> {
+        match self {
+            Field::Float(f) if f.is_finite() => f.to_string().parse().ok(),
+            value => value.as_f64(),
+        }
+    }
+
+    /// Returns the values of two numbers as f64s for floating point
+    /// arithmetic in the given common type.
+    fn float_operands(&self, other: &Field, data_type: DataType) -> (f64, f64) {
+        match data_type {
+            DataType::Double => (self.as_double().unwrap(), other.as_double().unwrap()),
+            _ => (self.as_f64().unwrap(), other.as_f64().unwrap()),
+        }
+    }
+
+    /// Builds an integer value of the given integer type, erroring if it
+    /// doesn't fit.
+    fn from_i64(value: i64, data_type: DataType) -> Result<Field> {
+        Ok(match data_type {
+            DataType::SmallInt => Field::SmallInt(value.try_into().or(Err(Error::OverflowError))?),
+            DataType::Int => Field::Integer(value.try_into().or(Err(Error::OverflowError))?),
+            DataType::BigInt => Field::BigInt(value),
+            data_type => return errdata!("{data_type} is not an integer type"),
+        })
+    }
+
+    /// Builds a floating point value of the given float type.
+    fn from_f64(value: f64, data_type: DataType) -> Result<Field> {
+        Ok(match data_type {
+            DataType::Float => Field::Float(value as f32),
+            DataType::Double => Field::Double(value),
+            data_type => return errdata!("{data_type} is not a floating point type"),
+        })
+    }
+
+    /// Returns the common type that two numeric operands are widened to before
+    /// arithmetic or comparison, or None if either operand isn't a number.
+    ///
+    /// Integers widen to the wider integer type, and integers mixed with
+    /// decimals yield a decimal. So do REALs mixed with decimals, since float
+    /// literals such as 0.1 are REALs. Otherwise, mixing floats with integers
+    /// or decimals yields a float, which is a DOUBLE if either operand is 64
+    /// bits wide or a decimal.
+    pub fn numeric_type(lhs: &Field, rhs: &Field) -> Option<DataType> {
+        lhs.get_type().numeric_type(rhs.get_type())
+    }
+
+    /// Applies an arithmetic operation to two numbers, after widening them to
+    /// their common type. Integer and decimal operations are checked for
+    /// overflow against the common type. NULLs yield NULL.
+    fn checked_arithmetic(
+        &self,
+        other: &Field,
+        verb: &str,
+        int: impl Fn(i64, i64) -> Option<i64>,
+        decimal: impl Fn(Decimal, Decimal) -> Result<Decimal>,
+        float: impl Fn(f64, f64) -> f64,
+    ) -> Result<Field> {
+        let Some(data_type) = Self::numeric_type(self, other) else {
+            return match (self, other) {
+                (Field::Null, Field::Null) => Ok(Field::Null),
+                (Field::Null, v) | (v, Field::Null) if v.is_numeric() || v.is_temporal() => {
+                    Ok(Field::Null)
+                }
+                _ => errdata!("Cannot {verb} {self:?} and {other:?}"),
+            };
+        };
+        if data_type.is_integer() {
+            let (lhs, rhs) = (self.as_i64().unwrap(), other.as_i64().unwrap());
+            let result = int(lhs, rhs).ok_or(Error::OverflowError)?;
+            Self::from_i64(result, data_type)
+        } else if let DataType::Decimal(_, _) = data_type {
+            let (Some(lhs), Some(rhs)) = (self.as_decimal(), other.as_decimal()) else {
+                return errinput!("can't {verb} {self} and {other} as decimals");
+            };
+            Ok(Field::Decimal(decimal(lhs, rhs)?))
+        } else {
+            let (lhs, rhs) = self.float_operands(other, data_type);
+            Self::from_f64(float(lhs, rhs), data_type)
+        }
+    }
+
+    pub fn checked_add(&self, other: &Field) -> Result<Field> {
+        use Field::*;
+        match (self, other) {
+            // Adding an integer to a date adds days. A saturated sum is out of
+            // range too.
+            (Date(date), n) | (n, Date(date)) if n.get_type().is_integer() => {
+                let days = n.as_i64().unwrap().saturating_add(*date as i64);
+                Ok(Date(temporal::check_date(days)?))
             }
-            (Float(lhs), Integer(rhs)) => Ok(Float(lhs + (*rhs as f32))),
-            (Float(lhs), Float(rhs)) => Ok(Float(lhs + rhs)),
-            (Null, Integer(_)) | (Null, Float(_)) => Ok(Null),
-            (Integer(_), Null) | (Float(_), Null) => Ok(Null),
-            (Null, Null) => Ok(Null),
-            _ => {
-                let msg = format!("Cannot add {:?} and {:?}", self, other);
-                Result::from(Error::InvalidData(msg))
+            // A time is less than a day, so this stays within the date's range.
+            (Date(date), Time(time)) | (Time(time), Date(date)) => {
+                Ok(Timestamp(temporal::date_to_timestamp(*date)? + time))
+            }
+            (Date(date), Interval(interval)) | (Interval(interval), Date(date)) => Ok(Timestamp(
+                temporal::add_interval(temporal::date_to_timestamp(*date)?, interval)?,
+            )),
+            (Timestamp(ts), Interval(interval)) | (Interval(interval), Timestamp(ts)) => {
+                Ok(Timestamp(temporal::add_interval(*ts, interval)?))
             }
+            (Time(time), Interval(interval)) | (Interval(interval), Time(time)) => {
+                Ok(Time(temporal::add_interval_to_time(*time, interval)))
+            }
+            (Interval(lhs), Interval(rhs)) => Ok(Interval(lhs.checked_add(rhs)?)),
+            _ => self.checked_arithmetic(
+                other,
+                "add",
+                i64::checked_add,
+                decimal::Decimal::checked_add,
+                |l, r| l + r,
+            ),
         }
     }
 
     pub fn checked_sub(&self, other: &Field) -> Result<Field> {
         use Field::*;
-        match (&self, other) {
-            (Integer(lhs), Integer(rhs)) => match lhs.checked_sub(*rhs) {
-                Some(v) => Ok(Integer(v)),
-                None => Result::from(Error::OverflowError),
-            },
-            (Integer(lhs), Float(rhs)) => Ok(Float((*lhs as f32) - rhs)),
-            (Float(lhs), Integer(rhs)) => Ok(Float(lhs - (*rhs as f32))),
-            (Float(lhs), Float(rhs)) => Ok(Float(lhs - rhs)),
-            (Null, Integer(_)) | (Null, Float(_)) => Ok(Null),
-            (Integer(_), Null) | (Float(_), Null) => Ok(Null),
-            (Null, Null) => Ok(Null),
-            _ => {
-                let msg = format!("Cannot subtract {:?} and {:?}", self, other);
-                Result::from(Error::InvalidData(msg))
+        match (self, other) {
+            // Subtracting dates yields the number of days between them.
+            (Date(lhs), Date(rhs)) => {
+                Ok(Integer(lhs.checked_sub(*rhs).ok_or(Error::OverflowError)?))
+            }
+            (Date(_), n) if n.get_type().is_integer() => self.checked_add(&n.checked_neg()?),
+            (Date(_) | Time(_) | Timestamp(_) | Interval(_), Interval(_)) => {
+                self.checked_add(&other.checked_neg()?)
+            }
+            (Timestamp(lhs), Timestamp(rhs)) => {
+                Ok(Interval(temporal::timestamp_difference(*lhs, *rhs)?))
             }
+            (Time(lhs), Time(rhs)) => Ok(Interval(temporal::Interval::new(0, 0, lhs - rhs))),
+            _ => self.checked_arithmetic(
+                other,
+                "subtract",
+                i64::checked_sub,
+                decimal::Decimal::checked_sub,
+                |l, r| l - r,
+            ),
         }
     }
 
     pub fn checked_mul(&self, other: &Field) -> Result<Field> {
         use Field::*;
-        match (&self, other) {
-            (Integer(lhs), Integer(rhs)) => match lhs.checked_mul(*rhs) {
-                Some(v) => Ok(Integer(v)),
-                None => Result::from(Error::OverflowError),
-            },
-            (Integer(lhs), Float(rhs)) => Ok(Float((*lhs as f32) * rhs)),
-            (Float(lhs), Integer(rhs)) => Ok(Float(lhs * (*rhs as f32))),
-            (Float(lhs), Float(rhs)) => Ok(Float(lhs * rhs)),
-            (Null, Integer(_)) | (Null, Float(_)) => Ok(Null),
-            (Integer(_), Null) | (Float(_), Null) => Ok(Null),
-            (Null, Null) => Ok(Null),
-            _ => {
-                let msg = format!("Cannot multiply {:?} and {:?}", self, other);
-                Result::from(Error::InvalidData(msg))
+        match (self, other) {
+            (Interval(interval), n) | (n, Interval(interval)) if n.get_type().is_integer() => {
+                Ok(Interval(interval.checked_mul(n.as_i64().unwrap())?))
             }
+            _ => self.checked_arithmetic(
+                other,
+                "multiply",
+                i64::checked_mul,
+                decimal::Decimal::checked_mul,
+                |l, r| l * r,
+            ),
         }
     }
 
-    pub fn checked_div(&self, other: &Field) -> Result<Field> {
+    /// Negates a number or interval. Errors on integer or decimal overflow.
+    pub fn checked_neg(&self) -> Result<Field> {
         use Field::*;
+        Ok(match self {
+            SmallInt(i) => SmallInt(i.checked_neg().ok_or(Error::OverflowError)?),
+            Integer(i) => Integer(i.checked_neg().ok_or(Error::OverflowError)?),
+            BigInt(i) => BigInt(i.checked_neg().ok_or(Error::OverflowError)?),
+            Float(f) => Float(-f),
+            Double(f) => Double(-f),
+            Field::Decimal(d) => Field::Decimal(d.checked_neg()?),
+            Interval(interval) => Interval(interval.checked_neg()?),
+            Null => Null,
+            value => return errinput!("can't negate {value}"),
+        })
+    }
+
+    pub fn checked_div(&self, other: &Field) -> Result<Field> {
+        Self::check_divisor(other)?;
 
-        if matches!(other, Integer(0) | Float(0.0)) {
+        // Integer division yields an integer if it's exact, otherwise a float
+        // of the corresponding width. The remainder overflows for MIN / -1,
+        // in which case so does the division.
+        if let (Some(lhs), Some(rhs)) = (self.as_i64(), other.as_i64()) {
+            if lhs.checked_rem(rhs).is_some_and(|rem| rem != 0) {
+                let data_type = match Self::numeric_type(self, other) {
+                    Some(DataType::BigInt) => DataType::Double,
+                    _ => DataType::Float,
+                };
+                return Self::from_f64(lhs as f64 / rhs as f64, data_type);
+            }
+        }
+        self.checked_arithmetic(
+            other,
+            "divide",
+            i64::checked_div,
+            decimal::Decimal::checked_div,
+            |l, r| l / r,
+        )
+    }
+
+    /// Errors if a divisor is zero.
+    fn check_divisor(divisor: &Field) -> Result<()> {
+        if divisor.as_f64() == Some(0.0) {
             return Err(Error::InvalidData("Division by zero".to_string()));
         }
+        Ok(())
+    }
 
-        match (self, other) {
-            (Integer(lhs), Integer(rhs)) => {
-                if lhs % rhs == 0 {
-                    Ok(Integer(lhs / rhs))
-                } else {
-                    Ok(Float((*lhs as f32) / (*rhs as f32)))
+    /// Exponentiates two values. Errors when invalid.
+    pub fn checked_pow(&self, other: &Self) -> Result<Self> {
+        use Field::*;
+        let Some(data_type) = Self::numeric_type(self, other) else {
+            return match (self, other) {
+                (Null, Null) => Ok(Null),
+                (Null, v) | (v, Null) if v.is_numeric() => Ok(Null),
+                (lhs, rhs) => errinput!("can't exponentiate {lhs} and {rhs}"),
+            };
+        };
+        match (self.as_i64(), other.as_i64()) {
+            (Some(lhs), Some(rhs)) if rhs >= 0 => {
+                let rhs = rhs.try_into().or_else(|_| errinput!("integer overflow"))?;
+                match lhs.checked_pow(rhs) {
+                    Some(i) => {
+                        Self::from_i64(i, data_type).or_else(|_| errinput!("integer overflow"))
+                    }
+                    None => errinput!("integer overflow"),
                 }
             }
-            (Integer(lhs), Float(rhs)) => Ok(Float((*lhs as f32) / *rhs)),
-            (Float(lhs), Integer(rhs)) => Ok(Float(*lhs / (*rhs as f32))),
-            (Float(lhs), Float(rhs)) => Ok(Float(*lhs / *rhs)),
-            (Null, Integer(_)) | (Null, Float(_)) => Ok(Null),
-            (Integer(_), Null) | (Float(_), Null) => Ok(Null),
-            (Null, Null) => Ok(Null),
+            // Negative integer exponents yield a float.
+            (Some(lhs), Some(rhs)) => {
+                let data_type = match data_type {
+                    DataType::BigInt => DataType::Double,
+                    _ => DataType::Float,
+                };
+                Self::from_f64((lhs as f64).powf(rhs as f64), data_type)
+            }
+            // Decimals raised to non-negative integer exponents stay exact.
+            (_, Some(rhs)) if matches!(data_type, DataType::Decimal(_, _)) && rhs >= 0 => {
+                let rhs = rhs.try_into().or_else(|_| errinput!("decimal overflow"))?;
+                Ok(Field::Decimal(self.as_decimal().unwrap().checked_pow(rhs)?))
+            }
             _ => {
-                let msg = format!("Cannot divide {:?} and {:?}", self, other);
-                Err(Error::InvalidData(msg))
+                let data_type = match data_type {
+                    DataType::Decimal(_, _) => DataType::Double,
+                    data_type => data_type,
+                };
+                let (lhs, rhs) = self.float_operands(other, data_type);
+                Self::from_f64(lhs.powf(rhs), data_type)
             }
         }
     }
 
-    /// Exponentiates two values. Errors when invalid.
-    pub fn checked_pow(&self, other: &Self) -> Result<Self> {
-        use Field::*;
-        Ok(match (self, other) {
-            (Integer(lhs), Integer(rhs)) if *rhs >= 0 => {
-                let rhs = (*rhs)
+    pub fn checked_mod(&self, other: &Field) -> Result<Field> {
+        Self::check_divisor(other)?;
+        self.checked_arithmetic(
+            other,
+            "mod",
+            i64::checked_rem,
+            decimal::Decimal::checked_rem,
+            |l, r| l % r,
+        )
+    }
+
+    /// Compares two numbers of any type, after widening them to their common
+    /// type. Returns None if either value isn't a number, or if the values are
+    /// unordered (i.e. NaN).
+    pub fn partial_cmp_numeric(&self, other: &Field) -> Option<std::cmp::Ordering> {
+        let data_type = Self::numeric_type(self, other)?;
+        if data_type.is_integer() {
+            self.as_i64()?.partial_cmp(&other.as_i64()?)
+        } else if let DataType::Decimal(_, _) = data_type {
+            self.as_decimal()?.partial_cmp(&other.as_decimal()?)
+        } else {
+            let (lhs, rhs) = self.float_operands(other, data_type);
+            lhs.partial_cmp(&rhs)
+        }
+    }
+
+    /// Converts a number to the given numeric type, e.g. to store an INT value
+    /// in a BIGINT column. Integers must fit in the target type, numbers are
+    /// rounded to the scale of a target decimal and must fit its precision, and
+    /// floats and decimals can't be converted to integers. Other values are
+    /// returned unchanged.
+    pub fn convert_numeric(self, to: DataType) -> Result<Field> {
+        if !self.is_numeric() || !to.is_numeric() || self.get_type() == to {
+            return Ok(self);
+        }
+        if let DataType::Decimal(precision, scale) = to {
+            let decimal = match self.as_decimal() {
+                Some(decimal) => decimal,
+                None => Decimal::from_f64(self.as_double().unwrap(), scale)?,
+            };
+            return Ok(Field::Decimal(decimal.fit(precision, scale)?));
+        }
+        match self.as_i64() {
+            Some(i) if to.is_integer() => Self::from_i64(i, to),
+            Some(i) => Self::from_f64(i as f64, to),
+            None if to.is_integer() => errinput!("can't convert {self} to {to}"),
+            None => Self::from_f64(self.as_double().unwrap(), to),
+        }
+    }
+
+    /// Explicitly converts a value to the given type, i.e. CAST(value AS type).
+    /// Numbers are rounded when converted to integers or smaller decimals, and
+    /// strings are parsed. NULL casts to NULL. Errors if the value can't be
+    /// represented in the given type.
+    pub fn cast(self, to: DataType) -> Result<Field> {
+        use DataType::*;
+        let invalid = |value: &Field| errinput!("invalid input for type {to}: {value}");
+        Ok(match (self, to) {
+            (Field::Null, _) => Field::Null,
+            (value, to) if value.get_type() == to => value,
+
+            // Floats and decimals are rounded half away from zero.
+            (value @ (Field::Float(_) | Field::Double(_)), to) if to.is_integer() => {
+                let f = value.as_f64().unwrap().round();
+                if !f.is_finite() || f < i64::MIN as f64 || f >= i64::MAX as f64 {
+                    return Err(Error::OverflowError);
+                }
+                Self::from_i64(f as i64, to)?
+            }
+            (Field::Decimal(d), to) if to.is_integer() => {
+                let i = d
+                    .rescale(0)?
+                    .mantissa()
                     .try_into()
-                    .or_else(|_| errinput!("integer overflow"))?;
-                match lhs.checked_pow(rhs) {
-                    Some(i) => Integer(i),
-                    None => return errinput!("integer overflow"),
+                    .or(Err(Error::OverflowError))?;
+                Self::from_i64(i, to)?
+            }
+            (value, to) if value.is_numeric() && to.is_numeric() => value.convert_numeric(to)?,
+            (Field::Boolean(b), to) if to.is_integer() => Self::from_i64(b as i64, to)?,
+            (value, Bool) if value.get_type().is_integer() => {
+                Field::Boolean(value.as_i64() != Some(0))
+            }
+
+            (Field::Date(d), Timestamp) => Field::Timestamp(temporal::date_to_timestamp(d)?),
+            (Field::Timestamp(t), Date) => Field::Date(temporal::check_date(
+                t.div_euclid(temporal::MICROS_PER_DAY),
+            )?),
+            (Field::Timestamp(t), Time) => Field::Time(t.rem_euclid(temporal::MICROS_PER_DAY)),
+
+            (value, Text) => Field::String(value.to_string()),
+            (Field::String(s), to) => {
+                let text = s.trim();
+                match to {
+                    Bool => match text.to_lowercase().as_str() {
+                        "t" | "true" | "y" | "yes" | "on" | "1" => Field::Boolean(true),
+                        "f" | "false" | "n" | "no" | "off" | "0" => Field::Boolean(false),
+                        _ => return invalid(&Field::String(s)),
+                    },
+                    to if to.is_integer() => match text.parse() {
+                        Ok(i) => Self::from_i64(i, to)?,
+                        Err(_) => return invalid(&Field::String(s)),
+                    },
+                    Float | Double => match text.parse() {
+                        Ok(f) => Self::from_f64(f, to)?,
+                        Err(_) => return invalid(&Field::String(s)),
+                    },
+                    DataType::Decimal(precision, scale) => {
+                        Field::Decimal(decimal::Decimal::parse(text)?.fit(precision, scale)?)
+                    }
+                    Date => Field::Date(temporal::parse_date(text)?),
+                    Time => Field::Time(temporal::parse_time(text)?),
+                    Timestamp => Field::Timestamp(temporal::parse_timestamp(text)?),
+                    Interval => Field::Interval(temporal::Interval::parse(text)?),
+                    // Byte strings are given as \x-prefixed hex, or escaped text.
+                    Bytea => match s.strip_prefix("\\x") {
+                        Some(hex) => Field::Bytes(bytes::decode_hex(hex)?),
+                        None => Field::Bytes(bytes::decode(&s, "escape")?),
+                    },
+                    to => return errinput!("can't cast {} to {to}", Field::String(s)),
                 }
             }
-            (Integer(lhs), Integer(rhs)) => Float((*lhs as f32).powf(*rhs as f32)),
-            (Integer(lhs), Float(rhs)) => Float((*lhs as f32).powf(*rhs)),
-            (Float(lhs), Integer(rhs)) => Float((lhs).powi(*rhs as i32)),
-            (Float(lhs), Float(rhs)) => Float((lhs).powf(*rhs)),
-            (Integer(_) | Float(_), Null) => Null,
-            (Null, Integer(_) | Float(_) | Null) => Null,
-            (lhs, rhs) => return errinput!("can't exponentiate {lhs} and {rhs}"),
+            (value, to) => return errinput!("can't cast {value} to {to}"),
         })
     }
 
-    pub fn checked_mod(&self, other: &Field) -> Result<Field> {
-        use Field::*;
-        match (&self, other) {
-            (Integer(lhs), Integer(rhs)) => match lhs.checked_rem(*rhs) {
-                Some(v) => Ok(Integer(v)),
-                None => Result::from(Error::OverflowError),
-            },
-            (Integer(lhs), Float(rhs)) => {
-                let result = (*lhs as f32) % rhs;
-                Ok(Float(result))
-            }
-            (Float(lhs), Integer(rhs)) => Ok(Float(lhs % (*rhs as f32))),
-            (Float(lhs), Float(rhs)) => Ok(Float(lhs % rhs)),
-            (Null, Integer(_)) | (Null, Float(_)) => Ok(Null),
-            (Integer(_), Null) | (Float(_), Null) => Ok(Null),
-            (Null, Null) => Ok(Null),
-            _ => {
-                let msg = format!("Cannot mod {:?} and {:?}", self, other);
-                Result::from(Error::InvalidData(msg))
+    /// Implicitly converts a value for storage in a column of the given type,
+    /// e.g. on INSERT and UPDATE. Numbers are converted as convert_numeric(),
+    /// strings are parsed as the column type, and any value can be stored as
+    /// text. NULL is returned as is. Errors for other type combinations.
+    pub fn coerce(self, to: DataType) -> Result<Field> {
+        match (&self, to) {
+            (Field::Null, _) => Ok(self),
+            (value, to) if value.is_numeric() && to.is_numeric() => self.convert_numeric(to),
+            (value, to) if value.get_type() == to => Ok(self),
+            (Field::String(_), _) | (_, DataType::Text) | (Field::Date(_), DataType::Timestamp) => {
+                self.cast(to)
             }
+            (value, to) => errinput!("can't store {value} in a {to} column"),
         }
-        //  _ =>  Null,
     }
 
     pub fn is_null(&self) -> bool {
@@ -377,9 +864,23 @@ impl Field {
                     vec![0]
                 }
             }
+            Field::SmallInt(i) => i.to_le_bytes().to_vec(),
             Field::Integer(i) => i.to_le_bytes().to_vec(),
+            Field::BigInt(i) => i.to_le_bytes().to_vec(),
             Field::Float(f) => f.to_le_bytes().to_vec(),
+            Field::Double(f) => f.to_le_bytes().to_vec(),
+            Field::Decimal(d) => d.mantissa().to_le_bytes().to_vec(),
+            Field::Date(d) => d.to_le_bytes().to_vec(),
+            Field::Time(t) => t.to_le_bytes().to_vec(),
+            Field::Timestamp(t) => t.to_le_bytes().to_vec(),
+            Field::Interval(i) => [
+                i.months.to_le_bytes().as_slice(),
+                &i.days.to_le_bytes(),
+                &i.micros.to_le_bytes(),
+            ]
+            .concat(),
             Field::String(s) => s.as_bytes().to_vec(),
+            Field::Bytes(b) => b.clone(),
         }
     }
 
@@ -392,16 +893,37 @@ impl Field {
                     Field::Boolean(true)
                 }
             }
+            DataType::SmallInt => Field::SmallInt(i16::from_le_bytes(data.try_into().unwrap())),
             DataType::Int => Field::Integer(i32::from_le_bytes(data.try_into().unwrap())),
+            DataType::BigInt => Field::BigInt(i64::from_le_bytes(data.try_into().unwrap())),
             DataType::Float => Field::Float(f32::from_le_bytes(data.try_into().unwrap())),
+            DataType::Double => Field::Double(f64::from_le_bytes(data.try_into().unwrap())),
+            DataType::Decimal(_, scale) => Field::Decimal(Decimal::new(
+                i128::from_le_bytes(data.try_into().unwrap()),
+                scale,
+            )),
+            DataType::Date => Field::Date(i32::from_le_bytes(data.try_into().unwrap())),
+            DataType::Time => Field::Time(i64::from_le_bytes(data.try_into().unwrap())),
+            DataType::Timestamp => Field::Timestamp(i64::from_le_bytes(data.try_into().unwrap())),
+            DataType::Interval => Field::Interval(Interval::new(
+                i32::from_le_bytes(data[0..4].try_into().unwrap()),
+                i32::from_le_bytes(data[4..8].try_into().unwrap()),
+                i64::from_le_bytes(data[8..16].try_into().unwrap()),
+            )),
             DataType::Text => Field::String(String::from_utf8(data.to_vec()).unwrap()),
+            DataType::Bytea => Field::Bytes(data.to_vec()),
             _ => Field::Null,
         }
     }
 
     /// Returns true if the value is undefined (NULL or NaN).
     pub fn is_undefined(&self) -> bool {
-        *self == Self::Null || matches!(self, Self::Float(f) if f.is_nan())
+        match self {
+            Self::Null => true,
+            Self::Float(f) => f.is_nan(),
+            Self::Double(f) => f.is_nan(),
+            _ => false,
+        }
     }
 }
 
@@ -444,6 +966,8 @@ impl From<Option<String>> for Label {
 
 #[allow(unused_imports)]
 mod tests {
+    use crate::common::Error;
+    use crate::types::decimal::Decimal;
     use crate::types::field::Field;
     use crate::types::DataType;
 
@@ -504,6 +1028,122 @@ mod tests {
         assert!(lhs > rhs);
     }
 
+    /// Asserts that a field has the expected value and type, since numbers of
+    /// different types compare equal.
+    #[cfg(test)]
+    fn assert_typed(actual: Field, expected: Field) {
+        assert_eq!(actual.get_type(), expected.get_type());
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    pub fn test_numeric_equality() {
+        use std::hash::{DefaultHasher, Hash, Hasher};
+        let hash = |field: &Field| {
+            let mut hasher = DefaultHasher::new();
+            field.hash(&mut hasher);
+            hasher.finish()
+        };
+        let d = |s| Field::Decimal(Decimal::parse(s).unwrap());
+        let ones = [
+            Field::SmallInt(1),
+            Field::Integer(1),
+            Field::BigInt(1),
+            Field::Float(1.0),
+            Field::Double(1.0),
+            d("1.00"),
+        ];
+        for (lhs, rhs) in ones.iter().zip(ones.iter().skip(1)) {
+            assert_eq!(lhs, rhs);
+            assert_eq!(lhs.cmp(rhs), std::cmp::Ordering::Equal);
+            assert_eq!(hash(lhs), hash(rhs));
+        }
+        assert_eq!(d("0.5"), Field::Double(0.5));
+        assert_eq!(hash(&d("0.5")), hash(&Field::Double(0.5)));
+        assert_eq!(Field::Double(-0.0), Field::Integer(0));
+        assert_ne!(d("0.1"), Field::Double(0.1));
+        assert_ne!(
+            Field::BigInt(1 << 53 | 1),
+            Field::Double((1_i64 << 53) as f64)
+        );
+        assert_ne!(Field::Integer(1), Field::String("1".to_string()));
+        assert!(Field::Integer(2) > Field::Double(1.5));
+        assert!(Field::Double(f64::NAN) > Field::BigInt(i64::MAX));
+    }
+
+    #[test]
+    pub fn test_numeric_widening() {
+        // Integers widen to the wider integer type.
+        let result = Field::SmallInt(7).checked_add(&Field::Integer(10)).unwrap();
+        assert_typed(result, Field::Integer(17));
+        let result = Field::Integer(i32::MAX)
+            .checked_add(&Field::BigInt(1))
+            .unwrap();
+        assert_typed(result, Field::BigInt(i32::MAX as i64 + 1));
+
+        // Overflow is checked against the common type.
+        assert!(Field::Integer(i32::MAX)
+            .checked_add(&Field::Integer(1))
+            .is_err());
+        assert!(Field::SmallInt(i16::MAX)
+            .checked_mul(&Field::SmallInt(2))
+            .is_err());
+
+        // Mixing integers and floats yields a float, DOUBLE if 64 bits are involved.
+        let result = Field::Integer(1).checked_add(&Field::Float(0.5)).unwrap();
+        assert_typed(result, Field::Float(1.5));
+        let result = Field::BigInt(1).checked_add(&Field::Float(0.5)).unwrap();
+        assert_typed(result, Field::Double(1.5));
+        let result = Field::Float(1.0).checked_div(&Field::Double(4.0)).unwrap();
+        assert_typed(result, Field::Double(0.25));
+
+        // Inexact integer division yields a float of the corresponding width.
+        let result = Field::BigInt(1).checked_div(&Field::Integer(4)).unwrap();
+        assert_typed(result, Field::Double(0.25));
+
+        // MIN / -1 overflows, and zero divisors error for both / and %.
+        let result = Field::BigInt(i64::MIN).checked_div(&Field::BigInt(-1));
+        assert_eq!(result, Err(Error::OverflowError));
+        let zero = Err(Error::InvalidData("Division by zero".to_string()));
+        assert_eq!(Field::Integer(7).checked_div(&Field::Integer(0)), zero);
+        assert_eq!(Field::Integer(7).checked_mod(&Field::Integer(0)), zero);
+
+        // Comparisons widen too.
+        let result = Field::BigInt(1 << 40).partial_cmp_numeric(&Field::Integer(7));
+        assert_eq!(result, Some(std::cmp::Ordering::Greater));
+    }
+
+    #[test]
+    pub fn test_convert_numeric() {
+        let result = Field::Integer(7).convert_numeric(DataType::BigInt).unwrap();
+        assert_typed(result, Field::BigInt(7));
+        let result = Field::Integer(7).convert_numeric(DataType::Double).unwrap();
+        assert_typed(result, Field::Double(7.0));
+        assert!(Field::Integer(1 << 20)
+            .convert_numeric(DataType::SmallInt)
+            .is_err());
+        assert!(Field::Float(1.5).convert_numeric(DataType::Int).is_err());
+    }
+
+    #[test]
+    pub fn test_decimal_arithmetic() {
+        let d = |s| Field::Decimal(Decimal::parse(s).unwrap());
+        let result = d("1.25").checked_add(&Field::Integer(2)).unwrap();
+        assert_eq!(result.to_string(), "3.25");
+        let result = d("0.10").checked_mul(&Field::Float(0.1)).unwrap();
+        assert_eq!(result.to_string(), "0.010");
+        let result = d("0.5").checked_add(&Field::Double(0.25)).unwrap();
+        assert_typed(result, Field::Double(0.75));
+        let result = d("1.5").checked_pow(&Field::Integer(2)).unwrap();
+        assert_eq!(result.to_string(), "2.25");
+        let result = Field::Integer(1).partial_cmp_numeric(&d("1.00"));
+        assert_eq!(result, Some(std::cmp::Ordering::Equal));
+        let result = Field::Float(2.345).convert_numeric(DataType::Decimal(4, 2));
+        assert_eq!(result.unwrap().to_string(), "2.35");
+        assert!(d("123.4").convert_numeric(DataType::Decimal(4, 2)).is_err());
+        assert!(d("1").convert_numeric(DataType::Int).is_err());
+    }
+
     #[test]
     pub fn test_serialization() {
         let v = Field::Integer(10);
@@ -512,6 +1152,17 @@ mod tests {
 
         assert_eq!(v, deserialized);
 
+        for v in [
+            Field::SmallInt(-3),
+            Field::BigInt(1 << 40),
+            Field::Double(0.1),
+            Field::Decimal(Decimal::new(-1234, 2)),
+        ] {
+            let serialized = v.serialize();
+            assert_eq!(serialized.len(), v.get_type().length_bytes() as usize);
+            assert_eq!(v, Field::deserialize(&serialized, v.get_type()));
+        }
+
         let s = Field::String("testing, 1, 2, 3".to_string());
         let serialized = s.serialize();
         let deserialized = Field::deserialize(&serialized, DataType::Text);
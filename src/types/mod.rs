@@ -1,4 +1,8 @@
+pub mod bytes;
+pub mod collation;
+pub mod decimal;
 pub mod field;
 mod schema;
+pub mod temporal;
 
-pub use schema::{Column, DataType, Table, TableBuilder};
+pub use schema::{Column, DataType, Table, TableBuilder, View};
@@ -1,15 +1,29 @@
+use crate::types::collation::Collation;
+use crate::types::decimal::MAX_PRECISION;
 use crate::types::field::Field;
 use core::ops::Deref;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 use std::fmt;
 use std::sync::Arc;
 
 #[derive(PartialEq, Eq, Hash, Clone, Debug, Copy, Serialize, Deserialize)]
 pub enum DataType {
     Bool,
+    SmallInt,
     Int,
+    BigInt,
     Float,
+    Double,
+    /// An exact fixed-point number with the given precision (total number of
+    /// digits) and scale (number of fractional digits).
+    Decimal(u8, u8),
+    Date,
+    Time,
+    Timestamp,
+    Interval,
     Text,
+    Bytea,
     Invalid,
 }
 
@@ -17,9 +31,18 @@ impl fmt::Display for DataType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             DataType::Bool => write!(f, "bool"),
+            DataType::SmallInt => write!(f, "smallint"),
             DataType::Int => write!(f, "int"),
+            DataType::BigInt => write!(f, "bigint"),
             DataType::Float => write!(f, "float"),
+            DataType::Double => write!(f, "double"),
+            DataType::Decimal(precision, scale) => write!(f, "decimal({precision},{scale})"),
+            DataType::Date => write!(f, "date"),
+            DataType::Time => write!(f, "time"),
+            DataType::Timestamp => write!(f, "timestamp"),
+            DataType::Interval => write!(f, "interval"),
             DataType::Text => write!(f, "varchar"),
+            DataType::Bytea => write!(f, "bytea"),
             DataType::Invalid => write!(f, "invalid"),
         }
     }
@@ -29,9 +52,18 @@ impl DataType {
     pub fn from_string(data_type: &str) -> DataType {
         match data_type {
             "Bool" => DataType::Bool,
+            "SmallInt" => DataType::SmallInt,
             "Int" => DataType::Int,
+            "BigInt" => DataType::BigInt,
             "Float" => DataType::Float,
+            "Double" => DataType::Double,
+            "Decimal" => DataType::Decimal(MAX_PRECISION, 0),
+            "Date" => DataType::Date,
+            "Time" => DataType::Time,
+            "Timestamp" => DataType::Timestamp,
+            "Interval" => DataType::Interval,
             "Text" => DataType::Text,
+            "Bytea" => DataType::Bytea,
             "Invalid" => DataType::Invalid,
             "Null" => DataType::Invalid,
             _ => panic!("Unknown data type"),
@@ -42,12 +74,93 @@ impl DataType {
     pub fn length_bytes(&self) -> u16 {
         match self {
             DataType::Bool => 1,
+            DataType::SmallInt => 2,
             DataType::Int => 4,
+            DataType::BigInt => 8,
             DataType::Float => 4,
+            DataType::Double => 8,
+            DataType::Decimal(_, _) => 16,
+            DataType::Date => 4,
+            DataType::Time => 8,
+            DataType::Timestamp => 8,
+            DataType::Interval => 16,
             DataType::Text => 0,
+            DataType::Bytea => 0,
             DataType::Invalid => 0,
         }
     }
+
+    /// Returns true for integer types of any width.
+    pub fn is_integer(&self) -> bool {
+        matches!(self, DataType::SmallInt | DataType::Int | DataType::BigInt)
+    }
+
+    /// Returns true for integer, decimal and floating point types.
+    pub fn is_numeric(&self) -> bool {
+        self.is_integer()
+            || matches!(
+                self,
+                DataType::Float | DataType::Double | DataType::Decimal(_, _)
+            )
+    }
+
+    /// Returns the common type that two numeric types are widened to, or None
+    /// if either isn't numeric. See Field::numeric_type for the rules.
+    pub fn numeric_type(self, other: DataType) -> Option<DataType> {
+        use DataType::*;
+        Some(match (self, other) {
+            (l, r) if !l.is_numeric() || !r.is_numeric() => return None,
+            (SmallInt, SmallInt) => SmallInt,
+            (l, r) if l.is_integer() && r.is_integer() => match (l, r) {
+                (BigInt, _) | (_, BigInt) => BigInt,
+                _ => Int,
+            },
+            (Decimal(p, s), t) | (t, Decimal(p, s)) if t != Double => Decimal(p, s),
+            (Double | BigInt | Decimal(_, _), _) | (_, Double | BigInt | Decimal(_, _)) => Double,
+            _ => Float,
+        })
+    }
+
+    /// Returns the type that values of both types can be converted to without
+    /// losing information, e.g. to combine the columns of a UNION, or None if
+    /// the types are incompatible. Unlike numeric_type, decimals are widened
+    /// to fit the integer digits of both types.
+    pub fn unify(self, other: DataType) -> Option<DataType> {
+        use DataType::*;
+        let digits = |data_type| match data_type {
+            SmallInt => Some((5, 0)),
+            Int => Some((10, 0)),
+            BigInt => Some((19, 0)),
+            Decimal(precision, scale) => Some((precision - scale, scale)),
+            _ => None,
+        };
+        match (self, other) {
+            (l, r) if l == r => Some(l),
+            (Decimal(_, _), t) | (t, Decimal(_, _))
+                if t.is_integer() || matches!(t, Decimal(_, _)) =>
+            {
+                let ((li, ls), (ri, rs)) = (digits(self)?, digits(other)?);
+                let scale = ls.max(rs);
+                Some(Decimal((li.max(ri) + scale).min(MAX_PRECISION), scale))
+            }
+            (Date, Timestamp) | (Timestamp, Date) => Some(Timestamp),
+            (l, r) => l.numeric_type(r),
+        }
+    }
+
+    /// Returns true for types stored in the variable-length section of a row,
+    /// i.e. strings and byte strings.
+    pub fn is_variable_length(&self) -> bool {
+        matches!(self, DataType::Text | DataType::Bytea)
+    }
+
+    /// Returns true for dates, times, timestamps and intervals.
+    pub fn is_temporal(&self) -> bool {
+        matches!(
+            self,
+            DataType::Date | DataType::Time | DataType::Timestamp | DataType::Interval
+        )
+    }
 }
 
 #[derive(PartialEq, Eq, Hash, Clone, Debug, Serialize, Deserialize)]
@@ -64,6 +177,13 @@ pub struct Column {
     default: Option<Field>,
     /// 0 for varchar / bound of MAX_STRING_LENGTH
     max_str_len: u16,
+    /// The collation used to compare and order string values.
+    collation: Collation,
+    /// Whether the column is (part of) the table's primary key. Like unique,
+    /// this is only enforced by INSERT ... ON CONFLICT.
+    primary_key: bool,
+    /// Whether the column has a unique constraint.
+    unique: bool,
     /// For fixed length fields: The offset in bytes of the field from the start of the field data
     /// For variable length fields: The index of the offset, rather than the offset itself.
     ///
@@ -89,6 +209,9 @@ impl Column {
                 None => None,
             },
             max_str_len: max_str_chars.unwrap_or(0),
+            collation: Collation::Binary,
+            primary_key: false,
+            unique: false,
             stored_offset: 0,
         }
     }
@@ -137,6 +260,30 @@ impl Column {
     pub fn get_max_str_len(&self) -> u16 {
         self.max_str_len
     }
+
+    pub fn set_collation(&mut self, collation: Collation) {
+        self.collation = collation;
+    }
+
+    pub fn collation(&self) -> Collation {
+        self.collation
+    }
+
+    pub fn set_primary_key(&mut self, primary_key: bool) {
+        self.primary_key = primary_key;
+    }
+
+    pub fn is_primary_key(&self) -> bool {
+        self.primary_key
+    }
+
+    pub fn set_unique(&mut self, unique: bool) {
+        self.unique = unique;
+    }
+
+    pub fn is_unique(&self) -> bool {
+        self.unique
+    }
 }
 
 pub struct ColumnBuilder {
@@ -145,6 +292,7 @@ pub struct ColumnBuilder {
     nullable: Option<bool>,
     default: Option<Field>,
     max_str_len: Option<u16>,
+    collation: Option<Collation>,
 }
 
 impl ColumnBuilder {
@@ -155,6 +303,7 @@ impl ColumnBuilder {
             nullable: None,
             default: None,
             max_str_len: None,
+            collation: None,
         }
     }
 
@@ -185,6 +334,11 @@ impl ColumnBuilder {
         self
     }
 
+    pub fn collation(mut self, collation: Collation) -> Self {
+        self.collation = Some(collation);
+        self
+    }
+
     pub fn build(self) -> Column {
         let nullable = self.nullable.unwrap_or(false);
         Column {
@@ -199,6 +353,9 @@ impl ColumnBuilder {
                 None => None,
             },
             max_str_len: self.max_str_len.unwrap_or(0),
+            collation: self.collation.unwrap_or_default(),
+            primary_key: false,
+            unique: false,
             stored_offset: 0,
         }
     }
@@ -212,6 +369,9 @@ impl From<DataType> for Column {
             nullable: false,
             default: None,
             max_str_len: 0,
+            collation: Collation::Binary,
+            primary_key: false,
+            unique: false,
             stored_offset: 0,
         }
     }
@@ -225,6 +385,9 @@ impl From<(DataType, u16)> for Column {
             nullable: false,
             default: None,
             max_str_len: str_len,
+            collation: Collation::Binary,
+            primary_key: false,
+            unique: false,
             stored_offset: 0,
         }
     }
@@ -265,7 +428,7 @@ impl Table {
         let data_type = column.get_data_type();
         let mut to_push = column.clone();
 
-        if data_type == DataType::Text {
+        if data_type.is_variable_length() {
             to_push.stored_offset = self.variable_length_fields() as u16;
             self.columns.push(to_push);
         } else {
@@ -346,7 +509,7 @@ impl Table {
     pub fn variable_length_fields(&self) -> usize {
         self.columns
             .iter()
-            .filter(|&col| col.get_data_type() == DataType::Text)
+            .filter(|&col| col.get_data_type().is_variable_length())
             .count()
     }
 
@@ -357,7 +520,7 @@ impl Table {
 
         schema.fixed_field_size_bytes = 0;
         for i in 0..schema.col_count() {
-            if schema.columns[i].data_type != DataType::Text {
+            if !schema.columns[i].data_type.is_variable_length() {
                 schema.columns[i].stored_offset = schema.fixed_field_size_bytes;
                 schema.fixed_field_size_bytes += schema.columns[i].data_type.length_bytes();
             }
@@ -408,6 +571,23 @@ impl Deref for Table {
     }
 }
 
+/// A view, i.e. a named query. A plain view is expanded into its query when
+/// referenced, while a materialized view stores the query's rows in a table
+/// of the same name, which is recomputed on REFRESH MATERIALIZED VIEW.
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Serialize, Deserialize)]
+pub struct View {
+    /// The name of the view.
+    pub name: String,
+    /// The view's query, as SQL text. It's parsed and planned when used, such
+    /// that it picks up changes to the tables it references.
+    pub query: String,
+    /// Whether the view is materialized.
+    pub materialized: bool,
+    /// The views that the query references directly. A view can't be dropped
+    /// while other views depend on it.
+    pub dependencies: BTreeSet<String>,
+}
+
 #[derive(Default)]
 pub struct TableBuilder {
     name: Option<String>,
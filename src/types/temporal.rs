@@ -0,0 +1,693 @@
+//! Dates, times, timestamps and intervals.
+//!
+//! Temporal values are stored as plain integers: a DATE is the number of days
+//! since 1970-01-01, a TIME is the number of microseconds since midnight, and a
+//! TIMESTAMP is the number of microseconds since 1970-01-01 00:00:00. There are
+//! no time zones. Calendar conversions use the proleptic Gregorian calendar.
+
+use crate::common::{Error, Result};
+use crate::errinput;
+use crate::types::field::Field;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const MICROS_PER_SECOND: i64 = 1_000_000;
+pub const MICROS_PER_MINUTE: i64 = 60 * MICROS_PER_SECOND;
+pub const MICROS_PER_HOUR: i64 = 60 * MICROS_PER_MINUTE;
+pub const MICROS_PER_DAY: i64 = 24 * MICROS_PER_HOUR;
+
+/// A time interval. Months and days are kept separate from the time part,
+/// since their lengths vary: adding 1 month to January 31st yields February
+/// 28th (or 29th), and adding 1 day is not always 24 hours in other databases.
+///
+/// For comparisons, a month counts as 30 days and a day as 24 hours, such that
+/// e.g. '1 mon' = '30 days'.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Interval {
+    pub months: i32,
+    pub days: i32,
+    pub micros: i64,
+}
+
+impl Interval {
+    pub fn new(months: i32, days: i32, micros: i64) -> Self {
+        Self {
+            months,
+            days,
+            micros,
+        }
+    }
+
+    /// Parses an interval such as '1 year 2 months 3 days 04:05:06.5' or
+    /// '-90 minutes'. Numbers may be fractional, in which case the fraction
+    /// cascades down to smaller units (e.g. '1.5 days' is 1 day 12 hours).
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut interval = Interval::default();
+        let mut tokens = s.split_whitespace().peekable();
+        if tokens.peek().is_none() {
+            return errinput!("invalid interval '{s}'");
+        }
+        while let Some(token) = tokens.next() {
+            // A time part, e.g. 04:05:06 or -01:30.
+            if token.contains(':') {
+                let (negative, time) = match token.strip_prefix('-') {
+                    Some(time) => (true, time),
+                    None => (false, token.strip_prefix('+').unwrap_or(token)),
+                };
+                let micros = parse_time_parts(time, false)
+                    .ok_or_else(|| Error::InvalidInput(format!("invalid interval '{s}'")))?;
+                let micros = if negative { -micros } else { micros };
+                interval = interval.checked_add(&Interval::new(0, 0, micros))?;
+                continue;
+            }
+
+            let Ok(number) = token.parse::<f64>() else {
+                return errinput!("invalid interval '{s}'");
+            };
+            let Some(unit) = tokens.next() else {
+                return errinput!("missing unit for {number} in interval '{s}'");
+            };
+            let component = match unit.to_lowercase().as_str() {
+                "year" | "years" | "yr" | "yrs" => Self::from_months(number * 12.0),
+                "month" | "months" | "mon" | "mons" => Self::from_months(number),
+                "week" | "weeks" => Self::from_days(number * 7.0),
+                "day" | "days" => Self::from_days(number),
+                "hour" | "hours" | "hr" | "hrs" => {
+                    Self::from_micros(number * MICROS_PER_HOUR as f64)
+                }
+                "minute" | "minutes" | "min" | "mins" => {
+                    Self::from_micros(number * MICROS_PER_MINUTE as f64)
+                }
+                "second" | "seconds" | "sec" | "secs" => {
+                    Self::from_micros(number * MICROS_PER_SECOND as f64)
+                }
+                "millisecond" | "milliseconds" | "ms" => Self::from_micros(number * 1000.0),
+                "microsecond" | "microseconds" | "us" => Self::from_micros(number),
+                unit => return errinput!("unknown interval unit {unit}"),
+            }?;
+            interval = interval.checked_add(&component)?;
+        }
+        Ok(interval)
+    }
+
+    /// Builds an interval from a fractional number of months, with the
+    /// fraction carried into days.
+    fn from_months(months: f64) -> Result<Self> {
+        let whole = months.trunc();
+        if whole.abs() > i32::MAX as f64 {
+            return Err(Error::OverflowError);
+        }
+        let days = Self::from_days((months - whole) * 30.0)?;
+        Ok(Self::new(whole as i32, days.days, days.micros))
+    }
+
+    /// Builds an interval from a fractional number of days, with the fraction
+    /// carried into microseconds.
+    fn from_days(days: f64) -> Result<Self> {
+        let whole = days.trunc();
+        if whole.abs() > i32::MAX as f64 {
+            return Err(Error::OverflowError);
+        }
+        let micros = Self::from_micros((days - whole) * MICROS_PER_DAY as f64)?;
+        Ok(Self::new(0, whole as i32, micros.micros))
+    }
+
+    /// Builds an interval from a number of microseconds, rounded.
+    fn from_micros(micros: f64) -> Result<Self> {
+        let micros = micros.round();
+        if micros.abs() >= i64::MAX as f64 {
+            return Err(Error::OverflowError);
+        }
+        Ok(Self::new(0, 0, micros as i64))
+    }
+
+    /// Returns the interval length in microseconds, counting months as 30 days.
+    pub fn normalized_micros(&self) -> i128 {
+        (self.months as i128 * 30 + self.days as i128) * MICROS_PER_DAY as i128
+            + self.micros as i128
+    }
+
+    pub fn checked_add(&self, other: &Self) -> Result<Self> {
+        Ok(Self {
+            months: self
+                .months
+                .checked_add(other.months)
+                .ok_or(Error::OverflowError)?,
+            days: self
+                .days
+                .checked_add(other.days)
+                .ok_or(Error::OverflowError)?,
+            micros: self
+                .micros
+                .checked_add(other.micros)
+                .ok_or(Error::OverflowError)?,
+        })
+    }
+
+    pub fn checked_sub(&self, other: &Self) -> Result<Self> {
+        self.checked_add(&other.checked_neg()?)
+    }
+
+    pub fn checked_mul(&self, factor: i64) -> Result<Self> {
+        let factor: i32 = factor.try_into().or(Err(Error::OverflowError))?;
+        Ok(Self {
+            months: self
+                .months
+                .checked_mul(factor)
+                .ok_or(Error::OverflowError)?,
+            days: self.days.checked_mul(factor).ok_or(Error::OverflowError)?,
+            micros: self
+                .micros
+                .checked_mul(factor as i64)
+                .ok_or(Error::OverflowError)?,
+        })
+    }
+
+    pub fn checked_neg(&self) -> Result<Self> {
+        Ok(Self {
+            months: self.months.checked_neg().ok_or(Error::OverflowError)?,
+            days: self.days.checked_neg().ok_or(Error::OverflowError)?,
+            micros: self.micros.checked_neg().ok_or(Error::OverflowError)?,
+        })
+    }
+}
+
+impl PartialEq for Interval {
+    fn eq(&self, other: &Self) -> bool {
+        self.normalized_micros() == other.normalized_micros()
+    }
+}
+
+impl Eq for Interval {}
+
+impl std::hash::Hash for Interval {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.normalized_micros().hash(state)
+    }
+}
+
+impl Ord for Interval {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.normalized_micros().cmp(&other.normalized_micros())
+    }
+}
+
+impl PartialOrd for Interval {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl std::fmt::Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plural = |n: i32| if n.abs() == 1 { "" } else { "s" };
+        let mut parts = Vec::new();
+        let (years, months) = (self.months / 12, self.months % 12);
+        if years != 0 {
+            parts.push(format!("{years} year{}", plural(years)));
+        }
+        if months != 0 {
+            parts.push(format!("{months} mon{}", plural(months)));
+        }
+        if self.days != 0 {
+            parts.push(format!("{} day{}", self.days, plural(self.days)));
+        }
+        if self.micros != 0 || parts.is_empty() {
+            let sign = if self.micros < 0 { "-" } else { "" };
+            let micros = self.micros.unsigned_abs();
+            let hours = micros / MICROS_PER_HOUR as u64;
+            parts.push(format!(
+                "{sign}{hours:02}:{}",
+                format_time_parts(micros as i64)
+            ));
+        }
+        f.write_str(&parts.join(" "))
+    }
+}
+
+/// A part of a date or time, used with extract() and date_trunc().
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DateTimeField {
+    Year,
+    Quarter,
+    Month,
+    Week,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    Millisecond,
+    Microsecond,
+    /// The day of the week, from 0 (Sunday) to 6 (Saturday).
+    DayOfWeek,
+    /// The day of the year, from 1 to 366.
+    DayOfYear,
+    /// Seconds since 1970-01-01 00:00:00, or the total seconds of an interval.
+    Epoch,
+}
+
+impl TryFrom<&str> for DateTimeField {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        Ok(match value.to_lowercase().as_str() {
+            "year" | "years" => Self::Year,
+            "quarter" => Self::Quarter,
+            "month" | "months" => Self::Month,
+            "week" | "weeks" => Self::Week,
+            "day" | "days" => Self::Day,
+            "hour" | "hours" => Self::Hour,
+            "minute" | "minutes" => Self::Minute,
+            "second" | "seconds" => Self::Second,
+            "millisecond" | "milliseconds" => Self::Millisecond,
+            "microsecond" | "microseconds" => Self::Microsecond,
+            "dow" => Self::DayOfWeek,
+            "doy" => Self::DayOfYear,
+            "epoch" => Self::Epoch,
+            field => return errinput!("unknown date/time field {field}"),
+        })
+    }
+}
+
+impl std::fmt::Display for DateTimeField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Year => "year",
+            Self::Quarter => "quarter",
+            Self::Month => "month",
+            Self::Week => "week",
+            Self::Day => "day",
+            Self::Hour => "hour",
+            Self::Minute => "minute",
+            Self::Second => "second",
+            Self::Millisecond => "millisecond",
+            Self::Microsecond => "microsecond",
+            Self::DayOfWeek => "dow",
+            Self::DayOfYear => "doy",
+            Self::Epoch => "epoch",
+        })
+    }
+}
+
+/// The earliest supported date, 0001-01-01, in days since 1970-01-01.
+pub const MIN_DATE: i32 = days_from_civil(1, 1, 1) as i32;
+/// The latest supported date, 9999-12-31, in days since 1970-01-01.
+pub const MAX_DATE: i32 = days_from_civil(9999, 12, 31) as i32;
+
+/// Returns the number of days since 1970-01-01 for the given civil date.
+const fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Returns the civil (year, month, day) for the given days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Parses a YYYY-MM-DD date into days since 1970-01-01.
+pub fn parse_date(s: &str) -> Result<i32> {
+    let parse = || -> Option<i32> {
+        let mut parts = s.trim().splitn(3, '-');
+        let year: i64 = parts.next()?.parse().ok()?;
+        let month: i64 = parts.next()?.parse().ok()?;
+        let day: i64 = parts.next()?.parse().ok()?;
+        if !(1..=9999).contains(&year) || !(1..=12).contains(&month) {
+            return None;
+        }
+        if !(1..=days_in_month(year, month)).contains(&day) {
+            return None;
+        }
+        days_from_civil(year, month, day).try_into().ok()
+    };
+    parse().ok_or_else(|| Error::InvalidInput(format!("invalid date '{s}'")))
+}
+
+/// Formats days since 1970-01-01 as a YYYY-MM-DD date.
+pub fn format_date(days: i32) -> String {
+    let (year, month, day) = civil_from_days(days as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Parses HH:MM[:SS[.ffffff]] into microseconds. If bounded, the hours must be
+/// less than 24, otherwise any number of hours is allowed (for intervals).
+fn parse_time_parts(s: &str, bounded: bool) -> Option<i64> {
+    let mut parts = s.splitn(3, ':');
+    let hours: i64 = parts.next()?.parse().ok()?;
+    let minutes: i64 = parts.next()?.parse().ok()?;
+    let (seconds, fraction) = match parts.next() {
+        Some(seconds) => match seconds.split_once('.') {
+            Some((seconds, fraction)) => (seconds.parse().ok()?, fraction),
+            None => (seconds.parse().ok()?, ""),
+        },
+        None => (0, ""),
+    };
+    if hours < 0 || (bounded && hours > 23) || !(0..60).contains(&minutes) {
+        return None;
+    }
+    if !(0..60).contains(&seconds) || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    // Only microsecond precision is kept, any further digits are truncated.
+    let fraction: i64 = format!("{:0<6.6}", fraction).parse().ok()?;
+    hours
+        .checked_mul(MICROS_PER_HOUR)?
+        .checked_add(minutes * MICROS_PER_MINUTE + seconds * MICROS_PER_SECOND + fraction)
+}
+
+/// Formats the minutes, seconds and fractional seconds of a time.
+fn format_time_parts(micros: i64) -> String {
+    let minutes = micros / MICROS_PER_MINUTE % 60;
+    let seconds = micros / MICROS_PER_SECOND % 60;
+    let fraction = micros % MICROS_PER_SECOND;
+    if fraction == 0 {
+        format!("{minutes:02}:{seconds:02}")
+    } else {
+        let fraction = format!("{fraction:06}");
+        format!(
+            "{minutes:02}:{seconds:02}.{}",
+            fraction.trim_end_matches('0')
+        )
+    }
+}
+
+/// Parses an HH:MM[:SS[.ffffff]] time into microseconds since midnight.
+pub fn parse_time(s: &str) -> Result<i64> {
+    parse_time_parts(s.trim(), true)
+        .ok_or_else(|| Error::InvalidInput(format!("invalid time '{s}'")))
+}
+
+/// Formats microseconds since midnight as an HH:MM:SS[.ffffff] time.
+pub fn format_time(micros: i64) -> String {
+    format!(
+        "{:02}:{}",
+        micros / MICROS_PER_HOUR,
+        format_time_parts(micros)
+    )
+}
+
+/// Parses a 'YYYY-MM-DD[ HH:MM[:SS[.ffffff]]]' timestamp into microseconds
+/// since 1970-01-01 00:00:00. The date and time may also be separated by T.
+pub fn parse_timestamp(s: &str) -> Result<i64> {
+    let s = s.trim();
+    let (date, time) = match s.split_once([' ', 'T']) {
+        Some((date, time)) => (date, parse_time(time.trim_start())?),
+        None => (s, 0),
+    };
+    (parse_date(date)? as i64)
+        .checked_mul(MICROS_PER_DAY)
+        .and_then(|ts| ts.checked_add(time))
+        .ok_or_else(|| Error::InvalidInput(format!("invalid timestamp '{s}'")))
+}
+
+/// Formats microseconds since 1970-01-01 00:00:00 as a timestamp.
+pub fn format_timestamp(micros: i64) -> String {
+    let (days, time) = (
+        micros.div_euclid(MICROS_PER_DAY),
+        micros.rem_euclid(MICROS_PER_DAY),
+    );
+    let (year, month, day) = civil_from_days(days);
+    format!("{year:04}-{month:02}-{day:02} {}", format_time(time))
+}
+
+/// Returns the current timestamp.
+pub fn now() -> i64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    now.as_micros() as i64
+}
+
+/// Checks that a number of days since 1970-01-01 is within the supported
+/// dates, i.e. years 1 to 9999, and returns it as a date.
+pub fn check_date(days: i64) -> Result<i32> {
+    match i32::try_from(days) {
+        Ok(days) if (MIN_DATE..=MAX_DATE).contains(&days) => Ok(days),
+        _ => errinput!("date out of range"),
+    }
+}
+
+/// Checks that a timestamp is within the supported dates.
+pub fn check_timestamp(micros: i64) -> Result<i64> {
+    let (min, max) = (MIN_DATE as i64, MAX_DATE as i64 + 1);
+    if !(min * MICROS_PER_DAY..max * MICROS_PER_DAY).contains(&micros) {
+        return errinput!("timestamp out of range");
+    }
+    Ok(micros)
+}
+
+/// Converts a number of days since 1970-01-01 to a timestamp at midnight.
+/// Errors if it's out of range.
+fn days_to_timestamp(days: i64) -> Result<i64> {
+    days.checked_mul(MICROS_PER_DAY)
+        .map_or_else(|| errinput!("timestamp out of range"), check_timestamp)
+}
+
+/// Converts a date to a timestamp at midnight.
+pub fn date_to_timestamp(days: i32) -> Result<i64> {
+    days_to_timestamp(days as i64)
+}
+
+/// Adds an interval to a timestamp. Months are added first, clamping the day
+/// to the end of the month, followed by days and then the time part. Errors
+/// if the result is out of range.
+pub fn add_interval(timestamp: i64, interval: &Interval) -> Result<i64> {
+    let mut timestamp = timestamp;
+    if interval.months != 0 {
+        let (days, time) = (
+            timestamp.div_euclid(MICROS_PER_DAY),
+            timestamp.rem_euclid(MICROS_PER_DAY),
+        );
+        let (year, month, day) = civil_from_days(days);
+        let months = year * 12 + month - 1 + interval.months as i64;
+        let (year, month) = (months.div_euclid(12), months.rem_euclid(12) + 1);
+        let day = day.min(days_in_month(year, month));
+        timestamp = days_to_timestamp(days_from_civil(year, month, day))? + time;
+    }
+    (interval.days as i64)
+        .checked_mul(MICROS_PER_DAY)
+        .and_then(|micros| micros.checked_add(interval.micros))
+        .and_then(|micros| timestamp.checked_add(micros))
+        .map_or_else(|| errinput!("timestamp out of range"), check_timestamp)
+}
+
+/// Adds an interval to a time of day, wrapping around midnight. Months and
+/// days are ignored.
+pub fn add_interval_to_time(time: i64, interval: &Interval) -> i64 {
+    (time + interval.micros % MICROS_PER_DAY).rem_euclid(MICROS_PER_DAY)
+}
+
+/// Returns the interval between two timestamps, in days and microseconds.
+pub fn timestamp_difference(lhs: i64, rhs: i64) -> Result<Interval> {
+    let micros = lhs.checked_sub(rhs).ok_or(Error::OverflowError)?;
+    let days = (micros / MICROS_PER_DAY)
+        .try_into()
+        .or(Err(Error::OverflowError))?;
+    Ok(Interval::new(0, days, micros % MICROS_PER_DAY))
+}
+
+/// Extracts a date/time field from a temporal value. Seconds and epochs
+/// (which may be fractional) are returned as DOUBLE, everything else as INT.
+pub fn extract(field: DateTimeField, value: &Field) -> Result<Field> {
+    use DateTimeField::*;
+    let seconds = |micros: i64| Field::Double(micros as f64 / MICROS_PER_SECOND as f64);
+    let int = |i: i64| Field::from(i).convert_numeric(crate::types::DataType::Int);
+
+    let (timestamp, has_date) = match value {
+        Field::Null => return Ok(Field::Null),
+        Field::Date(days) => (date_to_timestamp(*days)?, true),
+        Field::Timestamp(ts) => (*ts, true),
+        Field::Time(time) => (*time, false),
+        Field::Interval(interval) => {
+            return match field {
+                Year => int(interval.months as i64 / 12),
+                Quarter => int((interval.months as i64 % 12) / 3 + 1),
+                Month => int(interval.months as i64 % 12),
+                Day => int(interval.days as i64),
+                Hour => int(interval.micros / MICROS_PER_HOUR),
+                Minute => int(interval.micros / MICROS_PER_MINUTE % 60),
+                Second => Ok(seconds(interval.micros % MICROS_PER_MINUTE)),
+                Millisecond => Ok(Field::Double(
+                    (interval.micros % MICROS_PER_MINUTE) as f64 / 1000.0,
+                )),
+                Microsecond => Ok(Field::BigInt(interval.micros % MICROS_PER_MINUTE)),
+                Epoch => Ok(Field::Double(
+                    interval.normalized_micros() as f64 / MICROS_PER_SECOND as f64,
+                )),
+                field => errinput!("can't extract {field} from interval"),
+            };
+        }
+        value => return errinput!("can't extract {field} from {value}"),
+    };
+
+    let (days, time) = (
+        timestamp.div_euclid(MICROS_PER_DAY),
+        timestamp.rem_euclid(MICROS_PER_DAY),
+    );
+    let (year, month, day) = civil_from_days(days);
+    match field {
+        Year | Quarter | Month | Week | Day | DayOfWeek | DayOfYear if !has_date => {
+            errinput!("can't extract {field} from {value}")
+        }
+        Year => int(year),
+        Quarter => int((month - 1) / 3 + 1),
+        Month => int(month),
+        Week => int(iso_week(days)),
+        Day => int(day),
+        DayOfWeek => int((days + 4).rem_euclid(7)),
+        DayOfYear => int(days - days_from_civil(year, 1, 1) + 1),
+        Hour => int(time / MICROS_PER_HOUR),
+        Minute => int(time / MICROS_PER_MINUTE % 60),
+        Second => Ok(seconds(time % MICROS_PER_MINUTE)),
+        Millisecond => Ok(Field::Double((time % MICROS_PER_MINUTE) as f64 / 1000.0)),
+        Microsecond => Ok(Field::BigInt(time % MICROS_PER_MINUTE)),
+        Epoch => Ok(seconds(timestamp)),
+    }
+}
+
+/// Returns the ISO 8601 week number of a date, where weeks start on Monday and
+/// week 1 is the week containing the year's first Thursday.
+fn iso_week(days: i64) -> i64 {
+    let weekday = (days + 3).rem_euclid(7); // 0 is Monday
+    let thursday = days - weekday + 3;
+    let (year, _, _) = civil_from_days(thursday);
+    (thursday - days_from_civil(year, 1, 1)) / 7 + 1
+}
+
+/// Truncates a date or timestamp to the given precision, returning a timestamp.
+pub fn date_trunc(field: DateTimeField, value: &Field) -> Result<Field> {
+    use DateTimeField::*;
+    let timestamp = match value {
+        Field::Null => return Ok(Field::Null),
+        Field::Date(days) => date_to_timestamp(*days)?,
+        Field::Timestamp(ts) => *ts,
+        value => return errinput!("can't truncate {value}"),
+    };
+    let (days, time) = (
+        timestamp.div_euclid(MICROS_PER_DAY),
+        timestamp.rem_euclid(MICROS_PER_DAY),
+    );
+    let (year, month, _) = civil_from_days(days);
+    let midnight = days_to_timestamp(days)?;
+    let truncate = |unit: i64| midnight + time / unit * unit;
+    Ok(Field::Timestamp(match field {
+        Year => days_to_timestamp(days_from_civil(year, 1, 1))?,
+        Quarter => days_to_timestamp(days_from_civil(year, (month - 1) / 3 * 3 + 1, 1))?,
+        Month => days_to_timestamp(days_from_civil(year, month, 1))?,
+        Week => days_to_timestamp(days - (days + 3).rem_euclid(7))?,
+        Day => midnight,
+        Hour => truncate(MICROS_PER_HOUR),
+        Minute => truncate(MICROS_PER_MINUTE),
+        Second => truncate(MICROS_PER_SECOND),
+        Millisecond => truncate(1000),
+        Microsecond => timestamp,
+        field => return errinput!("can't truncate to {field}"),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DataType;
+
+    #[test]
+    fn test_dates() -> Result<()> {
+        assert_eq!(parse_date("1970-01-01")?, 0);
+        assert_eq!(parse_date("2000-03-01")?, 11017);
+        assert_eq!(format_date(parse_date("2024-02-29")?), "2024-02-29");
+        assert_eq!(format_date(-1), "1969-12-31");
+        assert!(parse_date("2023-02-29").is_err());
+        assert!(parse_date("2024-13-01").is_err());
+        assert!(parse_date("yesterday").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_times() -> Result<()> {
+        assert_eq!(parse_time("00:00")?, 0);
+        assert_eq!(format_time(parse_time("23:59:59.25")?), "23:59:59.25");
+        assert!(parse_time("24:00:00").is_err());
+        let ts = parse_timestamp("2024-01-31T12:30:00")?;
+        assert_eq!(format_timestamp(ts), "2024-01-31 12:30:00");
+        assert_eq!(
+            format_timestamp(parse_timestamp("1969-12-31 23:00")?),
+            "1969-12-31 23:00:00"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_intervals() -> Result<()> {
+        let interval = Interval::parse("1 year 2 mons 3 days 04:05:06")?;
+        assert_eq!(interval.to_string(), "1 year 2 mons 3 days 04:05:06");
+        assert_eq!(Interval::parse("1.5 days")?.to_string(), "1 day 12:00:00");
+        assert_eq!(Interval::parse("-90 minutes")?.to_string(), "-01:30:00");
+        assert_eq!(Interval::parse("1 month")?, Interval::parse("30 days")?);
+        assert!(Interval::parse("3 fortnights").is_err());
+
+        // Adding months clamps to the end of the month.
+        let ts = parse_timestamp("2024-01-31 10:00")?;
+        let ts = add_interval(ts, &Interval::parse("1 month 1 hour")?)?;
+        assert_eq!(format_timestamp(ts), "2024-02-29 11:00:00");
+
+        // Results must be within the supported dates, without overflowing.
+        let huge = Interval::parse("100000000 years")?;
+        assert!(add_interval(ts, &huge).is_err());
+        assert!(add_interval(ts, &Interval::new(0, i32::MAX, 0)).is_err());
+        let ts = parse_timestamp("9999-12-31 23:00")?;
+        assert!(add_interval(ts, &Interval::parse("1 hour")?).is_err());
+        assert!(date_to_timestamp(i32::MAX).is_err());
+        assert!(check_date(MAX_DATE as i64 + 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_and_trunc() -> Result<()> {
+        let ts = Field::Timestamp(parse_timestamp("2024-05-17 13:45:30.5")?);
+        assert_eq!(extract(DateTimeField::Year, &ts)?, Field::Integer(2024));
+        assert_eq!(extract(DateTimeField::Quarter, &ts)?, Field::Integer(2));
+        assert_eq!(extract(DateTimeField::Week, &ts)?, Field::Integer(20));
+        assert_eq!(extract(DateTimeField::DayOfWeek, &ts)?, Field::Integer(5));
+        assert_eq!(extract(DateTimeField::Second, &ts)?, Field::Double(30.5));
+        assert_eq!(extract(DateTimeField::Year, &ts)?.get_type(), DataType::Int);
+        assert_eq!(
+            extract(DateTimeField::Second, &ts)?.get_type(),
+            DataType::Double
+        );
+        assert!(extract(DateTimeField::Year, &Field::Time(0)).is_err());
+
+        let trunc = date_trunc(DateTimeField::Month, &ts)?;
+        assert_eq!(trunc, Field::Timestamp(parse_timestamp("2024-05-01")?));
+        let trunc = date_trunc(DateTimeField::Week, &ts)?;
+        assert_eq!(trunc, Field::Timestamp(parse_timestamp("2024-05-13")?));
+        Ok(())
+    }
+}